@@ -1,9 +1,9 @@
 use anyhow::{anyhow, Context, Error, Result};
 use libloading::{Library, Symbol};
-use once_cell::unsync::OnceCell;
+use once_cell::sync::OnceCell;
 use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::BufReader;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
@@ -91,6 +91,51 @@ pub struct LanguageConfiguration<'a> {
     tags_config: OnceCell<Option<TagsConfiguration>>,
     highlight_names: &'a Mutex<Vec<String>>,
     use_all_highlight_names: bool,
+    query_files_by_language_name: &'a Mutex<HashMap<String, QueryFiles>>,
+}
+
+/// The query files of a language configuration, which can be used by the queries of
+/// other languages via the `inherits` and `include` directives.
+#[derive(Clone)]
+struct QueryFiles {
+    root_path: PathBuf,
+    highlights_filenames: Option<Vec<String>>,
+    injections_filenames: Option<Vec<String>>,
+    locals_filenames: Option<Vec<String>>,
+    tags_filenames: Option<Vec<String>>,
+}
+
+/// A part of a query file, within the source of a query that was assembled from
+/// several query files.
+struct QueryFileRange {
+    path: String,
+    range: Range<usize>,
+    start_offset: usize,
+    start_row: usize,
+}
+
+/// Assembles the source of a query from a language's query files, resolving the
+/// directives that compose them with the query files of other languages. Directives
+/// are written as comments, so that each file remains a valid query:
+///
+/// * `; inherits: <language>[, <language>...]`, among the comments at the top of a
+///   file, adds the other languages' queries of the same kind before the file.
+/// * `; include: [<language>:]<path>` adds the query file at the given path, relative
+///   to the other language's directory, or to the current language's directory.
+/// * `; name: <name>`, before a pattern, names the pattern. When several patterns have
+///   the same name, only the last one is used, so that a query can override the
+///   patterns that it inherits.
+///
+/// Each file is read at most once, so when several inherited languages share an
+/// ancestor, the ancestor's queries are only added once.
+struct QueryReader<'a> {
+    query_files_by_language_name: &'a HashMap<String, QueryFiles>,
+    default_path: &'a str,
+    query: String,
+    ranges: Vec<QueryFileRange>,
+    pattern_names: Vec<(String, usize)>,
+    stack: Vec<PathBuf>,
+    read_paths: HashSet<PathBuf>,
 }
 
 pub struct Loader {
@@ -100,12 +145,10 @@ pub struct Loader {
     language_configuration_ids_by_file_type: HashMap<String, Vec<usize>>,
     highlight_names: Box<Mutex<Vec<String>>>,
     use_all_highlight_names: bool,
+    query_files_by_language_name: Box<Mutex<HashMap<String, QueryFiles>>>,
     debug_build: bool,
 }
 
-unsafe impl Send for Loader {}
-unsafe impl Sync for Loader {}
-
 impl Loader {
     pub fn new() -> Result<Self> {
         let parser_lib_path = dirs::cache_dir()
@@ -122,6 +165,7 @@ impl Loader {
             language_configuration_ids_by_file_type: HashMap::new(),
             highlight_names: Box::new(Mutex::new(Vec::new())),
             use_all_highlight_names: true,
+            query_files_by_language_name: Box::new(Mutex::new(HashMap::new())),
             debug_build: false,
         }
     }
@@ -572,6 +616,7 @@ impl Loader {
                         tags_config: OnceCell::new(),
                         highlight_names: &*self.highlight_names,
                         use_all_highlight_names: self.use_all_highlight_names,
+                        query_files_by_language_name: &self.query_files_by_language_name,
                     };
 
                     for file_type in &configuration.file_types {
@@ -583,6 +628,7 @@ impl Loader {
 
                     self.language_configurations
                         .push(unsafe { mem::transmute(configuration) });
+                    self.register_query_files(self.language_configurations.len() - 1);
                 }
             }
         }
@@ -606,16 +652,45 @@ impl Loader {
                 tags_config: OnceCell::new(),
                 highlight_names: &*self.highlight_names,
                 use_all_highlight_names: self.use_all_highlight_names,
+                query_files_by_language_name: &self.query_files_by_language_name,
             };
             self.language_configurations
                 .push(unsafe { mem::transmute(configuration) });
             self.languages_by_id
                 .push((parser_path.to_owned(), OnceCell::new()));
+            self.register_query_files(self.language_configurations.len() - 1);
         }
 
         Ok(&self.language_configurations[initial_language_configuration_count..])
     }
 
+    /// Make a language configuration's query files available to the queries of other
+    /// languages, under the name of the language's directory, without any `tree-sitter-`
+    /// prefix, and under the language's scope.
+    fn register_query_files(&self, configuration_id: usize) {
+        let configuration = &self.language_configurations[configuration_id];
+        let language_path = &self.languages_by_id[configuration.language_id].0;
+        let query_files = QueryFiles {
+            root_path: configuration.root_path.clone(),
+            highlights_filenames: configuration.highlights_filenames.clone(),
+            injections_filenames: configuration.injections_filenames.clone(),
+            locals_filenames: configuration.locals_filenames.clone(),
+            tags_filenames: configuration.tags_filenames.clone(),
+        };
+        let names = language_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.trim_start_matches("tree-sitter-"))
+            .into_iter()
+            .chain(configuration.scope.as_deref());
+        let mut query_files_by_language_name = self.query_files_by_language_name.lock().unwrap();
+        for name in names {
+            query_files_by_language_name
+                .entry(name.to_string())
+                .or_insert_with(|| query_files.clone());
+        }
+    }
+
     fn regex(pattern: Option<String>) -> Option<Regex> {
         pattern.and_then(|r| RegexBuilder::new(&r).multi_line(true).build().ok())
     }
@@ -766,50 +841,290 @@ impl<'a> LanguageConfiguration<'a> {
 
     fn include_path_in_query_error<'b>(
         mut error: QueryError,
-        ranges: &'b Vec<(String, Range<usize>)>,
+        ranges: &'b Vec<QueryFileRange>,
         source: &str,
         start_offset: usize,
     ) -> Error {
         let offset_within_section = error.offset - start_offset;
-        let (path, range) = ranges
+        let file_range = ranges
             .iter()
-            .find(|(_, range)| range.contains(&offset_within_section))
+            .find(|file_range| file_range.range.contains(&offset_within_section))
             .unwrap();
-        error.offset = offset_within_section - range.start;
-        error.row = source[range.start..offset_within_section]
-            .chars()
-            .filter(|c| *c == '\n')
-            .count();
-        Error::from(error).context(format!("Error in query file {:?}", path))
+        error.offset = file_range.start_offset + offset_within_section - file_range.range.start;
+        error.row = file_range.start_row
+            + source[file_range.range.start..offset_within_section]
+                .chars()
+                .filter(|c| *c == '\n')
+                .count();
+        Error::from(error).context(format!("Error in query file {:?}", file_range.path))
     }
 
     fn read_queries(
         &self,
         paths: &Option<Vec<String>>,
         default_path: &str,
-    ) -> Result<(String, Vec<(String, Range<usize>)>)> {
-        let mut query = String::new();
-        let mut path_ranges = Vec::new();
+    ) -> Result<(String, Vec<QueryFileRange>)> {
+        let query_files_by_language_name = self.query_files_by_language_name.lock().unwrap();
+        let mut reader = QueryReader {
+            query_files_by_language_name: &query_files_by_language_name,
+            default_path,
+            query: String::new(),
+            ranges: Vec::new(),
+            pattern_names: Vec::new(),
+            stack: Vec::new(),
+            read_paths: HashSet::new(),
+        };
+        reader.read_files(&self.root_path, paths, true)?;
+        Ok(reader.finish())
+    }
+}
+
+impl QueryFiles {
+    fn filenames(&self, default_path: &str) -> &Option<Vec<String>> {
+        match default_path {
+            "highlights.scm" => &self.highlights_filenames,
+            "injections.scm" => &self.injections_filenames,
+            "locals.scm" => &self.locals_filenames,
+            _ => &self.tags_filenames,
+        }
+    }
+}
+
+impl<'a> QueryReader<'a> {
+    fn read_files(
+        &mut self,
+        root_path: &Path,
+        paths: &Option<Vec<String>>,
+        is_own_language: bool,
+    ) -> Result<()> {
+        let display_path = |path: &str| {
+            if is_own_language {
+                path.to_string()
+            } else {
+                root_path.join(path).to_string_lossy().to_string()
+            }
+        };
         if let Some(paths) = paths.as_ref() {
             for path in paths {
-                let abs_path = self.root_path.join(path);
-                let prev_query_len = query.len();
-                query += &fs::read_to_string(&abs_path)
-                    .with_context(|| format!("Failed to read query file {:?}", path))?;
-                path_ranges.push((path.clone(), prev_query_len..query.len()));
+                self.read_file(root_path, path, display_path(path))?;
             }
         } else {
-            let queries_path = self.root_path.join("queries");
-            let path = queries_path.join(default_path);
-            if path.exists() {
-                query = fs::read_to_string(&path)
-                    .with_context(|| format!("Failed to read query file {:?}", path))?;
-                path_ranges.push((default_path.to_string(), 0..query.len()));
+            let path = format!("queries/{}", self.default_path);
+            if root_path.join(&path).exists() {
+                let display_path = if is_own_language {
+                    self.default_path.to_string()
+                } else {
+                    display_path(&path)
+                };
+                self.read_file(root_path, &path, display_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_file(&mut self, root_path: &Path, path: &str, display_path: String) -> Result<()> {
+        let abs_path = root_path.join(path);
+        if self.stack.contains(&abs_path) {
+            return Err(anyhow!("Query file {:?} includes itself", display_path));
+        }
+        if !self.read_paths.insert(abs_path.clone()) {
+            return Ok(());
+        }
+        let source = fs::read_to_string(&abs_path)
+            .with_context(|| format!("Failed to read query file {:?}", display_path))?;
+        self.stack.push(abs_path);
+
+        let mut is_header = true;
+        let mut chunk_start = 0;
+        let mut chunk_row = 0;
+        let mut offset = 0;
+        for (row, line) in source.split_inclusive('\n').enumerate() {
+            offset += line.len();
+            let comment = line.trim().strip_prefix(';');
+            if comment.is_none() && !line.trim().is_empty() {
+                is_header = false;
+            }
+            let (key, value) = match comment.and_then(|comment| comment.split_once(':')) {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "inherits" if is_header => {
+                    self.push_chunk(&source, chunk_start..offset, chunk_row, &display_path);
+                    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                        let query_files = self.query_files(name, &display_path)?;
+                        let paths = query_files.filenames(self.default_path);
+                        self.read_files(&query_files.root_path, paths, false)?;
+                    }
+                }
+                "include" => {
+                    self.push_chunk(&source, chunk_start..offset, chunk_row, &display_path);
+                    let (included_root_path, included_path) = match value.split_once(':') {
+                        Some((name, path)) => {
+                            let query_files = self.query_files(name.trim(), &display_path)?;
+                            (query_files.root_path, path.trim())
+                        }
+                        None => (root_path.to_owned(), value),
+                    };
+                    self.read_file(
+                        &included_root_path,
+                        included_path,
+                        included_root_path
+                            .join(included_path)
+                            .to_string_lossy()
+                            .to_string(),
+                    )?;
+                }
+                "name"
+                    if !value.is_empty()
+                        && value
+                            .chars()
+                            .all(|c| c.is_alphanumeric() || "_-.".contains(c)) =>
+                {
+                    let pattern_offset = self.query.len() + offset - chunk_start;
+                    self.pattern_names.push((value.to_string(), pattern_offset));
+                    continue;
+                }
+                _ => continue,
             }
+            chunk_start = offset;
+            chunk_row = row + 1;
+        }
+        self.push_chunk(&source, chunk_start..source.len(), chunk_row, &display_path);
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn query_files(&self, language_name: &str, display_path: &str) -> Result<QueryFiles> {
+        self.query_files_by_language_name
+            .get(language_name)
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown language {:?} in query file {:?}",
+                    language_name,
+                    display_path
+                )
+            })
+    }
+
+    fn push_chunk(&mut self, source: &str, range: Range<usize>, row: usize, path: &str) {
+        if range.is_empty() {
+            return;
         }
+        let start = self.query.len();
+        self.query += &source[range.clone()];
+        self.ranges.push(QueryFileRange {
+            path: path.to_string(),
+            range: start..self.query.len(),
+            start_offset: range.start,
+            start_row: row,
+        });
+    }
 
-        Ok((query, path_ranges))
+    /// Remove every named pattern that is overridden by a later pattern with the same
+    /// name. The patterns are replaced with whitespace, so that the positions of the
+    /// remaining patterns are unchanged.
+    fn finish(mut self) -> (String, Vec<QueryFileRange>) {
+        let mut pattern_ranges_by_name = HashMap::<_, Vec<_>>::new();
+        for (name, offset) in &self.pattern_names {
+            if let Some(range) = pattern_range(&self.query, *offset) {
+                pattern_ranges_by_name
+                    .entry(name.as_str())
+                    .or_default()
+                    .push(range);
+            }
+        }
+        let mut overridden_ranges = pattern_ranges_by_name
+            .into_values()
+            .flat_map(|mut ranges| {
+                ranges.pop();
+                ranges
+            })
+            .collect::<Vec<_>>();
+        overridden_ranges.sort_unstable_by_key(|range| range.start);
+        for range in overridden_ranges {
+            let whitespace = self.query[range.clone()]
+                .chars()
+                .map(|c| {
+                    if c == '\n' {
+                        "\n".to_string()
+                    } else {
+                        " ".repeat(c.len_utf8())
+                    }
+                })
+                .collect::<String>();
+            self.query.replace_range(range, &whitespace);
+        }
+        (self.query, self.ranges)
+    }
+}
+
+/// Find the range of the first pattern after the given offset in a query, including its
+/// quantifiers and captures.
+fn pattern_range(query: &str, offset: usize) -> Option<Range<usize>> {
+    let bytes = query.as_bytes();
+    let skip_whitespace = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    let skip_comment = |mut i: usize| {
+        while i < bytes.len() && bytes[i] != b'\n' {
+            i += 1;
+        }
+        i
+    };
+
+    let mut start = skip_whitespace(offset);
+    while bytes.get(start) == Some(&b';') {
+        start = skip_whitespace(skip_comment(start));
+    }
+
+    let mut end = start;
+    let mut depth = 0;
+    loop {
+        let c = *bytes.get(end)?;
+        end += 1;
+        match c {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b';' => end = skip_comment(end),
+            b'"' => loop {
+                match *bytes.get(end)? {
+                    b'\\' => end += 2,
+                    b'"' => {
+                        end += 1;
+                        break;
+                    }
+                    _ => end += 1,
+                }
+            },
+            _ => {}
+        }
+        if depth <= 0 {
+            break;
+        }
+    }
+
+    loop {
+        let next = skip_whitespace(end);
+        match bytes.get(next) {
+            Some(b'*') | Some(b'+') | Some(b'?') => end = next + 1,
+            Some(b'@') => {
+                end = next + 1;
+                while end < bytes.len()
+                    && (bytes[end].is_ascii_alphanumeric() || b"_-.".contains(&bytes[end]))
+                {
+                    end += 1;
+                }
+            }
+            _ => break,
+        }
     }
+    Some(start..end)
 }
 
 fn needs_recompile(
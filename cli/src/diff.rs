@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use std::{fs, io::Write, path::Path};
+use tree_sitter::{DiffEditKind, Language, Node, Parser};
+
+pub fn diff_files_at_paths(
+    language: Language,
+    old_path: &Path,
+    new_path: &Path,
+    output: &mut impl Write,
+) -> Result<()> {
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+
+    let old_source =
+        fs::read(old_path).with_context(|| format!("Error reading source file {:?}", old_path))?;
+    let new_source =
+        fs::read(new_path).with_context(|| format!("Error reading source file {:?}", new_path))?;
+    let old_tree = parser.parse(&old_source, None).unwrap();
+    let new_tree = parser.parse(&new_source, None).unwrap();
+
+    for edit in old_tree.diff(&old_source, &new_tree, &new_source) {
+        let (label, node) = match edit.kind {
+            DiffEditKind::Insert => ("insert", edit.new_node.unwrap()),
+            DiffEditKind::Delete => ("delete", edit.old_node.unwrap()),
+            DiffEditKind::Update => ("update", edit.new_node.unwrap()),
+            DiffEditKind::Move => ("move", edit.new_node.unwrap()),
+        };
+        write!(output, "{}: ", label)?;
+        if let Some(field_name) = edit.field_name {
+            write!(output, "{}: ", field_name)?;
+        }
+        if node.is_named() {
+            write!(output, "{}", node.kind())?;
+        } else {
+            write!(output, "{:?}", node.kind())?;
+        }
+        if let Some(old_node) = edit.old_node {
+            write!(
+                output,
+                ", old: {} - {}",
+                old_node.start_position(),
+                old_node.end_position()
+            )?;
+        }
+        if let Some(new_node) = edit.new_node {
+            write!(
+                output,
+                ", new: {} - {}",
+                new_node.start_position(),
+                new_node.end_position()
+            )?;
+        }
+        match (edit.kind, edit.old_node, edit.new_node) {
+            (DiffEditKind::Update, Some(old_node), Some(new_node)) => write!(
+                output,
+                ", text: `{}` -> `{}`",
+                node_text(old_node, &old_source),
+                node_text(new_node, &new_source)
+            )?,
+            (DiffEditKind::Insert, _, Some(node)) if is_single_line(node) => {
+                write!(output, ", text: `{}`", node_text(node, &new_source))?
+            }
+            (DiffEditKind::Delete, Some(node), _) if is_single_line(node) => {
+                write!(output, ", text: `{}`", node_text(node, &old_source))?
+            }
+            _ => {}
+        }
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
+
+fn is_single_line(node: Node) -> bool {
+    node.start_position().row == node.end_position().row
+}
+
+fn node_text<'a>(node: Node, source: &'a [u8]) -> &'a str {
+    node.utf8_text(source).unwrap_or("")
+}
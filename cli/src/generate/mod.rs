@@ -8,6 +8,7 @@ mod node_types;
 pub mod parse_grammar;
 mod prepare_grammar;
 mod render;
+mod render_ast;
 mod rules;
 mod tables;
 
@@ -16,6 +17,7 @@ use self::grammars::{InlinedProductionMap, LexicalGrammar, SyntaxGrammar};
 use self::parse_grammar::parse_grammar;
 use self::prepare_grammar::prepare_grammar;
 use self::render::render_c_code;
+use self::render_ast::render_rust_ast;
 use self::rules::AliasMap;
 use anyhow::{anyhow, Context, Result};
 use lazy_static::lazy_static;
@@ -36,6 +38,7 @@ lazy_static! {
 struct GeneratedParser {
     c_code: String,
     node_types_json: String,
+    rust_ast: String,
 }
 
 pub fn generate_parser_in_directory(
@@ -75,6 +78,7 @@ pub fn generate_parser_in_directory(
     let GeneratedParser {
         c_code,
         node_types_json,
+        rust_ast,
     } = generate_parser_for_grammar_with_opts(
         &language_name,
         syntax_grammar,
@@ -91,6 +95,10 @@ pub fn generate_parser_in_directory(
 
     if generate_bindings {
         binding_files::generate_binding_files(&repo_path, &language_name)?;
+        write_file(
+            &repo_path.join("bindings").join("rust").join("ast.rs"),
+            rust_ast,
+        )?;
     }
 
     Ok(())
@@ -130,6 +138,8 @@ fn generate_parser_for_grammar_with_opts(
         &simple_aliases,
         &variable_info,
     );
+    let rust_ast = render_rust_ast(&node_types_json);
+    let node_types_json_string = serde_json::to_string_pretty(&node_types_json).unwrap();
     let (parse_table, main_lex_table, keyword_lex_table, keyword_capture_token) = build_tables(
         &syntax_grammar,
         &lexical_grammar,
@@ -147,11 +157,13 @@ fn generate_parser_for_grammar_with_opts(
         syntax_grammar,
         lexical_grammar,
         simple_aliases,
+        node_types_json,
         abi_version,
     );
     Ok(GeneratedParser {
         c_code,
-        node_types_json: serde_json::to_string_pretty(&node_types_json).unwrap(),
+        node_types_json: node_types_json_string,
+        rust_ast,
     })
 }
 
@@ -1,6 +1,7 @@
 use super::{
     char_tree::{CharacterTree, Comparator},
     grammars::{ExternalToken, LexicalGrammar, SyntaxGrammar, VariableType},
+    node_types::{NodeInfoJSON, NodeTypeJSON},
     rules::{Alias, AliasMap, Symbol, SymbolType},
     tables::{
         AdvanceAction, FieldLocation, GotoAction, LexState, LexTable, ParseAction, ParseTable,
@@ -20,6 +21,7 @@ const SMALL_STATE_THRESHOLD: usize = 64;
 const ABI_VERSION_MIN: usize = 13;
 const ABI_VERSION_MAX: usize = tree_sitter::LANGUAGE_VERSION;
 const ABI_VERSION_WITH_PRIMARY_STATES: usize = 14;
+const ABI_VERSION_WITH_NODE_TYPES: usize = 15;
 
 macro_rules! add {
     ($this: tt, $($arg: tt)*) => {{
@@ -68,6 +70,7 @@ struct Generator {
     syntax_grammar: SyntaxGrammar,
     lexical_grammar: LexicalGrammar,
     default_aliases: AliasMap,
+    node_types: Vec<NodeInfoJSON>,
     symbol_order: HashMap<Symbol, usize>,
     symbol_ids: HashMap<Symbol, String>,
     alias_ids: HashMap<Alias, String>,
@@ -79,6 +82,13 @@ struct Generator {
     abi_version: usize,
 }
 
+/// A field id, the `multiple` and `required` flags, and the index of a list of child
+/// types.
+type ChildInfoEntry = (String, bool, bool, usize);
+
+/// The C identifiers of the node types that a set of children can have.
+type ChildTypeList = Vec<String>;
+
 struct TransitionSummary {
     is_included: bool,
     ranges: Vec<Range<char>>,
@@ -118,6 +128,11 @@ impl Generator {
             self.add_primary_state_id_list();
         }
 
+        if self.abi_version >= ABI_VERSION_WITH_NODE_TYPES {
+            self.add_supertype_map();
+            self.add_child_info_map();
+        }
+
         let mut main_lex_table = LexTable::default();
         swap(&mut main_lex_table, &mut self.main_lex_table);
         self.add_lex_function("ts_lex", main_lex_table, true);
@@ -322,6 +337,13 @@ impl Generator {
             self.syntax_grammar.external_tokens.len()
         );
         add_line!(self, "#define FIELD_COUNT {}", self.field_names.len());
+        if self.abi_version >= ABI_VERSION_WITH_NODE_TYPES {
+            add_line!(
+                self,
+                "#define SUPERTYPE_COUNT {}",
+                self.supertype_map().len()
+            );
+        }
         add_line!(
             self,
             "#define MAX_ALIAS_SEQUENCE_LENGTH {}",
@@ -590,6 +612,251 @@ impl Generator {
         add_line!(self, "");
     }
 
+    fn add_supertype_map(&mut self) {
+        let supertypes = self.supertype_map();
+        if supertypes.is_empty() {
+            return;
+        }
+
+        add_line!(
+            self,
+            "static const TSSymbol ts_supertype_symbols[SUPERTYPE_COUNT] = {{"
+        );
+        indent!(self);
+        for (id, _) in &supertypes {
+            add_line!(self, "{},", id);
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+
+        add_line!(
+            self,
+            "static const TSMapSlice ts_supertype_map_slices[SYMBOL_COUNT] = {{"
+        );
+        indent!(self);
+        let mut index = 0;
+        for (id, subtype_ids) in &supertypes {
+            add_line!(
+                self,
+                "[{}] = {{.index = {}, .length = {}}},",
+                id,
+                index,
+                subtype_ids.len()
+            );
+            index += subtype_ids.len();
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+
+        add_line!(
+            self,
+            "static const TSSymbol ts_supertype_map_entries[] = {{"
+        );
+        indent!(self);
+        let mut index = 0;
+        for (_, subtype_ids) in &supertypes {
+            add_line!(self, "[{}] =", index);
+            indent!(self);
+            for subtype_id in subtype_ids {
+                add_line!(self, "{},", subtype_id);
+            }
+            dedent!(self);
+            index += subtype_ids.len();
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+    }
+
+    fn add_child_info_map(&mut self) {
+        let (child_infos, type_lists) = self.child_info_map();
+        if child_infos.is_empty() {
+            return;
+        }
+
+        let mut type_list_offsets = Vec::with_capacity(type_lists.len());
+        let mut offset = 0;
+        for type_list in &type_lists {
+            type_list_offsets.push(offset);
+            offset += type_list.len();
+        }
+
+        add_line!(
+            self,
+            "static const TSMapSlice ts_child_info_map_slices[SYMBOL_COUNT + ALIAS_COUNT] = {{"
+        );
+        indent!(self);
+        let mut index = 0;
+        for (id, entries) in &child_infos {
+            add_line!(
+                self,
+                "[{}] = {{.index = {}, .length = {}}},",
+                id,
+                index,
+                entries.len()
+            );
+            index += entries.len();
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+
+        add_line!(
+            self,
+            "static const TSChildInfoEntry ts_child_info_entries[] = {{"
+        );
+        indent!(self);
+        let mut index = 0;
+        for (_, entries) in &child_infos {
+            add_line!(self, "[{}] =", index);
+            indent!(self);
+            for (field_id, multiple, required, type_list_index) in entries {
+                add_whitespace!(self);
+                add!(self, "{{{}", field_id);
+                if *multiple {
+                    add!(self, ", .multiple = true");
+                }
+                if *required {
+                    add!(self, ", .required = true");
+                }
+                add!(
+                    self,
+                    ", .types = {{.index = {}, .length = {}}}}},\n",
+                    type_list_offsets[*type_list_index],
+                    type_lists[*type_list_index].len()
+                );
+            }
+            dedent!(self);
+            index += entries.len();
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+
+        add_line!(self, "static const TSSymbol ts_child_type_entries[] = {{");
+        indent!(self);
+        for (type_list, offset) in type_lists.iter().zip(type_list_offsets) {
+            add_line!(self, "[{}] =", offset);
+            indent!(self);
+            for type_id in type_list {
+                add_line!(self, "{},", type_id);
+            }
+            dedent!(self);
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+    }
+
+    /// Get the C identifier of each supertype, along with those of its subtypes.
+    fn supertype_map(&self) -> Vec<(String, Vec<String>)> {
+        let node_type_ids = self.node_type_ids();
+        let mut result = Vec::new();
+        for node_type in &self.node_types {
+            if let (Some(id), Some(subtypes)) = (
+                node_type_ids.get(&(node_type.kind.as_str(), node_type.named)),
+                &node_type.subtypes,
+            ) {
+                result.push((id.clone(), self.node_type_id_list(&node_type_ids, subtypes)));
+            }
+        }
+        result
+    }
+
+    /// Get the C identifier of each node type that can have children, along with a
+    /// list of entries describing its fields and its children without fields. Each
+    /// entry contains a field id, the `multiple` and `required` flags, and the index
+    /// of a list of child types. The lists of child types are returned separately,
+    /// without duplicates.
+    fn child_info_map(&self) -> (Vec<(String, Vec<ChildInfoEntry>)>, Vec<ChildTypeList>) {
+        let node_type_ids = self.node_type_ids();
+        let mut child_infos = Vec::new();
+        let mut type_lists = Vec::<ChildTypeList>::new();
+        for node_type in &self.node_types {
+            let id = match node_type_ids.get(&(node_type.kind.as_str(), node_type.named)) {
+                Some(id) => id.clone(),
+                None => continue,
+            };
+            let fields = node_type
+                .children
+                .iter()
+                .map(|children| ("0".to_string(), children))
+                .chain(
+                    node_type
+                        .fields
+                        .iter()
+                        .flatten()
+                        .filter_map(|(name, field)| {
+                            if self.field_names.contains(name) {
+                                Some((self.field_id(name), field))
+                            } else {
+                                None
+                            }
+                        }),
+                );
+
+            let mut entries = Vec::new();
+            for (field_id, field) in fields {
+                let type_ids = self.node_type_id_list(&node_type_ids, &field.types);
+                let type_list_index = match type_lists.iter().position(|l| *l == type_ids) {
+                    Some(index) => index,
+                    None => {
+                        type_lists.push(type_ids);
+                        type_lists.len() - 1
+                    }
+                };
+                entries.push((field_id, field.multiple, field.required, type_list_index));
+            }
+            if !entries.is_empty() {
+                child_infos.push((id, entries));
+            }
+        }
+        (child_infos, type_lists)
+    }
+
+    /// Map the name and named-ness of each node type in `node-types.json` to the C
+    /// identifier of its public symbol.
+    fn node_type_ids(&self) -> HashMap<(&str, bool), String> {
+        let mut result = HashMap::new();
+        for symbol in &self.parse_table.symbols {
+            let key = match self.default_aliases.get(symbol) {
+                Some(alias) => (alias.value.as_str(), alias.is_named),
+                None => match self.metadata_for_symbol(*symbol) {
+                    (name, VariableType::Named) => (name, true),
+                    (name, VariableType::Anonymous) => (name, false),
+                    (name, VariableType::Hidden)
+                        if self.syntax_grammar.supertype_symbols.contains(symbol) =>
+                    {
+                        (name, true)
+                    }
+                    _ => continue,
+                },
+            };
+            result
+                .entry(key)
+                .or_insert_with(|| self.symbol_ids[&self.symbol_map[symbol]].clone());
+        }
+        for (alias, id) in &self.alias_ids {
+            result
+                .entry((alias.value.as_str(), alias.is_named))
+                .or_insert_with(|| id.clone());
+        }
+        result
+    }
+
+    fn node_type_id_list(
+        &self,
+        node_type_ids: &HashMap<(&str, bool), String>,
+        node_types: &[NodeTypeJSON],
+    ) -> Vec<String> {
+        node_types
+            .iter()
+            .filter_map(|t| node_type_ids.get(&(t.kind.as_str(), t.named)).cloned())
+            .collect()
+    }
+
     fn add_field_sequences(&mut self) {
         let mut flat_field_maps = vec![];
         let mut next_flat_field_map_index = 0;
@@ -1423,6 +1690,20 @@ impl Generator {
             add_line!(self, ".primary_state_ids = ts_primary_state_ids,");
         }
 
+        if self.abi_version >= ABI_VERSION_WITH_NODE_TYPES {
+            if !self.supertype_map().is_empty() {
+                add_line!(self, ".supertype_count = SUPERTYPE_COUNT,");
+                add_line!(self, ".supertype_symbols = ts_supertype_symbols,");
+                add_line!(self, ".supertype_map_slices = ts_supertype_map_slices,");
+                add_line!(self, ".supertype_map_entries = ts_supertype_map_entries,");
+            }
+            if !self.child_info_map().0.is_empty() {
+                add_line!(self, ".child_info_map_slices = ts_child_info_map_slices,");
+                add_line!(self, ".child_info_entries = ts_child_info_entries,");
+                add_line!(self, ".child_type_entries = ts_child_type_entries,");
+            }
+        }
+
         dedent!(self);
         add_line!(self, "}};");
         add_line!(self, "return &language;");
@@ -1629,6 +1910,7 @@ impl Generator {
 /// * `default_aliases` - A map describing the global rename rules that should apply.
 ///    the keys are symbols that are *always* aliased in the same way, and the values
 ///    are the aliases that are applied to those symbols.
+/// * `node_types` - The node types that are listed in the `node-types.json` file.
 /// * `abi_version` - The language ABI version that should be generated. Usually
 ///    you want Tree-sitter's current version, but right after making an ABI
 ///    change, it may be useful to generate code with the previous ABI.
@@ -1641,6 +1923,7 @@ pub(crate) fn render_c_code(
     syntax_grammar: SyntaxGrammar,
     lexical_grammar: LexicalGrammar,
     default_aliases: AliasMap,
+    node_types: Vec<NodeInfoJSON>,
     abi_version: usize,
 ) -> String {
     if !(ABI_VERSION_MIN..=ABI_VERSION_MAX).contains(&abi_version) {
@@ -1662,6 +1945,7 @@ pub(crate) fn render_c_code(
         syntax_grammar,
         lexical_grammar,
         default_aliases,
+        node_types,
         symbol_ids: HashMap::new(),
         symbol_order: HashMap::new(),
         alias_ids: HashMap::new(),
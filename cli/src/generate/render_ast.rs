@@ -0,0 +1,596 @@
+use super::node_types::{FieldInfoJSON, NodeInfoJSON, NodeTypeJSON};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const RESERVED_TYPE_NAMES: &[&str] = &[
+    "AstNode", "Iterator", "Node", "None", "Option", "Self", "Sized", "Some", "Vec",
+];
+
+const RESERVED_METHOD_NAMES: &[&str] = &["cast", "node"];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+macro_rules! add_line {
+    ($this: tt, $($arg: tt)*) => {
+        $this.buffer.write_fmt(format_args!($($arg)*)).unwrap();
+        $this.buffer += "\n";
+    }
+}
+
+struct Generator<'a> {
+    buffer: String,
+    node_types: &'a [NodeInfoJSON],
+    type_names: HashMap<&'a str, String>,
+    used_type_names: HashSet<String>,
+}
+
+/// The Rust type that is used to represent a set of possible child node types.
+enum ChildRepr {
+    Node,
+    Single(String),
+    Enum(String),
+}
+
+/// An enum type that still needs to be rendered, because it is only referenced
+/// by an accessor that has already been rendered.
+struct PendingEnum<'a> {
+    name: String,
+    doc: String,
+    types: &'a [NodeTypeJSON],
+}
+
+impl<'a> Generator<'a> {
+    fn generate(mut self) -> String {
+        self.assign_type_names();
+        self.add_header();
+
+        let node_types = self.node_types;
+        for node_type in node_types.iter().filter(|t| t.named) {
+            let name = self.type_names[node_type.kind.as_str()].clone();
+            if let Some(subtypes) = &node_type.subtypes {
+                self.add_enum(PendingEnum {
+                    name,
+                    doc: format!(
+                        "A syntax node of supertype `{}`, which can be any of its subtypes.",
+                        node_type.kind
+                    ),
+                    types: subtypes,
+                });
+            } else {
+                self.add_struct(&name, node_type);
+            }
+        }
+
+        self.buffer
+    }
+
+    fn assign_type_names(&mut self) {
+        self.used_type_names = RESERVED_TYPE_NAMES.iter().map(|s| s.to_string()).collect();
+        let node_types = self.node_types;
+        for node_type in node_types.iter().filter(|t| t.named) {
+            let name = unique_name(
+                &mut self.used_type_names,
+                type_name_for_kind(&node_type.kind),
+            );
+            self.type_names.insert(&node_type.kind, name);
+        }
+    }
+
+    fn add_header(&mut self) {
+        add_line!(
+            self,
+            "// This file is automatically generated by `tree-sitter generate` from"
+        );
+        add_line!(self, "// `src/node-types.json`. Do not edit it by hand.");
+        add_line!(self, "");
+        add_line!(self, "use tree_sitter::Node;");
+        add_line!(self, "");
+        add_line!(
+            self,
+            "/// A typed wrapper around a syntax node of one or more particular kinds."
+        );
+        add_line!(self, "pub trait AstNode<'tree>: Sized {{");
+        add_line!(
+            self,
+            "    /// Wrap the given node if its kind is represented by this type."
+        );
+        add_line!(self, "    fn cast(node: Node<'tree>) -> Option<Self>;");
+        add_line!(self, "");
+        add_line!(self, "    /// Get the underlying syntax node.");
+        add_line!(self, "    fn node(&self) -> Node<'tree>;");
+        add_line!(self, "}}");
+        add_line!(self, "");
+        add_line!(
+            self,
+            "fn children_by_field<'tree>(node: Node<'tree>, field_name: &str) -> impl Iterator<Item = Node<'tree>> {{"
+        );
+        add_line!(self, "    let mut cursor = node.walk();");
+        add_line!(
+            self,
+            "    node.children_by_field_name(field_name, &mut cursor)"
+        );
+        add_line!(self, "        .collect::<Vec<_>>()");
+        add_line!(self, "        .into_iter()");
+        add_line!(self, "}}");
+        add_line!(self, "");
+        add_line!(
+            self,
+            "fn children_without_field<'tree>(node: Node<'tree>) -> impl Iterator<Item = Node<'tree>> {{"
+        );
+        add_line!(self, "    let mut cursor = node.walk();");
+        add_line!(self, "    let mut result = Vec::new();");
+        add_line!(self, "    if cursor.goto_first_child() {{");
+        add_line!(self, "        loop {{");
+        add_line!(
+            self,
+            "            if cursor.field_name().is_none() && cursor.node().is_named() {{"
+        );
+        add_line!(self, "                result.push(cursor.node());");
+        add_line!(self, "            }}");
+        add_line!(self, "            if !cursor.goto_next_sibling() {{");
+        add_line!(self, "                break;");
+        add_line!(self, "            }}");
+        add_line!(self, "        }}");
+        add_line!(self, "    }}");
+        add_line!(self, "    result.into_iter()");
+        add_line!(self, "}}");
+    }
+
+    fn add_struct(&mut self, name: &str, node_type: &'a NodeInfoJSON) {
+        add_line!(self, "");
+        add_line!(self, "/// A syntax node of kind `{}`.", node_type.kind);
+        add_line!(self, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]");
+        add_line!(self, "pub struct {}<'tree>(Node<'tree>);", name);
+        add_line!(self, "");
+        add_line!(self, "impl<'tree> AstNode<'tree> for {}<'tree> {{", name);
+        add_line!(self, "    fn cast(node: Node<'tree>) -> Option<Self> {{");
+        add_line!(
+            self,
+            "        if node.is_named() && node.kind() == {:?} {{",
+            node_type.kind
+        );
+        add_line!(self, "            Some(Self(node))");
+        add_line!(self, "        }} else {{");
+        add_line!(self, "            None");
+        add_line!(self, "        }}");
+        add_line!(self, "    }}");
+        add_line!(self, "");
+        add_line!(self, "    fn node(&self) -> Node<'tree> {{");
+        add_line!(self, "        self.0");
+        add_line!(self, "    }}");
+        add_line!(self, "}}");
+
+        let fields = node_type.fields.as_ref().filter(|f| !f.is_empty());
+        if fields.is_none() && node_type.children.is_none() {
+            return;
+        }
+
+        let mut pending_enums = Vec::new();
+        let mut used_method_names = RESERVED_METHOD_NAMES
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<HashSet<_>>();
+
+        add_line!(self, "");
+        add_line!(self, "impl<'tree> {}<'tree> {{", name);
+        let mut is_first_method = true;
+        for (field_name, field_info) in fields.into_iter().flatten() {
+            if !is_first_method {
+                add_line!(self, "");
+            }
+            is_first_method = false;
+
+            let method_name =
+                unique_name(&mut used_method_names, method_name_for_field(field_name));
+            let repr = self.child_repr(
+                &format!("{}{}", name, type_name_for_kind(field_name)),
+                &format!(
+                    "A child in the `{}` field of a `{}` node.",
+                    field_name, node_type.kind
+                ),
+                field_info,
+                &mut pending_enums,
+            );
+            let nodes = format!("children_by_field(self.0, {:?})", field_name);
+            self.add_accessor(
+                &method_name,
+                &format!("the node's `{}` field", field_name),
+                &nodes,
+                &repr,
+                field_info,
+            );
+        }
+        if let Some(children) = &node_type.children {
+            if !is_first_method {
+                add_line!(self, "");
+            }
+            let method_name = if children.multiple {
+                "children"
+            } else {
+                "child"
+            };
+            let method_name = unique_name(&mut used_method_names, method_name.to_string());
+            let repr = self.child_repr(
+                &format!("{}Child", name),
+                &format!(
+                    "A child of a `{}` node that has no field name.",
+                    node_type.kind
+                ),
+                children,
+                &mut pending_enums,
+            );
+            self.add_accessor(
+                &method_name,
+                "the node's named children that have no field name",
+                "children_without_field(self.0)",
+                &repr,
+                children,
+            );
+        }
+        add_line!(self, "}}");
+
+        for pending_enum in pending_enums {
+            self.add_enum(pending_enum);
+        }
+    }
+
+    fn add_accessor(
+        &mut self,
+        method_name: &str,
+        description: &str,
+        nodes: &str,
+        repr: &ChildRepr,
+        info: &FieldInfoJSON,
+    ) {
+        let (item_type, cast) = match repr {
+            ChildRepr::Node => ("Node<'tree>".to_string(), None),
+            ChildRepr::Single(name) | ChildRepr::Enum(name) => {
+                (format!("{}<'tree>", name), Some(format!("{}::cast", name)))
+            }
+        };
+
+        if info.multiple {
+            add_line!(self, "    /// Iterate over {}.", description);
+            if info.required {
+                add_line!(self, "    ///");
+                add_line!(
+                    self,
+                    "    /// The grammar requires at least one such child, so this is only empty"
+                );
+                add_line!(self, "    /// in trees that contain errors.");
+            }
+            add_line!(
+                self,
+                "    pub fn {}(&self) -> impl Iterator<Item = {}> {{",
+                method_name,
+                item_type
+            );
+            if let Some(cast) = cast {
+                add_line!(self, "        {}.filter_map({})", nodes, cast);
+            } else {
+                add_line!(self, "        {}", nodes);
+            }
+        } else {
+            add_line!(self, "    /// Get {}.", description);
+            if info.required {
+                add_line!(self, "    ///");
+                add_line!(
+                    self,
+                    "    /// The grammar requires this child, so this only returns `None` in"
+                );
+                add_line!(self, "    /// trees that contain errors.");
+            }
+            add_line!(
+                self,
+                "    pub fn {}(&self) -> Option<{}> {{",
+                method_name,
+                item_type
+            );
+            if let Some(cast) = cast {
+                add_line!(self, "        {}.find_map({})", nodes, cast);
+            } else {
+                add_line!(self, "        {}.next()", nodes);
+            }
+        }
+        add_line!(self, "    }}");
+    }
+
+    fn add_enum(&mut self, pending_enum: PendingEnum<'a>) {
+        let PendingEnum { name, doc, types } = pending_enum;
+        let mut used_variant_names = HashSet::new();
+        let named_variants = types
+            .iter()
+            .filter(|t| t.named)
+            .map(|t| {
+                let type_name = self.type_names[t.kind.as_str()].clone();
+                used_variant_names.insert(type_name.clone());
+                type_name
+            })
+            .collect::<Vec<_>>();
+        let anonymous_kinds = types
+            .iter()
+            .filter(|t| !t.named)
+            .map(|t| format!("{:?}", t.kind))
+            .collect::<Vec<_>>();
+        let token_variant = if anonymous_kinds.is_empty() {
+            None
+        } else {
+            Some(unique_name(&mut used_variant_names, "Token".to_string()))
+        };
+
+        add_line!(self, "");
+        add_line!(self, "/// {}", doc);
+        add_line!(self, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]");
+        add_line!(self, "pub enum {}<'tree> {{", name);
+        for variant in &named_variants {
+            add_line!(self, "    {}({}<'tree>),", variant, variant);
+        }
+        if let Some(token_variant) = &token_variant {
+            add_line!(
+                self,
+                "    /// An anonymous node: one of {}.",
+                anonymous_kinds.join(", ")
+            );
+            add_line!(self, "    {}(Node<'tree>),", token_variant);
+        }
+        add_line!(self, "}}");
+        add_line!(self, "");
+        add_line!(self, "impl<'tree> AstNode<'tree> for {}<'tree> {{", name);
+        add_line!(self, "    fn cast(node: Node<'tree>) -> Option<Self> {{");
+        for variant in &named_variants {
+            add_line!(self, "        if let Some(n) = {}::cast(node) {{", variant);
+            add_line!(self, "            return Some(Self::{}(n));", variant);
+            add_line!(self, "        }}");
+        }
+        if let Some(token_variant) = &token_variant {
+            add_line!(
+                self,
+                "        if !node.is_named() && matches!(node.kind(), {}) {{",
+                anonymous_kinds.join(" | ")
+            );
+            add_line!(
+                self,
+                "            return Some(Self::{}(node));",
+                token_variant
+            );
+            add_line!(self, "        }}");
+        }
+        add_line!(self, "        None");
+        add_line!(self, "    }}");
+        add_line!(self, "");
+        add_line!(self, "    fn node(&self) -> Node<'tree> {{");
+        add_line!(self, "        match self {{");
+        for variant in &named_variants {
+            add_line!(self, "            Self::{}(n) => n.node(),", variant);
+        }
+        if let Some(token_variant) = &token_variant {
+            add_line!(self, "            Self::{}(n) => *n,", token_variant);
+        }
+        add_line!(self, "        }}");
+        add_line!(self, "    }}");
+        add_line!(self, "}}");
+    }
+
+    fn child_repr(
+        &mut self,
+        enum_name: &str,
+        enum_doc: &str,
+        info: &'a FieldInfoJSON,
+        pending_enums: &mut Vec<PendingEnum<'a>>,
+    ) -> ChildRepr {
+        let mut named_types = info.types.iter().filter(|t| t.named);
+        let has_anonymous_types = info.types.iter().any(|t| !t.named);
+
+        // Fall back to untyped nodes if any of the named types is unknown.
+        if info
+            .types
+            .iter()
+            .any(|t| t.named && !self.type_names.contains_key(t.kind.as_str()))
+        {
+            return ChildRepr::Node;
+        }
+
+        match (named_types.next(), named_types.next(), has_anonymous_types) {
+            (None, _, _) => ChildRepr::Node,
+            (Some(named_type), None, false) => {
+                ChildRepr::Single(self.type_names[named_type.kind.as_str()].clone())
+            }
+            _ => {
+                let name = unique_name(&mut self.used_type_names, enum_name.to_string());
+                pending_enums.push(PendingEnum {
+                    name: name.clone(),
+                    doc: enum_doc.to_string(),
+                    types: &info.types,
+                });
+                ChildRepr::Enum(name)
+            }
+        }
+    }
+}
+
+fn type_name_for_kind(kind: &str) -> String {
+    let mut result = String::with_capacity(kind.len());
+    let mut capitalize = true;
+    for c in kind.chars() {
+        if c.is_ascii_alphanumeric() {
+            if capitalize {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+            capitalize = false;
+        } else {
+            capitalize = true;
+        }
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert_str(0, "Kind");
+    }
+    result
+}
+
+fn method_name_for_field(field_name: &str) -> String {
+    let mut result = String::with_capacity(field_name.len());
+    let mut prev_is_lowercase = false;
+    for c in field_name.chars() {
+        if c.is_ascii_uppercase() && prev_is_lowercase {
+            result.push('_');
+        }
+        if c.is_ascii_alphanumeric() {
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push('_');
+        }
+        prev_is_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        result.insert(0, '_');
+    }
+    if RUST_KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+    result
+}
+
+fn unique_name(used_names: &mut HashSet<String>, name: String) -> String {
+    let mut result = name.clone();
+    let mut suffix_number = 1;
+    while used_names.contains(&result) {
+        suffix_number += 1;
+        result = format!("{}{}", name, suffix_number);
+    }
+    used_names.insert(result.clone());
+    result
+}
+
+/// Returns a String of Rust code that defines a typed wrapper for each kind of
+/// named node described by the given node types.
+///
+/// Structs are generated for concrete node kinds, with one accessor per field,
+/// and enums are generated for supertypes and for fields that can contain more
+/// than one kind of node.
+pub(crate) fn render_rust_ast(node_types: &[NodeInfoJSON]) -> String {
+    Generator {
+        buffer: String::new(),
+        node_types,
+        type_names: HashMap::new(),
+        used_type_names: HashSet::new(),
+    }
+    .generate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::grammars::{InputGrammar, Variable, VariableType};
+    use crate::generate::node_types::{generate_node_types_json, get_variable_info};
+    use crate::generate::prepare_grammar::prepare_grammar;
+    use crate::generate::rules::Rule;
+
+    #[test]
+    fn test_render_rust_ast() {
+        let code = get_rust_ast(InputGrammar {
+            supertype_symbols: vec!["_expression".to_string()],
+            variables: vec![
+                Variable {
+                    name: "program".to_string(),
+                    kind: VariableType::Named,
+                    rule: Rule::repeat(Rule::named("call")),
+                },
+                Variable {
+                    name: "call".to_string(),
+                    kind: VariableType::Named,
+                    rule: Rule::seq(vec![
+                        Rule::field("function".to_string(), Rule::named("identifier")),
+                        Rule::string("("),
+                        Rule::repeat(Rule::field(
+                            "arguments".to_string(),
+                            Rule::named("_expression"),
+                        )),
+                        Rule::string(")"),
+                        Rule::choice(vec![
+                            Rule::field("type".to_string(), Rule::string("!")),
+                            Rule::Blank,
+                        ]),
+                    ]),
+                },
+                Variable {
+                    name: "_expression".to_string(),
+                    kind: VariableType::Hidden,
+                    rule: Rule::choice(vec![
+                        Rule::named("identifier"),
+                        Rule::named("call"),
+                        Rule::string("null"),
+                    ]),
+                },
+                Variable {
+                    name: "identifier".to_string(),
+                    kind: VariableType::Named,
+                    rule: Rule::pattern("\\w+"),
+                },
+            ],
+            ..Default::default()
+        });
+
+        assert!(code.contains("pub enum Expression<'tree> {"));
+        assert!(code.contains("    Call(Call<'tree>),"));
+        assert!(code.contains("    Identifier(Identifier<'tree>),"));
+        assert!(code.contains("    Token(Node<'tree>),"));
+        assert!(code.contains("if !node.is_named() && matches!(node.kind(), \"null\") {"));
+
+        assert!(code.contains("pub struct Call<'tree>(Node<'tree>);"));
+        assert!(code.contains("if node.is_named() && node.kind() == \"call\" {"));
+        assert!(code.contains("pub fn function(&self) -> Option<Identifier<'tree>> {"));
+        assert!(
+            code.contains("pub fn arguments(&self) -> impl Iterator<Item = Expression<'tree>> {")
+        );
+        assert!(code.contains("pub fn type_(&self) -> Option<Node<'tree>> {"));
+
+        assert!(code.contains("pub struct Program<'tree>(Node<'tree>);"));
+        assert!(code.contains("pub fn children(&self) -> impl Iterator<Item = Call<'tree>> {"));
+
+        assert!(code.contains("pub struct Identifier<'tree>(Node<'tree>);"));
+        assert!(!code.contains("impl<'tree> Identifier<'tree> {"));
+    }
+
+    #[test]
+    fn test_render_rust_ast_names() {
+        assert_eq!(type_name_for_kind("binary_expression"), "BinaryExpression");
+        assert_eq!(type_name_for_kind("_statement"), "Statement");
+        assert_eq!(type_name_for_kind("escape-sequence"), "EscapeSequence");
+        assert_eq!(type_name_for_kind("3d_point"), "Kind3dPoint");
+        assert_eq!(method_name_for_field("type"), "type_");
+        assert_eq!(method_name_for_field("returnType"), "return_type");
+
+        let mut used_names = RESERVED_TYPE_NAMES.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            unique_name(&mut used_names, "Option".to_string()),
+            "Option2"
+        );
+        assert_eq!(
+            unique_name(&mut used_names, "Option".to_string()),
+            "Option3"
+        );
+        assert_eq!(unique_name(&mut used_names, "Block".to_string()), "Block");
+    }
+
+    fn get_rust_ast(grammar: InputGrammar) -> String {
+        let (syntax_grammar, lexical_grammar, _, default_aliases) =
+            prepare_grammar(&grammar).unwrap();
+        let variable_info =
+            get_variable_info(&syntax_grammar, &lexical_grammar, &default_aliases).unwrap();
+        let node_types = generate_node_types_json(
+            &syntax_grammar,
+            &lexical_grammar,
+            &default_aliases,
+            &variable_info,
+        );
+        render_rust_ast(&node_types)
+    }
+}
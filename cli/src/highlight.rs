@@ -342,9 +342,8 @@ pub fn ansi(
     config: &HighlightConfiguration,
     print_time: bool,
     cancellation_flag: Option<&AtomicUsize>,
+    output: &mut impl io::Write,
 ) -> Result<()> {
-    let stdout = io::stdout();
-    let mut stdout = stdout.lock();
     let time = Instant::now();
     let mut highlighter = Highlighter::new();
 
@@ -366,7 +365,7 @@ pub fn ansi(
                     .last()
                     .unwrap()
                     .paint(&source[start..end])
-                    .write_to(&mut stdout)?;
+                    .write_to(output)?;
             }
         }
     }
@@ -385,11 +384,8 @@ pub fn html(
     config: &HighlightConfiguration,
     quiet: bool,
     print_time: bool,
+    output: &mut impl io::Write,
 ) -> Result<()> {
-    use std::io::Write;
-
-    let stdout = io::stdout();
-    let mut stdout = stdout.lock();
     let time = Instant::now();
     let cancellation_flag = util::cancel_on_stdin();
     let mut highlighter = Highlighter::new();
@@ -408,17 +404,17 @@ pub fn html(
     })?;
 
     if !quiet {
-        write!(&mut stdout, "<table>\n")?;
+        write!(output, "<table>\n")?;
         for (i, line) in renderer.lines().enumerate() {
             write!(
-                &mut stdout,
+                output,
                 "<tr><td class=line-number>{}</td><td class=line>{}</td></tr>\n",
                 i + 1,
                 line
             )?;
         }
 
-        write!(&mut stdout, "</table>\n")?;
+        write!(output, "</table>\n")?;
     }
 
     if print_time {
@@ -1,9 +1,13 @@
+pub mod diff;
 pub mod generate;
 pub mod highlight;
 pub mod logger;
 pub mod parse;
 pub mod playground;
 pub mod query;
+pub mod query_format;
+pub mod query_lint;
+pub mod query_syntax;
 pub mod query_testing;
 pub mod tags;
 pub mod test;
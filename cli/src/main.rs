@@ -1,11 +1,13 @@
 use anyhow::{anyhow, Context, Result};
 use clap::{App, AppSettings, Arg, SubCommand};
 use glob::glob;
+use std::io::{self, Write};
 use std::path::Path;
-use std::{env, fs, u64};
+use std::{collections::HashMap, env, fs, u64};
+use tree_sitter::ParserPool;
 use tree_sitter_cli::{
-    generate, highlight, logger, parse, playground, query, tags, test, test_highlight, test_tags,
-    util, wasm,
+    diff, generate, highlight, logger, parse, playground, query, tags, test, test_highlight,
+    test_tags, util, wasm,
 };
 use tree_sitter_config::Config;
 use tree_sitter_loader as loader;
@@ -76,6 +78,12 @@ fn run() -> Result<()> {
         .long("quiet")
         .short("q");
 
+    let jobs_arg = Arg::with_name("jobs")
+        .help("The number of files to process in parallel (0 to use all available cores)")
+        .long("jobs")
+        .short("j")
+        .takes_value(true);
+
     let matches = App::new("tree-sitter")
         .author("Max Brunsfeld <maxbrunsfeld@gmail.com>")
         .about("Generates and tests parsers")
@@ -99,7 +107,9 @@ fn run() -> Result<()> {
                         .help(&format!(
                             concat!(
                                 "Select the language ABI version to generate (default {}).\n",
-                                "Use --abi=latest to generate the newest supported version ({}).",
+                                "Use --abi=latest to generate the newest supported version ({}).\n",
+                                "Supertype and child type information is only included in ",
+                                "version 15 and later.",
                             ),
                             DEFAULT_GENERATE_ABI_VERSION,
                             tree_sitter::LANGUAGE_VERSION,
@@ -127,10 +137,15 @@ fn run() -> Result<()> {
                 .arg(Arg::with_name("debug-xml").long("xml").short("x"))
                 .arg(
                     Arg::with_name("stat")
-                        .help("Show parsing statistic")
+                        .help("Show parsing statistics, and the size of each syntax tree")
                         .long("stat")
                         .short("s"),
                 )
+                .arg(
+                    Arg::with_name("errors")
+                        .help("Print every syntax error, along with the symbols that were expected")
+                        .long("errors"),
+                )
                 .arg(
                     Arg::with_name("timeout")
                         .help("Interrupt the parsing process by timeout (µs)")
@@ -139,6 +154,7 @@ fn run() -> Result<()> {
                 )
                 .arg(&time_arg)
                 .arg(&quiet_arg)
+                .arg(&jobs_arg)
                 .arg(
                     Arg::with_name("edits")
                         .help("Apply edits in the format: \"row,col del_count insert_text\"")
@@ -169,7 +185,46 @@ fn run() -> Result<()> {
                 )
                 .arg(&scope_arg)
                 .arg(Arg::with_name("captures").long("captures").short("c"))
-                .arg(Arg::with_name("test").long("test")),
+                .arg(Arg::with_name("test").long("test"))
+                .arg(
+                    Arg::with_name("lint")
+                        .help("Check the query for patterns that can never match, instead of running it")
+                        .long("lint"),
+                )
+                .arg(&jobs_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt-query")
+                .about("Format query files")
+                .arg(
+                    Arg::with_name("check")
+                        .help("List the files that are not formatted, instead of formatting them")
+                        .long("check"),
+                )
+                .arg(&scope_arg)
+                .arg(&paths_file_arg)
+                .arg(
+                    Arg::with_name("paths")
+                        .help("The query file(s) to format")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compare the syntax trees of two files")
+                .arg(
+                    Arg::with_name("old-path")
+                        .help("The file to compare from")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("new-path")
+                        .help("The file to compare to")
+                        .index(2)
+                        .required(true),
+                )
+                .arg(&scope_arg),
         )
         .subcommand(
             SubCommand::with_name("tags")
@@ -177,6 +232,7 @@ fn run() -> Result<()> {
                 .arg(&scope_arg)
                 .arg(&time_arg)
                 .arg(&quiet_arg)
+                .arg(&jobs_arg)
                 .arg(&paths_file_arg)
                 .arg(&paths_arg),
         )
@@ -213,6 +269,7 @@ fn run() -> Result<()> {
                 .arg(&scope_arg)
                 .arg(&time_arg)
                 .arg(&quiet_arg)
+                .arg(&jobs_arg)
                 .arg(&paths_file_arg)
                 .arg(&paths_arg),
         )
@@ -356,8 +413,15 @@ fn run() -> Result<()> {
             let edits = matches
                 .values_of("edits")
                 .map_or(Vec::new(), |e| e.collect());
+            let jobs = jobs_from_matches(matches)?;
             let cancellation_flag = util::cancel_on_stdin();
 
+            if jobs != 1 && (debug || debug_graph) {
+                return Err(anyhow!(
+                    "The --jobs option cannot be combined with --debug or --debug-graph"
+                ));
+            }
+
             if debug {
                 // For augmenting debug logging in external scanners
                 env::set_var("TREE_SITTER_DEBUG", "1");
@@ -377,36 +441,56 @@ fn run() -> Result<()> {
             loader.find_all_languages(&loader_config)?;
 
             let should_track_stats = matches.is_present("stat");
+            let should_print_errors = matches.is_present("errors");
             let mut stats = parse::Stats::default();
 
-            for path in paths {
-                let path = Path::new(&path);
+            let mut files = Vec::new();
+            for path in &paths {
+                let path = Path::new(path);
                 let language =
                     loader.select_language(path, &current_dir, matches.value_of("scope"))?;
+                files.push((path, language));
+            }
 
-                let this_file_errored = parse::parse_file_at_path(
-                    language,
-                    path,
-                    &edits,
-                    max_path_length,
-                    quiet,
-                    time,
-                    timeout,
-                    debug,
-                    debug_graph,
-                    debug_xml,
-                    Some(&cancellation_flag),
-                )?;
-
-                if should_track_stats {
-                    stats.total_parses += 1;
-                    if !this_file_errored {
-                        stats.successful_parses += 1;
+            let stdout = io::stdout();
+            ParserPool::new(jobs).map_in_order(
+                &files,
+                |parser, (path, language)| {
+                    let mut output = Vec::new();
+                    let result = parse::parse_file_at_path(
+                        parser,
+                        *language,
+                        path,
+                        &edits,
+                        max_path_length,
+                        quiet,
+                        time,
+                        should_track_stats,
+                        should_print_errors,
+                        timeout,
+                        debug,
+                        debug_graph,
+                        debug_xml,
+                        Some(&cancellation_flag),
+                        &mut output,
+                    );
+                    (result, output)
+                },
+                |(result, output)| -> Result<()> {
+                    stdout.lock().write_all(&output)?;
+                    let this_file_errored = result?;
+
+                    if should_track_stats {
+                        stats.total_parses += 1;
+                        if !this_file_errored {
+                            stats.successful_parses += 1;
+                        }
                     }
-                }
 
-                has_error |= this_file_errored;
-            }
+                    has_error |= this_file_errored;
+                    Ok(())
+                },
+            )?;
 
             if should_track_stats {
                 println!("{}", stats)
@@ -417,6 +501,51 @@ fn run() -> Result<()> {
             }
         }
 
+        ("query", Some(matches)) if matches.is_present("lint") => {
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let query_path = Path::new(matches.value_of("query-path").unwrap());
+            let paths = if matches.is_present("paths") || matches.is_present("paths-file") {
+                collect_paths(matches.value_of("paths-file"), matches.values_of("paths"))?
+            } else {
+                Vec::new()
+            };
+            let language = loader.select_language(
+                paths.first().map_or(current_dir.as_path(), Path::new),
+                &current_dir,
+                matches.value_of("scope"),
+            )?;
+            let node_types_path = query::find_node_types_path(query_path, &current_dir)?;
+
+            // Captures in highlight queries are checked against the theme's highlight names.
+            let is_highlights_query = query_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("")
+                .starts_with("highlights");
+            let highlight_names = if is_highlights_query {
+                let theme_config: tree_sitter_cli::highlight::ThemeConfig = config.get()?;
+                Some(theme_config.theme.highlight_names)
+            } else {
+                None
+            };
+
+            let warning_count = query::lint_query_file(
+                language,
+                query_path,
+                &node_types_path,
+                highlight_names.as_deref(),
+                &mut io::stdout().lock(),
+            )?;
+            if warning_count > 0 {
+                return Err(anyhow!(
+                    "Found {} warning(s) in {:?}",
+                    warning_count,
+                    query_path
+                ));
+            }
+        }
+
         ("query", Some(matches)) => {
             let ordered_captures = matches.values_of("captures").is_some();
             let paths = collect_paths(matches.value_of("paths-file"), matches.values_of("paths"))?;
@@ -433,6 +562,7 @@ fn run() -> Result<()> {
                 r[0].parse().unwrap()..r[1].parse().unwrap()
             });
             let should_test = matches.is_present("test");
+            let jobs = jobs_from_matches(matches)?;
             query::query_files_at_paths(
                 language,
                 paths,
@@ -440,9 +570,55 @@ fn run() -> Result<()> {
                 ordered_captures,
                 range,
                 should_test,
+                jobs,
             )?;
         }
 
+        ("fmt-query", Some(matches)) => {
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let paths = collect_paths(matches.value_of("paths-file"), matches.values_of("paths"))?;
+            let check = matches.is_present("check");
+            let scope = matches.value_of("scope");
+            let mut languages_by_grammar_path = HashMap::new();
+            let unformatted_count = query::format_query_files(
+                &paths,
+                check,
+                |path| {
+                    if scope.is_some() {
+                        return loader.select_language(path, &current_dir, scope);
+                    }
+                    let grammar_path = query::find_grammar_path(path, &current_dir)?;
+                    if let Some(language) = languages_by_grammar_path.get(&grammar_path) {
+                        return Ok(*language);
+                    }
+                    let language = *loader
+                        .languages_at_path(&grammar_path)?
+                        .first()
+                        .ok_or_else(|| anyhow!("No language found in {:?}", grammar_path))?;
+                    languages_by_grammar_path.insert(grammar_path, language);
+                    Ok(language)
+                },
+                &mut io::stdout().lock(),
+            )?;
+            if check && unformatted_count > 0 {
+                return Err(anyhow!(
+                    "{} query file(s) are not formatted",
+                    unformatted_count
+                ));
+            }
+        }
+
+        ("diff", Some(matches)) => {
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let old_path = Path::new(matches.value_of("old-path").unwrap());
+            let new_path = Path::new(matches.value_of("new-path").unwrap());
+            let language =
+                loader.select_language(new_path, &current_dir, matches.value_of("scope"))?;
+            diff::diff_files_at_paths(language, old_path, new_path, &mut io::stdout().lock())?;
+        }
+
         ("tags", Some(matches)) => {
             let loader_config = config.get()?;
             loader.find_all_languages(&loader_config)?;
@@ -453,6 +629,7 @@ fn run() -> Result<()> {
                 &paths,
                 matches.is_present("quiet"),
                 matches.is_present("time"),
+                jobs_from_matches(matches)?,
             )?;
         }
 
@@ -481,8 +658,9 @@ fn run() -> Result<()> {
                 }
             }
 
-            for path in paths {
-                let path = Path::new(&path);
+            let mut files = Vec::new();
+            for path in &paths {
+                let path = Path::new(path);
                 let (language, language_config) = match lang {
                     Some(v) => v,
                     None => match loader.language_configuration_for_file_name(path)? {
@@ -495,6 +673,19 @@ fn run() -> Result<()> {
                 };
 
                 if let Some(highlight_config) = language_config.highlight_config(language)? {
+                    files.push((path, highlight_config));
+                } else {
+                    eprintln!("No syntax highlighting config found for path {:?}", path);
+                }
+            }
+
+            let stdout = io::stdout();
+            ParserPool::new(jobs_from_matches(matches)?).map_in_order(
+                &files,
+                |_, (path, highlight_config)| -> Result<Vec<u8>> {
+                    // Each file is highlighted with its own highlighter, which parses the
+                    // file and its injections with its own parser.
+                    let mut output = Vec::new();
                     let source = fs::read(path)?;
                     if html_mode {
                         highlight::html(
@@ -504,6 +695,7 @@ fn run() -> Result<()> {
                             highlight_config,
                             quiet,
                             time,
+                            &mut output,
                         )?;
                     } else {
                         highlight::ansi(
@@ -513,12 +705,16 @@ fn run() -> Result<()> {
                             highlight_config,
                             time,
                             Some(&cancellation_flag),
+                            &mut output,
                         )?;
                     }
-                } else {
-                    eprintln!("No syntax highlighting config found for path {:?}", path);
-                }
-            }
+                    Ok(output)
+                },
+                |output| -> Result<()> {
+                    stdout.lock().write_all(&output?)?;
+                    Ok(())
+                },
+            )?;
 
             if html_mode && !quiet {
                 println!("{}", highlight::HTML_FOOTER);
@@ -564,6 +760,13 @@ fn run() -> Result<()> {
     Ok(())
 }
 
+fn jobs_from_matches(matches: &clap::ArgMatches) -> Result<usize> {
+    matches.value_of("jobs").map_or(Ok(1), |jobs| {
+        jobs.parse()
+            .with_context(|| format!("Invalid number of jobs {:?}", jobs))
+    })
+}
+
 fn collect_paths<'a>(
     paths_file: Option<&str>,
     paths: Option<impl Iterator<Item = &'a str>>,
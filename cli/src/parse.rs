@@ -4,8 +4,8 @@ use std::io::{self, Write};
 use std::path::Path;
 use std::sync::atomic::AtomicUsize;
 use std::time::Instant;
-use std::{fmt, fs, usize};
-use tree_sitter::{InputEdit, Language, LogType, Parser, Point, Tree};
+use std::{fmt, fs, mem, usize};
+use tree_sitter::{InputEdit, Language, LogType, Parser, Point, SexpOptions, Tree};
 
 #[derive(Debug)]
 pub struct Edit {
@@ -31,20 +31,23 @@ impl fmt::Display for Stats {
 }
 
 pub fn parse_file_at_path(
+    parser: &mut Parser,
     language: Language,
     path: &Path,
     edits: &Vec<&str>,
     max_path_length: usize,
     quiet: bool,
     print_time: bool,
+    print_stats: bool,
+    print_errors: bool,
     timeout: u64,
     debug: bool,
     debug_graph: bool,
     debug_xml: bool,
     cancellation_flag: Option<&AtomicUsize>,
+    output: &mut impl Write,
 ) -> Result<bool> {
     let mut _log_session = None;
-    let mut parser = Parser::new();
     parser.set_language(language)?;
     let mut source_code =
         fs::read(path).with_context(|| format!("Error reading source file {:?}", path))?;
@@ -58,7 +61,7 @@ pub fn parse_file_at_path(
 
     // Render an HTML graph if `--debug-graph` was passed
     if debug_graph {
-        _log_session = Some(util::log_graphs(&mut parser, "log.html")?);
+        _log_session = Some(util::log_graphs(parser, "log.html")?);
     }
     // Log to stderr if `--debug` was passed
     else if debug {
@@ -73,18 +76,17 @@ pub fn parse_file_at_path(
     let time = Instant::now();
     let tree = parser.parse(&source_code, None);
 
-    let stdout = io::stdout();
-    let mut stdout = stdout.lock();
-
     if let Some(mut tree) = tree {
         if debug_graph && !edits.is_empty() {
             println!("BEFORE:\n{}", String::from_utf8_lossy(&source_code));
         }
 
+        let mut old_tree = None;
         for (i, edit) in edits.iter().enumerate() {
             let edit = parse_edit_flag(&source_code, edit)?;
             perform_edit(&mut tree, &mut source_code, &edit);
-            tree = parser.parse(&source_code, Some(&tree)).unwrap();
+            let new_tree = parser.parse(&source_code, Some(&tree)).unwrap();
+            old_tree = Some(mem::replace(&mut tree, new_tree));
 
             if debug_graph {
                 println!("AFTER {}:\n{}", i, String::from_utf8_lossy(&source_code));
@@ -96,59 +98,17 @@ pub fn parse_file_at_path(
         let mut cursor = tree.walk();
 
         if !quiet {
-            let mut needs_newline = false;
-            let mut indent_level = 0;
-            let mut did_visit_children = false;
-            loop {
-                let node = cursor.node();
-                let is_named = node.is_named();
-                if did_visit_children {
-                    if is_named {
-                        stdout.write(b")")?;
-                        needs_newline = true;
-                    }
-                    if cursor.goto_next_sibling() {
-                        did_visit_children = false;
-                    } else if cursor.goto_parent() {
-                        did_visit_children = true;
-                        indent_level -= 1;
-                    } else {
-                        break;
-                    }
-                } else {
-                    if is_named {
-                        if needs_newline {
-                            stdout.write(b"\n")?;
-                        }
-                        for _ in 0..indent_level {
-                            stdout.write(b"  ")?;
-                        }
-                        let start = node.start_position();
-                        let end = node.end_position();
-                        if let Some(field_name) = cursor.field_name() {
-                            write!(&mut stdout, "{}: ", field_name)?;
-                        }
-                        write!(
-                            &mut stdout,
-                            "({} [{}, {}] - [{}, {}]",
-                            node.kind(),
-                            start.row,
-                            start.column,
-                            end.row,
-                            end.column
-                        )?;
-                        needs_newline = true;
-                    }
-                    if cursor.goto_first_child() {
-                        did_visit_children = false;
-                        indent_level += 1;
-                    } else {
-                        did_visit_children = true;
-                    }
-                }
-            }
-            cursor.reset(tree.root_node());
-            println!("");
+            let mut sexp = String::new();
+            tree.root_node().write_sexp(
+                &mut sexp,
+                &SexpOptions {
+                    indent: Some("  "),
+                    ranges: true,
+                    markers: false,
+                    ..Default::default()
+                },
+            )?;
+            writeln!(output, "{}", sexp)?;
         }
 
         if debug_xml {
@@ -162,7 +122,7 @@ pub fn parse_file_at_path(
                 if did_visit_children {
                     if is_named {
                         let tag = tags.pop();
-                        write!(&mut stdout, "</{}>\n", tag.expect("there is a tag"))?;
+                        write!(output, "</{}>\n", tag.expect("there is a tag"))?;
                         needs_newline = true;
                     }
                     if cursor.goto_next_sibling() {
@@ -176,16 +136,16 @@ pub fn parse_file_at_path(
                 } else {
                     if is_named {
                         if needs_newline {
-                            stdout.write(b"\n")?;
+                            output.write(b"\n")?;
                         }
                         for _ in 0..indent_level {
-                            stdout.write(b"  ")?;
+                            output.write(b"  ")?;
                         }
-                        write!(&mut stdout, "<{}", node.kind())?;
+                        write!(output, "<{}", node.kind())?;
                         if let Some(field_name) = cursor.field_name() {
-                            write!(&mut stdout, " type=\"{}\"", field_name)?;
+                            write!(output, " type=\"{}\"", field_name)?;
                         }
-                        write!(&mut stdout, ">")?;
+                        write!(output, ">")?;
                         tags.push(node.kind());
                         needs_newline = true;
                     }
@@ -198,63 +158,88 @@ pub fn parse_file_at_path(
                         let end = node.end_byte();
                         let value =
                             std::str::from_utf8(&source_code[start..end]).expect("has a string");
-                        write!(&mut stdout, "{}", html_escape::encode_text(value))?;
+                        write!(output, "{}", html_escape::encode_text(value))?;
                     }
                 }
             }
             cursor.reset(tree.root_node());
-            println!("");
+            writeln!(output)?;
         }
 
         let mut first_error = None;
-        loop {
-            let node = cursor.node();
-            if node.has_error() {
-                if node.is_error() || node.is_missing() {
-                    first_error = Some(node);
-                    break;
-                } else {
-                    if !cursor.goto_first_child() {
-                        break;
-                    }
-                }
-            } else if !cursor.goto_next_sibling() {
+        let mut nodes = tree.preorder();
+        while let Some(node) = nodes.next() {
+            if node.is_error() || node.is_missing() {
+                first_error = Some(node);
                 break;
+            } else if !node.has_error() {
+                nodes.skip_children();
             }
         }
 
-        if first_error.is_some() || print_time {
+        if print_errors {
+            for error in tree.root_node().syntax_errors(&source_code) {
+                let start = error.range.start_point;
+                writeln!(
+                    output,
+                    "{}:{}:{}: error: {}",
+                    path.to_str().unwrap(),
+                    start.row + 1,
+                    start.column + 1,
+                    error.message
+                )?;
+            }
+        }
+
+        if (first_error.is_some() && !print_errors) || print_time {
             write!(
-                &mut stdout,
+                output,
                 "{:width$}\t{} ms",
                 path.to_str().unwrap(),
                 duration_ms,
                 width = max_path_length
             )?;
-            if let Some(node) = first_error {
+            if let Some(node) = first_error.filter(|_| !print_errors) {
                 let start = node.start_position();
                 let end = node.end_position();
-                write!(&mut stdout, "\t(")?;
+                write!(output, "\t(")?;
                 if node.is_missing() {
                     if node.is_named() {
-                        write!(&mut stdout, "MISSING {}", node.kind())?;
+                        write!(output, "MISSING {}", node.kind())?;
                     } else {
-                        write!(
-                            &mut stdout,
-                            "MISSING \"{}\"",
-                            node.kind().replace("\n", "\\n")
-                        )?;
+                        write!(output, "MISSING \"{}\"", node.kind().replace("\n", "\\n"))?;
                     }
                 } else {
-                    write!(&mut stdout, "{}", node.kind())?;
+                    write!(output, "{}", node.kind())?;
                 }
                 write!(
-                    &mut stdout,
+                    output,
                     " [{}, {}] - [{}, {}])",
                     start.row, start.column, end.row, end.column
                 )?;
             }
-            write!(&mut stdout, "\n")?;
+            write!(output, "\n")?;
+        }
+
+        if print_stats {
+            let stats = tree.stats(old_tree.as_ref());
+            write!(
+                output,
+                "{:width$}\tnodes: {}, depth: {}",
+                path.to_str().unwrap(),
+                stats.node_count,
+                stats.depth,
+                width = max_path_length
+            )?;
+            if old_tree.is_some() {
+                write!(output, ", reused nodes: {}", stats.reused_node_count)?;
+            }
+            writeln!(
+                output,
+                ", tree memory: {} bytes, parser memory: {} bytes",
+                tree.memory_usage(),
+                parser.memory_usage()
+            )?;
         }
 
         return Ok(first_error.is_some());
@@ -262,7 +247,7 @@ pub fn parse_file_at_path(
         let duration = time.elapsed();
         let duration_ms = duration.as_secs() * 1000 + duration.subsec_nanos() as u64 / 1000000;
         writeln!(
-            &mut stdout,
+            output,
             "{:width$}\t{} ms (timed out)",
             path.to_str().unwrap(),
             duration_ms,
@@ -1,12 +1,16 @@
-use crate::query_testing;
-use anyhow::{Context, Result};
+use crate::{
+    query_format,
+    query_lint::{self, NodeTypes},
+    query_syntax, query_testing,
+};
+use anyhow::{anyhow, Context, Result};
 use std::{
     fs,
     io::{self, Write},
     ops::Range,
-    path::Path,
+    path::{Path, PathBuf},
 };
-use tree_sitter::{Language, Parser, Query, QueryCursor};
+use tree_sitter::{Language, Parser, ParserPool, Query, QueryCursor};
 
 pub fn query_files_at_paths(
     language: Language,
@@ -15,95 +19,214 @@ pub fn query_files_at_paths(
     ordered_captures: bool,
     range: Option<Range<usize>>,
     should_test: bool,
+    jobs: usize,
 ) -> Result<()> {
     let stdout = io::stdout();
-    let mut stdout = stdout.lock();
 
     let query_source = fs::read_to_string(query_path)
         .with_context(|| format!("Error reading query file {:?}", query_path))?;
     let query = Query::new(language, &query_source).with_context(|| "Query compilation failed")?;
 
+    ParserPool::new(jobs).map_in_order(
+        &paths,
+        |parser, path| {
+            let mut output = Vec::new();
+            let result = query_file_at_path(
+                parser,
+                language,
+                &query,
+                path,
+                ordered_captures,
+                range.clone(),
+                should_test,
+                &mut output,
+            );
+            (result, output)
+        },
+        |(result, output)| {
+            stdout.lock().write_all(&output)?;
+            result
+        },
+    )
+}
+
+fn query_file_at_path(
+    parser: &mut Parser,
+    language: Language,
+    query: &Query,
+    path: &str,
+    ordered_captures: bool,
+    range: Option<Range<usize>>,
+    should_test: bool,
+    output: &mut impl Write,
+) -> Result<()> {
     let mut query_cursor = QueryCursor::new();
     if let Some(range) = range {
         query_cursor.set_byte_range(range);
     }
 
-    let mut parser = Parser::new();
     parser.set_language(language)?;
 
-    for path in paths {
-        let mut results = Vec::new();
+    let mut results = Vec::new();
 
-        writeln!(&mut stdout, "{}", path)?;
+    writeln!(output, "{}", path)?;
 
-        let source_code =
-            fs::read(&path).with_context(|| format!("Error reading source file {:?}", path))?;
-        let tree = parser.parse(&source_code, None).unwrap();
+    let source_code =
+        fs::read(path).with_context(|| format!("Error reading source file {:?}", path))?;
+    let tree = parser.parse(&source_code, None).unwrap();
 
-        if ordered_captures {
-            for (mat, capture_index) in
-                query_cursor.captures(&query, tree.root_node(), source_code.as_slice())
-            {
-                let capture = mat.captures[capture_index];
+    if ordered_captures {
+        for (mat, capture_index) in
+            query_cursor.captures(query, tree.root_node(), source_code.as_slice())
+        {
+            let capture = mat.captures[capture_index];
+            let capture_name = &query.capture_names()[capture.index as usize];
+            writeln!(
+                output,
+                "    pattern: {:>2}, capture: {} - {}, start: {}, end: {}, text: `{}`",
+                mat.pattern_index,
+                capture.index,
+                capture_name,
+                capture.node.start_position(),
+                capture.node.end_position(),
+                capture.node.utf8_text(&source_code).unwrap_or("")
+            )?;
+            results.push(query_testing::CaptureInfo {
+                name: capture_name.to_string(),
+                start: capture.node.start_position(),
+                end: capture.node.end_position(),
+            });
+        }
+    } else {
+        for m in query_cursor.matches(query, tree.root_node(), source_code.as_slice()) {
+            writeln!(output, "  pattern: {}", m.pattern_index)?;
+            for capture in m.captures {
+                let start = capture.node.start_position();
+                let end = capture.node.end_position();
                 let capture_name = &query.capture_names()[capture.index as usize];
-                writeln!(
-                    &mut stdout,
-                    "    pattern: {:>2}, capture: {} - {}, start: {}, end: {}, text: `{}`",
-                    mat.pattern_index,
-                    capture.index,
-                    capture_name,
-                    capture.node.start_position(),
-                    capture.node.end_position(),
-                    capture.node.utf8_text(&source_code).unwrap_or("")
-                )?;
+                if end.row == start.row {
+                    writeln!(
+                        output,
+                        "    capture: {} - {}, start: {}, end: {}, text: `{}`",
+                        capture.index,
+                        capture_name,
+                        start,
+                        end,
+                        capture.node.utf8_text(&source_code).unwrap_or("")
+                    )?;
+                } else {
+                    writeln!(
+                        output,
+                        "    capture: {}, start: {}, end: {}",
+                        capture_name, start, end,
+                    )?;
+                }
                 results.push(query_testing::CaptureInfo {
                     name: capture_name.to_string(),
                     start: capture.node.start_position(),
                     end: capture.node.end_position(),
                 });
             }
-        } else {
-            for m in query_cursor.matches(&query, tree.root_node(), source_code.as_slice()) {
-                writeln!(&mut stdout, "  pattern: {}", m.pattern_index)?;
-                for capture in m.captures {
-                    let start = capture.node.start_position();
-                    let end = capture.node.end_position();
-                    let capture_name = &query.capture_names()[capture.index as usize];
-                    if end.row == start.row {
-                        writeln!(
-                            &mut stdout,
-                            "    capture: {} - {}, start: {}, end: {}, text: `{}`",
-                            capture.index,
-                            capture_name,
-                            start,
-                            end,
-                            capture.node.utf8_text(&source_code).unwrap_or("")
-                        )?;
-                    } else {
-                        writeln!(
-                            &mut stdout,
-                            "    capture: {}, start: {}, end: {}",
-                            capture_name, start, end,
-                        )?;
-                    }
-                    results.push(query_testing::CaptureInfo {
-                        name: capture_name.to_string(),
-                        start: capture.node.start_position(),
-                        end: capture.node.end_position(),
-                    });
-                }
-            }
-        }
-        if query_cursor.did_exceed_match_limit() {
-            writeln!(
-                &mut stdout,
-                "  WARNING: Query exceeded maximum number of in-progress captures!"
-            )?;
-        }
-        if should_test {
-            query_testing::assert_expected_captures(results, path, &mut parser, language)?
         }
     }
+    if query_cursor.did_exceed_match_limit() {
+        writeln!(
+            output,
+            "  WARNING: Query exceeded maximum number of in-progress captures!"
+        )?;
+    }
+    if should_test {
+        query_testing::assert_expected_captures(results, path.to_string(), parser, language)?
+    }
 
     Ok(())
 }
+
+/// Check a query file for patterns that can never match, and for captures that are
+/// not recognized highlight names, printing a warning for each problem. Returns the
+/// number of warnings.
+pub fn lint_query_file(
+    language: Language,
+    query_path: &Path,
+    node_types_path: &Path,
+    highlight_names: Option<&[String]>,
+    output: &mut impl Write,
+) -> Result<usize> {
+    let query_source = fs::read_to_string(query_path)
+        .with_context(|| format!("Error reading query file {:?}", query_path))?;
+    let node_types_json = fs::read_to_string(node_types_path)
+        .with_context(|| format!("Error reading node types file {:?}", node_types_path))?;
+    let node_types = NodeTypes::new(&node_types_json)?;
+
+    let warnings = query_lint::lint_query(language, &query_source, &node_types, highlight_names)
+        .with_context(|| "Query compilation failed")?;
+    for warning in &warnings {
+        let (row, column) = query_syntax::position_for_offset(&query_source, warning.range.start);
+        writeln!(
+            output,
+            "{}:{}:{}: warning: {} (bytes {}-{})",
+            query_path.display(),
+            row + 1,
+            column + 1,
+            warning.message,
+            warning.range.start,
+            warning.range.end,
+        )?;
+    }
+    Ok(warnings.len())
+}
+
+/// Rewrite the given query files in the canonical style. If `check` is set, the files
+/// are left unchanged, and the ones that are not already formatted are listed instead.
+/// The language of each file, given by `language_for_path`, is used to parse it, and to
+/// check that formatting does not change its patterns. Returns the number of files that
+/// were not already formatted.
+pub fn format_query_files(
+    paths: &[String],
+    check: bool,
+    mut language_for_path: impl FnMut(&Path) -> Result<Language>,
+    output: &mut impl Write,
+) -> Result<usize> {
+    let mut unformatted_count = 0;
+    for path in paths {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Error reading query file {:?}", path))?;
+        let language = language_for_path(Path::new(path))?;
+        let formatted = query_format::format_query(language, &source)
+            .with_context(|| format!("Error parsing query file {:?}", path))?;
+        query_format::check_formatted_query(language, &source, &formatted)
+            .with_context(|| format!("Error formatting query file {:?}", path))?;
+        if formatted != source {
+            unformatted_count += 1;
+            if check {
+                writeln!(output, "{}", path)?;
+            } else {
+                fs::write(path, formatted)
+                    .with_context(|| format!("Error writing query file {:?}", path))?;
+            }
+        }
+    }
+    Ok(unformatted_count)
+}
+
+/// Find the directory of the grammar that contains the given query file.
+pub fn find_grammar_path(query_path: &Path, current_dir: &Path) -> Result<PathBuf> {
+    let query_path = current_dir.join(query_path);
+    query_path
+        .ancestors()
+        .find(|dir| dir.join("src").join("grammar.json").exists())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("Could not find a grammar for {:?}", query_path))
+}
+
+/// Find the `node-types.json` file of the grammar that contains the given query file,
+/// or of the grammar in the given directory.
+pub fn find_node_types_path(query_path: &Path, current_dir: &Path) -> Result<PathBuf> {
+    let query_path = current_dir.join(query_path);
+    query_path
+        .ancestors()
+        .chain(Some(current_dir))
+        .map(|dir| dir.join("src").join("node-types.json"))
+        .find(|path| path.exists())
+        .ok_or_else(|| anyhow!("Could not find a node-types.json file for {:?}", query_path))
+}
@@ -0,0 +1,293 @@
+use crate::query_syntax::{self, Item, Pattern, PatternKind, PredicateArg, Suffix};
+use anyhow::{anyhow, Context, Result};
+use tree_sitter::{CaptureQuantifier, Language, Query, QueryPredicate, QueryProperty};
+
+const MAX_LINE_LENGTH: usize = 80;
+const INDENT: usize = 2;
+
+/// Re-emit the source code of a query in a canonical style:
+/// * Each top-level pattern starts on its own line. Single blank lines between
+///   patterns are preserved.
+/// * A pattern is written on one line if it fits and contains no comments or
+///   predicates. Otherwise, each child of a node pattern is written on its own line,
+///   indented by two spaces, and the elements of a grouped sequence are aligned with
+///   its first element, so that predicates line up with the patterns they apply to.
+/// * Comments are preserved, including comments at the end of a line.
+pub fn format_query(language: Language, source: &str) -> Result<String> {
+    let items = query_syntax::parse(language, source)?;
+    let mut formatter = Formatter {
+        source,
+        output: String::new(),
+    };
+    formatter.write_items(&items, 0, false);
+    formatter.output.push('\n');
+    Ok(formatter.output)
+}
+
+/// Check that the library compiles a query and its formatted version into the same
+/// patterns, which guards against any change to a query's meaning by the formatter.
+pub fn check_formatted_query(language: Language, source: &str, formatted: &str) -> Result<()> {
+    let query = Query::new(language, source).with_context(|| "Query compilation failed")?;
+    let formatted_query = Query::new(language, formatted)
+        .with_context(|| "Compilation of the formatted query failed")?;
+
+    let patterns = summarize_patterns(&query);
+    let formatted_patterns = summarize_patterns(&formatted_query);
+    if patterns.len() != formatted_patterns.len() {
+        return Err(anyhow!(
+            "Formatting changed the number of patterns from {} to {}",
+            patterns.len(),
+            formatted_patterns.len()
+        ));
+    }
+    for (i, (pattern, formatted_pattern)) in patterns.iter().zip(&formatted_patterns).enumerate() {
+        if pattern != formatted_pattern {
+            let (row, column) =
+                query_syntax::position_for_offset(source, query.start_byte_for_pattern(i));
+            return Err(anyhow!(
+                "Formatting changed the pattern at {}:{}",
+                row + 1,
+                column + 1
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The parts of a pattern that the library exposes, which are compared to check that
+/// formatting a query did not change its meaning.
+#[derive(PartialEq, Eq)]
+struct PatternSummary<'a> {
+    captures: Vec<(&'a str, CaptureQuantifier)>,
+    property_settings: &'a [QueryProperty],
+    property_predicates: &'a [(QueryProperty, bool)],
+    general_predicates: &'a [QueryPredicate],
+    is_rooted: bool,
+}
+
+fn summarize_patterns(query: &Query) -> Vec<PatternSummary<'_>> {
+    (0..query.pattern_count())
+        .map(|i| PatternSummary {
+            captures: query
+                .capture_quantifiers(i)
+                .iter()
+                .zip(query.capture_names())
+                .filter(|(quantifier, _)| **quantifier != CaptureQuantifier::Zero)
+                .map(|(quantifier, name)| (name.as_str(), *quantifier))
+                .collect(),
+            property_settings: query.property_settings(i),
+            property_predicates: query.property_predicates(i),
+            general_predicates: query.general_predicates(i),
+            is_rooted: query.is_pattern_rooted(i),
+        })
+        .collect()
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    output: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn current_column(&self) -> usize {
+        self.output.len() - self.output.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.output.push('\n');
+        self.output += &" ".repeat(indent);
+    }
+
+    /// Write a list of items, each on its own line at the given indentation. If
+    /// `first_inline` is set, the first item is written on the current line.
+    fn write_items(&mut self, items: &[Item], indent: usize, first_inline: bool) {
+        let mut previous_end = None;
+        for item in items {
+            let range = item.range();
+            let is_comment = matches!(item, Item::Comment(_));
+            match previous_end {
+                Some(previous_end) => {
+                    let newline_count =
+                        self.source[previous_end..range.start].matches('\n').count();
+                    if newline_count == 0 && is_comment {
+                        self.output.push(' ');
+                    } else {
+                        if newline_count > 1 {
+                            self.output.push('\n');
+                        }
+                        self.newline(indent);
+                    }
+                }
+                None if first_inline && !is_comment => {}
+                None => {
+                    if !self.output.is_empty() {
+                        self.newline(indent);
+                    }
+                }
+            }
+            self.write_item(item, indent);
+            previous_end = Some(range.end);
+        }
+    }
+
+    /// Write a list of items that is followed by a closing delimiter. The delimiter is
+    /// written on its own line if `close_on_new_line` is set, or if the list ends with a
+    /// comment.
+    fn write_delimited_items(
+        &mut self,
+        items: &[Item],
+        indent: usize,
+        first_inline: bool,
+        close: char,
+        close_indent: usize,
+        close_on_new_line: bool,
+    ) {
+        self.write_items(items, indent, first_inline);
+        if close_on_new_line || matches!(items.last(), Some(Item::Comment(_))) {
+            self.newline(close_indent);
+        }
+        self.output.push(close);
+    }
+
+    fn write_item(&mut self, item: &Item, indent: usize) {
+        match item {
+            Item::Comment(comment) => self.output += &comment.text,
+            Item::Pattern(pattern) => self.write_pattern(pattern, indent),
+            Item::Field { name, pattern, .. } => {
+                self.output += name;
+                self.output += ": ";
+                self.write_pattern(pattern, indent);
+            }
+            Item::NegatedField { .. } | Item::Anchor(_) | Item::Predicate(_) => {
+                if let Some(text) = flat_item(item) {
+                    self.output += &text;
+                }
+            }
+        }
+    }
+
+    fn write_pattern(&mut self, pattern: &Pattern, indent: usize) {
+        if let Some(text) = flat_pattern(pattern) {
+            if self.current_column() + text.len() <= MAX_LINE_LENGTH {
+                self.output += &text;
+                return;
+            }
+        }
+
+        match &pattern.kind {
+            PatternKind::Node {
+                kind,
+                supertype,
+                children,
+                ..
+            } => {
+                self.output.push('(');
+                if let Some(supertype) = supertype {
+                    self.output += supertype;
+                    self.output.push('/');
+                }
+                self.output += kind;
+                self.write_delimited_items(children, indent + INDENT, false, ')', indent, false);
+            }
+            PatternKind::Group(items) => {
+                let column = self.current_column();
+                self.output.push('(');
+                self.write_delimited_items(items, column + 1, true, ')', column, false);
+            }
+            PatternKind::Alternation(items) => {
+                self.output.push('[');
+                self.write_delimited_items(items, indent + INDENT, false, ']', indent, true);
+            }
+            PatternKind::Anonymous { .. } | PatternKind::Wildcard => {}
+        }
+        self.output += &flat_suffixes(&pattern.suffixes);
+    }
+}
+
+/// Get the text of a pattern written on a single line, or `None` if it contains
+/// comments or predicates, which must be written on their own lines.
+fn flat_pattern(pattern: &Pattern) -> Option<String> {
+    let mut result = match &pattern.kind {
+        PatternKind::Node {
+            kind,
+            supertype,
+            children,
+            ..
+        } => {
+            let mut result = String::from("(");
+            if let Some(supertype) = supertype {
+                result += supertype;
+                result.push('/');
+            }
+            result += kind;
+            for child in children {
+                result.push(' ');
+                result += &flat_nested_item(child)?;
+            }
+            result.push(')');
+            result
+        }
+        PatternKind::Group(items) => format!("({})", flat_nested_items(items)?),
+        PatternKind::Alternation(items) => format!("[{}]", flat_nested_items(items)?),
+        PatternKind::Anonymous { text } => text.clone(),
+        PatternKind::Wildcard => "_".to_string(),
+    };
+    result += &flat_suffixes(&pattern.suffixes);
+    Some(result)
+}
+
+fn flat_nested_items(items: &[Item]) -> Option<String> {
+    Some(
+        items
+            .iter()
+            .map(flat_nested_item)
+            .collect::<Option<Vec<_>>>()?
+            .join(" "),
+    )
+}
+
+fn flat_nested_item(item: &Item) -> Option<String> {
+    match item {
+        Item::Comment(_) | Item::Predicate(_) => None,
+        _ => flat_item(item),
+    }
+}
+
+fn flat_item(item: &Item) -> Option<String> {
+    match item {
+        Item::Comment(_) => None,
+        Item::Pattern(pattern) => flat_pattern(pattern),
+        Item::Field { name, pattern, .. } => Some(format!("{}: {}", name, flat_pattern(pattern)?)),
+        Item::NegatedField { name, .. } => Some(format!("!{}", name)),
+        Item::Anchor(_) => Some(".".to_string()),
+        Item::Predicate(predicate) => {
+            let mut result = format!("({}", predicate.name);
+            for arg in &predicate.args {
+                result.push(' ');
+                match arg {
+                    PredicateArg::Capture(name) => {
+                        result.push('@');
+                        result += name;
+                    }
+                    PredicateArg::String(text) | PredicateArg::Identifier(text) => result += text,
+                }
+            }
+            result.push(')');
+            Some(result)
+        }
+    }
+}
+
+fn flat_suffixes(suffixes: &[Suffix]) -> String {
+    let mut result = String::new();
+    for suffix in suffixes {
+        match suffix {
+            Suffix::Quantifier(c) => result.push(*c),
+            Suffix::Capture { name, .. } => {
+                result += " @";
+                result += name;
+            }
+        }
+    }
+    result
+}
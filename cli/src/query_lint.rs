@@ -0,0 +1,412 @@
+use crate::query_syntax::{self, Item, Pattern, PatternKind};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+use tree_sitter::Language;
+
+/// A problem with a query that does not prevent it from being compiled, but that
+/// probably prevents it from working as intended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintWarning {
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+/// The structure of a grammar's syntax nodes, as described by its `node-types.json`.
+pub struct NodeTypes {
+    nodes: HashMap<NodeType, NodeInfo>,
+    referenced: HashSet<NodeType>,
+}
+
+type NodeType = (String, bool);
+
+#[derive(Default)]
+struct NodeInfo {
+    fields: HashMap<String, Vec<NodeType>>,
+    children: Vec<NodeType>,
+    subtypes: Vec<NodeType>,
+}
+
+#[derive(Deserialize)]
+struct NodeInfoJSON {
+    #[serde(rename = "type")]
+    kind: String,
+    named: bool,
+    #[serde(default)]
+    fields: HashMap<String, FieldInfoJSON>,
+    children: Option<FieldInfoJSON>,
+    #[serde(default)]
+    subtypes: Vec<NodeTypeJSON>,
+}
+
+#[derive(Deserialize)]
+struct FieldInfoJSON {
+    types: Vec<NodeTypeJSON>,
+}
+
+#[derive(Deserialize)]
+struct NodeTypeJSON {
+    #[serde(rename = "type")]
+    kind: String,
+    named: bool,
+}
+
+/// The possible children of a node kind within a query pattern.
+struct Parent {
+    kind: String,
+    fields: HashMap<String, HashSet<NodeType>>,
+    children: HashSet<NodeType>,
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    node_types: &'a NodeTypes,
+    warnings: Vec<LintWarning>,
+}
+
+impl NodeTypes {
+    pub fn new(json: &str) -> Result<Self> {
+        let node_infos = serde_json::from_str::<Vec<NodeInfoJSON>>(json)
+            .with_context(|| "Failed to parse node types")?;
+        let mut result = NodeTypes {
+            nodes: HashMap::new(),
+            referenced: HashSet::new(),
+        };
+        for node_info in node_infos {
+            let types = |types: Vec<NodeTypeJSON>| {
+                types
+                    .into_iter()
+                    .map(|t| (t.kind, t.named))
+                    .collect::<Vec<_>>()
+            };
+            let info = NodeInfo {
+                fields: node_info
+                    .fields
+                    .into_iter()
+                    .map(|(name, field)| (name, types(field.types)))
+                    .collect(),
+                children: node_info
+                    .children
+                    .map_or(Vec::new(), |children| types(children.types)),
+                subtypes: types(node_info.subtypes),
+            };
+            result.referenced.extend(
+                info.fields
+                    .values()
+                    .flatten()
+                    .chain(&info.children)
+                    .chain(&info.subtypes)
+                    .cloned(),
+            );
+            result.nodes.insert((node_info.kind, node_info.named), info);
+        }
+        Ok(result)
+    }
+
+    /// Get the given node type, along with all of its subtypes, if it is a supertype.
+    fn expand(&self, node_type: &NodeType) -> HashSet<NodeType> {
+        let mut result = HashSet::new();
+        let mut stack = vec![node_type.clone()];
+        while let Some(node_type) = stack.pop() {
+            if let Some(info) = self.nodes.get(&node_type) {
+                stack.extend(info.subtypes.iter().cloned());
+            }
+            result.insert(node_type);
+        }
+        result
+    }
+
+    fn expand_all<'b>(
+        &self,
+        node_types: impl IntoIterator<Item = &'b NodeType>,
+    ) -> HashSet<NodeType> {
+        node_types
+            .into_iter()
+            .flat_map(|node_type| self.expand(node_type))
+            .collect()
+    }
+
+    fn parent(&self, kind: &str) -> Option<Parent> {
+        let node_type = (kind.to_string(), true);
+        if !self.nodes.contains_key(&node_type) {
+            return None;
+        }
+        let mut result = Parent {
+            kind: kind.to_string(),
+            fields: HashMap::new(),
+            children: HashSet::new(),
+        };
+        for node_type in self.expand(&node_type) {
+            if let Some(info) = self.nodes.get(&node_type) {
+                for (name, types) in &info.fields {
+                    let types = self.expand_all(types);
+                    result.children.extend(types.iter().cloned());
+                    result.fields.entry(name.clone()).or_default().extend(types);
+                }
+                result.children.extend(self.expand_all(&info.children));
+            }
+        }
+        Some(result)
+    }
+
+    /// Check if a node of the given type can appear among the given node types. Node
+    /// types that never appear as children, such as comments and other extras, can
+    /// appear anywhere.
+    fn can_appear_in(&self, node_type: &NodeType, node_types: &HashSet<NodeType>) -> bool {
+        !self.nodes.contains_key(node_type)
+            || !self.referenced.contains(node_type)
+            || self
+                .expand(node_type)
+                .iter()
+                .any(|node_type| node_types.contains(node_type))
+    }
+}
+
+/// Check a query for patterns that can never match, according to the grammar's node
+/// types, and for captures that are not recognized as highlight names, if any are
+/// given. The query is parsed into its elements, along with their positions, using
+/// the tokens of the library's query parser.
+pub fn lint_query(
+    language: Language,
+    source: &str,
+    node_types: &NodeTypes,
+    highlight_names: Option<&[String]>,
+) -> Result<Vec<LintWarning>> {
+    let items = query_syntax::parse(language, source)?;
+    let mut linter = Linter {
+        source,
+        node_types,
+        warnings: Vec::new(),
+    };
+    linter.check_items(&items, None);
+    if let Some(highlight_names) = highlight_names {
+        let mut seen_names = HashSet::new();
+        linter.check_captures(&items, highlight_names, &mut seen_names);
+    }
+    linter
+        .warnings
+        .sort_unstable_by_key(|warning| (warning.range.start, warning.range.end));
+    Ok(linter.warnings)
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, range: Range<usize>, message: String) {
+        self.warnings.push(LintWarning { range, message });
+    }
+
+    fn check_items(&mut self, items: &[Item], parent: Option<&Parent>) {
+        for item in items {
+            match item {
+                Item::Pattern(pattern) => {
+                    if let Some(parent) = parent {
+                        self.check_membership(pattern, parent, None, false);
+                    }
+                    self.check_nested(pattern);
+                }
+                Item::Field {
+                    name,
+                    name_range,
+                    pattern,
+                } => {
+                    if let Some(parent) = parent {
+                        self.check_field(name, name_range, pattern, parent);
+                    }
+                    self.check_nested(pattern);
+                }
+                Item::NegatedField { name, range } => {
+                    if let Some(parent) = parent {
+                        if !parent.fields.contains_key(name) {
+                            self.warn(
+                                range.clone(),
+                                format!(
+                                    "`{}` nodes have no `{}` field, so this negated field is always satisfied",
+                                    parent.kind, name
+                                ),
+                            );
+                        }
+                    }
+                }
+                Item::Comment(_) | Item::Anchor(_) | Item::Predicate(_) => {}
+            }
+        }
+    }
+
+    fn check_field(
+        &mut self,
+        name: &str,
+        name_range: &Range<usize>,
+        pattern: &Pattern,
+        parent: &Parent,
+    ) {
+        if parent.fields.contains_key(name) {
+            self.check_membership(pattern, parent, Some(name), false);
+        } else {
+            self.warn(
+                name_range.clone(),
+                format!("`{}` nodes have no `{}` field", parent.kind, name),
+            );
+        }
+    }
+
+    /// Check that a pattern can match a child of the given parent, or a node in the
+    /// given field of the parent.
+    fn check_membership(
+        &mut self,
+        pattern: &Pattern,
+        parent: &Parent,
+        field: Option<&str>,
+        is_alternative: bool,
+    ) {
+        let (node_type, display_name) = match &pattern.kind {
+            PatternKind::Node { kind, .. } => {
+                if kind == "_" || kind == "ERROR" || kind == "MISSING" {
+                    return;
+                }
+                ((kind.clone(), true), kind.clone())
+            }
+            // Anonymous children are only listed in the node types if they are
+            // associated with a field.
+            PatternKind::Anonymous { text } if field.is_some() => {
+                ((query_syntax::unescape(text), false), text.clone())
+            }
+            PatternKind::Alternation(items) => {
+                for item in items {
+                    if let Item::Pattern(alternative) = item {
+                        self.check_membership(alternative, parent, field, true);
+                    }
+                }
+                return;
+            }
+            PatternKind::Group(items) => {
+                for item in items {
+                    match item {
+                        Item::Pattern(pattern) => {
+                            self.check_membership(pattern, parent, field, false)
+                        }
+                        Item::Field {
+                            name,
+                            name_range,
+                            pattern,
+                        } => self.check_field(name, name_range, pattern, parent),
+                        _ => {}
+                    }
+                }
+                return;
+            }
+            PatternKind::Anonymous { .. } | PatternKind::Wildcard => return,
+        };
+
+        let (possible_types, location) = match field {
+            Some(field) => (
+                &parent.fields[field],
+                format!("the `{}` field of `{}` nodes", field, parent.kind),
+            ),
+            None => (&parent.children, format!("`{}` nodes", parent.kind)),
+        };
+        if !self.node_types.can_appear_in(&node_type, possible_types) {
+            let message = if field.is_some() {
+                format!("`{}` nodes can never appear in {}", display_name, location)
+            } else {
+                format!(
+                    "`{}` nodes can never be children of {}",
+                    display_name, location
+                )
+            };
+            let message = if is_alternative {
+                format!("unreachable alternative: {}", message)
+            } else {
+                message
+            };
+            self.warn(pattern.range.clone(), message);
+        }
+    }
+
+    /// Check the patterns that are nested within a pattern.
+    fn check_nested(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Node { kind, children, .. } => {
+                let parent = self.node_types.parent(kind);
+                self.check_items(children, parent.as_ref());
+            }
+            PatternKind::Group(items) => {
+                for item in items {
+                    match item {
+                        Item::Pattern(pattern) | Item::Field { pattern, .. } => {
+                            self.check_nested(pattern)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            PatternKind::Alternation(items) => {
+                let mut alternatives = HashSet::new();
+                for item in items {
+                    if let Item::Pattern(alternative) = item {
+                        let text = self.source[alternative.range.clone()]
+                            .split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        if !alternatives.insert(text) {
+                            self.warn(
+                                alternative.range.clone(),
+                                "unreachable alternative: it is identical to a previous alternative"
+                                    .to_string(),
+                            );
+                        }
+                        self.check_nested(alternative);
+                    }
+                }
+            }
+            PatternKind::Anonymous { .. } | PatternKind::Wildcard => {}
+        }
+    }
+
+    fn check_captures(
+        &mut self,
+        items: &[Item],
+        highlight_names: &[String],
+        seen_names: &mut HashSet<String>,
+    ) {
+        for item in items {
+            let pattern = match item {
+                Item::Pattern(pattern) | Item::Field { pattern, .. } => pattern,
+                _ => continue,
+            };
+            for (name, range) in pattern.captures() {
+                if !name.starts_with('_')
+                    && seen_names.insert(name.to_string())
+                    && !is_recognized_highlight(name, highlight_names)
+                {
+                    self.warn(
+                        range,
+                        format!("`@{}` is not a recognized highlight name", name),
+                    );
+                }
+            }
+            match &pattern.kind {
+                PatternKind::Node { children, .. } => {
+                    self.check_captures(children, highlight_names, seen_names)
+                }
+                PatternKind::Group(items) | PatternKind::Alternation(items) => {
+                    self.check_captures(items, highlight_names, seen_names)
+                }
+                PatternKind::Anonymous { .. } | PatternKind::Wildcard => {}
+            }
+        }
+    }
+}
+
+/// Check if a capture name would be assigned a highlight, using the same rules as the
+/// highlighter: a highlight name is recognized if each of its dot-separated parts
+/// appears in the capture name.
+fn is_recognized_highlight(capture_name: &str, highlight_names: &[String]) -> bool {
+    let capture_parts = capture_name.split('.').collect::<Vec<_>>();
+    highlight_names.iter().any(|highlight_name| {
+        highlight_name
+            .split('.')
+            .all(|part| capture_parts.contains(&part))
+    })
+}
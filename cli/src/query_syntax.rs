@@ -0,0 +1,346 @@
+//! The syntax tree of a tree-sitter query's patterns, which is built from the tokens
+//! that the library's query parser produces. Unlike `Query::new`, this preserves the
+//! query's comments and the positions of its elements, for use by tools that analyze
+//! or rewrite query files.
+
+use anyhow::{anyhow, Result};
+use std::{iter::Peekable, ops::Range, vec};
+use tree_sitter::{Language, Query, QueryToken, QueryTokenKind};
+
+/// An element of a query: either a top-level element, or an element within a node
+/// pattern or a grouped sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Comment(Comment),
+    Pattern(Pattern),
+    Field {
+        name: String,
+        name_range: Range<usize>,
+        pattern: Pattern,
+    },
+    NegatedField {
+        name: String,
+        range: Range<usize>,
+    },
+    Anchor(usize),
+    Predicate(Predicate),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub range: Range<usize>,
+}
+
+/// A pattern, along with the quantifiers and captures that follow it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub suffixes: Vec<Suffix>,
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternKind {
+    /// A named node, such as `(identifier)` or `(_)`, with an optional supertype
+    /// prefix, such as `(expression/identifier)`.
+    Node {
+        kind: String,
+        supertype: Option<String>,
+        kind_range: Range<usize>,
+        children: Vec<Item>,
+    },
+    /// An anonymous node, such as `"if"`. The text includes the quotes and escape
+    /// sequences.
+    Anonymous { text: String },
+    /// The `_` wildcard, which matches any node.
+    Wildcard,
+    /// A sequence of sibling patterns, such as `((comment) (function_declaration))`.
+    Group(Vec<Item>),
+    /// A set of alternative patterns, such as `[(identifier) (number)]`.
+    Alternation(Vec<Item>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Suffix {
+    /// One of the quantifiers `*`, `+` or `?`.
+    Quantifier(char),
+    Capture {
+        name: String,
+        range: Range<usize>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Predicate {
+    /// The predicate's name, including its prefix, such as `#eq?`.
+    pub name: String,
+    pub args: Vec<PredicateArg>,
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PredicateArg {
+    Capture(String),
+    /// A string literal, including the quotes and escape sequences.
+    String(String),
+    Identifier(String),
+}
+
+impl Item {
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            Item::Comment(comment) => comment.range.clone(),
+            Item::Pattern(pattern) => pattern.range.clone(),
+            Item::Field {
+                name_range,
+                pattern,
+                ..
+            } => name_range.start..pattern.range.end,
+            Item::NegatedField { range, .. } => range.clone(),
+            Item::Anchor(position) => *position..*position + 1,
+            Item::Predicate(predicate) => predicate.range.clone(),
+        }
+    }
+}
+
+impl Pattern {
+    pub fn captures(&self) -> impl Iterator<Item = (&str, Range<usize>)> {
+        self.suffixes.iter().filter_map(|suffix| match suffix {
+            Suffix::Capture { name, range } => Some((name.as_str(), range.clone())),
+            Suffix::Quantifier(_) => None,
+        })
+    }
+}
+
+/// Parse the source code of a query into a list of top-level items, using the tokens
+/// of the query's syntax from the library's query parser.
+pub fn parse(language: Language, source: &str) -> Result<Vec<Item>> {
+    let mut builder = Builder {
+        source,
+        tokens: Query::tokenize(language, source)?.into_iter().peekable(),
+        comments: Vec::new(),
+        end: 0,
+    };
+    builder.build_items()
+}
+
+/// Get the value of a string literal, including its quotes and escape sequences.
+pub fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text[1..text.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some('t') => result.push('\t'),
+                Some('0') => result.push('\0'),
+                Some(c) => result.push(c),
+                None => {}
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Get the row and column of a byte offset within a query's source code.
+pub fn position_for_offset(source: &str, offset: usize) -> (usize, usize) {
+    let prefix = &source[..offset];
+    let row = prefix.matches('\n').count();
+    let column = offset - prefix.rfind('\n').map_or(0, |i| i + 1);
+    (row, column)
+}
+
+/// Builds the syntax tree of a query from its tokens. The library has already checked
+/// that the tokens are valid, so they are expected to be well-formed.
+struct Builder<'a> {
+    source: &'a str,
+    tokens: Peekable<vec::IntoIter<QueryToken>>,
+    comments: Vec<Comment>,
+    /// The end of the last token that was returned by `next`.
+    end: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn text(&self, token: &QueryToken) -> &'a str {
+        &self.source[token.start_byte..token.end_byte]
+    }
+
+    fn comment(&self, token: &QueryToken) -> Comment {
+        Comment {
+            text: self.text(token).trim_end().to_string(),
+            range: token.start_byte..token.end_byte,
+        }
+    }
+
+    /// Get the next token that is not a comment, saving any comments before it so that
+    /// they can be added to the enclosing list of items.
+    fn next(&mut self) -> Result<QueryToken> {
+        while let Some(token) = self.tokens.next() {
+            if token.kind == QueryTokenKind::Comment {
+                let comment = self.comment(&token);
+                self.comments.push(comment);
+            } else {
+                self.end = token.end_byte;
+                return Ok(token);
+            }
+        }
+        Err(anyhow!("Unexpected end of query"))
+    }
+
+    fn expect(&mut self, kind: QueryTokenKind) -> Result<QueryToken> {
+        let token = self.next()?;
+        if token.kind == kind {
+            Ok(token)
+        } else {
+            Err(self.unexpected(&token))
+        }
+    }
+
+    /// Peek at the kind of the next token that is not a comment, saving any comments
+    /// before it.
+    fn peek_kind(&mut self) -> Option<QueryTokenKind> {
+        while let Some(token) = self.tokens.peek().copied() {
+            if token.kind == QueryTokenKind::Comment {
+                let comment = self.comment(&token);
+                self.comments.push(comment);
+                self.tokens.next();
+            } else {
+                return Some(token.kind);
+            }
+        }
+        None
+    }
+
+    fn unexpected(&self, token: &QueryToken) -> anyhow::Error {
+        let (row, column) = position_for_offset(self.source, token.start_byte);
+        anyhow!(
+            "Unexpected token {:?} at {}:{}",
+            self.text(token),
+            row + 1,
+            column + 1
+        )
+    }
+
+    /// Build the items of an alternation, a node pattern or a grouped sequence, up to
+    /// its end, or the top-level items of the query.
+    fn build_items(&mut self) -> Result<Vec<Item>> {
+        let mut items = Vec::new();
+        loop {
+            let kind = self.peek_kind();
+            items.extend(self.comments.drain(..).map(Item::Comment));
+            let item = match kind {
+                None => return Ok(items),
+                Some(QueryTokenKind::End) => {
+                    self.next()?;
+                    return Ok(items);
+                }
+                Some(QueryTokenKind::Anchor) => Item::Anchor(self.next()?.start_byte),
+                Some(QueryTokenKind::Negation) => {
+                    let start = self.next()?.start_byte;
+                    let name = self.expect(QueryTokenKind::FieldName)?;
+                    Item::NegatedField {
+                        name: self.text(&name).to_string(),
+                        range: start..name.end_byte,
+                    }
+                }
+                Some(QueryTokenKind::PredicateStart) => Item::Predicate(self.build_predicate()?),
+                Some(QueryTokenKind::FieldName) => {
+                    let name = self.next()?;
+                    self.expect(QueryTokenKind::Colon)?;
+                    Item::Field {
+                        name: self.text(&name).to_string(),
+                        name_range: name.start_byte..name.end_byte,
+                        pattern: self.build_pattern()?,
+                    }
+                }
+                Some(_) => Item::Pattern(self.build_pattern()?),
+            };
+            items.push(item);
+        }
+    }
+
+    fn build_pattern(&mut self) -> Result<Pattern> {
+        let token = self.next()?;
+        let kind = match token.kind {
+            QueryTokenKind::AlternationStart => PatternKind::Alternation(self.build_items()?),
+            QueryTokenKind::GroupStart => PatternKind::Group(self.build_items()?),
+            QueryTokenKind::String => PatternKind::Anonymous {
+                text: self.text(&token).to_string(),
+            },
+            QueryTokenKind::Wildcard => PatternKind::Wildcard,
+            QueryTokenKind::NodeStart => {
+                let name = self.expect(QueryTokenKind::NodeName)?;
+                let mut kind_range = name.start_byte..name.end_byte;
+                let mut kind = self.text(&name).to_string();
+                let mut supertype = None;
+                if self.peek_kind() == Some(QueryTokenKind::SupertypeSeparator) {
+                    self.next()?;
+                    let name = self.expect(QueryTokenKind::NodeName)?;
+                    supertype = Some(kind);
+                    kind = self.text(&name).to_string();
+                    kind_range.end = name.end_byte;
+                }
+                PatternKind::Node {
+                    kind,
+                    supertype,
+                    kind_range,
+                    children: self.build_items()?,
+                }
+            }
+            _ => return Err(self.unexpected(&token)),
+        };
+
+        let mut suffixes = Vec::new();
+        loop {
+            match self.peek_kind() {
+                Some(QueryTokenKind::Quantifier) => {
+                    let token = self.next()?;
+                    suffixes.push(Suffix::Quantifier(
+                        self.text(&token).chars().next().unwrap(),
+                    ));
+                }
+                Some(QueryTokenKind::Capture) => {
+                    let token = self.next()?;
+                    suffixes.push(Suffix::Capture {
+                        name: self.text(&token)[1..].to_string(),
+                        range: token.start_byte..token.end_byte,
+                    });
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Pattern {
+            kind,
+            suffixes,
+            range: token.start_byte..self.end,
+        })
+    }
+
+    fn build_predicate(&mut self) -> Result<Predicate> {
+        let start = self.expect(QueryTokenKind::PredicateStart)?.start_byte;
+        let name = self.expect(QueryTokenKind::PredicateName)?;
+        let mut args = Vec::new();
+        loop {
+            let token = self.next()?;
+            let text = self.text(&token);
+            args.push(match token.kind {
+                QueryTokenKind::End => {
+                    return Ok(Predicate {
+                        name: self.text(&name).to_string(),
+                        args,
+                        range: start..token.end_byte,
+                    })
+                }
+                QueryTokenKind::Capture => PredicateArg::Capture(text[1..].to_string()),
+                QueryTokenKind::String => PredicateArg::String(text.to_string()),
+                QueryTokenKind::Identifier => PredicateArg::Identifier(text.to_string()),
+                _ => return Err(self.unexpected(&token)),
+            });
+        }
+    }
+}
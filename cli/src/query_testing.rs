@@ -38,60 +38,45 @@ pub fn parse_position_comments(
     let tree = parser.parse(source, None).unwrap();
 
     // Walk the tree, finding comment nodes that contain assertions.
-    let mut ascending = false;
-    let mut cursor = tree.root_node().walk();
-    loop {
-        if ascending {
-            let node = cursor.node();
-
-            // Find every comment node.
-            if node.kind().contains("comment") {
-                if let Ok(text) = node.utf8_text(source) {
-                    let mut position = node.start_position();
-                    if position.row > 0 {
-                        // Find the arrow character ("^" or '<-") in the comment. A left arrow
-                        // refers to the column where the comment node starts. An up arrow refers
-                        // to its own column.
-                        let mut has_left_caret = false;
-                        let mut has_arrow = false;
-                        let mut arrow_end = 0;
-                        for (i, c) in text.char_indices() {
-                            arrow_end = i + 1;
-                            if c == '-' && has_left_caret {
-                                has_arrow = true;
-                                break;
-                            }
-                            if c == '^' {
-                                has_arrow = true;
-                                position.column += i;
-                                break;
-                            }
-                            has_left_caret = c == '<';
+    for node in tree.postorder() {
+        // Find every comment node.
+        if node.kind().contains("comment") {
+            if let Ok(text) = node.utf8_text(source) {
+                let mut position = node.start_position();
+                if position.row > 0 {
+                    // Find the arrow character ("^" or '<-") in the comment. A left arrow
+                    // refers to the column where the comment node starts. An up arrow refers
+                    // to its own column.
+                    let mut has_left_caret = false;
+                    let mut has_arrow = false;
+                    let mut arrow_end = 0;
+                    for (i, c) in text.char_indices() {
+                        arrow_end = i + 1;
+                        if c == '-' && has_left_caret {
+                            has_arrow = true;
+                            break;
                         }
-
-                        // If the comment node contains an arrow and a highlight name, record the
-                        // highlight name and the position.
-                        if let (true, Some(mat)) =
-                            (has_arrow, CAPTURE_NAME_REGEX.find(&text[arrow_end..]))
-                        {
-                            assertion_ranges.push((node.start_position(), node.end_position()));
-                            result.push(Assertion {
-                                position: position,
-                                expected_capture_name: mat.as_str().to_string(),
-                            });
+                        if c == '^' {
+                            has_arrow = true;
+                            position.column += i;
+                            break;
                         }
+                        has_left_caret = c == '<';
                     }
-                }
-            }
 
-            // Continue walking the tree.
-            if cursor.goto_next_sibling() {
-                ascending = false;
-            } else if !cursor.goto_parent() {
-                break;
+                    // If the comment node contains an arrow and a highlight name, record the
+                    // highlight name and the position.
+                    if let (true, Some(mat)) =
+                        (has_arrow, CAPTURE_NAME_REGEX.find(&text[arrow_end..]))
+                    {
+                        assertion_ranges.push((node.start_position(), node.end_position()));
+                        result.push(Assertion {
+                            position: position,
+                            expected_capture_name: mat.as_str().to_string(),
+                        });
+                    }
+                }
             }
-        } else if !cursor.goto_first_child() {
-            ascending = true;
         }
     }
 
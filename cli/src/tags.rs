@@ -2,10 +2,12 @@ use super::util;
 use anyhow::{anyhow, Result};
 use std::io::{self, Write};
 use std::path::Path;
+use std::sync::atomic::AtomicUsize;
 use std::time::Instant;
 use std::{fs, str};
+use tree_sitter::ParserPool;
 use tree_sitter_loader::Loader;
-use tree_sitter_tags::TagsContext;
+use tree_sitter_tags::{TagsConfiguration, TagsContext};
 
 pub fn generate_tags(
     loader: &Loader,
@@ -13,6 +15,7 @@ pub fn generate_tags(
     paths: &[String],
     quiet: bool,
     time: bool,
+    jobs: usize,
 ) -> Result<()> {
     let mut lang = None;
     if let Some(scope) = scope {
@@ -22,13 +25,9 @@ pub fn generate_tags(
         }
     }
 
-    let mut context = TagsContext::new();
-    let cancellation_flag = util::cancel_on_stdin();
-    let stdout = io::stdout();
-    let mut stdout = stdout.lock();
-
+    let mut files = Vec::new();
     for path in paths {
-        let path = Path::new(&path);
+        let path = Path::new(path);
         let (language, language_config) = match lang {
             Some(v) => v,
             None => match loader.language_configuration_for_file_name(path)? {
@@ -41,58 +40,88 @@ pub fn generate_tags(
         };
 
         if let Some(tags_config) = language_config.tags_config(language)? {
-            let indent;
-            if paths.len() > 1 {
-                if !quiet {
-                    writeln!(&mut stdout, "{}", path.to_string_lossy())?;
-                }
-                indent = "\t"
-            } else {
-                indent = "";
-            };
+            files.push((path, tags_config));
+        } else {
+            eprintln!("No tags config found for path {:?}", path);
+        }
+    }
 
-            let source = fs::read(path)?;
-            let t0 = Instant::now();
-            for tag in context
-                .generate_tags(tags_config, &source, Some(&cancellation_flag))?
-                .0
-            {
-                let tag = tag?;
-                if !quiet {
-                    write!(
-                        &mut stdout,
-                        "{}{:<10}\t | {:<8}\t{} {} - {} `{}`",
-                        indent,
-                        str::from_utf8(&source[tag.name_range]).unwrap_or(""),
-                        &tags_config.syntax_type_name(tag.syntax_type_id),
-                        if tag.is_definition { "def" } else { "ref" },
-                        tag.span.start,
-                        tag.span.end,
-                        str::from_utf8(&source[tag.line_range]).unwrap_or(""),
-                    )?;
-                    if let Some(docs) = tag.docs {
-                        if docs.len() > 120 {
-                            write!(&mut stdout, "\t{:?}...", docs.get(0..120).unwrap_or(""))?;
-                        } else {
-                            write!(&mut stdout, "\t{:?}", &docs)?;
-                        }
-                    }
-                    writeln!(&mut stdout, "")?;
-                }
-            }
+    let cancellation_flag = util::cancel_on_stdin();
+    let indent = if paths.len() > 1 { "\t" } else { "" };
+    let stdout = io::stdout();
 
-            if time {
-                writeln!(
-                    &mut stdout,
-                    "{}time: {}ms",
-                    indent,
-                    t0.elapsed().as_millis(),
-                )?;
+    ParserPool::new(jobs).map_in_order(
+        &files,
+        |_, (path, tags_config)| {
+            // Each file is tagged with its own context, which has its own parser.
+            let mut context = TagsContext::new();
+            let mut output = Vec::new();
+            let result = write_tags(
+                &mut context,
+                tags_config,
+                path,
+                indent,
+                quiet,
+                time,
+                &cancellation_flag,
+                &mut output,
+            );
+            (result, output)
+        },
+        |(result, output)| {
+            stdout.lock().write_all(&output)?;
+            result
+        },
+    )
+}
+
+fn write_tags(
+    context: &mut TagsContext,
+    tags_config: &TagsConfiguration,
+    path: &Path,
+    indent: &str,
+    quiet: bool,
+    time: bool,
+    cancellation_flag: &AtomicUsize,
+    output: &mut impl Write,
+) -> Result<()> {
+    if !indent.is_empty() && !quiet {
+        writeln!(output, "{}", path.to_string_lossy())?;
+    }
+
+    let source = fs::read(path)?;
+    let t0 = Instant::now();
+    for tag in context
+        .generate_tags(tags_config, &source, Some(cancellation_flag))?
+        .0
+    {
+        let tag = tag?;
+        if !quiet {
+            write!(
+                output,
+                "{}{:<10}\t | {:<8}\t{} {} - {} `{}`",
+                indent,
+                str::from_utf8(&source[tag.name_range]).unwrap_or(""),
+                &tags_config.syntax_type_name(tag.syntax_type_id),
+                if tag.is_definition { "def" } else { "ref" },
+                tag.span.start,
+                tag.span.end,
+                str::from_utf8(&source[tag.line_range]).unwrap_or(""),
+            )?;
+            if let Some(docs) = tag.docs {
+                if docs.len() > 120 {
+                    write!(output, "\t{:?}...", docs.get(0..120).unwrap_or(""))?;
+                } else {
+                    write!(output, "\t{:?}", &docs)?;
+                }
             }
-        } else {
-            eprintln!("No tags config found for path {:?}", path);
+            writeln!(output, "")?;
         }
     }
 
+    if time {
+        writeln!(output, "{}time: {}ms", indent, t0.elapsed().as_millis(),)?;
+    }
+
     Ok(())
 }
@@ -6,12 +6,11 @@ use lazy_static::lazy_static;
 use regex::bytes::{Regex as ByteRegex, RegexBuilder as ByteRegexBuilder};
 use regex::Regex;
 use std::ffi::OsStr;
-use std::fmt::Write as FmtWrite;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::str;
-use tree_sitter::{Language, LogType, Parser, Query};
+use tree_sitter::{format_sexp, Language, LogType, Parser, Query};
 use walkdir::WalkDir;
 
 lazy_static! {
@@ -114,8 +113,8 @@ pub fn run_tests_at_path(
             print_diff_key();
             for (i, (name, actual, expected)) in failures.iter().enumerate() {
                 println!("\n  {}. {}:", i + 1, name);
-                let actual = format_sexp_indented(&actual, 2);
-                let expected = format_sexp_indented(&expected, 2);
+                let actual = format_sexp(actual, "  ", 2);
+                let expected = format_sexp(expected, "  ", 2);
                 print_diff(&actual, &expected);
             }
             Err(anyhow!(""))
@@ -192,7 +191,7 @@ fn run_tests(
                 if !name.contains(filter) {
                     if update {
                         let input = String::from_utf8(input).unwrap();
-                        let output = format_sexp(&output);
+                        let output = format_sexp(&output, "  ", 0);
                         corrected_entries.push((name, input, output));
                     }
                     return Ok(());
@@ -210,13 +209,13 @@ fn run_tests(
                 println!("✓ {}", Colour::Green.paint(&name));
                 if update {
                     let input = String::from_utf8(input).unwrap();
-                    let output = format_sexp(&output);
+                    let output = format_sexp(&output, "  ", 0);
                     corrected_entries.push((name, input, output));
                 }
             } else {
                 if update {
                     let input = String::from_utf8(input).unwrap();
-                    let output = format_sexp(&actual);
+                    let output = format_sexp(&actual, "  ", 0);
                     corrected_entries.push((name.clone(), input, output));
                     println!("✓ {}", Colour::Blue.paint(&name));
                 } else {
@@ -263,62 +262,6 @@ fn run_tests(
     Ok(())
 }
 
-fn format_sexp(sexp: &String) -> String {
-    format_sexp_indented(sexp, 0)
-}
-
-fn format_sexp_indented(sexp: &String, initial_indent_level: u32) -> String {
-    let mut formatted = String::new();
-
-    let mut indent_level = initial_indent_level;
-    let mut has_field = false;
-    let mut s_iter = sexp.split(|c| c == ' ' || c == ')');
-    while let Some(s) = s_iter.next() {
-        if s.is_empty() {
-            // ")"
-            indent_level -= 1;
-            write!(formatted, ")").unwrap();
-        } else if s.starts_with('(') {
-            if has_field {
-                has_field = false;
-            } else {
-                if indent_level > 0 {
-                    writeln!(formatted, "").unwrap();
-                    for _ in 0..indent_level {
-                        write!(formatted, "  ").unwrap();
-                    }
-                }
-                indent_level += 1;
-            }
-
-            // "(node_name"
-            write!(formatted, "{}", s).unwrap();
-
-            let mut c_iter = s.chars();
-            c_iter.next();
-            match c_iter.next() {
-                Some('M') | Some('U') => {
-                    // "(MISSING node_name" or "(UNEXPECTED 'x'"
-                    let s = s_iter.next().unwrap();
-                    write!(formatted, " {}", s).unwrap();
-                }
-                Some(_) | None => {}
-            }
-        } else if s.ends_with(':') {
-            // "field:"
-            writeln!(formatted, "").unwrap();
-            for _ in 0..indent_level {
-                write!(formatted, "  ").unwrap();
-            }
-            write!(formatted, "{} ", s).unwrap();
-            has_field = true;
-            indent_level += 1;
-        }
-    }
-
-    formatted
-}
-
 fn write_tests(file_path: &Path, corrected_entries: &Vec<(String, String, String)>) -> Result<()> {
     let mut buffer = fs::File::create(file_path)?;
     write_tests_to_buffer(&mut buffer, corrected_entries)
@@ -580,25 +523,6 @@ abc
         );
     }
 
-    #[test]
-    fn test_format_sexp() {
-        assert_eq!(
-            format_sexp(&"(a b: (c) (d) e: (f (g (h (MISSING i)))))".to_string()),
-            r#"
-(a
-  b: (c)
-  (d)
-  e: (f
-    (g
-      (h
-        (MISSING i)))))
-"#
-            .trim()
-            .to_string()
-        );
-        assert_eq!(format_sexp(&"()".to_string()), "()".to_string());
-    }
-
     #[test]
     fn test_write_tests_to_buffer() {
         let mut buffer = Vec::new();
@@ -0,0 +1,234 @@
+use super::helpers::edits::get_random_edit;
+use super::helpers::fixtures::{get_language, get_language_queries_path};
+use super::helpers::random::Rand;
+use crate::parse::perform_edit;
+use std::fs;
+use tree_sitter::{
+    Document, LayerConfiguration, LayeredDocument, Parser, Point, Query, QueryCursor,
+};
+
+#[test]
+fn test_document_edits() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let mut document = Document::new("let a = 1;\nlet b = 2;\n");
+    let changed_ranges = document.parse(&mut parser).unwrap();
+    assert_eq!(
+        changed_ranges,
+        &[document.tree().unwrap().root_node().range()]
+    );
+    assert_eq!(document.line_count(), 3);
+
+    // Edit by byte offset.
+    let edit = document.edit(8..9, "foo(x)");
+    assert_eq!(edit.start_position, Point::new(0, 8));
+    assert_eq!(edit.old_end_position, Point::new(0, 9));
+    assert_eq!(edit.new_end_position, Point::new(0, 14));
+    assert_eq!(document.text(), b"let a = foo(x);\nlet b = 2;\n".as_ref());
+    assert_eq!(document.tree().unwrap().root_node().end_byte(), 27);
+
+    let changed_ranges = document.parse(&mut parser).unwrap();
+    assert_eq!(changed_ranges.len(), 1);
+    assert_eq!(changed_ranges[0].start_point, Point::new(0, 8));
+    assert_eq!(changed_ranges[0].end_point, Point::new(0, 14));
+
+    // Edit by position, inserting and removing lines.
+    let edit = document.edit_points(Point::new(0, 14), Point::new(1, 0), ";\nlet c;\n");
+    assert_eq!(edit.start_byte, 14);
+    assert_eq!(edit.old_end_byte, 16);
+    assert_eq!(edit.new_end_position, Point::new(2, 0));
+    assert_eq!(
+        document.text(),
+        b"let a = foo(x);\nlet c;\nlet b = 2;\n".as_ref()
+    );
+    assert_eq!(document.line_count(), 4);
+    assert_eq!(document.point_for_byte(25), Point::new(2, 2));
+    assert_eq!(document.byte_for_point(Point::new(2, 2)), 25);
+    assert_eq!(document.byte_for_point(Point::new(1, 100)), 22);
+    assert_eq!(document.byte_for_point(Point::new(10, 0)), document.len());
+
+    document.parse(&mut parser).unwrap();
+    assert_eq!(
+        document.tree().unwrap().root_node().to_sexp(),
+        parser
+            .parse(document.text(), None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+}
+
+#[test]
+fn test_document_utf16_positions() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let mut document = Document::new("s = '\u{1F600}é';\nt = 'x';");
+    document.parse(&mut parser).unwrap();
+
+    // The emoji is four UTF-8 bytes and two UTF-16 code units.
+    assert_eq!(document.utf16_point_for_byte(9), Point::new(0, 7));
+    assert_eq!(document.byte_for_utf16_point(Point::new(0, 7)), 9);
+    assert_eq!(document.utf16_point_for_byte(11), Point::new(0, 8));
+    assert_eq!(document.byte_for_utf16_point(Point::new(0, 6)), 9);
+    assert_eq!(document.byte_for_utf16_point(Point::new(1, 5)), 19);
+
+    let edit = document.edit_utf16_points(Point::new(0, 5), Point::new(0, 8), "ok");
+    assert_eq!(edit.start_byte, 5);
+    assert_eq!(edit.old_end_byte, 11);
+    assert_eq!(document.text(), b"s = 'ok';\nt = 'x';".as_ref());
+
+    document.parse(&mut parser).unwrap();
+    let string = document
+        .tree()
+        .unwrap()
+        .root_node()
+        .descendant_for_byte_range(5, 5)
+        .unwrap()
+        .parent()
+        .unwrap();
+    assert_eq!(string.kind(), "string");
+    assert_eq!(
+        document.text_for_byte_range(string.byte_range()),
+        b"'ok'".as_ref()
+    );
+}
+
+#[test]
+fn test_document_as_text_provider() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(language).unwrap();
+
+    let mut document = Document::new("a; b; c;");
+    document.parse(&mut parser).unwrap();
+
+    // After this edit, the text is split around the edit position.
+    document.edit(3..4, "bee");
+    document.parse(&mut parser).unwrap();
+
+    let query = Query::new(language, r#"((identifier) @id (#eq? @id "bee"))"#).unwrap();
+    let mut cursor = QueryCursor::new();
+    let matches = cursor
+        .matches(&query, document.tree().unwrap().root_node(), &document)
+        .map(|m| m.captures[0].node.byte_range())
+        .collect::<Vec<_>>();
+    assert_eq!(matches, vec![3..6]);
+}
+
+#[test]
+fn test_layered_document() {
+    let mut document = LayeredDocument::new(
+        "<div>\n<script>\nconst b = html`<b>${x}</b>`;\n</script>\n</div>\n",
+        get_layer_configuration("html"),
+    );
+    document.add_language(get_layer_configuration("javascript"));
+
+    let mut parser = Parser::new();
+    assert!(document.parse(&mut parser));
+    assert_eq!(
+        layer_summaries(&document),
+        ["html 0", "javascript 1", "html 2"]
+    );
+
+    let text = document.document().text().into_owned();
+    let text = std::str::from_utf8(&text).unwrap();
+    let script_start = text.find("const").unwrap();
+    let script_end = text.find("</script>").unwrap();
+    let script_layer = &document.layers()[1];
+    assert_eq!(script_layer.included_ranges().len(), 1);
+    assert_eq!(script_layer.included_ranges()[0].start_byte, script_start);
+    assert_eq!(script_layer.included_ranges()[0].end_byte, script_end);
+
+    assert_eq!(document.layer_for_byte(0).unwrap().depth(), 0);
+    assert_eq!(
+        document.layer_for_byte(script_start).unwrap().name(),
+        "javascript"
+    );
+    assert_eq!(
+        document
+            .layer_for_byte(text.find("<b>").unwrap() + 1)
+            .unwrap()
+            .depth(),
+        2
+    );
+    assert_eq!(
+        document.layer_for_point(Point::new(2, 0)).unwrap().name(),
+        "javascript"
+    );
+
+    // Edits are applied to every layer, and the injected layers are reparsed.
+    document.edit(script_start..script_start + 5, "let");
+    let script_layer = &document.layers()[1];
+    assert!(script_layer.tree().root_node().has_changes());
+    assert_eq!(script_layer.included_ranges()[0].end_byte, script_end - 2);
+    assert!(document.parse(&mut parser));
+    assert_eq!(
+        layer_summaries(&document),
+        ["html 0", "javascript 1", "html 2"]
+    );
+    assert_eq!(
+        document.layers()[1]
+            .tree()
+            .root_node()
+            .child(0)
+            .unwrap()
+            .kind(),
+        "lexical_declaration"
+    );
+
+    // Removing the template tag removes the nested layer.
+    let tag_start = script_start + "let b = ".len();
+    document.edit(tag_start..tag_start + 4, "");
+    assert!(document.parse(&mut parser));
+    assert_eq!(layer_summaries(&document), ["html 0", "javascript 1"]);
+    assert_eq!(
+        document.layer_for_byte(tag_start + 2).unwrap().name(),
+        "javascript"
+    );
+}
+
+#[test]
+fn test_document_random_edits() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let mut rand = Rand::new(0);
+    let mut input = b"function a(b) {\n  return [b, c + d];\n}\n".to_vec();
+    let mut document = Document::new(input.clone());
+    let mut tree = parser.parse(&input, None).unwrap();
+    document.parse(&mut parser).unwrap();
+
+    for _ in 0..20 {
+        let edit = get_random_edit(&mut rand, &input);
+        let expected_edit = perform_edit(&mut tree, &mut input, &edit);
+        let actual_edit = document.edit(
+            edit.position..edit.position + edit.deleted_length,
+            &edit.inserted_text,
+        );
+        assert_eq!(actual_edit, expected_edit);
+        assert_eq!(document.text(), input.as_slice());
+
+        tree = parser.parse(&input, Some(&tree)).unwrap();
+        document.parse(&mut parser).unwrap();
+        assert_eq!(
+            document.tree().unwrap().root_node().to_sexp(),
+            tree.root_node().to_sexp()
+        );
+    }
+}
+
+fn get_layer_configuration(name: &str) -> LayerConfiguration {
+    let injections =
+        fs::read_to_string(get_language_queries_path(name).join("injections.scm")).unwrap();
+    LayerConfiguration::new(name, get_language(name), &injections).unwrap()
+}
+
+fn layer_summaries(document: &LayeredDocument) -> Vec<String> {
+    document
+        .layers()
+        .iter()
+        .map(|layer| format!("{} {}", layer.name(), layer.depth()))
+        .collect()
+}
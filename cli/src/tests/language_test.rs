@@ -0,0 +1,256 @@
+use super::helpers::fixtures::{get_language, get_test_language};
+use crate::generate::generate_parser_for_grammar;
+use tree_sitter::{ChildInfo, Language, Parser, LANGUAGE_VERSION};
+
+#[test]
+fn test_lookahead_iterator() {
+    let language = get_language("rust");
+    let mut parser = Parser::new();
+    parser.set_language(language).unwrap();
+
+    let tree = parser.parse("struct Stuff {}", None).unwrap();
+    let mut cursor = tree.walk();
+    assert!(cursor.goto_first_child()); // struct_item
+    assert!(cursor.goto_first_child()); // struct
+
+    let node = cursor.node();
+    let next_state = node.next_parse_state();
+    assert_ne!(next_state, 0);
+    assert!((next_state as usize) < language.parse_state_count());
+    assert_eq!(
+        next_state,
+        language.next_state(node.parse_state(), node.grammar_id())
+    );
+
+    // The type name is an `identifier` in the grammar, aliased to `type_identifier`.
+    assert!(cursor.goto_next_sibling());
+    let node = cursor.node();
+    assert_eq!(node.parse_state(), next_state);
+    assert_eq!(node.kind(), "type_identifier");
+    assert_eq!(
+        language.node_kind_for_id(node.grammar_id()),
+        Some("identifier")
+    );
+    assert_ne!(node.grammar_id(), node.kind_id());
+
+    let expected_symbols = ["identifier", "block_comment", "line_comment"];
+    let mut lookahead = language.lookahead_iterator(next_state).unwrap();
+    assert_eq!(lookahead.language(), language);
+    assert_eq!(lookahead.current_symbol_name(), "ERROR");
+    assert_eq!(lookahead.iter_names().collect::<Vec<_>>(), expected_symbols);
+
+    assert!(lookahead.reset_state(next_state));
+    assert_eq!(lookahead.iter_names().collect::<Vec<_>>(), expected_symbols);
+
+    assert!(lookahead.reset(language, next_state));
+    assert_eq!(
+        lookahead
+            .map(|symbol| language.node_kind_for_id(symbol).unwrap())
+            .collect::<Vec<_>>(),
+        expected_symbols
+    );
+}
+
+#[test]
+fn test_lookahead_iterator_with_invalid_states() {
+    let language = get_language("rust");
+    let state_count = language.parse_state_count() as u16;
+    assert!(language.lookahead_iterator(state_count).is_none());
+    assert_eq!(language.next_state(state_count, 1), 0);
+    assert_eq!(language.next_state(1, language.node_kind_count() as u16), 0);
+    assert_eq!(language.next_state(1, u16::MAX), 0);
+
+    let mut lookahead = language.lookahead_iterator(1).unwrap();
+    assert!(!lookahead.reset_state(state_count));
+    assert!(!lookahead.reset(get_language("javascript"), u16::MAX));
+}
+
+#[test]
+fn test_lookahead_iterator_at_error() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(language).unwrap();
+
+    let tree = parser.parse("let x = ;", None).unwrap();
+    let error = tree
+        .root_node()
+        .descendant_for_byte_range(6, 7)
+        .unwrap()
+        .parent()
+        .unwrap();
+    assert!(error.is_error());
+
+    // The state of the error's first leaf describes what the parser expected there.
+    let leaf = error.child(0).unwrap();
+    let symbols = language
+        .lookahead_iterator(leaf.parse_state())
+        .unwrap()
+        .iter_names()
+        .collect::<Vec<_>>();
+    for symbol in ["=", ",", ";"] {
+        assert!(symbols.contains(&symbol), "{:?}", symbols);
+    }
+    assert!(!symbols.contains(&"identifier"), "{:?}", symbols);
+}
+
+#[test]
+fn test_language_supertypes_and_child_infos() {
+    let (parser_name, parser_code) = generate_parser_for_grammar(
+        r#"
+        {
+            "name": "test_grammar_with_supertypes",
+            "extras": [{"type": "PATTERN", "value": "\\s+"}],
+            "supertypes": ["_expression"],
+            "rules": {
+                "program": {
+                    "type": "REPEAT",
+                    "content": {"type": "SYMBOL", "name": "_expression"}
+                },
+                "_expression": {
+                    "type": "CHOICE",
+                    "members": [
+                        {"type": "SYMBOL", "name": "identifier"},
+                        {"type": "SYMBOL", "name": "number"},
+                        {"type": "SYMBOL", "name": "binary_expression"},
+                        {"type": "SYMBOL", "name": "call"}
+                    ]
+                },
+                "binary_expression": {
+                    "type": "PREC_LEFT",
+                    "value": 1,
+                    "content": {
+                        "type": "SEQ",
+                        "members": [
+                            {
+                                "type": "FIELD",
+                                "name": "left",
+                                "content": {"type": "SYMBOL", "name": "_expression"}
+                            },
+                            {
+                                "type": "FIELD",
+                                "name": "operator",
+                                "content": {
+                                    "type": "CHOICE",
+                                    "members": [
+                                        {"type": "STRING", "value": "+"},
+                                        {"type": "STRING", "value": "-"}
+                                    ]
+                                }
+                            },
+                            {
+                                "type": "FIELD",
+                                "name": "right",
+                                "content": {"type": "SYMBOL", "name": "_expression"}
+                            }
+                        ]
+                    }
+                },
+                "call": {
+                    "type": "SEQ",
+                    "members": [
+                        {
+                            "type": "FIELD",
+                            "name": "function",
+                            "content": {
+                                "type": "ALIAS",
+                                "value": "function_name",
+                                "named": true,
+                                "content": {"type": "SYMBOL", "name": "identifier"}
+                            }
+                        },
+                        {"type": "STRING", "value": "("},
+                        {
+                            "type": "REPEAT",
+                            "content": {"type": "SYMBOL", "name": "number"}
+                        },
+                        {"type": "STRING", "value": ")"}
+                    ]
+                },
+                "identifier": {"type": "PATTERN", "value": "[a-z]+"},
+                "number": {"type": "PATTERN", "value": "\\d+"}
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    let language = get_test_language(&parser_name, &parser_code, None);
+    assert_eq!(language.version(), LANGUAGE_VERSION);
+
+    let id = |kind, named| language.id_for_node_kind(kind, named);
+    let expression = id("_expression", true);
+    assert_eq!(language.supertypes(), &[expression]);
+    assert_eq!(
+        kinds(language, language.subtypes(expression)),
+        ["binary_expression", "call", "identifier", "number"]
+    );
+    assert_eq!(language.subtypes(id("call", true)), &[] as &[u16]);
+
+    let binary_expression = id("binary_expression", true);
+    assert_eq!(
+        summarize_child_infos(language, language.node_kind_fields(binary_expression)),
+        [
+            (Some("left"), false, true, vec!["_expression"]),
+            (Some("operator"), false, true, vec!["+", "-"]),
+            (Some("right"), false, true, vec!["_expression"]),
+        ]
+    );
+    assert_eq!(language.node_kind_children(binary_expression), None);
+
+    // Aliased node kinds are included, and children without fields are listed
+    // separately.
+    let call = id("call", true);
+    assert_eq!(
+        summarize_child_infos(language, language.node_kind_fields(call)),
+        [(Some("function"), false, true, vec!["function_name"])]
+    );
+    assert_eq!(
+        summarize_child_infos(language, language.node_kind_children(call)),
+        [(None, true, false, vec!["number"])]
+    );
+    assert_eq!(
+        summarize_child_infos(language, language.node_kind_children(id("program", true))),
+        [(None, true, false, vec!["_expression"])]
+    );
+
+    assert!(language.node_kind_fields(id("identifier", true)).is_empty());
+    assert_eq!(language.node_kind_children(id("identifier", true)), None);
+}
+
+#[test]
+fn test_language_supertypes_with_older_abi_version() {
+    // The fixture grammars were generated with an older ABI version, which does not
+    // include this information.
+    let language = get_language("javascript");
+    assert!(language.version() < 15);
+    assert!(language.supertypes().is_empty());
+    let statement = language.id_for_node_kind("_statement", true);
+    assert!(language.subtypes(statement).is_empty());
+    let program = language.id_for_node_kind("program", true);
+    assert_eq!(language.node_kind_children(program), None);
+}
+
+fn summarize_child_infos(
+    language: Language,
+    infos: impl IntoIterator<Item = ChildInfo>,
+) -> Vec<ChildInfoSummary> {
+    infos
+        .into_iter()
+        .map(|info| {
+            (
+                info.field_id
+                    .map(|id| language.field_name_for_id(id).unwrap()),
+                info.multiple,
+                info.required,
+                kinds(language, info.types),
+            )
+        })
+        .collect()
+}
+
+type ChildInfoSummary = (Option<&'static str>, bool, bool, Vec<&'static str>);
+
+fn kinds(language: Language, ids: &[u16]) -> Vec<&'static str> {
+    ids.iter()
+        .map(|id| language.node_kind_for_id(*id).unwrap())
+        .collect()
+}
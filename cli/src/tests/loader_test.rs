@@ -0,0 +1,163 @@
+use super::helpers::fixtures::get_language;
+use std::{fs, path::Path};
+use tree_sitter::{QueryError, QueryErrorKind};
+use tree_sitter_loader::Loader;
+
+#[test]
+fn test_loader_query_composition() {
+    let dir = tempfile::tempdir().unwrap();
+    write_grammar(
+        &dir.path().join("tree-sitter-base"),
+        &[
+            (
+                "queries/highlights.scm",
+                "; name: declarations\n[\"var\" \"let\"] @keyword\n\n(identifier) @variable\n",
+            ),
+            ("queries/constants.scm", "(number) @number\n"),
+        ],
+    );
+    write_grammar(
+        &dir.path().join("tree-sitter-derived"),
+        &[
+            (
+                "queries/highlights.scm",
+                r#"; A language that extends the base language
+; inherits: base
+
+; include: base:queries/constants.scm
+; include: queries/strings.scm
+
+; name: declarations
+["var" "let" "const"] @keyword.declaration
+"#,
+            ),
+            ("queries/strings.scm", "(string) @string\n"),
+        ],
+    );
+
+    let mut loader = Loader::with_parser_lib_path(dir.path().join("lib"));
+    loader
+        .find_language_configurations_at_path(&dir.path().join("tree-sitter-base"))
+        .unwrap();
+    let configuration = &loader
+        .find_language_configurations_at_path(&dir.path().join("tree-sitter-derived"))
+        .unwrap()[0];
+    let highlight_config = configuration
+        .highlight_config(get_language("javascript"))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        highlight_config.query.capture_names(),
+        &["variable", "number", "string", "keyword.declaration"]
+    );
+}
+
+#[test]
+fn test_loader_query_composition_with_a_shared_ancestor() {
+    let dir = tempfile::tempdir().unwrap();
+    write_grammar(
+        &dir.path().join("tree-sitter-base"),
+        &[("queries/highlights.scm", "(identifier) @variable\n")],
+    );
+    write_grammar(
+        &dir.path().join("tree-sitter-left"),
+        &[(
+            "queries/highlights.scm",
+            "; inherits: base\n\n(string) @string\n",
+        )],
+    );
+    write_grammar(
+        &dir.path().join("tree-sitter-right"),
+        &[(
+            "queries/highlights.scm",
+            "; inherits: base\n\n(comment) @comment\n",
+        )],
+    );
+    write_grammar(
+        &dir.path().join("tree-sitter-top"),
+        &[(
+            "queries/highlights.scm",
+            "; inherits: left, right\n\n(number) @number\n",
+        )],
+    );
+
+    let mut loader = Loader::with_parser_lib_path(dir.path().join("lib"));
+    for name in ["base", "left", "right"] {
+        loader
+            .find_language_configurations_at_path(&dir.path().join(format!("tree-sitter-{}", name)))
+            .unwrap();
+    }
+    let configuration = &loader
+        .find_language_configurations_at_path(&dir.path().join("tree-sitter-top"))
+        .unwrap()[0];
+    let highlight_config = configuration
+        .highlight_config(get_language("javascript"))
+        .unwrap()
+        .unwrap();
+
+    // The base language's queries are only included once.
+    assert_eq!(highlight_config.query.pattern_count(), 4);
+    assert_eq!(
+        highlight_config.query.capture_names(),
+        &["variable", "string", "comment", "number"]
+    );
+}
+
+#[test]
+fn test_loader_query_composition_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    write_grammar(
+        &dir.path().join("unknown-language"),
+        &[("queries/highlights.scm", "; inherits: unknown\n")],
+    );
+    write_grammar(
+        &dir.path().join("invalid-include"),
+        &[
+            (
+                "queries/highlights.scm",
+                "(identifier) @variable\n; include: queries/invalid.scm\n",
+            ),
+            (
+                "queries/invalid.scm",
+                "(number) @number\n\n(string (not_a_node))\n",
+            ),
+        ],
+    );
+
+    let mut loader = Loader::with_parser_lib_path(dir.path().join("lib"));
+    let configuration = &loader
+        .find_language_configurations_at_path(&dir.path().join("unknown-language"))
+        .unwrap()[0];
+    let error = configuration
+        .highlight_config(get_language("javascript"))
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Unknown language \"unknown\" in query file \"highlights.scm\""
+    );
+
+    let configuration = &loader
+        .find_language_configurations_at_path(&dir.path().join("invalid-include"))
+        .unwrap()[0];
+    let error = configuration
+        .highlight_config(get_language("javascript"))
+        .err()
+        .unwrap();
+    assert!(error.to_string().ends_with("invalid.scm\""));
+    let query_error = error.downcast_ref::<QueryError>().unwrap();
+    assert_eq!(query_error.kind, QueryErrorKind::NodeType);
+    assert_eq!(query_error.row, 2);
+    assert_eq!(query_error.offset, 27);
+}
+
+fn write_grammar(path: &Path, query_files: &[(&str, &str)]) {
+    fs::create_dir_all(path.join("src")).unwrap();
+    fs::write(path.join("src").join("grammar.json"), "{}").unwrap();
+    for (query_path, contents) in query_files {
+        let query_path = path.join(query_path);
+        fs::create_dir_all(query_path.parent().unwrap()).unwrap();
+        fs::write(query_path, contents).unwrap();
+    }
+}
@@ -1,11 +1,19 @@
 mod corpus_test;
+mod document_test;
 mod helpers;
 mod highlight_test;
+mod language_test;
+mod loader_test;
 mod node_test;
 mod parser_test;
 mod pathological_test;
+mod query_format_test;
+mod query_lint_test;
 mod query_test;
 mod tags_test;
 mod test_highlight_test;
 mod test_tags_test;
 mod tree_test;
+
+#[cfg(feature = "wasm")]
+mod wasm_language_test;
@@ -3,8 +3,10 @@ use super::helpers::fixtures::{fixtures_dir, get_language, get_test_language};
 use super::helpers::random::Rand;
 use crate::generate::generate_parser_for_grammar;
 use crate::parse::perform_edit;
-use std::fs;
-use tree_sitter::{Node, Parser, Point, Tree};
+use std::{borrow::Cow, fs, sync::Arc, thread};
+use tree_sitter::{
+    format_sexp, Document, Node, OwnedNode, Parser, Point, SexpOptions, SyntaxErrorKind, Tree,
+};
 
 const JSON_EXAMPLE: &'static str = r#"
 
@@ -572,6 +574,37 @@ fn test_node_is_extra() {
     assert!(comment_node.is_extra());
 }
 
+#[test]
+fn test_owned_node() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = Arc::new(parser.parse("foo(bar, baz);", None).unwrap());
+
+    let root = OwnedNode::root(tree.clone());
+    let call = root
+        .with_node(root.node().descendant_for_byte_range(0, 0).unwrap())
+        .unwrap();
+    assert_eq!(call.node().kind(), "identifier");
+    assert_eq!(call, OwnedNode::new(tree.clone(), call.node()).unwrap());
+    assert_ne!(call, root);
+
+    // Nodes from a different tree are rejected, even if that tree is a clone.
+    let other_tree = tree.as_ref().clone();
+    assert!(root.with_node(other_tree.root_node()).is_none());
+
+    let arguments = thread::spawn(move || {
+        drop(root);
+        let arguments = call.node().next_sibling().unwrap();
+        assert_eq!(arguments.kind(), "arguments");
+        call.with_node(arguments).unwrap()
+    })
+    .join()
+    .unwrap();
+    drop(tree);
+    assert_eq!(arguments.node().named_child_count(), 2);
+    assert_eq!(arguments.tree().root_node().kind(), "program");
+}
+
 #[test]
 fn test_node_sexp() {
     let mut parser = Parser::new();
@@ -836,6 +869,256 @@ fn test_node_numeric_symbols_respect_simple_aliases() {
     assert_eq!(unary_minus_node.kind_id(), binary_minus_node.kind_id());
 }
 
+#[test]
+fn test_node_to_sexp_with_options() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let source = "if (a) { b(c) }\nx = (1;";
+    let tree = parser.parse(source, None).unwrap();
+    let root = tree.root_node();
+    let if_statement = root.child(0).unwrap();
+
+    assert_eq!(root.to_sexp_with(&SexpOptions::default()), root.to_sexp());
+    for (options, expected) in [
+        (
+            SexpOptions {
+                fields: false,
+                ..Default::default()
+            },
+            "(if_statement (parenthesized_expression (identifier)) (statement_block (expression_statement (call_expression (identifier) (arguments (identifier))))))",
+        ),
+        (
+            SexpOptions {
+                markers: false,
+                anonymous_nodes: true,
+                ..Default::default()
+            },
+            "(if_statement \"if\" condition: (parenthesized_expression \"(\" (identifier) \")\") consequence: (statement_block \"{\" (expression_statement (call_expression function: (identifier) arguments: (arguments \"(\" (identifier) \")\"))) \"}\"))",
+        ),
+        (
+            SexpOptions {
+                ranges: true,
+                text: true,
+                markers: false,
+                source: Some(source.as_bytes()),
+                ..Default::default()
+            },
+            "(if_statement [0, 0] - [0, 15] condition: (parenthesized_expression [0, 3] - [0, 6] (identifier [0, 4] - [0, 5] \"a\")) consequence: (statement_block [0, 7] - [0, 15] (expression_statement [0, 9] - [0, 13] (call_expression [0, 9] - [0, 13] function: (identifier [0, 9] - [0, 10] \"b\") arguments: (arguments [0, 10] - [0, 13] (identifier [0, 11] - [0, 12] \"c\"))))))",
+        ),
+    ] {
+        assert_eq!(if_statement.to_sexp_with(&options), expected);
+    }
+
+    // Missing nodes are marked by default.
+    let statement = root.child(1).unwrap();
+    assert_eq!(
+        statement.to_sexp_with(&SexpOptions {
+            indent: Some("  "),
+            ..Default::default()
+        }),
+        [
+            "(expression_statement",
+            "  (assignment_expression",
+            "    left: (identifier)",
+            "    right: (parenthesized_expression",
+            "      (number)",
+            "      (MISSING \")\"))))",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        statement.to_sexp_with(&SexpOptions {
+            fields: false,
+            markers: false,
+            ..Default::default()
+        }),
+        "(expression_statement (assignment_expression (identifier) (parenthesized_expression (number))))",
+    );
+}
+
+#[test]
+fn test_format_sexp() {
+    assert_eq!(
+        format_sexp("(a b: (c) (d) e: (f (g (h (MISSING i)))))", "  ", 0),
+        [
+            "(a",
+            "  b: (c)",
+            "  (d)",
+            "  e: (f",
+            "    (g",
+            "      (h",
+            "        (MISSING i)))))",
+        ]
+        .join("\n")
+    );
+    assert_eq!(format_sexp("()", "  ", 0), "()");
+    assert_eq!(
+        format_sexp("(a (UNEXPECTED ')') (MISSING \"(\"))", "  ", 1),
+        "\n  (a\n    (UNEXPECTED ')')\n    (MISSING \"(\"))"
+    );
+
+    // Formatting the output of `to_sexp` is the same as printing with indentation.
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = parser.parse("if (a) { b(c) }\nx = (1;", None).unwrap();
+    let root = tree.root_node();
+    assert_eq!(
+        format_sexp(&root.to_sexp(), "  ", 0),
+        root.to_sexp_with(&SexpOptions {
+            indent: Some("  "),
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn test_node_syntax_errors() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("json")).unwrap();
+
+    // A pair that is missing its comma is wrapped in an error, so the unexpected token
+    // is the one that follows the error.
+    let source = r#"{"a": 1 "b": 2, "c": 3}"#;
+    let tree = parser.parse(source, None).unwrap();
+    let errors = tree.root_node().syntax_errors(source.as_bytes());
+    assert_eq!(errors.len(), 1);
+    let error = &errors[0];
+    assert_eq!(error.kind, SyntaxErrorKind::Unexpected);
+    assert_eq!(error.node.kind(), "ERROR");
+    assert_eq!(error.range.start_point, Point::new(0, 8));
+    assert_eq!(error.range.end_point, Point::new(0, 9));
+    assert_eq!(error.unexpected.as_deref(), Some("\""));
+    assert!(error.expected.contains(&","), "{:?}", error.expected);
+    assert!(error.expected.contains(&"}"), "{:?}", error.expected);
+    assert!(!error.expected.contains(&"string"), "{:?}", error.expected);
+    assert!(
+        error
+            .message
+            .starts_with(r#"unexpected "\"", expected one of ",", "}""#),
+        "{}",
+        error.message
+    );
+
+    // An invalid token within an error is reported along with the symbols that were
+    // valid at that position.
+    let source = "[1, 2 3]";
+    let tree = parser.parse(source, None).unwrap();
+    let errors = tree.root_node().syntax_errors(source.as_bytes());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].range.start_point, Point::new(0, 6));
+    assert_eq!(errors[0].unexpected.as_deref(), Some("3"));
+    assert!(
+        errors[0].expected.contains(&"]"),
+        "{:?}",
+        errors[0].expected
+    );
+
+    // Missing nodes are reported as well, in document order.
+    parser.set_language(get_language("rust")).unwrap();
+    let source = "fn a() { let x = 1 }\nfn b(c: i32 { c + 1 }";
+    let tree = parser.parse(source, None).unwrap();
+    let errors = tree.root_node().syntax_errors(source.as_bytes());
+    assert_eq!(
+        errors
+            .iter()
+            .map(|error| (error.kind, error.range.start_point, error.message.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (
+                SyntaxErrorKind::Missing,
+                Point::new(0, 18),
+                r#"missing ";""#
+            ),
+            (
+                SyntaxErrorKind::Missing,
+                Point::new(1, 11),
+                r#"missing ")""#
+            ),
+        ]
+    );
+    assert_eq!(errors[0].expected, [";"]);
+    assert_eq!(errors[0].unexpected, None);
+
+    // An error at the end of the input has no unexpected text.
+    parser.set_language(get_language("javascript")).unwrap();
+    let source = "function (";
+    let tree = parser.parse(source, None).unwrap();
+    let errors = tree.root_node().syntax_errors(source.as_bytes());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].unexpected, None);
+    assert_eq!(errors[0].range.start_byte, source.len());
+    assert!(
+        errors[0]
+            .message
+            .starts_with("unexpected end of input, expected "),
+        "{}",
+        errors[0].message
+    );
+
+    // Nodes without errors have no syntax errors.
+    let source = "function a() {}";
+    let tree = parser.parse(source, None).unwrap();
+    assert!(tree.root_node().syntax_errors(source.as_bytes()).is_empty());
+}
+
+#[test]
+fn test_node_text_with_chunked_source() {
+    let source = "s = 'h\u{e9}llo \u{1F600} w\u{f6}rld';";
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+    let string = tree
+        .root_node()
+        .descendant_for_byte_range(5, 5)
+        .unwrap()
+        .parent()
+        .unwrap();
+    assert_eq!(string.kind(), "string");
+    let expected_text = string.utf8_text(source.as_bytes()).unwrap();
+
+    // Split the source into small chunks, so that some characters are split between
+    // chunks.
+    let chunks = source.as_bytes().chunks(3).collect::<Vec<_>>();
+    let chunked_source = |node: Node| {
+        let range = node.byte_range();
+        chunks.iter().enumerate().filter_map(move |(i, chunk)| {
+            let start = range.start.saturating_sub(i * 3).min(chunk.len());
+            let end = range.end.saturating_sub(i * 3).min(chunk.len());
+            Some(&chunk[start..end]).filter(|chunk| !chunk.is_empty())
+        })
+    };
+
+    assert_eq!(string.utf8_text_with(&chunked_source), expected_text);
+    let text_chunks = string.utf8_text_chunks(&chunked_source).collect::<Vec<_>>();
+    assert!(text_chunks.len() > 1);
+    assert!(text_chunks
+        .iter()
+        .any(|chunk| matches!(chunk, Cow::Borrowed(_))));
+    assert_eq!(text_chunks.concat(), expected_text);
+
+    // Text that is contained in a single chunk is borrowed.
+    let text = string.utf8_text_with(source.as_bytes());
+    assert!(matches!(text, Cow::Borrowed(_)));
+    assert_eq!(text, expected_text);
+
+    // Documents store their text in two parts, which can also be read directly.
+    let mut document = Document::new(source);
+    document.parse(&mut parser);
+    document.edit(8..9, "L");
+    document.parse(&mut parser);
+    let tree = document.tree().unwrap();
+    let string = tree.root_node().descendant_for_byte_range(5, 5).unwrap();
+    assert_eq!(
+        string.utf8_text_with(&document),
+        "h\u{e9}Llo \u{1F600} w\u{f6}rld"
+    );
+
+    // Invalid UTF-8 is replaced.
+    let invalid_source = |_: Node| vec![&b"'\xe9"[..], &b"\x80'"[..]].into_iter();
+    assert_eq!(string.utf8_text_with(invalid_source), "'\u{fffd}'");
+}
+
 fn get_all_nodes(tree: &Tree) -> Vec<Node> {
     let mut result = Vec::new();
     let mut visited_children = false;
@@ -9,10 +9,19 @@ use crate::{
     parse::{perform_edit, Edit},
 };
 use std::{
-    sync::atomic::{AtomicUsize, Ordering},
+    collections::HashSet,
+    io,
+    os::raw::c_void,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     thread, time,
 };
-use tree_sitter::{IncludedRangesError, InputEdit, LogType, Parser, Point, Range};
+use tree_sitter::{
+    Allocator, IncludedRangesError, InputEdit, LogType, ParseAction, Parser, ParserPool, Point,
+    Query, QueryCursor, Range,
+};
 
 #[test]
 fn test_parsing_simple_string() {
@@ -211,6 +220,109 @@ fn test_parsing_with_callback_returning_owned_strings() {
     );
 }
 
+#[test]
+fn test_parsing_with_chunked_input() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("rust")).unwrap();
+
+    // Store the text as a list of chunks with their starting offsets, like a rope.
+    let chunks = ["pub f", "n foo", "() {", " 1 }"];
+    let mut offsets = vec![0];
+    for chunk in &chunks {
+        offsets.push(offsets.last().unwrap() + chunk.len());
+    }
+
+    let mut requested_offsets = Vec::new();
+    let tree = parser
+        .parse_chunks(
+            &mut |offset| {
+                requested_offsets.push(offset);
+                let i = offsets.partition_point(|&start| start <= offset) - 1;
+                (chunks.get(i).copied().unwrap_or(""), offsets[i])
+            },
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(source_file (function_item (visibility_modifier) name: (identifier) parameters: (parameters) body: (block (integer_literal))))"
+    );
+
+    // The parser may ask for offsets in the middle of a chunk when it backtracks.
+    assert!(requested_offsets
+        .iter()
+        .any(|offset| !offsets.contains(offset)));
+}
+
+#[test]
+fn test_parsing_with_a_reader() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    // Use a source that is larger than a single chunk.
+    let source = "const x = [1, 2, 3];\n".repeat(10000);
+    let expected_tree = parser.parse(&source, None).unwrap();
+
+    let mut reader = io::Cursor::new(source.as_bytes());
+    let tree = parser.parse_reader(&mut reader, None).unwrap().unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        expected_tree.root_node().to_sexp()
+    );
+
+    // Reparse after an edit, reusing the old tree.
+    let mut source = source.into_bytes();
+    let mut tree = tree;
+    perform_edit(
+        &mut tree,
+        &mut source,
+        &Edit {
+            position: 100000,
+            deleted_length: 0,
+            inserted_text: b"let y;\n".to_vec(),
+        },
+    );
+    let mut reader = io::Cursor::new(&source);
+    let new_tree = parser
+        .parse_reader(&mut reader, Some(&tree))
+        .unwrap()
+        .unwrap();
+    let expected_tree = parser.parse(&source, None).unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        expected_tree.root_node().to_sexp()
+    );
+}
+
+#[test]
+fn test_parsing_with_a_failing_reader() {
+    struct FailingReader(io::Cursor<Vec<u8>>);
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.position() > 0 {
+                return Err(io::Error::other("disconnected"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    impl io::Seek for FailingReader {
+        fn seek(&mut self, position: io::SeekFrom) -> io::Result<u64> {
+            self.0.seek(position)
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let source = "let a = 1;\n".repeat(10000).into_bytes();
+    let mut reader = FailingReader(io::Cursor::new(source));
+    let error = parser.parse_reader(&mut reader, None).unwrap_err();
+    assert_eq!(error.to_string(), "disconnected");
+}
+
 #[test]
 fn test_parsing_text_with_byte_order_mark() {
     let mut parser = Parser::new();
@@ -635,6 +747,131 @@ fn test_parsing_cancelled_by_another_thread() {
     assert!(tree.is_none());
 }
 
+#[test]
+fn test_parsing_with_a_parser_pool() {
+    let language = get_language("javascript");
+    let sources = (0..20)
+        .map(|i| format!("let x{} = [{}];", i, vec!["1"; i].join(", ")))
+        .collect::<Vec<_>>();
+
+    let mut parser = Parser::new();
+    parser.set_language(language).unwrap();
+    let expected_sexps = sources
+        .iter()
+        .map(|source| parser.parse(source, None).unwrap().root_node().to_sexp())
+        .collect::<Vec<_>>();
+
+    let pool = ParserPool::new(3);
+    assert_eq!(pool.jobs(), 3);
+    for _ in 0..2 {
+        let sexps = pool
+            .parse_all(language, &sources, None)
+            .unwrap()
+            .into_iter()
+            .map(|tree| tree.unwrap().root_node().to_sexp())
+            .collect::<Vec<_>>();
+        assert_eq!(sexps, expected_sexps);
+    }
+
+    // Arbitrary work can be performed with the pool's parsers. Their settings are
+    // cleared after each item.
+    let lengths = pool.map(&sources, |parser, source| {
+        assert_eq!(parser.timeout_micros(), 0);
+        parser.set_language(language).unwrap();
+        parser.set_timeout_micros(1);
+        parser.parse(source, None);
+        source.len()
+    });
+    assert_eq!(
+        lengths,
+        sources
+            .iter()
+            .map(|source| source.len())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_parsing_with_a_parser_pool_and_a_timeout_or_cancellation() {
+    let language = get_language("json");
+    let sources = vec![
+        "[1]".to_string(),
+        format!("[{}]", vec!["0"; 100000].join(",")),
+        "[2]".to_string(),
+    ];
+
+    let mut pool = ParserPool::new(2);
+    pool.set_timeout_micros(1000);
+    let trees = pool.parse_all(language, &sources, None).unwrap();
+    assert!(trees[0].is_some());
+    assert!(trees[1].is_none());
+    assert!(trees[2].is_some());
+
+    // Parsers are reset after a timeout, so they can be reused for other documents.
+    pool.set_timeout_micros(0);
+    let trees = pool.parse_all(language, &sources[..1], None).unwrap();
+    assert_eq!(
+        trees[0].as_ref().unwrap().root_node().to_sexp(),
+        "(document (array (number)))"
+    );
+
+    // Each document has its own cancellation flag.
+    let cancellation_flags = [
+        AtomicUsize::new(0),
+        AtomicUsize::new(1),
+        AtomicUsize::new(0),
+    ];
+    let trees = pool
+        .parse_all(language, &sources, Some(&cancellation_flags))
+        .unwrap();
+    assert!(trees[0].is_some());
+    assert!(trees[1].is_none());
+    assert!(trees[2].is_some());
+}
+
+#[test]
+fn test_parser_pool_results_in_order() {
+    let pool = ParserPool::new(4);
+    let items = (0..100).collect::<Vec<usize>>();
+
+    // Results are handled in the same order as the items, even when later items
+    // finish first.
+    let mut results = Vec::new();
+    pool.map_in_order(
+        &items,
+        |_, item| {
+            if item % 10 == 0 {
+                thread::sleep(time::Duration::from_millis(5));
+            }
+            *item
+        },
+        |result| {
+            results.push(result);
+            Ok::<_, ()>(())
+        },
+    )
+    .unwrap();
+    assert_eq!(results, items);
+
+    // After an error, no more results are handled.
+    let mut results = Vec::new();
+    let error = pool
+        .map_in_order(
+            &items,
+            |_, item| *item,
+            |result| {
+                if result == 20 {
+                    return Err(result);
+                }
+                results.push(result);
+                Ok(())
+            },
+        )
+        .unwrap_err();
+    assert_eq!(error, 20);
+    assert_eq!(results, (0..20).collect::<Vec<_>>());
+}
+
 // Timeouts
 
 #[test]
@@ -799,6 +1036,212 @@ fn test_parsing_with_timeout_and_no_completion() {
     });
 }
 
+#[test]
+fn test_parsing_with_progress_callback() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let source = "const x = [1, 2, 3];\n".repeat(2000);
+    let expected_tree = parser.parse(&source, None).unwrap();
+    let input = source.as_bytes();
+
+    // Yield whenever the parser has read another 10 kilobytes, resuming each time.
+    let mut offsets = Vec::new();
+    let mut yield_count = 0;
+    let tree = loop {
+        let mut budget_start = None;
+        let tree = parser.parse_with_progress(
+            &mut |offset, _| &input[offset.min(input.len())..],
+            None,
+            &mut |offset| {
+                offsets.push(offset);
+                let start = *budget_start.get_or_insert(offset);
+                if offset - start >= 10 * 1024 {
+                    ParseAction::Yield
+                } else {
+                    ParseAction::Continue
+                }
+            },
+        );
+        match tree {
+            Some(tree) => break tree,
+            None => yield_count += 1,
+        }
+    };
+
+    assert!(yield_count > 1);
+    assert!(offsets.contains(&input.len()));
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        expected_tree.root_node().to_sexp()
+    );
+}
+
+#[test]
+fn test_parsing_with_progress_callback_and_cancellation() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let source = "const x = [1, 2, 3];\n".repeat(2000);
+    let input = source.as_bytes();
+
+    // Cancel the parse, and check that the next parse starts from the beginning.
+    let tree = parser.parse_with_progress(
+        &mut |offset, _| &input[offset.min(input.len())..],
+        None,
+        &mut |offset| {
+            if offset > 10 * 1024 {
+                ParseAction::Cancel
+            } else {
+                ParseAction::Continue
+            }
+        },
+    );
+    assert!(tree.is_none());
+
+    let mut first_offset = None;
+    let tree = parser.parse_with_progress(
+        &mut |offset, _| &input[offset.min(input.len())..],
+        None,
+        &mut |offset| {
+            first_offset.get_or_insert(offset);
+            ParseAction::Continue
+        },
+    );
+    assert_eq!(first_offset, Some(0));
+    assert!(!tree.unwrap().root_node().has_error());
+
+    // The parser's own cancellation flag still applies, and is restored afterward.
+    let flag = AtomicUsize::new(1);
+    unsafe { parser.set_cancellation_flag(Some(&flag)) };
+    let tree = parser.parse_with_progress(
+        &mut |offset, _| &input[offset.min(input.len())..],
+        None,
+        &mut |_| ParseAction::Continue,
+    );
+    assert!(tree.is_none());
+    assert!(std::ptr::eq(
+        unsafe { parser.cancellation_flag() }.unwrap(),
+        &flag
+    ));
+}
+
+// Allocators
+
+#[test]
+fn test_parsing_with_a_custom_allocator() {
+    allocations::record(|| {
+        let allocator = Arc::new(TrackingAllocator::default());
+        let language = get_language("javascript");
+        let mut parser = Parser::with_allocator(allocator.clone());
+        parser.set_language(language).unwrap();
+        assert!(allocator.outstanding_count() > 0);
+
+        let mut source = b"const x = [1, 2, 3];".to_vec();
+        let mut tree = parser.parse(&source, None).unwrap();
+        perform_edit(
+            &mut tree,
+            &mut source,
+            &Edit {
+                position: 14,
+                deleted_length: 1,
+                inserted_text: b"4".to_vec(),
+            },
+        );
+        let new_tree = parser.parse(&source, Some(&tree)).unwrap();
+        assert!(new_tree.stats(Some(&tree)).reused_node_count > 0);
+
+        // The trees keep using the allocator after the parser is dropped.
+        drop(parser);
+        drop(tree);
+        let tree = new_tree.clone();
+        drop(new_tree);
+
+        let query = Query::new(language, "(number) @number").unwrap();
+        let mut cursor = QueryCursor::with_allocator(allocator.clone());
+        let numbers = cursor
+            .captures(&query, tree.root_node(), source.as_slice())
+            .map(|(m, i)| m.captures[i].node.utf8_text(&source).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, ["1", "4", "3"]);
+
+        drop(cursor);
+        drop(tree);
+        assert_eq!(allocator.outstanding_count(), 0);
+        assert_eq!(allocator.unknown_deallocation_count(), 0);
+    });
+}
+
+#[test]
+fn test_parsing_with_an_old_tree_from_a_different_allocator() {
+    allocations::record(|| {
+        let language = get_language("javascript");
+        let source = "function a() { return b; }";
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let old_tree = parser.parse(source, None).unwrap();
+
+        let allocator = Arc::new(TrackingAllocator::default());
+        let mut parser = Parser::with_allocator(allocator.clone());
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, Some(&old_tree)).unwrap();
+        assert_eq!(tree.root_node().to_sexp(), old_tree.root_node().to_sexp());
+        assert_eq!(tree.stats(Some(&old_tree)).reused_node_count, 0);
+
+        drop(parser);
+        drop(tree);
+        drop(old_tree);
+        assert_eq!(allocator.outstanding_count(), 0);
+        assert_eq!(allocator.unknown_deallocation_count(), 0);
+    });
+}
+
+#[test]
+fn test_parsing_with_a_custom_allocator_and_callbacks_that_allocate() {
+    allocations::record(|| {
+        let allocator = Arc::new(TrackingAllocator::default());
+        let language = get_language("javascript");
+        let mut parser = Parser::with_allocator(allocator.clone());
+        parser.set_language(language).unwrap();
+
+        // Objects that are created by the callbacks use the global allocation
+        // functions, so they can outlive the parse.
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let logger_queries = queries.clone();
+        parser.set_logger(Some(Box::new(move |_, _| {
+            let mut queries = logger_queries.lock().unwrap();
+            if queries.len() < 4 {
+                queries.push(Query::new(language, "(identifier) @id").unwrap());
+            }
+        })));
+
+        let source = b"const x = [1, 2, 3];";
+        let tree = parser
+            .parse_with(
+                &mut |offset, _| {
+                    queries
+                        .lock()
+                        .unwrap()
+                        .push(Query::new(language, "(number) @number").unwrap());
+                    &source[offset.min(source.len())..]
+                },
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            tree.root_node().to_sexp(),
+            "(program (lexical_declaration (variable_declarator name: (identifier) value: (array (number) (number) (number)))))"
+        );
+
+        drop(parser);
+        drop(tree);
+        assert!(queries.lock().unwrap().len() > 4);
+        drop(queries);
+        assert_eq!(allocator.outstanding_count(), 0);
+        assert_eq!(allocator.unknown_deallocation_count(), 0);
+    });
+}
+
 // Included Ranges
 
 #[test]
@@ -1288,3 +1731,62 @@ fn simple_range(start: usize, end: usize) -> Range {
         end_point: Point::new(0, end),
     }
 }
+
+// An allocator that keeps track of the blocks of memory that it has allocated, in
+// order to detect leaks, and memory that is freed by the wrong allocator.
+#[derive(Default)]
+struct TrackingAllocator {
+    blocks: Mutex<HashSet<usize>>,
+    unknown_deallocation_count: AtomicUsize,
+}
+
+impl TrackingAllocator {
+    fn outstanding_count(&self) -> usize {
+        self.blocks.lock().unwrap().len()
+    }
+
+    fn unknown_deallocation_count(&self) -> usize {
+        self.unknown_deallocation_count.load(Ordering::SeqCst)
+    }
+
+    fn track(&self, buffer: *mut c_void) -> *mut c_void {
+        self.blocks.lock().unwrap().insert(buffer as usize);
+        buffer
+    }
+
+    fn untrack(&self, buffer: *mut c_void) {
+        if !self.blocks.lock().unwrap().remove(&(buffer as usize)) {
+            self.unknown_deallocation_count
+                .fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn calloc(count: usize, size: usize) -> *mut c_void;
+    fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+unsafe impl Allocator for TrackingAllocator {
+    fn allocate(&self, size: usize) -> *mut c_void {
+        self.track(unsafe { malloc(size.max(1)) })
+    }
+
+    fn allocate_zeroed(&self, count: usize, size: usize) -> *mut c_void {
+        self.track(unsafe { calloc(count.max(1), size.max(1)) })
+    }
+
+    unsafe fn reallocate(&self, buffer: *mut c_void, size: usize) -> *mut c_void {
+        if !buffer.is_null() {
+            self.untrack(buffer);
+        }
+        self.track(realloc(buffer, size.max(1)))
+    }
+
+    unsafe fn deallocate(&self, buffer: *mut c_void) {
+        self.untrack(buffer);
+        free(buffer);
+    }
+}
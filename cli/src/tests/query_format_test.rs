@@ -0,0 +1,153 @@
+use super::helpers::fixtures::{fixtures_dir, get_language};
+use crate::query_format::{check_formatted_query, format_query};
+use std::fs;
+use tree_sitter::Query;
+
+#[test]
+fn test_query_format() {
+    let language = get_language("javascript");
+    let source = r#"
+; Constants
+(  [(identifier)   (shorthand_property_identifier)] @constant (#match? @constant "^[A-Z_]+$")  )
+
+
+
+(call_expression function: (member_expression object: (identifier) @object property: (property_identifier) @function.method) arguments: (arguments)) ; method calls
+(pair key: (_) !value) [
+  "(" ")"
+  ; brackets
+  "[" "]"] @punctuation.bracket
+((comment)* @doc . (function_declaration name: (identifier) @name) @definition.function (#select-adjacent! @doc @definition.function))
+"#;
+
+    assert_eq!(
+        format_query(language, source).unwrap(),
+        r#"; Constants
+([(identifier) (shorthand_property_identifier)] @constant
+ (#match? @constant "^[A-Z_]+$"))
+
+(call_expression
+  function: (member_expression
+    object: (identifier) @object
+    property: (property_identifier) @function.method)
+  arguments: (arguments)) ; method calls
+(pair key: (_) !value)
+[
+  "("
+  ")"
+  ; brackets
+  "["
+  "]"
+] @punctuation.bracket
+((comment)* @doc
+ .
+ (function_declaration name: (identifier) @name) @definition.function
+ (#select-adjacent! @doc @definition.function))
+"#
+    );
+}
+
+#[test]
+fn test_query_format_preserves_queries() {
+    for entry in fs::read_dir(fixtures_dir().join("grammars")).unwrap() {
+        let grammar_path = entry.unwrap().path();
+        let queries_dir = grammar_path.join("queries");
+        if !queries_dir.exists() {
+            continue;
+        }
+
+        // The TypeScript grammar's queries are shared by its `typescript` and `tsx` dialects.
+        let name = grammar_path.file_name().unwrap().to_str().unwrap();
+        let language = if grammar_path.join("src").exists() {
+            get_language(name)
+        } else {
+            get_language(&format!("{0}/{0}", name))
+        };
+        for entry in fs::read_dir(queries_dir).unwrap() {
+            let path = entry.unwrap().path();
+            let source = fs::read_to_string(&path).unwrap();
+            let formatted = format_query(language, &source).unwrap();
+            check_formatted_query(language, &source, &formatted).unwrap();
+            assert_eq!(
+                without_whitespace(&formatted),
+                without_whitespace(&source),
+                "Formatting changed the contents of {:?}",
+                path
+            );
+            assert_eq!(
+                format_query(language, &formatted).unwrap(),
+                formatted,
+                "Formatting {:?} twice produced different results",
+                path
+            );
+        }
+    }
+}
+
+#[test]
+fn test_query_format_with_the_syntax_of_the_library() {
+    let language = get_language("javascript");
+
+    // A `_` is a wildcard even when it is followed by a field name, and whitespace
+    // includes vertical tabs.
+    for (source, expected) in [
+        ("(pair _value: (string))", "(pair _ value: (string))\n"),
+        ("(identifier)\u{b}@a", "(identifier) @a\n"),
+        (
+            "(identifier) @a (eq? @a \"b\")",
+            "(identifier) @a\n(eq? @a \"b\")\n",
+        ),
+    ] {
+        let formatted = format_query(language, source).unwrap();
+        assert_eq!(formatted, expected, "Formatting {:?}", source);
+        check_formatted_query(language, source, &formatted).unwrap();
+    }
+
+    // Non-ASCII characters are neither whitespace nor parts of identifiers, and anchors,
+    // negated fields, captures and predicates are only accepted where the library
+    // accepts them.
+    for source in [
+        "(identifier)\u{a0}@a",
+        "(identifier) @\u{e9}",
+        "(identifier) @",
+        "(# eq? @a @b)",
+        "((identifier) . . (number))",
+        "((identifier) !name)",
+        "(array . !name)",
+        "(array .)",
+        "[. (identifier)]",
+        "[]",
+        "( )",
+        "(array/)",
+    ] {
+        assert!(
+            Query::new(language, source).is_err(),
+            "The library accepted {:?}",
+            source
+        );
+        assert!(
+            format_query(language, source).is_err(),
+            "Formatted {:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_query_format_checking_formatted_queries() {
+    let language = get_language("javascript");
+    let error = check_formatted_query(language, "(identifier) @a", "(identifier) @b").unwrap_err();
+    assert_eq!(error.to_string(), "Formatting changed the pattern at 1:1");
+    let error = check_formatted_query(language, "(identifier) (number)", "((identifier) (number))")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Formatting changed the number of patterns from 2 to 1"
+    );
+    let error = check_formatted_query(language, "(identifier) @a (#eq? @b)", "").unwrap_err();
+    assert_eq!(error.to_string(), "Query compilation failed");
+}
+
+fn without_whitespace(text: &str) -> String {
+    text.split_whitespace().collect()
+}
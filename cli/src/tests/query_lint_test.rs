@@ -0,0 +1,102 @@
+use super::helpers::fixtures::{fixtures_dir, get_language};
+use crate::query_lint::{lint_query, LintWarning, NodeTypes};
+use std::fs;
+use tree_sitter::{Query, QueryError};
+
+#[test]
+fn test_query_lint_with_impossible_patterns() {
+    let language = get_language("javascript");
+    let node_types = get_node_types("javascript");
+    let source = r#"
+; Fields and children that are never present
+(function_declaration !condition name: [(identifier) (number) @name])
+(object [(pair) (method_definition) (pair)] @member)
+
+; Supertypes and extras can appear wherever their subtypes can
+(pair value: (primary_expression) @value)
+(statement_block (comment) (expression_statement))
+"#;
+
+    assert_eq!(
+        summarize_warnings(source, &lint_query(language, source, &node_types, None).unwrap()),
+        &[
+            (
+                "!condition",
+                "`function_declaration` nodes have no `condition` field, so this negated field is always satisfied"
+            ),
+            (
+                "(number) @name",
+                "unreachable alternative: `number` nodes can never appear in the `name` field of `function_declaration` nodes"
+            ),
+            (
+                "(pair)",
+                "unreachable alternative: it is identical to a previous alternative"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_query_lint_with_highlight_names() {
+    let language = get_language("javascript");
+    let node_types = get_node_types("javascript");
+    let source = r#"
+(identifier) @variable.builtin
+(number) @numbr
+((identifier) @_name (#eq? @_name "x"))
+(string) @numbr
+"#;
+    let highlight_names = vec!["variable".to_string(), "number".to_string()];
+
+    assert_eq!(
+        summarize_warnings(
+            source,
+            &lint_query(language, source, &node_types, Some(&highlight_names)).unwrap()
+        ),
+        &[("@numbr", "`@numbr` is not a recognized highlight name")]
+    );
+}
+
+#[test]
+fn test_query_lint_with_the_syntax_of_the_library() {
+    let language = get_language("javascript");
+    let node_types = get_node_types("javascript");
+
+    // A `_` is a wildcard even when it is followed by a field name, and whitespace
+    // includes vertical tabs.
+    let source = "(pair _value: (number) @value)\u{b}(function_declaration !condition)";
+    assert_eq!(
+        summarize_warnings(
+            source,
+            &lint_query(language, source, &node_types, None).unwrap()
+        ),
+        &[(
+            "!condition",
+            "`function_declaration` nodes have no `condition` field, so this negated field is always satisfied"
+        )]
+    );
+
+    // Queries that the library rejects are reported with the library's error.
+    let source = "(identifier) (number";
+    let error = lint_query(language, source, &node_types, None).unwrap_err();
+    assert_eq!(
+        error.downcast::<QueryError>().unwrap(),
+        Query::new(language, source).unwrap_err()
+    );
+}
+
+fn get_node_types(language_name: &str) -> NodeTypes {
+    let path = fixtures_dir()
+        .join("grammars")
+        .join(language_name)
+        .join("src")
+        .join("node-types.json");
+    NodeTypes::new(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn summarize_warnings<'a>(source: &'a str, warnings: &'a [LintWarning]) -> Vec<(&'a str, &'a str)> {
+    warnings
+        .iter()
+        .map(|warning| (&source[warning.range.clone()], warning.message.as_str()))
+        .collect()
+}
@@ -7,8 +7,9 @@ use lazy_static::lazy_static;
 use rand::{prelude::StdRng, SeedableRng};
 use std::{env, fmt::Write};
 use tree_sitter::{
-    CaptureQuantifier, Language, Node, Parser, Point, Query, QueryCapture, QueryCursor, QueryError,
-    QueryErrorKind, QueryMatch, QueryPredicate, QueryPredicateArg, QueryProperty,
+    CaptureQuantifier, Language, Node, Parser, Point, PredicateArg, PredicateArgType, Query,
+    QueryCapture, QueryCursor, QueryError, QueryErrorKind, QueryMatch, QueryPredicate,
+    QueryPredicateArg, QueryPredicates, QueryProperty, QueryTokenKind,
 };
 
 lazy_static! {
@@ -2784,6 +2785,257 @@ fn test_query_captures_with_predicates() {
     });
 }
 
+#[test]
+fn test_query_with_custom_predicates() {
+    allocations::record(|| {
+        let language = get_language("javascript");
+
+        let mut predicates = QueryPredicates::new();
+        predicates
+            .register(
+                "contains?",
+                &[PredicateArgType::Capture, PredicateArgType::String],
+                |args| match args {
+                    [PredicateArg::Capture(captures), PredicateArg::String(s)] => captures
+                        .iter()
+                        .all(|capture| capture.text().windows(s.len()).any(|w| w == s.as_bytes())),
+                    _ => unreachable!(),
+                },
+            )
+            .register(
+                "kind-eq?",
+                &[PredicateArgType::Capture, PredicateArgType::Any],
+                |args| match args {
+                    [PredicateArg::Capture(captures), PredicateArg::String(kind)] => {
+                        captures.iter().all(|capture| capture.node.kind() == *kind)
+                    }
+                    [PredicateArg::Capture(captures1), PredicateArg::Capture(captures2)] => {
+                        captures1
+                            .iter()
+                            .zip(captures2.iter())
+                            .all(|(capture1, capture2)| {
+                                capture1.node.kind() == capture2.node.kind()
+                            })
+                    }
+                    _ => unreachable!(),
+                },
+            );
+
+        let query = Query::with_predicates(
+            language,
+            r#"
+            ((identifier) @id (#contains? @id "oo"))
+            ((pair key: (_) @key value: (_) @value) (#kind-eq? @key @value))
+            ((pair value: (_) @number) (#kind-eq? @number "number") (#other? @number))
+            "#,
+            &predicates,
+        )
+        .unwrap();
+        assert_eq!(
+            query.general_predicates(2),
+            &[QueryPredicate {
+                operator: "other?".to_string().into_boxed_str(),
+                args: vec![QueryPredicateArg::Capture(3)],
+            }]
+        );
+
+        let source = "foo(bar, boo); x = {a: b, 'c': 'd', e: 1};";
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let mut cursor = QueryCursor::new();
+        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_matches(matches, &query, source),
+            &[
+                (0, vec![("id", "foo")]),
+                (0, vec![("id", "boo")]),
+                (1, vec![("key", "'c'"), ("value", "'d'")]),
+                (2, vec![("number", "1")]),
+            ]
+        );
+        let captures = cursor.captures(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_captures(captures, &query, source),
+            &[
+                ("id", "foo"),
+                ("id", "boo"),
+                ("key", "'c'"),
+                ("value", "'d'"),
+                ("number", "1"),
+            ]
+        );
+
+        // Registered predicates are validated when the query is created.
+        assert_eq!(
+            Query::with_predicates(language, "((identifier) @id (#contains? @id))", &predicates)
+                .unwrap_err(),
+            QueryError {
+                kind: QueryErrorKind::Predicate,
+                row: 0,
+                column: 0,
+                offset: 0,
+                message: "Wrong number of arguments to #contains? predicate. Expected 2, got 1."
+                    .to_string()
+            }
+        );
+        assert_eq!(
+            Query::with_predicates(
+                language,
+                "((identifier) @id (#contains? @id @id))",
+                &predicates
+            )
+            .unwrap_err()
+            .message,
+            "Argument 2 to #contains? predicate must be a literal. Got capture @id."
+        );
+        assert_eq!(
+            Query::with_predicates(
+                language,
+                "((identifier) @id (#kind-eq? \"a\" @id))",
+                &predicates
+            )
+            .unwrap_err()
+            .message,
+            "Argument 1 to #kind-eq? predicate must be a capture name. Got literal \"a\"."
+        );
+    });
+}
+
+#[test]
+#[should_panic(expected = "Cannot register the built-in predicate #eq?")]
+fn test_query_with_custom_predicates_replacing_a_built_in_predicate() {
+    QueryPredicates::new().register(
+        "eq?",
+        &[PredicateArgType::Capture, PredicateArgType::Capture],
+        |_| true,
+    );
+}
+
+#[test]
+fn test_query_text_predicates_with_quantified_captures() {
+    allocations::record(|| {
+        let language = get_language("javascript");
+        let query = Query::new(
+            language,
+            r#"
+            ((comment)+ @all . (lexical_declaration) (#eq? @all "// a"))
+            ((comment)+ @any . (lexical_declaration) (#any-eq? @any "// a"))
+            ((comment)+ @none . (lexical_declaration) (#not-eq? @none "// a"))
+            ((comment)+ @not-all . (lexical_declaration) (#any-not-eq? @not-all "// a"))
+            ((comment)+ @in-set . (lexical_declaration) (#any-of? @in-set "// b" "// c"))
+            ((comment)+ @not-in-set . (lexical_declaration) (#not-any-of? @not-in-set "// b" "// c"))
+            ((comment)+ @any-match . (lexical_declaration) (#any-match? @any-match "c$"))
+            ((comment)+ @all-match . (lexical_declaration) (#match? @all-match "[ab]$"))
+            "#,
+        )
+        .unwrap();
+
+        let source = "// a\n// a\nlet x;\n// a\n// b\nlet y;\n// b\n// c\nlet z;\n";
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let mut cursor = QueryCursor::new();
+        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_matches(matches, &query, source),
+            &[
+                (0, vec![("all", "// a"), ("all", "// a")]),
+                (1, vec![("any", "// a"), ("any", "// a")]),
+                (5, vec![("not-in-set", "// a"), ("not-in-set", "// a")]),
+                (7, vec![("all-match", "// a"), ("all-match", "// a")]),
+                (1, vec![("any", "// a"), ("any", "// b")]),
+                (3, vec![("not-all", "// a"), ("not-all", "// b")]),
+                (7, vec![("all-match", "// a"), ("all-match", "// b")]),
+                (2, vec![("none", "// b"), ("none", "// c")]),
+                (3, vec![("not-all", "// b"), ("not-all", "// c")]),
+                (4, vec![("in-set", "// b"), ("in-set", "// c")]),
+                (6, vec![("any-match", "// b"), ("any-match", "// c")]),
+            ]
+        );
+
+        assert_eq!(
+            Query::new(language, "((identifier) @id (#any-of? @id))")
+                .unwrap_err()
+                .message,
+            "Wrong number of arguments to #any-of? predicate. Expected at least 2, got 1."
+        );
+        assert_eq!(
+            Query::new(language, "((identifier) @id (#any-of? @id \"a\" @id))")
+                .unwrap_err()
+                .message,
+            "Arguments to #any-of? predicate must be literals. Got capture @id."
+        );
+        assert_eq!(
+            Query::new(language, "((identifier) @id (#any-eq? \"a\" @id))")
+                .unwrap_err()
+                .message,
+            "First argument to #any-eq? predicate must be a capture name. Got literal \"a\"."
+        );
+    });
+}
+
+#[test]
+fn test_query_text_predicates_comparing_captures_with_multiple_nodes() {
+    allocations::record(|| {
+        let language = get_language("javascript");
+        let query = Query::new(
+            language,
+            r#"
+            ((arguments . (array . (identifier) @a . (identifier) @a .) . (array . (identifier) @b .) .)
+             (#eq? @a @b))
+            ((arguments . (array . (identifier) @c . (identifier) @c .) . (array . (identifier) @d .) .)
+             (#any-eq? @c @d))
+            ((arguments . (array . (identifier) @e . (identifier) @e .) . (array . (identifier) @f . (identifier) @f .) .)
+             (#eq? @e @f))
+            ((arguments . (array . (identifier) @g . (identifier) @g .) . (array . (identifier) @h .) .)
+             (#not-eq? @g @h))
+            "#,
+        )
+        .unwrap();
+
+        // Captures with different numbers of nodes are unequal, unless only one pair of
+        // nodes needs to be equal. The nodes are paired by position.
+        let source = "f([a, b], [a]);\nf([a, b], [b]);\nf([a, b], [a, b]);\nf([a, b], [b, a]);\n";
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let mut cursor = QueryCursor::new();
+        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_matches(matches, &query, source),
+            &[
+                (1, vec![("c", "a"), ("c", "b"), ("d", "a")]),
+                (3, vec![("g", "a"), ("g", "b"), ("h", "a")]),
+                (3, vec![("g", "a"), ("g", "b"), ("h", "b")]),
+                (2, vec![("e", "a"), ("e", "b"), ("f", "a"), ("f", "b")]),
+            ]
+        );
+
+        // A capture that did not match any nodes satisfies the predicate.
+        let query = Query::new(
+            language,
+            r#"
+            ((call_expression
+               function: (identifier) @a
+               arguments: (arguments (identifier)? @b))
+             (#eq? @a @b))
+            "#,
+        )
+        .unwrap();
+        let source = "f();
+g(g);
+h(x);
+";
+        let tree = parser.parse(source, None).unwrap();
+        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_matches(matches, &query, source),
+            &[(0, vec![("a", "f")]), (0, vec![("a", "g"), ("b", "g")])]
+        );
+    });
+}
+
 #[test]
 fn test_query_captures_with_quoted_predicate_args() {
     allocations::record(|| {
@@ -3417,6 +3669,73 @@ fn test_query_start_byte_for_pattern() {
     );
 }
 
+#[test]
+fn test_query_tokenize() {
+    allocations::record(|| {
+        let language = get_language("javascript");
+        let source = r#"
+; Comment
+((pair key: (_) @key !value) . [(primary_expression/identifier) "="]* (#eq? @key "a"))
+_ @any
+(eq? @any b)
+"#;
+
+        let tokens = Query::tokenize(language, source).unwrap();
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| (token.kind, &source[token.start_byte..token.end_byte]))
+                .collect::<Vec<_>>(),
+            &[
+                (QueryTokenKind::Comment, "; Comment"),
+                (QueryTokenKind::GroupStart, "("),
+                (QueryTokenKind::NodeStart, "("),
+                (QueryTokenKind::NodeName, "pair"),
+                (QueryTokenKind::FieldName, "key"),
+                (QueryTokenKind::Colon, ":"),
+                (QueryTokenKind::NodeStart, "("),
+                (QueryTokenKind::NodeName, "_"),
+                (QueryTokenKind::End, ")"),
+                (QueryTokenKind::Capture, "@key"),
+                (QueryTokenKind::Negation, "!"),
+                (QueryTokenKind::FieldName, "value"),
+                (QueryTokenKind::End, ")"),
+                (QueryTokenKind::Anchor, "."),
+                (QueryTokenKind::AlternationStart, "["),
+                (QueryTokenKind::NodeStart, "("),
+                (QueryTokenKind::NodeName, "primary_expression"),
+                (QueryTokenKind::SupertypeSeparator, "/"),
+                (QueryTokenKind::NodeName, "identifier"),
+                (QueryTokenKind::End, ")"),
+                (QueryTokenKind::String, "\"=\""),
+                (QueryTokenKind::End, "]"),
+                (QueryTokenKind::Quantifier, "*"),
+                (QueryTokenKind::PredicateStart, "("),
+                (QueryTokenKind::PredicateName, "#eq?"),
+                (QueryTokenKind::Capture, "@key"),
+                (QueryTokenKind::String, "\"a\""),
+                (QueryTokenKind::End, ")"),
+                (QueryTokenKind::End, ")"),
+                (QueryTokenKind::Wildcard, "_"),
+                (QueryTokenKind::Capture, "@any"),
+                (QueryTokenKind::PredicateStart, "("),
+                (QueryTokenKind::PredicateName, "eq?"),
+                (QueryTokenKind::Capture, "@any"),
+                (QueryTokenKind::Identifier, "b"),
+                (QueryTokenKind::End, ")"),
+            ]
+        );
+
+        assert_eq!(Query::tokenize(language, "").unwrap(), &[]);
+        for source in ["(identifier", "(identifier) @a (#eq? @b)", "(pair !color)"] {
+            assert_eq!(
+                Query::tokenize(language, source).unwrap_err(),
+                Query::new(language, source).unwrap_err(),
+            );
+        }
+    });
+}
+
 #[test]
 fn test_query_capture_names() {
     allocations::record(|| {
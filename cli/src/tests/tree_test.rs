@@ -2,7 +2,10 @@ use super::helpers::edits::invert_edit;
 use super::helpers::fixtures::get_language;
 use crate::parse::{perform_edit, Edit};
 use std::str;
-use tree_sitter::{InputEdit, Parser, Point, Range, Tree};
+use tree_sitter::{
+    DiffEdit, DiffEditKind, InputEdit, Node, NodeChangeKind, Parser, Point, Range, SnapshotNode,
+    Tree, TreeSnapshot, VisitAction, Visitor,
+};
 
 #[test]
 fn test_tree_edit() {
@@ -305,6 +308,155 @@ fn test_tree_cursor_fields() {
     assert_eq!(cursor.field_name(), Some("parameters"));
 }
 
+#[test]
+fn test_tree_cursor_previous_sibling() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("rust")).unwrap();
+
+    let tree = parser
+        .parse(
+            "
+                struct Stuff {
+                    a: A,
+                    b: Option<B>,
+                }
+            ",
+            None,
+        )
+        .unwrap();
+
+    let mut cursor = tree.walk();
+    assert!(cursor.goto_last_child());
+    assert_eq!(cursor.node().kind(), "struct_item");
+
+    assert!(cursor.goto_last_child());
+    assert_eq!(cursor.node().kind(), "field_declaration_list");
+    assert_eq!(cursor.field_name(), Some("body"));
+
+    assert!(cursor.goto_last_child());
+    assert_eq!(cursor.node().kind(), "}");
+
+    assert!(cursor.goto_previous_sibling());
+    assert_eq!(cursor.node().kind(), ",");
+
+    assert!(cursor.goto_previous_sibling());
+    assert_eq!(cursor.node().kind(), "field_declaration");
+    assert_eq!(cursor.node().start_position().row, 3);
+
+    assert!(cursor.goto_parent());
+    assert!(cursor.goto_previous_sibling());
+    assert_eq!(cursor.node().kind(), "type_identifier");
+    assert!(cursor.goto_previous_sibling());
+    assert_eq!(cursor.node().kind(), "struct");
+    assert!(!cursor.goto_previous_sibling());
+    assert_eq!(cursor.node().kind(), "struct");
+
+    // Walking each node's children backward visits the same nodes as walking them
+    // forward, in the opposite order.
+    let mut cursor = tree.walk();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        cursor.reset(node);
+        let mut forward = Vec::new();
+        if cursor.goto_first_child() {
+            loop {
+                forward.push(cursor.node());
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        cursor.reset(node);
+        let mut backward = Vec::new();
+        if cursor.goto_last_child() {
+            loop {
+                backward.push(cursor.node());
+                if !cursor.goto_previous_sibling() {
+                    break;
+                }
+            }
+        }
+        backward.reverse();
+        assert_eq!(forward, backward);
+        stack.extend(forward);
+    }
+}
+
+#[test]
+fn test_tree_cursor_descendant_index() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let source = "
+        class A extends B {
+            /** doc */
+            c(d = [1, 2, ...e]) {
+                return <F g={h}>i</F>;
+            }
+        }
+    ";
+    let tree = parser.parse(source, None).unwrap();
+
+    // Enumerate the nodes in pre-order, along with their depths.
+    let mut nodes = Vec::new();
+    let mut cursor = tree.walk();
+    let mut depth = 0;
+    loop {
+        nodes.push((cursor.node(), depth));
+        if cursor.goto_first_child() {
+            depth += 1;
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                break;
+            }
+            depth -= 1;
+        }
+        if depth == 0 {
+            break;
+        }
+    }
+    assert_eq!(tree.root_node().descendant_count(), nodes.len());
+
+    let mut cursor = tree.walk();
+    for (index, (node, depth)) in nodes.iter().enumerate() {
+        cursor.goto_descendant(index);
+        assert_eq!(cursor.node(), *node);
+        assert_eq!(cursor.depth(), *depth);
+        assert_eq!(cursor.descendant_index(), index);
+    }
+
+    // The indices are relative to the node that the cursor was created with.
+    let (class_index, class_node) = nodes
+        .iter()
+        .map(|(node, _)| *node)
+        .enumerate()
+        .find(|(_, node)| node.kind() == "class_declaration")
+        .unwrap();
+    let mut cursor = class_node.walk();
+    cursor.goto_descendant(3);
+    assert_eq!(cursor.node(), nodes[class_index + 3].0);
+    assert_eq!(cursor.descendant_index(), 3);
+    assert_eq!(
+        cursor.depth(),
+        nodes[class_index + 3].1 - nodes[class_index].1
+    );
+    assert_eq!(
+        class_node.descendant_count(),
+        nodes[class_index..]
+            .iter()
+            .take_while(|(node, _)| node.end_byte() <= class_node.end_byte())
+            .count()
+    );
+
+    // Out-of-range indices move the cursor to the original node.
+    cursor.goto_descendant(1000);
+    assert_eq!(cursor.node(), class_node);
+    assert_eq!(cursor.descendant_index(), 0);
+}
+
 #[test]
 fn test_tree_cursor_child_for_point() {
     let mut parser = Parser::new();
@@ -424,6 +576,44 @@ fn test_tree_node_equality() {
     assert_ne!(node1.child(0).unwrap(), node2);
 }
 
+#[test]
+fn test_tree_included_ranges() {
+    let source_code = "<span>hi</span><script>console.log('sup');</script>";
+
+    let mut parser = Parser::new();
+    parser.set_language(get_language("html")).unwrap();
+    let html_tree = parser.parse(source_code, None).unwrap();
+    let script_content_node = html_tree.root_node().child(1).unwrap().child(1).unwrap();
+    assert_eq!(script_content_node.kind(), "raw_text");
+
+    parser
+        .set_included_ranges(&[script_content_node.range()])
+        .unwrap();
+    parser.set_language(get_language("javascript")).unwrap();
+    let mut js_tree = parser.parse(source_code, None).unwrap();
+    assert_eq!(js_tree.included_ranges(), &[script_content_node.range()]);
+
+    // The ranges are updated when the tree is edited.
+    js_tree.edit(&InputEdit {
+        start_byte: 0,
+        old_end_byte: 0,
+        new_end_byte: 1,
+        start_position: Point::new(0, 0),
+        old_end_position: Point::new(0, 0),
+        new_end_position: Point::new(0, 1),
+    });
+    let range = script_content_node.range();
+    assert_eq!(
+        js_tree.included_ranges(),
+        &[Range {
+            start_byte: range.start_byte + 1,
+            end_byte: range.end_byte + 1,
+            start_point: Point::new(0, range.start_point.column + 1),
+            end_point: Point::new(0, range.end_point.column + 1),
+        }]
+    );
+}
+
 #[test]
 fn test_get_changed_ranges() {
     let source_code = b"{a: null};\n".to_vec();
@@ -531,6 +721,280 @@ fn test_get_changed_ranges() {
     }
 }
 
+#[test]
+fn test_tree_visitor() {
+    struct Recorder(Vec<String>);
+
+    impl<'tree> Visitor<'tree> for Recorder {
+        fn enter_node(
+            &mut self,
+            node: Node<'tree>,
+            field_name: Option<&'static str>,
+            depth: usize,
+        ) -> VisitAction {
+            self.0.push(format!(
+                "{}enter {}{}",
+                "  ".repeat(depth),
+                field_name.map_or(String::new(), |f| format!("{}: ", f)),
+                node.kind()
+            ));
+            match node.kind() {
+                "arguments" => VisitAction::SkipChildren,
+                "return" => VisitAction::Stop,
+                _ => VisitAction::Continue,
+            }
+        }
+
+        fn leave_node(&mut self, node: Node<'tree>, _: Option<&'static str>, depth: usize) {
+            self.0
+                .push(format!("{}leave {}", "  ".repeat(depth), node.kind()));
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = parser.parse("a.b(c); return", None).unwrap();
+
+    let mut recorder = Recorder(Vec::new());
+    tree.visit(&mut recorder);
+    assert_eq!(
+        recorder.0,
+        &[
+            "enter program",
+            "  enter expression_statement",
+            "    enter call_expression",
+            "      enter function: member_expression",
+            "        enter object: identifier",
+            "        leave identifier",
+            "        enter .",
+            "        leave .",
+            "        enter property: property_identifier",
+            "        leave property_identifier",
+            "      leave member_expression",
+            "      enter arguments: arguments",
+            "      leave arguments",
+            "    leave call_expression",
+            "    enter ;",
+            "    leave ;",
+            "  leave expression_statement",
+            "  enter return_statement",
+            "    enter return",
+        ]
+    );
+
+    // Visiting a node does not walk any of its siblings or ancestors.
+    let member_expression = tree.root_node().descendant_for_byte_range(1, 1).unwrap();
+    let mut recorder = Recorder(Vec::new());
+    member_expression.parent().unwrap().visit(&mut recorder);
+    assert_eq!(
+        recorder.0,
+        &[
+            "enter member_expression",
+            "  enter object: identifier",
+            "  leave identifier",
+            "  enter .",
+            "  leave .",
+            "  enter property: property_identifier",
+            "  leave property_identifier",
+            "leave member_expression",
+        ]
+    );
+}
+
+#[test]
+fn test_tree_traversals() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = parser.parse("a(b, c); d;", None).unwrap();
+
+    assert_eq!(
+        tree.preorder().map(|node| node.kind()).collect::<Vec<_>>(),
+        &[
+            "program",
+            "expression_statement",
+            "call_expression",
+            "identifier",
+            "arguments",
+            "(",
+            "identifier",
+            ",",
+            "identifier",
+            ")",
+            ";",
+            "expression_statement",
+            "identifier",
+            ";",
+        ]
+    );
+    assert_eq!(
+        tree.postorder().map(|node| node.kind()).collect::<Vec<_>>(),
+        &[
+            "identifier",
+            "(",
+            "identifier",
+            ",",
+            "identifier",
+            ")",
+            "arguments",
+            "call_expression",
+            ";",
+            "expression_statement",
+            "identifier",
+            ";",
+            "expression_statement",
+            "program",
+        ]
+    );
+
+    let call = tree.root_node().child(0).unwrap().child(0).unwrap();
+    let mut nodes = call.preorder();
+    let mut visited = Vec::new();
+    while let Some(node) = nodes.next() {
+        visited.push((node.kind(), nodes.field_name(), nodes.depth()));
+        if node.kind() == "arguments" {
+            nodes.skip_children();
+        }
+    }
+    assert_eq!(
+        visited,
+        &[
+            ("call_expression", None, 0),
+            ("identifier", Some("function"), 1),
+            ("arguments", Some("arguments"), 1),
+        ]
+    );
+
+    let mut nodes = call.postorder();
+    let mut visited = Vec::new();
+    while let Some(node) = nodes.next() {
+        visited.push((node.kind(), nodes.depth()));
+    }
+    assert_eq!(visited.len(), 8);
+    assert_eq!(visited[0], ("identifier", 1));
+    assert_eq!(visited[1], ("(", 2));
+    assert_eq!(visited[7], ("call_expression", 0));
+}
+
+#[test]
+fn test_tree_snapshot() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = parser
+        .parse("function /*1*/ bar(a) { a(1 + ; }\nif (a) { b", None)
+        .unwrap();
+    assert!(tree.root_node().has_error());
+
+    let snapshot = TreeSnapshot::new(&tree);
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let snapshot: TreeSnapshot = serde_json::from_str(&json).unwrap();
+    assert_snapshot_matches_node(snapshot.root_node(), tree.root_node());
+
+    let function = snapshot.root_node().child(0).unwrap();
+    assert_eq!(function.kind(), "function_declaration");
+    assert_eq!(
+        function.child_by_field_name("name").unwrap().kind(),
+        "identifier"
+    );
+    assert_eq!(
+        function
+            .child(1)
+            .unwrap()
+            .next_sibling()
+            .unwrap()
+            .field_name(),
+        Some("name")
+    );
+    assert_eq!(
+        function
+            .named_children()
+            .map(|child| child.kind())
+            .collect::<Vec<_>>(),
+        &[
+            "comment",
+            "identifier",
+            "formal_parameters",
+            "statement_block"
+        ]
+    );
+    assert!(snapshot
+        .root_node()
+        .descendants()
+        .any(|node| node.is_missing() && node.kind() == "}"));
+}
+
+#[test]
+fn test_tree_snapshot_with_invalid_data() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let tree = parser.parse("a(b, c);", None).unwrap();
+
+    let mut json = serde_json::to_value(TreeSnapshot::new(&tree)).unwrap();
+    json["nodes"][1]["kind"] = 1000.into();
+    let error = serde_json::from_value::<TreeSnapshot>(json).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid tree snapshot at node 1");
+}
+
+#[test]
+fn test_tree_stats() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+    let initial_parser_memory_usage = parser.memory_usage();
+    assert!(initial_parser_memory_usage > 0);
+
+    let mut source = "const a = [1, 2, 3];\nfunction b() { return c(d); }\n"
+        .repeat(20)
+        .into_bytes();
+    let tree = parser.parse(&source, None).unwrap();
+    let stats = tree.stats(None);
+    assert_eq!(stats.node_count, tree.root_node().descendant_count());
+    assert_eq!(stats.reused_node_count, 0);
+    assert_eq!(stats.depth, max_depth(tree.root_node()));
+    assert!(stats.memory_usage > 0);
+    assert!(stats.memory_usage < tree.memory_usage());
+    assert!(parser.memory_usage() >= initial_parser_memory_usage);
+
+    let function = tree.root_node().child(1).unwrap();
+    assert_eq!(function.kind(), "function_declaration");
+    let function_stats = function.stats(None);
+    assert_eq!(function_stats.node_count, function.descendant_count());
+    assert_eq!(function_stats.depth, max_depth(function));
+    assert!(function_stats.memory_usage < stats.memory_usage);
+
+    // After a small edit, most of the nodes are reused from the old tree.
+    let mut old_tree = tree.clone();
+    let position = index_of(&source, "c(d)");
+    perform_edit(
+        &mut old_tree,
+        &mut source,
+        &Edit {
+            position,
+            deleted_length: 1,
+            inserted_text: b"e".to_vec(),
+        },
+    );
+    let new_tree = parser.parse(&source, Some(&old_tree)).unwrap();
+    let new_stats = new_tree.stats(Some(&old_tree));
+    assert_eq!(new_stats.node_count, stats.node_count);
+    assert!(new_stats.reused_node_count < new_stats.node_count);
+    assert!(new_stats.reused_node_count > new_stats.node_count * 9 / 10);
+
+    // A tree shares all of its nodes with itself, and none with a tree that was
+    // parsed from scratch.
+    assert_eq!(
+        new_tree.stats(Some(&new_tree)).reused_node_count,
+        new_stats.node_count
+    );
+    let fresh_tree = parser.parse(&source, None).unwrap();
+    assert_eq!(fresh_tree.stats(Some(&old_tree)).reused_node_count, 0);
+    assert_eq!(fresh_tree.stats(None).memory_usage, new_stats.memory_usage);
+}
+
+fn max_depth(node: Node) -> usize {
+    let mut cursor = node.walk();
+    let children = node.children(&mut cursor).collect::<Vec<_>>();
+    1 + children.into_iter().map(max_depth).max().unwrap_or(0)
+}
+
 fn index_of(text: &Vec<u8>, substring: &str) -> usize {
     str::from_utf8(text.as_slice())
         .unwrap()
@@ -561,3 +1025,296 @@ fn get_changed_ranges(
     *tree = new_tree;
     result
 }
+
+fn assert_snapshot_matches_node(snapshot_node: SnapshotNode, node: Node) {
+    assert_eq!(snapshot_node.kind(), node.kind());
+    assert_eq!(snapshot_node.kind_id(), node.kind_id());
+    assert_eq!(snapshot_node.is_named(), node.is_named());
+    assert_eq!(snapshot_node.is_extra(), node.is_extra());
+    assert_eq!(snapshot_node.is_error(), node.is_error());
+    assert_eq!(snapshot_node.is_missing(), node.is_missing());
+    assert_eq!(snapshot_node.has_error(), node.has_error());
+    assert_eq!(snapshot_node.range(), node.range());
+    assert_eq!(snapshot_node.child_count(), node.child_count());
+    let mut cursor = node.walk();
+    cursor.goto_first_child();
+    for (i, child) in snapshot_node.children().enumerate() {
+        assert_eq!(child.parent(), Some(snapshot_node));
+        assert_eq!(child.field_name(), cursor.field_name());
+        cursor.goto_next_sibling();
+        assert_eq!(child.next_sibling(), snapshot_node.child(i + 1));
+        assert_snapshot_matches_node(child, node.child(i).unwrap());
+    }
+}
+
+#[test]
+fn test_tree_diff() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let old_source = "let a = 1;\nfoo(a);\nbar();\n";
+    let new_source = "bar();\nlet a = 2;\nfoo(a, b);\n";
+    let old_tree = parser.parse(old_source, None).unwrap();
+    let new_tree = parser.parse(new_source, None).unwrap();
+
+    let edits = old_tree.diff(old_source.as_bytes(), &new_tree, new_source.as_bytes());
+    assert_eq!(
+        summarize_diff(&edits),
+        &[
+            (
+                DiffEditKind::Move,
+                None,
+                Some(("expression_statement", Point::new(2, 0))),
+                Some(("expression_statement", Point::new(0, 0))),
+            ),
+            (
+                DiffEditKind::Update,
+                Some("value"),
+                Some(("number", Point::new(0, 8))),
+                Some(("number", Point::new(1, 8))),
+            ),
+            (
+                DiffEditKind::Insert,
+                None,
+                None,
+                Some((",", Point::new(2, 5)))
+            ),
+            (
+                DiffEditKind::Insert,
+                None,
+                None,
+                Some(("identifier", Point::new(2, 7)))
+            ),
+        ]
+    );
+
+    // Identical trees have no differences, even if they were parsed separately.
+    let other_tree = parser.parse(old_source, None).unwrap();
+    assert!(old_tree
+        .diff(old_source.as_bytes(), &other_tree, old_source.as_bytes())
+        .is_empty());
+}
+
+#[test]
+fn test_tree_diff_with_moves_between_parents() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let old_source = "function f() {\n  first();\n}\nsecond();\n";
+    let new_source = "function f() {\n  second();\n}\nfirst(1);\n";
+    let old_tree = parser.parse(old_source, None).unwrap();
+    let new_tree = parser.parse(new_source, None).unwrap();
+
+    let edits = old_tree.diff(old_source.as_bytes(), &new_tree, new_source.as_bytes());
+    assert_eq!(
+        summarize_diff(&edits),
+        &[
+            (
+                DiffEditKind::Move,
+                None,
+                Some(("expression_statement", Point::new(3, 0))),
+                Some(("expression_statement", Point::new(1, 2))),
+            ),
+            (
+                DiffEditKind::Move,
+                None,
+                Some(("expression_statement", Point::new(1, 2))),
+                Some(("expression_statement", Point::new(3, 0))),
+            ),
+            (
+                DiffEditKind::Insert,
+                None,
+                None,
+                Some(("number", Point::new(3, 6)))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_tree_map_nodes() {
+    let mut parser = Parser::new();
+    parser.set_language(get_language("javascript")).unwrap();
+
+    let mut source = b"a(1);\nfunction f() { return 2; }\nb(3);\n".to_vec();
+    let old_tree = parser.parse(&source, None).unwrap();
+
+    // Insert a statement at the start, change the returned number, and remove the
+    // last statement.
+    let mut tree = old_tree.clone();
+    let edits = vec![
+        perform_edit(
+            &mut tree,
+            &mut source,
+            &Edit {
+                position: 0,
+                deleted_length: 0,
+                inserted_text: b"x;\n".to_vec(),
+            },
+        ),
+        perform_edit(
+            &mut tree,
+            &mut source,
+            &Edit {
+                position: 31,
+                deleted_length: 1,
+                inserted_text: b"22".to_vec(),
+            },
+        ),
+        perform_edit(
+            &mut tree,
+            &mut source,
+            &Edit {
+                position: 37,
+                deleted_length: 6,
+                inserted_text: Vec::new(),
+            },
+        ),
+    ];
+    assert_eq!(
+        str::from_utf8(&source).unwrap(),
+        "x;\na(1);\nfunction f() { return 22; }\n"
+    );
+    let new_tree = parser.parse(&source, Some(&tree)).unwrap();
+
+    let map = old_tree.map_nodes(&edits, &new_tree);
+    assert_eq!(
+        map.changes()
+            .iter()
+            .filter(|change| change.old_node.is_named())
+            .map(|change| (
+                change.kind,
+                change.old_node.kind(),
+                change.old_node.start_position(),
+                change.new_node.map(|node| node.start_position()),
+            ))
+            .collect::<Vec<_>>(),
+        &[
+            (
+                NodeChangeKind::Reused,
+                "program",
+                Point::new(0, 0),
+                Some(Point::new(0, 0)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "expression_statement",
+                Point::new(0, 0),
+                Some(Point::new(1, 0)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "call_expression",
+                Point::new(0, 0),
+                Some(Point::new(1, 0)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "identifier",
+                Point::new(0, 0),
+                Some(Point::new(1, 0)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "arguments",
+                Point::new(0, 1),
+                Some(Point::new(1, 1)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "number",
+                Point::new(0, 2),
+                Some(Point::new(1, 2)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "function_declaration",
+                Point::new(1, 0),
+                Some(Point::new(2, 0)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "identifier",
+                Point::new(1, 9),
+                Some(Point::new(2, 9)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "formal_parameters",
+                Point::new(1, 10),
+                Some(Point::new(2, 10)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "statement_block",
+                Point::new(1, 13),
+                Some(Point::new(2, 13)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "return_statement",
+                Point::new(1, 15),
+                Some(Point::new(2, 15)),
+            ),
+            (
+                NodeChangeKind::Moved,
+                "number",
+                Point::new(1, 22),
+                Some(Point::new(2, 22)),
+            ),
+            (
+                NodeChangeKind::Removed,
+                "expression_statement",
+                Point::new(2, 0),
+                None,
+            ),
+            (
+                NodeChangeKind::Removed,
+                "call_expression",
+                Point::new(2, 0),
+                None,
+            ),
+            (
+                NodeChangeKind::Removed,
+                "identifier",
+                Point::new(2, 0),
+                None,
+            ),
+            (NodeChangeKind::Removed, "arguments", Point::new(2, 1), None,),
+            (NodeChangeKind::Removed, "number", Point::new(2, 2), None,),
+        ]
+    );
+
+    let old_number = old_tree
+        .root_node()
+        .descendant_for_byte_range(28, 29)
+        .unwrap();
+    let new_number = map.new_node(old_number).unwrap();
+    assert_eq!(new_number.kind(), "number");
+    assert_eq!(new_number.utf8_text(&source).unwrap(), "22");
+    assert_eq!(map.old_node(new_number), Some(old_number));
+    assert_eq!(map.old_node(new_tree.root_node().child(0).unwrap()), None);
+}
+
+type DiffEditSummary = (
+    DiffEditKind,
+    Option<&'static str>,
+    Option<(&'static str, Point)>,
+    Option<(&'static str, Point)>,
+);
+
+fn summarize_diff(edits: &[DiffEdit]) -> Vec<DiffEditSummary> {
+    edits
+        .iter()
+        .map(|edit| {
+            (
+                edit.kind,
+                edit.field_name,
+                edit.old_node
+                    .map(|node| (node.kind(), node.start_position())),
+                edit.new_node
+                    .map(|node| (node.kind(), node.start_position())),
+            )
+        })
+        .collect()
+}
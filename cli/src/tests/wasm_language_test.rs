@@ -0,0 +1,889 @@
+use super::helpers::{allocations, fixtures::get_test_language};
+use crate::generate::generate_parser_for_grammar;
+use std::{
+    cmp,
+    ffi::{c_void, CStr},
+    fs, mem,
+    os::raw::c_char,
+    slice,
+};
+use tree_sitter::{wasmtime::Engine, InputEdit, Language, Parser, Point, WasmErrorKind, WasmStore};
+
+const LANGUAGE_NAME: &str = "wasm_test_language";
+
+const GRAMMAR: &str = r#"{
+    "name": "wasm_test_language",
+    "word": "word",
+    "externals": [{"type": "SYMBOL", "name": "comment"}],
+    "extras": [
+        {"type": "PATTERN", "value": "\\s"},
+        {"type": "SYMBOL", "name": "comment"}
+    ],
+    "rules": {
+        "program": {
+            "type": "REPEAT",
+            "content": {
+                "type": "CHOICE",
+                "members": [
+                    {"type": "SYMBOL", "name": "pair"},
+                    {"type": "SYMBOL", "name": "declaration"},
+                    {"type": "SYMBOL", "name": "word"},
+                    {"type": "SYMBOL", "name": "number"}
+                ]
+            }
+        },
+        "pair": {
+            "type": "SEQ",
+            "members": [
+                {"type": "FIELD", "name": "key", "content": {"type": "SYMBOL", "name": "word"}},
+                {"type": "STRING", "value": "="},
+                {"type": "FIELD", "name": "value", "content": {"type": "SYMBOL", "name": "number"}}
+            ]
+        },
+        "declaration": {
+            "type": "SEQ",
+            "members": [
+                {"type": "STRING", "value": "let"},
+                {"type": "FIELD", "name": "name", "content": {"type": "SYMBOL", "name": "word"}}
+            ]
+        },
+        "word": {"type": "PATTERN", "value": "[a-z]+"},
+        "number": {"type": "PATTERN", "value": "[0-9]+"}
+    }
+}"#;
+
+// An external scanner for `#` comments, which counts the comments that it has
+// scanned, so that its state is serialized along with the tree.
+const SCANNER: &str = r#"
+#include <tree_sitter/parser.h>
+#include <stdlib.h>
+
+void *tree_sitter_wasm_test_language_external_scanner_create() {
+  return calloc(1, sizeof(unsigned));
+}
+
+void tree_sitter_wasm_test_language_external_scanner_destroy(void *payload) {
+  free(payload);
+}
+
+bool tree_sitter_wasm_test_language_external_scanner_scan(
+  void *payload,
+  TSLexer *lexer,
+  const bool *valid_symbols
+) {
+  if (!valid_symbols[0]) return false;
+  while (!lexer->eof(lexer) && lexer->lookahead <= ' ') lexer->advance(lexer, true);
+  if (lexer->lookahead != '#') return false;
+  while (!lexer->eof(lexer) && lexer->lookahead != '\n') lexer->advance(lexer, false);
+  *(unsigned *)payload += 1;
+  lexer->result_symbol = 0;
+  lexer->mark_end(lexer);
+  return true;
+}
+
+unsigned tree_sitter_wasm_test_language_external_scanner_serialize(void *payload, char *buffer) {
+  buffer[0] = *(unsigned *)payload;
+  return 1;
+}
+
+void tree_sitter_wasm_test_language_external_scanner_deserialize(
+  void *payload,
+  const char *buffer,
+  unsigned length
+) {
+  *(unsigned *)payload = length > 0 ? (unsigned char)buffer[0] : 0;
+}
+"#;
+
+#[test]
+fn test_wasm_language_parsing() {
+    let native_language = get_native_test_language();
+    let engine = Engine::default();
+    let mut store = WasmStore::new(&engine).unwrap();
+    let wasm_language = store
+        .load_language(LANGUAGE_NAME, &side_module(native_language))
+        .unwrap();
+
+    assert!(wasm_language.is_wasm());
+    assert!(!native_language.is_wasm());
+    assert_eq!(wasm_language.version(), native_language.version());
+    assert_eq!(
+        wasm_language.node_kind_count(),
+        native_language.node_kind_count()
+    );
+    assert_eq!(wasm_language.field_count(), native_language.field_count());
+    assert_eq!(
+        wasm_language.field_id_for_name("value"),
+        native_language.field_id_for_name("value")
+    );
+
+    let mut native_parser = Parser::new();
+    native_parser.set_language(native_language).unwrap();
+    let mut wasm_parser = Parser::new();
+    wasm_parser.set_wasm_store(store);
+    wasm_parser.set_language(wasm_language).unwrap();
+
+    let mut source = "let x\nkey = 1 # a comment\nfoo 42\n# another\n".to_string();
+    let native_tree = native_parser.parse(&source, None).unwrap();
+    let mut wasm_tree = wasm_parser.parse(&source, None).unwrap();
+    assert_eq!(
+        wasm_tree.root_node().to_sexp(),
+        "(program (declaration name: (word)) (pair key: (word) value: (number)) (comment) (word) (number) (comment))"
+    );
+    assert_eq!(
+        wasm_tree.root_node().to_sexp(),
+        native_tree.root_node().to_sexp()
+    );
+    let pair = wasm_tree.root_node().named_child(1).unwrap();
+    assert_eq!(
+        pair.child_by_field_name("value")
+            .unwrap()
+            .utf8_text(source.as_bytes()),
+        Ok("1")
+    );
+
+    // Reparsing after an edit reuses the scanner states that were serialized in
+    // the old tree.
+    let position = source.find("foo").unwrap();
+    source.insert_str(position, "bar = 5 ");
+    let edit = InputEdit {
+        start_byte: position,
+        old_end_byte: position,
+        new_end_byte: position + 8,
+        start_position: Point::new(2, 0),
+        old_end_position: Point::new(2, 0),
+        new_end_position: Point::new(2, 8),
+    };
+    wasm_tree.edit(&edit);
+    let wasm_tree = wasm_parser.parse(&source, Some(&wasm_tree)).unwrap();
+    let native_tree = native_parser.parse(&source, None).unwrap();
+    assert_eq!(
+        wasm_tree.root_node().to_sexp(),
+        native_tree.root_node().to_sexp()
+    );
+
+    // Invalid input produces the same errors.
+    let source = "key = = 1 let";
+    assert_eq!(
+        wasm_parser
+            .parse(source, None)
+            .unwrap()
+            .root_node()
+            .to_sexp(),
+        native_parser
+            .parse(source, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+}
+
+#[test]
+fn test_wasm_language_in_multiple_stores() {
+    let native_language = get_native_test_language();
+    let engine = Engine::default();
+    let mut store = WasmStore::new(&engine).unwrap();
+    let wasm_language = store
+        .load_language(LANGUAGE_NAME, &side_module(native_language))
+        .unwrap();
+
+    // A language can be used in any store that belongs to the same engine.
+    let mut parser = Parser::new();
+    parser.set_wasm_store(WasmStore::new(&engine).unwrap());
+    parser.set_language(wasm_language).unwrap();
+    let tree = parser.parse("a = 1 # one", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(program (pair key: (word) value: (number)) (comment))"
+    );
+
+    // Taking the store unsets the language, because it can't be used without one.
+    let store = parser.take_wasm_store().unwrap();
+    assert_eq!(parser.language(), None);
+    assert!(parser.take_wasm_store().is_none());
+    assert!(parser.set_language(wasm_language).is_err());
+    assert!(parser.parse("a", None).is_none());
+
+    parser.set_wasm_store(store);
+    parser.set_language(wasm_language).unwrap();
+    let tree = parser.parse("let b", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(program (declaration name: (word)))"
+    );
+
+    // Native languages can still be used by a parser with a store.
+    parser.set_language(native_language).unwrap();
+    let tree = parser.parse("let b", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(program (declaration name: (word)))"
+    );
+}
+
+#[test]
+fn test_wasm_language_is_freed_with_its_stores() {
+    let native_language = get_native_test_language();
+    let module = side_module(native_language);
+    let engine = Engine::default();
+
+    allocations::record(|| {
+        let mut store = WasmStore::new(&engine).unwrap();
+        let wasm_language = store.load_language(LANGUAGE_NAME, &module).unwrap();
+
+        // The language remains usable in other stores after the store that loaded
+        // it has been dropped.
+        let mut parser = Parser::new();
+        parser.set_wasm_store(WasmStore::new(&engine).unwrap());
+        parser.set_language(wasm_language).unwrap();
+        parser.parse("a = 1", None).unwrap();
+        drop(store);
+        let tree = parser.parse("let b", None).unwrap();
+        assert_eq!(
+            tree.root_node().to_sexp(),
+            "(program (declaration name: (word)))"
+        );
+
+        // The language is freed along with the last store that used it.
+        drop(tree);
+        drop(parser);
+    });
+}
+
+#[test]
+fn test_wasm_language_with_a_trapping_lexer() {
+    let native_language = get_native_test_language();
+    let engine = Engine::default();
+    let mut store = WasmStore::new(&engine).unwrap();
+    let wasm_language = store
+        .load_language(LANGUAGE_NAME, &side_module(native_language))
+        .unwrap();
+
+    let mut parser = Parser::new();
+    parser.set_wasm_store(store);
+    parser.set_language(wasm_language).unwrap();
+
+    // The lexer calls `abort` when it sees a `!`, which traps.
+    assert!(parser.parse("a = 1 ! b", None).is_none());
+
+    // The parser is reset, so it can parse other documents afterward.
+    let tree = parser.parse("a = 1", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(program (pair key: (word) value: (number)))"
+    );
+}
+
+#[test]
+fn test_wasm_language_loading_errors() {
+    let native_language = get_native_test_language();
+    let engine = Engine::default();
+    let mut store = WasmStore::new(&engine).unwrap();
+
+    let error = store.load_language(LANGUAGE_NAME, b"not wasm").unwrap_err();
+    assert_eq!(error.kind, WasmErrorKind::Parse);
+
+    let error = store
+        .load_language(LANGUAGE_NAME, &wat::parse_str("(module)").unwrap())
+        .unwrap_err();
+    assert_eq!(error.kind, WasmErrorKind::Parse);
+
+    let error = store
+        .load_language("other_language", &side_module(native_language))
+        .unwrap_err();
+    assert_eq!(error.kind, WasmErrorKind::Instantiate);
+    assert_eq!(
+        error.message,
+        "The module does not export tree_sitter_other_language"
+    );
+
+    // The store is still usable after an error.
+    store
+        .load_language(LANGUAGE_NAME, &side_module(native_language))
+        .unwrap();
+}
+
+#[test]
+fn test_wasm_language_with_corrupted_tables() {
+    let native_language = get_native_test_language();
+    let state_count = native_language.parse_state_count() as u16;
+    let symbol_count = native_language.node_kind_count() as u16;
+    let field_count = native_language.field_count() as u16;
+    let engine = Engine::default();
+    let mut store = WasmStore::new(&engine).unwrap();
+
+    let corruptions: [&Corruption<'_>; 5] = [
+        // A shift action to a nonexistent state
+        &|image, tables| {
+            let action = find_parse_action(image, tables, 0);
+            image.set_u16(action + 2, state_count);
+        },
+        // A reduce action to a nonexistent symbol
+        &|image, tables| {
+            let action = find_parse_action(image, tables, 1);
+            image.set_u16(action + 2, symbol_count);
+        },
+        // A nonexistent primary state
+        &|image, tables| image.set_u16(table_address(tables, 132), state_count),
+        // A nonexistent public symbol
+        &|image, tables| image.set_u16(table_address(tables, 76) + 2, symbol_count),
+        // A nonexistent field
+        &|image, tables| image.set_u16(table_address(tables, 68), field_count + 1),
+    ];
+    for corrupt in corruptions {
+        let error = store
+            .load_language(
+                LANGUAGE_NAME,
+                &corrupted_side_module(native_language, corrupt),
+            )
+            .unwrap_err();
+        assert_eq!(error.kind, WasmErrorKind::Parse);
+        assert_eq!(
+            error.message,
+            "The module does not contain a valid language"
+        );
+    }
+
+    // The uncorrupted module is valid.
+    store
+        .load_language(LANGUAGE_NAME, &side_module(native_language))
+        .unwrap();
+}
+
+/// The image offsets of a language's tables, keyed by the offsets of their
+/// pointers in the language struct.
+type Tables = [(usize, usize)];
+
+/// A function that modifies the data image of a side module.
+type Corruption<'a> = dyn Fn(&mut DataImage, &Tables) + 'a;
+
+/// Find the offset of the image's table that is stored at the given offset
+/// in the language struct.
+fn table_address(tables: &Tables, field_offset: usize) -> usize {
+    tables
+        .iter()
+        .find(|(offset, _)| *offset == field_offset)
+        .unwrap()
+        .1
+}
+
+/// Find the offset of the first parse action of the given type in the image.
+fn find_parse_action(image: &DataImage, tables: &Tables, action_type: u8) -> usize {
+    let parse_actions = table_address(tables, 52);
+    let mut entry = parse_actions;
+    loop {
+        let action_count = image.data[entry] as usize;
+        for i in 1..=action_count {
+            let action = entry + 8 * i;
+            if image.data[action] == action_type {
+                return action;
+            }
+        }
+        entry += 8 * (1 + action_count);
+    }
+}
+
+fn get_native_test_language() -> Language {
+    let (name, parser_code) = generate_parser_for_grammar(GRAMMAR).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("scanner.c"), SCANNER).unwrap();
+    get_test_language(&name, &parser_code, Some(dir.path()))
+}
+
+// The layout of the `TSLanguage` struct in native memory.
+#[repr(C)]
+struct NativeLanguage {
+    version: u32,
+    symbol_count: u32,
+    alias_count: u32,
+    token_count: u32,
+    external_token_count: u32,
+    state_count: u32,
+    large_state_count: u32,
+    production_id_count: u32,
+    field_count: u32,
+    max_alias_sequence_length: u16,
+    parse_table: *const u16,
+    small_parse_table: *const u16,
+    small_parse_table_map: *const u32,
+    parse_actions: *const [u16; 4],
+    symbol_names: *const *const c_char,
+    field_names: *const *const c_char,
+    field_map_slices: *const [u16; 2],
+    field_map_entries: *const [u16; 2],
+    symbol_metadata: *const [bool; 3],
+    public_symbol_map: *const u16,
+    alias_map: *const u16,
+    alias_sequences: *const u16,
+    lex_modes: *const [u16; 2],
+    lex_fn: *const c_void,
+    keyword_lex_fn: *const c_void,
+    keyword_capture_token: u16,
+    external_scanner_states: *const bool,
+    external_scanner_symbol_map: *const u16,
+    external_scanner_functions: [*const c_void; 5],
+    primary_state_ids: *const u16,
+    supertype_count: u32,
+    supertype_symbols: *const u16,
+    supertype_map_slices: *const [u16; 2],
+    supertype_map_entries: *const u16,
+    child_info_map_slices: *const [u16; 2],
+    child_info_entries: *const [u16; 4],
+    child_type_entries: *const u16,
+}
+
+// The size of the `TSLanguage` struct in 32-bit WebAssembly memory.
+const WASM_LANGUAGE_SIZE: usize = 164;
+
+/// A copy of a language's parse tables, laid out in 32-bit WebAssembly memory
+/// starting with the language struct.
+struct DataImage {
+    data: Vec<u8>,
+    relocations: Vec<(usize, usize)>,
+}
+
+impl DataImage {
+    fn push<T>(&mut self, pointer: *const T, count: usize) -> usize {
+        if pointer.is_null() || count == 0 {
+            return 0;
+        }
+        self.data.resize((self.data.len() + 3) / 4 * 4, 0);
+        let offset = self.data.len();
+        let bytes =
+            unsafe { slice::from_raw_parts(pointer as *const u8, count * mem::size_of::<T>()) };
+        self.data.extend_from_slice(bytes);
+        offset
+    }
+
+    fn push_strings(&mut self, pointer: *const *const c_char, count: usize) -> usize {
+        let offset = self.push(vec![0u32; count].as_ptr(), count);
+        for (i, string) in native_slice(pointer, count).iter().enumerate() {
+            if !string.is_null() {
+                let bytes = unsafe { CStr::from_ptr(*string) }.to_bytes_with_nul();
+                let target = self.push(bytes.as_ptr(), bytes.len());
+                self.relocations.push((offset + 4 * i, target));
+            }
+        }
+        offset
+    }
+
+    fn set_u32(&mut self, offset: usize, value: u32) {
+        self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn set_u16(&mut self, offset: usize, value: u16) {
+        self.data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn set_pointer(&mut self, offset: usize, target: usize) {
+        if target != 0 {
+            self.relocations.push((offset, target));
+        }
+    }
+}
+
+fn native_slice<'a, T>(pointer: *const T, count: usize) -> &'a [T] {
+    if pointer.is_null() || count == 0 {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(pointer, count) }
+    }
+}
+
+/// Build an Emscripten-style side module for the given native language, with
+/// a copy of its parse tables, and hand-written lexing functions that are
+/// equivalent to the native ones.
+fn side_module(language: Language) -> Vec<u8> {
+    corrupted_side_module(language, |_, _| {})
+}
+
+/// Build a side module like `side_module`, but modify its data image first.
+/// The modification function receives the image and the offsets of its tables.
+fn corrupted_side_module(
+    language: Language,
+    corrupt: impl FnOnce(&mut DataImage, &Tables),
+) -> Vec<u8> {
+    let native = unsafe { &*mem::transmute::<Language, *const NativeLanguage>(language) };
+    let symbol_count = native.symbol_count as usize;
+    let token_count = native.token_count as usize;
+    let total_symbol_count = symbol_count + native.alias_count as usize;
+    let state_count = native.state_count as usize;
+    let large_state_count = native.large_state_count as usize;
+    let production_id_count = native.production_id_count as usize;
+
+    // Find the lengths of the tables, which are not stored in the language.
+    let parse_table = native_slice(native.parse_table, large_state_count * symbol_count);
+    let mut parse_action_count = 1;
+    for (i, action_index) in parse_table.iter().enumerate() {
+        if i % symbol_count < token_count {
+            parse_action_count = cmp::max(parse_action_count, *action_index as usize + 1);
+        }
+    }
+    let small_parse_table = |index: usize| unsafe { *native.small_parse_table.add(index) } as usize;
+    let mut small_parse_table_length = 0;
+    let small_parse_table_map = native_slice(
+        native.small_parse_table_map,
+        state_count - large_state_count,
+    );
+    for index in small_parse_table_map {
+        let mut index = *index as usize;
+        let group_count = small_parse_table(index);
+        index += 1;
+        for _ in 0..group_count {
+            let value = small_parse_table(index);
+            let group_symbol_count = small_parse_table(index + 1);
+            index += 2;
+            for _ in 0..group_symbol_count {
+                if small_parse_table(index) < token_count {
+                    parse_action_count = cmp::max(parse_action_count, value + 1);
+                }
+                index += 1;
+            }
+        }
+        small_parse_table_length = cmp::max(small_parse_table_length, index);
+    }
+    let mut i = 0;
+    while i < parse_action_count {
+        let action_count = (unsafe { (*native.parse_actions.add(i))[0] } & 0xff) as usize;
+        parse_action_count = cmp::max(parse_action_count, i + 1 + action_count);
+        i += 1 + action_count;
+    }
+    let alias_map = |index: usize| unsafe { *native.alias_map.add(index) } as usize;
+    let mut alias_map_length = 0;
+    loop {
+        alias_map_length += 1;
+        if alias_map(alias_map_length - 1) == 0 {
+            break;
+        }
+        alias_map_length += 1 + alias_map(alias_map_length);
+    }
+    let slice_entry_count = |slices: &[[u16; 2]]| {
+        slices
+            .iter()
+            .map(|[index, length]| (index + length) as usize)
+            .max()
+            .unwrap_or(0)
+    };
+    let field_map_slices = native_slice(native.field_map_slices, production_id_count);
+    let lex_modes = native_slice(native.lex_modes, state_count);
+    let external_lex_state_count = lex_modes.iter().map(|m| m[1] as usize + 1).max().unwrap();
+    let supertype_map_slices = native_slice(native.supertype_map_slices, symbol_count);
+    let child_info_map_slices = native_slice(native.child_info_map_slices, total_symbol_count);
+    let child_info_entries = native_slice(
+        native.child_info_entries,
+        slice_entry_count(child_info_map_slices),
+    );
+    let child_type_count = slice_entry_count(
+        &child_info_entries
+            .iter()
+            .map(|entry| [entry[2], entry[3]])
+            .collect::<Vec<_>>(),
+    );
+
+    let mut image = DataImage {
+        data: vec![0; WASM_LANGUAGE_SIZE],
+        relocations: Vec::new(),
+    };
+    let counts = [
+        native.version,
+        native.symbol_count,
+        native.alias_count,
+        native.token_count,
+        native.external_token_count,
+        native.state_count,
+        native.large_state_count,
+        native.production_id_count,
+        native.field_count,
+    ];
+    for (i, count) in counts.iter().enumerate() {
+        image.set_u32(4 * i, *count);
+    }
+    image.set_u16(36, native.max_alias_sequence_length);
+    image.set_u16(100, native.keyword_capture_token);
+    image.set_u32(136, native.supertype_count);
+
+    let tables = [
+        (40, image.push(native.parse_table, parse_table.len())),
+        (
+            44,
+            image.push(native.small_parse_table, small_parse_table_length),
+        ),
+        (
+            48,
+            image.push(native.small_parse_table_map, small_parse_table_map.len()),
+        ),
+        (52, image.push(native.parse_actions, parse_action_count)),
+        (
+            56,
+            image.push_strings(native.symbol_names, total_symbol_count),
+        ),
+        (
+            60,
+            image.push_strings(native.field_names, native.field_count as usize + 1),
+        ),
+        (64, image.push(native.field_map_slices, production_id_count)),
+        (
+            68,
+            image.push(
+                native.field_map_entries,
+                slice_entry_count(field_map_slices),
+            ),
+        ),
+        (72, image.push(native.symbol_metadata, total_symbol_count)),
+        (76, image.push(native.public_symbol_map, total_symbol_count)),
+        (80, image.push(native.alias_map, alias_map_length)),
+        (
+            84,
+            image.push(
+                native.alias_sequences,
+                production_id_count * native.max_alias_sequence_length as usize,
+            ),
+        ),
+        (88, image.push(native.lex_modes, state_count)),
+        (
+            104,
+            image.push(
+                native.external_scanner_states,
+                external_lex_state_count * native.external_token_count as usize,
+            ),
+        ),
+        (
+            108,
+            image.push(
+                native.external_scanner_symbol_map,
+                native.external_token_count as usize,
+            ),
+        ),
+        (132, image.push(native.primary_state_ids, state_count)),
+        (
+            140,
+            image.push(native.supertype_symbols, native.supertype_count as usize),
+        ),
+        (
+            144,
+            image.push(native.supertype_map_slices, supertype_map_slices.len()),
+        ),
+        (
+            148,
+            image.push(
+                native.supertype_map_entries,
+                slice_entry_count(supertype_map_slices),
+            ),
+        ),
+        (
+            152,
+            image.push(native.child_info_map_slices, child_info_map_slices.len()),
+        ),
+        (
+            156,
+            image.push(native.child_info_entries, child_info_entries.len()),
+        ),
+        (160, image.push(native.child_type_entries, child_type_count)),
+    ];
+    corrupt(&mut image, &tables);
+    for (offset, target) in tables {
+        image.set_pointer(offset, target);
+    }
+
+    // The function pointers are indices in the function table, relative to the
+    // module's table base.
+    assert!(!native.keyword_lex_fn.is_null());
+    let function_relocations = [
+        (92, 0),
+        (96, 1),
+        (112, 2),
+        (116, 3),
+        (120, 4),
+        (124, 5),
+        (128, 6),
+    ];
+
+    let mut relocation_code = String::new();
+    for (offset, target) in &image.relocations {
+        relocation_code += &format!(
+            "(i32.store (i32.add (global.get $memory_base) (i32.const {})) (i32.add (global.get $memory_base) (i32.const {})))\n",
+            offset, target
+        );
+    }
+    for (offset, index) in function_relocations {
+        relocation_code += &format!(
+            "(i32.store (i32.add (global.get $memory_base) (i32.const {})) (i32.add (global.get $table_base) (i32.const {})))\n",
+            offset, index
+        );
+    }
+    let data = image
+        .data
+        .iter()
+        .map(|byte| format!("\\{:02x}", byte))
+        .collect::<String>();
+
+    let symbol = |name: &str, named: bool| language.id_for_node_kind(name, named);
+    let wat = LEXER_MODULE_TEMPLATE
+        .replace("$MEMORY_INFO", &memory_info(image.data.len(), 7))
+        .replace("$NAME", LANGUAGE_NAME)
+        .replace("$DATA", &data)
+        .replace("$RELOCATIONS", &relocation_code)
+        .replace("$WORD", &symbol("word", true).to_string())
+        .replace("$NUMBER", &symbol("number", true).to_string())
+        .replace("$EQUALS", &symbol("=", false).to_string())
+        .replace("$LET", &symbol("let", false).to_string());
+    wat::parse_str(wat).unwrap()
+}
+
+/// The contents of the `dylink.0` custom section, with the sizes of the memory
+/// and the table that the module needs.
+fn memory_info(memory_size: usize, table_size: usize) -> String {
+    let mut subsection = Vec::new();
+    for value in [memory_size, 2, table_size, 0] {
+        let mut value = value;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                subsection.push(byte);
+                break;
+            }
+            subsection.push(byte | 0x80);
+        }
+    }
+    let mut section = vec![1, subsection.len() as u8];
+    section.extend(subsection);
+    section
+        .iter()
+        .map(|byte| format!("\\{:02x}", byte))
+        .collect()
+}
+
+const LEXER_MODULE_TEMPLATE: &str = r#"
+(module
+  (@custom "dylink.0" (before first) "$MEMORY_INFO")
+
+  (type $advance_fn (func (param i32 i32)))
+  (type $mark_end_fn (func (param i32)))
+  (type $eof_fn (func (param i32) (result i32)))
+
+  (import "env" "memory" (memory 0))
+  (import "env" "__indirect_function_table" (table $table 0 funcref))
+  (import "env" "__memory_base" (global $memory_base i32))
+  (import "env" "__table_base" (global $table_base i32))
+  (import "env" "malloc" (func $malloc (param i32) (result i32)))
+  (import "env" "free" (func $free (param i32)))
+  (import "env" "abort" (func $abort))
+
+  (data (global.get $memory_base) "$DATA")
+
+  (elem (table $table) (global.get $table_base) func
+    $lex_main $lex_keyword
+    $scanner_create $scanner_destroy $scanner_scan $scanner_serialize $scanner_deserialize)
+
+  (func (export "__wasm_apply_data_relocs")
+    $RELOCATIONS)
+
+  (func (export "tree_sitter_$NAME") (result i32)
+    (global.get $memory_base))
+
+  (func $lookahead (param $lexer i32) (result i32)
+    (i32.load (local.get $lexer)))
+
+  (func $advance (param $lexer i32) (param $skip i32)
+    (call_indirect (type $advance_fn)
+      (local.get $lexer) (local.get $skip) (i32.load offset=8 (local.get $lexer))))
+
+  (func $eof (param $lexer i32) (result i32)
+    (call_indirect (type $eof_fn) (local.get $lexer) (i32.load offset=24 (local.get $lexer))))
+
+  (func $accept (param $lexer i32) (param $symbol i32) (result i32)
+    (i32.store16 offset=4 (local.get $lexer) (local.get $symbol))
+    (call_indirect (type $mark_end_fn) (local.get $lexer) (i32.load offset=12 (local.get $lexer)))
+    (i32.const 1))
+
+  (func $in_range (param $c i32) (param $min i32) (param $max i32) (result i32)
+    (i32.le_u
+      (i32.sub (local.get $c) (local.get $min))
+      (i32.sub (local.get $max) (local.get $min))))
+
+  (func $skip_whitespace (param $lexer i32)
+    (block $done
+      (loop $continue
+        (br_if $done (call $eof (local.get $lexer)))
+        (br_if $done (i32.gt_u (call $lookahead (local.get $lexer)) (i32.const 32)))
+        (call $advance (local.get $lexer) (i32.const 1))
+        (br $continue))))
+
+  (func $advance_in_range (param $lexer i32) (param $min i32) (param $max i32)
+    (block $done
+      (loop $continue
+        (br_if $done (call $eof (local.get $lexer)))
+        (br_if $done
+          (i32.eqz (call $in_range (call $lookahead (local.get $lexer)) (local.get $min) (local.get $max))))
+        (call $advance (local.get $lexer) (i32.const 0))
+        (br $continue))))
+
+  (func $lex_main (param $lexer i32) (param $state i32) (result i32)
+    (local $c i32)
+    (call $skip_whitespace (local.get $lexer))
+    (if (call $eof (local.get $lexer))
+      (then (return (call $accept (local.get $lexer) (i32.const 0)))))
+    (local.set $c (call $lookahead (local.get $lexer)))
+    (if (call $in_range (local.get $c) (i32.const 97) (i32.const 122))
+      (then
+        (call $advance_in_range (local.get $lexer) (i32.const 97) (i32.const 122))
+        (return (call $accept (local.get $lexer) (i32.const $WORD)))))
+    (if (call $in_range (local.get $c) (i32.const 48) (i32.const 57))
+      (then
+        (call $advance_in_range (local.get $lexer) (i32.const 48) (i32.const 57))
+        (return (call $accept (local.get $lexer) (i32.const $NUMBER)))))
+    (if (i32.eq (local.get $c) (i32.const 61))
+      (then
+        (call $advance (local.get $lexer) (i32.const 0))
+        (return (call $accept (local.get $lexer) (i32.const $EQUALS)))))
+    (if (i32.eq (local.get $c) (i32.const 33))
+      (then (call $abort)))
+    (i32.const 0))
+
+  (func $lex_keyword (param $lexer i32) (param $state i32) (result i32)
+    (if (i32.ne (call $lookahead (local.get $lexer)) (i32.const 108))
+      (then (return (i32.const 0))))
+    (call $advance (local.get $lexer) (i32.const 0))
+    (if (i32.ne (call $lookahead (local.get $lexer)) (i32.const 101))
+      (then (return (i32.const 0))))
+    (call $advance (local.get $lexer) (i32.const 0))
+    (if (i32.ne (call $lookahead (local.get $lexer)) (i32.const 116))
+      (then (return (i32.const 0))))
+    (call $advance (local.get $lexer) (i32.const 0))
+    (call $accept (local.get $lexer) (i32.const $LET)))
+
+  (func $scanner_create (result i32)
+    (local $payload i32)
+    (local.set $payload (call $malloc (i32.const 4)))
+    (i32.store (local.get $payload) (i32.const 0))
+    (local.get $payload))
+
+  (func $scanner_destroy (param $payload i32)
+    (call $free (local.get $payload)))
+
+  (func $scanner_scan (param $payload i32) (param $lexer i32) (param $valid_symbols i32) (result i32)
+    (if (i32.eqz (i32.load8_u (local.get $valid_symbols)))
+      (then (return (i32.const 0))))
+    (call $skip_whitespace (local.get $lexer))
+    (if (i32.ne (call $lookahead (local.get $lexer)) (i32.const 35))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $continue
+        (br_if $done (call $eof (local.get $lexer)))
+        (br_if $done (i32.eq (call $lookahead (local.get $lexer)) (i32.const 10)))
+        (call $advance (local.get $lexer) (i32.const 0))
+        (br $continue)))
+    (i32.store (local.get $payload) (i32.add (i32.load (local.get $payload)) (i32.const 1)))
+    (call $accept (local.get $lexer) (i32.const 0)))
+
+  (func $scanner_serialize (param $payload i32) (param $buffer i32) (result i32)
+    (i32.store8 (local.get $buffer) (i32.load (local.get $payload)))
+    (i32.const 1))
+
+  (func $scanner_deserialize (param $payload i32) (param $buffer i32) (param $length i32)
+    (i32.store (local.get $payload)
+      (if (result i32) (local.get $length)
+        (then (i32.load8_u (local.get $buffer)))
+        (else (i32.const 0))))))
+"#;
@@ -29,6 +29,7 @@ pub fn cancel_on_stdin() -> Arc<AtomicUsize> {
     }
     result
 }
+
 #[cfg(windows)]
 pub struct LogSession();
 
@@ -1,5 +1,6 @@
 /* automatically generated by rust-bindgen 0.59.2 */
 
+pub type TSStateId = u16;
 pub type TSSymbol = u16;
 pub type TSFieldId = u16;
 #[repr(C)]
@@ -27,6 +28,16 @@ pub struct TSQuery {
 pub struct TSQueryCursor {
     _unused: [u8; 0],
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSLookaheadIterator {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSWasmStore {
+    _unused: [u8; 0],
+}
 pub const TSInputEncoding_TSInputEncodingUTF8: TSInputEncoding = 0;
 pub const TSInputEncoding_TSInputEncodingUTF16: TSInputEncoding = 1;
 pub type TSInputEncoding = ::std::os::raw::c_uint;
@@ -79,6 +90,37 @@ pub struct TSLogger {
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+pub struct TSAllocator {
+    pub payload: *mut ::std::os::raw::c_void,
+    pub allocate: ::std::option::Option<
+        unsafe extern "C" fn(
+            payload: *mut ::std::os::raw::c_void,
+            size: usize,
+        ) -> *mut ::std::os::raw::c_void,
+    >,
+    pub allocate_zeroed: ::std::option::Option<
+        unsafe extern "C" fn(
+            payload: *mut ::std::os::raw::c_void,
+            count: usize,
+            size: usize,
+        ) -> *mut ::std::os::raw::c_void,
+    >,
+    pub reallocate: ::std::option::Option<
+        unsafe extern "C" fn(
+            payload: *mut ::std::os::raw::c_void,
+            buffer: *mut ::std::os::raw::c_void,
+            size: usize,
+        ) -> *mut ::std::os::raw::c_void,
+    >,
+    pub deallocate: ::std::option::Option<
+        unsafe extern "C" fn(
+            payload: *mut ::std::os::raw::c_void,
+            buffer: *mut ::std::os::raw::c_void,
+        ),
+    >,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
 pub struct TSInputEdit {
     pub start_byte: u32,
     pub old_end_byte: u32,
@@ -121,6 +163,23 @@ pub struct TSQueryMatch {
     pub capture_count: u16,
     pub captures: *const TSQueryCapture,
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSChildInfo {
+    pub field_id: TSFieldId,
+    pub multiple: bool,
+    pub required: bool,
+    pub types: *const TSSymbol,
+    pub type_count: u32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSNodeStats {
+    pub node_count: u32,
+    pub reused_node_count: u32,
+    pub depth: u32,
+    pub memory_usage: usize,
+}
 pub const TSQueryPredicateStepType_TSQueryPredicateStepTypeDone: TSQueryPredicateStepType = 0;
 pub const TSQueryPredicateStepType_TSQueryPredicateStepTypeCapture: TSQueryPredicateStepType = 1;
 pub const TSQueryPredicateStepType_TSQueryPredicateStepTypeString: TSQueryPredicateStepType = 2;
@@ -139,10 +198,52 @@ pub const TSQueryError_TSQueryErrorCapture: TSQueryError = 4;
 pub const TSQueryError_TSQueryErrorStructure: TSQueryError = 5;
 pub const TSQueryError_TSQueryErrorLanguage: TSQueryError = 6;
 pub type TSQueryError = ::std::os::raw::c_uint;
+pub const TSQueryTokenType_TSQueryTokenTypeComment: TSQueryTokenType = 0;
+pub const TSQueryTokenType_TSQueryTokenTypeAlternationStart: TSQueryTokenType = 1;
+pub const TSQueryTokenType_TSQueryTokenTypeGroupStart: TSQueryTokenType = 2;
+pub const TSQueryTokenType_TSQueryTokenTypeNodeStart: TSQueryTokenType = 3;
+pub const TSQueryTokenType_TSQueryTokenTypePredicateStart: TSQueryTokenType = 4;
+pub const TSQueryTokenType_TSQueryTokenTypeEnd: TSQueryTokenType = 5;
+pub const TSQueryTokenType_TSQueryTokenTypeNodeName: TSQueryTokenType = 6;
+pub const TSQueryTokenType_TSQueryTokenTypeSupertypeSeparator: TSQueryTokenType = 7;
+pub const TSQueryTokenType_TSQueryTokenTypeWildcard: TSQueryTokenType = 8;
+pub const TSQueryTokenType_TSQueryTokenTypeString: TSQueryTokenType = 9;
+pub const TSQueryTokenType_TSQueryTokenTypeFieldName: TSQueryTokenType = 10;
+pub const TSQueryTokenType_TSQueryTokenTypeColon: TSQueryTokenType = 11;
+pub const TSQueryTokenType_TSQueryTokenTypeNegation: TSQueryTokenType = 12;
+pub const TSQueryTokenType_TSQueryTokenTypeAnchor: TSQueryTokenType = 13;
+pub const TSQueryTokenType_TSQueryTokenTypeQuantifier: TSQueryTokenType = 14;
+pub const TSQueryTokenType_TSQueryTokenTypeCapture: TSQueryTokenType = 15;
+pub const TSQueryTokenType_TSQueryTokenTypePredicateName: TSQueryTokenType = 16;
+pub const TSQueryTokenType_TSQueryTokenTypeIdentifier: TSQueryTokenType = 17;
+pub type TSQueryTokenType = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSQueryToken {
+    pub type_: TSQueryTokenType,
+    pub start_byte: u32,
+    pub end_byte: u32,
+}
 extern "C" {
     #[doc = " Create a new parser."]
     pub fn ts_parser_new() -> *mut TSParser;
 }
+extern "C" {
+    #[doc = " Create a new parser that uses the given allocator, instead of the global"]
+    #[doc = " allocation functions, for all of its memory. The syntax trees that the"]
+    #[doc = " parser produces, and their copies, use the same allocator."]
+    #[doc = ""]
+    #[doc = " The allocator's `payload` is passed to each of its functions, which behave"]
+    #[doc = " like `malloc`, `calloc`, `realloc`, and `free`, and which must remain valid"]
+    #[doc = " until the parser and all of its trees have been deleted. The functions must"]
+    #[doc = " never return `NULL` for a non-zero size. To limit the memory that a parse"]
+    #[doc = " can use, set the parser's cancellation flag from within the allocator once"]
+    #[doc = " the limit is reached."]
+    #[doc = ""]
+    #[doc = " An old tree that was passed to `ts_parser_parse` is only reused if it was"]
+    #[doc = " created with the same allocator as the parser."]
+    pub fn ts_parser_new_with_allocator(allocator: TSAllocator) -> *mut TSParser;
+}
 extern "C" {
     #[doc = " Delete the parser, freeing all of the memory that it used."]
     pub fn ts_parser_delete(parser: *mut TSParser);
@@ -317,6 +418,14 @@ extern "C" {
     #[doc = " SVG output. You can turn off this logging by passing a negative number."]
     pub fn ts_parser_print_dot_graphs(self_: *mut TSParser, file: ::std::os::raw::c_int);
 }
+extern "C" {
+    #[doc = " Get an estimate of the number of bytes of memory that the parser is using."]
+    #[doc = ""]
+    #[doc = " This includes the buffers that the parser keeps between parses, but not the"]
+    #[doc = " syntax trees that it has returned, or the memory used by a language's"]
+    #[doc = " external scanner."]
+    pub fn ts_parser_memory_usage(self_: *const TSParser) -> usize;
+}
 extern "C" {
     #[doc = " Create a shallow copy of the syntax tree. This is very fast."]
     #[doc = ""]
@@ -345,6 +454,22 @@ extern "C" {
     #[doc = " Get the language that was used to parse the syntax tree."]
     pub fn ts_tree_language(arg1: *const TSTree) -> *const TSLanguage;
 }
+extern "C" {
+    #[doc = " Get the array of included ranges that was used to parse the syntax tree,"]
+    #[doc = " updated to reflect any edits that have been made to the tree since then."]
+    #[doc = ""]
+    #[doc = " The returned pointer must be freed by the caller."]
+    pub fn ts_tree_included_ranges(arg1: *const TSTree, length: *mut u32) -> *mut TSRange;
+}
+extern "C" {
+    #[doc = " Get the number of bytes of memory that the syntax tree is using."]
+    #[doc = ""]
+    #[doc = " Syntax trees that are created by incremental parsing share the parts of the"]
+    #[doc = " old tree that were reused, and copies made with `ts_tree_copy` share all of"]
+    #[doc = " their nodes. Shared memory is included in the usage of every tree that"]
+    #[doc = " shares it."]
+    pub fn ts_tree_memory_usage(self_: *const TSTree) -> usize;
+}
 extern "C" {
     #[doc = " Edit the syntax tree to keep it in sync with source code that has been"]
     #[doc = " edited."]
@@ -380,6 +505,22 @@ extern "C" {
     #[doc = " Get the node's type as a numerical id."]
     pub fn ts_node_symbol(arg1: TSNode) -> TSSymbol;
 }
+extern "C" {
+    #[doc = " Get the node's type as a numerical id as it appears in the grammar, ignoring"]
+    #[doc = " aliases. This should be used in `ts_language_next_state` instead of"]
+    #[doc = " `ts_node_symbol`."]
+    pub fn ts_node_grammar_symbol(arg1: TSNode) -> TSSymbol;
+}
+extern "C" {
+    #[doc = " Get the parse state of this node. If the parse state is not known, this"]
+    #[doc = " returns `UINT16_MAX`."]
+    pub fn ts_node_parse_state(arg1: TSNode) -> TSStateId;
+}
+extern "C" {
+    #[doc = " Get the parse state after this node. If the parse state is not known, this"]
+    #[doc = " returns `UINT16_MAX`."]
+    pub fn ts_node_next_parse_state(arg1: TSNode) -> TSStateId;
+}
 extern "C" {
     #[doc = " Get the node's start byte."]
     pub fn ts_node_start_byte(arg1: TSNode) -> u32;
@@ -463,6 +604,25 @@ extern "C" {
     #[doc = " See also `ts_node_is_named`."]
     pub fn ts_node_named_child_count(arg1: TSNode) -> u32;
 }
+extern "C" {
+    #[doc = " Get the number of nodes within this node's subtree, including the node"]
+    #[doc = " itself. Only nodes that would be visited by a tree cursor are counted."]
+    pub fn ts_node_descendant_count(arg1: TSNode) -> u32;
+}
+extern "C" {
+    #[doc = " Get statistics about the node's subtree:"]
+    #[doc = " - `node_count` is the number of nodes within the subtree, including the node"]
+    #[doc = "   itself, as returned by `ts_node_descendant_count`."]
+    #[doc = " - `reused_node_count` is the number of those nodes that are shared with the"]
+    #[doc = "   given `old_tree`. If the node's tree was parsed incrementally from"]
+    #[doc = "   `old_tree`, these are the nodes that were reused. It is zero if `old_tree`"]
+    #[doc = "   is `NULL`."]
+    #[doc = " - `depth` is the number of nodes on the longest path from the node to a"]
+    #[doc = "   leaf, including both of them."]
+    #[doc = " - `memory_usage` is the number of bytes of memory that are used by the"]
+    #[doc = "   subtree."]
+    pub fn ts_node_stats(self_: TSNode, old_tree: *const TSTree) -> TSNodeStats;
+}
 extern "C" {
     #[doc = " Get the node's child with the given field name."]
     pub fn ts_node_child_by_field_name(
@@ -592,6 +752,45 @@ extern "C" {
     #[doc = " if there were no children."]
     pub fn ts_tree_cursor_goto_first_child(arg1: *mut TSTreeCursor) -> bool;
 }
+extern "C" {
+    #[doc = " Move the cursor to the last child of its current node."]
+    #[doc = ""]
+    #[doc = " This returns `true` if the cursor successfully moved, and returns `false`"]
+    #[doc = " if there were no children."]
+    #[doc = ""]
+    #[doc = " Note that this function may be slower than `ts_tree_cursor_goto_first_child`"]
+    #[doc = " because it needs to iterate through all the children to find the last one."]
+    pub fn ts_tree_cursor_goto_last_child(arg1: *mut TSTreeCursor) -> bool;
+}
+extern "C" {
+    #[doc = " Move the cursor to the previous sibling of its current node."]
+    #[doc = ""]
+    #[doc = " This returns `true` if the cursor successfully moved, and returns `false`"]
+    #[doc = " if there was no previous sibling node."]
+    #[doc = ""]
+    #[doc = " Note that this function may be slower than `ts_tree_cursor_goto_next_sibling`"]
+    #[doc = " because it needs to iterate through the earlier siblings to find the"]
+    #[doc = " previous one."]
+    pub fn ts_tree_cursor_goto_previous_sibling(arg1: *mut TSTreeCursor) -> bool;
+}
+extern "C" {
+    #[doc = " Move the cursor to the node that is the nth descendant of the node that"]
+    #[doc = " the cursor was constructed with, where zero represents the original node"]
+    #[doc = " itself. Descendants are numbered in pre-order."]
+    #[doc = ""]
+    #[doc = " If the index is out of range, the cursor is moved to the original node."]
+    pub fn ts_tree_cursor_goto_descendant(arg1: *mut TSTreeCursor, arg2: u32);
+}
+extern "C" {
+    #[doc = " Get the index of the cursor's current node out of all of the descendants"]
+    #[doc = " of the node that the cursor was constructed with, in pre-order."]
+    pub fn ts_tree_cursor_current_descendant_index(arg1: *const TSTreeCursor) -> u32;
+}
+extern "C" {
+    #[doc = " Get the depth of the cursor's current node relative to the node that the"]
+    #[doc = " cursor was constructed with."]
+    pub fn ts_tree_cursor_current_depth(arg1: *const TSTreeCursor) -> u32;
+}
 extern "C" {
     #[doc = " Move the cursor to the first child of its current node that extends beyond"]
     #[doc = " the given byte offset or point."]
@@ -625,6 +824,40 @@ extern "C" {
         error_type: *mut TSQueryError,
     ) -> *mut TSQuery;
 }
+extern "C" {
+    #[doc = " Parse a string containing one or more S-expression patterns in the same way"]
+    #[doc = " as `ts_query_new`, and get the tokens of the query's syntax, in order,"]
+    #[doc = " including its comments. Every character of the source code, except for"]
+    #[doc = " whitespace, is part of exactly one token:"]
+    #[doc = " - `TSQueryTokenTypeAlternationStart`, `TSQueryTokenTypeGroupStart`,"]
+    #[doc = "   `TSQueryTokenTypeNodeStart` and `TSQueryTokenTypePredicateStart` - The"]
+    #[doc = "   opening bracket or parenthesis of an alternation, a grouped sequence, a"]
+    #[doc = "   named node or a predicate. Each one is closed by a `TSQueryTokenTypeEnd`."]
+    #[doc = " - `TSQueryTokenTypeNodeName` - The name of a named node, or of the"]
+    #[doc = "   supertype before a `TSQueryTokenTypeSupertypeSeparator`."]
+    #[doc = " - `TSQueryTokenTypeString` - An anonymous node, or a string argument to a"]
+    #[doc = "   predicate, including its quotes."]
+    #[doc = " - `TSQueryTokenTypeFieldName` - The name of a field, which is followed by a"]
+    #[doc = "   `TSQueryTokenTypeColon`, or preceded by a `TSQueryTokenTypeNegation`."]
+    #[doc = " - `TSQueryTokenTypeCapture` - A capture name, including its `@`."]
+    #[doc = " - `TSQueryTokenTypePredicateName` - The name of a predicate, including its"]
+    #[doc = "   `#`, if any."]
+    #[doc = " - `TSQueryTokenTypeIdentifier` - A bare symbol argument to a predicate."]
+    #[doc = ""]
+    #[doc = " If the query is valid, this returns an array of tokens, writes its length"]
+    #[doc = " to the `token_count` parameter, and writes `TSQueryErrorNone` to the"]
+    #[doc = " `error_type` parameter. The array is allocated using `malloc` and the caller"]
+    #[doc = " is responsible for freeing it using `free`. If the query is invalid, this"]
+    #[doc = " returns `NULL`, and reports the error in the same way as `ts_query_new`."]
+    pub fn ts_query_tokenize(
+        language: *const TSLanguage,
+        source: *const ::std::os::raw::c_char,
+        source_len: u32,
+        token_count: *mut u32,
+        error_offset: *mut u32,
+        error_type: *mut TSQueryError,
+    ) -> *mut TSQueryToken;
+}
 extern "C" {
     #[doc = " Delete a query, freeing all of the memory that it used."]
     pub fn ts_query_delete(arg1: *mut TSQuery);
@@ -741,6 +974,12 @@ extern "C" {
     #[doc = "  `ts_query_cursor_exec` again."]
     pub fn ts_query_cursor_new() -> *mut TSQueryCursor;
 }
+extern "C" {
+    #[doc = " Create a new cursor for executing a given query, which uses the given"]
+    #[doc = " allocator for all of its memory. See `ts_parser_new_with_allocator` for"]
+    #[doc = " the requirements on the allocator."]
+    pub fn ts_query_cursor_new_with_allocator(allocator: TSAllocator) -> *mut TSQueryCursor;
+}
 extern "C" {
     #[doc = " Delete a query cursor, freeing all of the memory that it used."]
     pub fn ts_query_cursor_delete(arg1: *mut TSQueryCursor);
@@ -807,6 +1046,10 @@ extern "C" {
         arg2: TSSymbol,
     ) -> *const ::std::os::raw::c_char;
 }
+extern "C" {
+    #[doc = " Get the number of valid states in this language."]
+    pub fn ts_language_state_count(arg1: *const TSLanguage) -> u32;
+}
 extern "C" {
     #[doc = " Get the numerical id for the given node type string."]
     pub fn ts_language_symbol_for_name(
@@ -842,6 +1085,50 @@ extern "C" {
     #[doc = " See also `ts_node_is_named`. Hidden nodes are never returned from the API."]
     pub fn ts_language_symbol_type(arg1: *const TSLanguage, arg2: TSSymbol) -> TSSymbolType;
 }
+extern "C" {
+    #[doc = " Get the supertypes of this language, as an array of node type ids."]
+    #[doc = ""]
+    #[doc = " The length of the array is written to `*length`. Languages generated with"]
+    #[doc = " an ABI version earlier than 15 do not contain this information, so the"]
+    #[doc = " array is empty for them. `tree-sitter generate` produces ABI version 14"]
+    #[doc = " unless it is run with `--abi 15` or `--abi latest`."]
+    pub fn ts_language_supertypes(arg1: *const TSLanguage, length: *mut u32) -> *const TSSymbol;
+}
+extern "C" {
+    #[doc = " Get the subtypes of the given supertype, as an array of node type ids."]
+    #[doc = ""]
+    #[doc = " The length of the array is written to `*length`. If the given node type is"]
+    #[doc = " not a supertype, or if the language was generated with an ABI version"]
+    #[doc = " earlier than 15, the array is empty."]
+    pub fn ts_language_subtypes(
+        arg1: *const TSLanguage,
+        supertype: TSSymbol,
+        length: *mut u32,
+    ) -> *const TSSymbol;
+}
+extern "C" {
+    #[doc = " Get the number of descriptions of the children that a node of the given"]
+    #[doc = " type can have. There is one description for each field that the node type"]
+    #[doc = " can contain, and one for the named children that are not associated with"]
+    #[doc = " any field, if there can be any."]
+    #[doc = ""]
+    #[doc = " Languages generated with an ABI version earlier than 15 do not contain this"]
+    #[doc = " information, so this is always zero for them."]
+    pub fn ts_language_child_info_count(arg1: *const TSLanguage, arg2: TSSymbol) -> u32;
+}
+extern "C" {
+    #[doc = " Get one of the descriptions of the children that a node of the given type"]
+    #[doc = " can have. The description's field id is zero for the named children that"]
+    #[doc = " are not associated with any field."]
+    #[doc = ""]
+    #[doc = " The index must be less than the count returned by"]
+    #[doc = " `ts_language_child_info_count`."]
+    pub fn ts_language_child_info_at(
+        arg1: *const TSLanguage,
+        arg2: TSSymbol,
+        index: u32,
+    ) -> TSChildInfo;
+}
 extern "C" {
     #[doc = " Get the ABI version number for this language. This version number is used"]
     #[doc = " to ensure that languages were generated by a compatible version of"]
@@ -850,6 +1137,136 @@ extern "C" {
     #[doc = " See also `ts_parser_set_language`."]
     pub fn ts_language_version(arg1: *const TSLanguage) -> u32;
 }
+extern "C" {
+    #[doc = " Get the next parse state. Combine this with lookahead iterators to generate"]
+    #[doc = " completion suggestions or valid symbols in error nodes. Use"]
+    #[doc = " `ts_node_grammar_symbol` for valid symbols."]
+    pub fn ts_language_next_state(
+        arg1: *const TSLanguage,
+        arg2: TSStateId,
+        arg3: TSSymbol,
+    ) -> TSStateId;
+}
+extern "C" {
+    #[doc = " Create a new lookahead iterator for the given language and parse state."]
+    #[doc = ""]
+    #[doc = " This returns `NULL` if state is invalid for the language."]
+    #[doc = ""]
+    #[doc = " Repeatedly using `ts_lookahead_iterator_next` and"]
+    #[doc = " `ts_lookahead_iterator_current_symbol` will generate valid symbols in the"]
+    #[doc = " given parse state. Newly created lookahead iterators will contain the `ERROR`"]
+    #[doc = " symbol."]
+    #[doc = ""]
+    #[doc = " Lookahead iterators can be useful to generate suggestions and improve syntax"]
+    #[doc = " error diagnostics. To get symbols valid in an ERROR node, use the lookahead"]
+    #[doc = " iterator on its first leaf node state. For `MISSING` nodes, a lookahead"]
+    #[doc = " iterator created on the previous non-extra leaf node may be appropriate."]
+    pub fn ts_lookahead_iterator_new(
+        arg1: *const TSLanguage,
+        arg2: TSStateId,
+    ) -> *mut TSLookaheadIterator;
+}
+extern "C" {
+    #[doc = " Delete a lookahead iterator freeing all the memory used."]
+    pub fn ts_lookahead_iterator_delete(arg1: *mut TSLookaheadIterator);
+}
+extern "C" {
+    #[doc = " Reset the lookahead iterator to another state."]
+    #[doc = ""]
+    #[doc = " This returns `true` if the iterator was reset to the given state and `false`"]
+    #[doc = " otherwise."]
+    pub fn ts_lookahead_iterator_reset_state(
+        arg1: *mut TSLookaheadIterator,
+        arg2: TSStateId,
+    ) -> bool;
+}
+extern "C" {
+    #[doc = " Reset the lookahead iterator."]
+    #[doc = ""]
+    #[doc = " This returns `true` if the language was set successfully and `false`"]
+    #[doc = " otherwise."]
+    pub fn ts_lookahead_iterator_reset(
+        arg1: *mut TSLookaheadIterator,
+        arg2: *const TSLanguage,
+        arg3: TSStateId,
+    ) -> bool;
+}
+extern "C" {
+    #[doc = " Get the current language of the lookahead iterator."]
+    pub fn ts_lookahead_iterator_language(
+        arg1: *const TSLookaheadIterator,
+    ) -> *const TSLanguage;
+}
+extern "C" {
+    #[doc = " Advance the lookahead iterator to the next symbol."]
+    #[doc = ""]
+    #[doc = " This returns `true` if there is a new symbol and `false` otherwise."]
+    pub fn ts_lookahead_iterator_next(arg1: *mut TSLookaheadIterator) -> bool;
+}
+extern "C" {
+    #[doc = " Get the current symbol of the lookahead iterator."]
+    pub fn ts_lookahead_iterator_current_symbol(arg1: *const TSLookaheadIterator) -> TSSymbol;
+}
+extern "C" {
+    #[doc = " Get the current symbol type of the lookahead iterator as a null terminated"]
+    #[doc = " string."]
+    pub fn ts_lookahead_iterator_current_symbol_name(
+        arg1: *const TSLookaheadIterator,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    #[doc = " Assign a WebAssembly store to the parser. A parser needs a WebAssembly store"]
+    #[doc = " in order to use languages that were loaded from WebAssembly modules."]
+    #[doc = ""]
+    #[doc = " The parser takes ownership of the store, and deletes the store that it"]
+    #[doc = " previously owned, if any. If the parser's current language is a WebAssembly"]
+    #[doc = " language, then the parser's language is unset."]
+    #[doc = ""]
+    #[doc = " WebAssembly stores are implemented by the Rust bindings, when they are"]
+    #[doc = " compiled with the `wasm` feature."]
+    pub fn ts_parser_set_wasm_store(self_: *mut TSParser, store: *mut TSWasmStore);
+}
+extern "C" {
+    #[doc = " Remove the parser's WebAssembly store and return it, transferring ownership"]
+    #[doc = " of the store to the caller. If the parser's current language is a"]
+    #[doc = " WebAssembly language, then the parser's language is unset."]
+    pub fn ts_parser_take_wasm_store(self_: *mut TSParser) -> *mut TSWasmStore;
+}
+extern "C" {
+    #[doc = " Check whether the given language was loaded from a WebAssembly module."]
+    pub fn ts_language_is_wasm(self_: *const TSLanguage) -> bool;
+}
+extern "C" {
+    #[doc = " Create a language by copying the parse tables of the `TSLanguage` struct at"]
+    #[doc = " the given address in the linear memory of a 32-bit WebAssembly module."]
+    #[doc = ""]
+    #[doc = " The `module` pointer is stored in the language, so that the language can be"]
+    #[doc = " instantiated in other WebAssembly stores. It can be retrieved using"]
+    #[doc = " `ts_wasm_language_module`."]
+    #[doc = ""]
+    #[doc = " This returns `NULL` if the struct or any of its tables do not fit within"]
+    #[doc = " the given memory, or if their contents are invalid. The resulting language"]
+    #[doc = " must be freed using `ts_language_delete`. Freeing it does not free the"]
+    #[doc = " `module` pointer."]
+    pub fn ts_wasm_language_new(
+        memory: *const u8,
+        memory_length: u32,
+        address: u32,
+        module: *mut ::std::os::raw::c_void,
+    ) -> *const TSLanguage;
+}
+extern "C" {
+    #[doc = " Get the module pointer that was passed to `ts_wasm_language_new` when the"]
+    #[doc = " given WebAssembly language was created, or `NULL` for a native language."]
+    pub fn ts_wasm_language_module(self_: *const TSLanguage) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    #[doc = " Free a language that was created using `ts_wasm_language_new`. Native"]
+    #[doc = " languages are statically allocated, so this does nothing for them."]
+    #[doc = ""]
+    #[doc = " The language must not be used afterward, by parsers, trees, or queries."]
+    pub fn ts_language_delete(self_: *const TSLanguage);
+}
 extern "C" {
     #[doc = " Set the allocation functions used by the library."]
     #[doc = ""]
@@ -882,5 +1299,5 @@ extern "C" {
     );
 }
 
-pub const TREE_SITTER_LANGUAGE_VERSION: usize = 14;
+pub const TREE_SITTER_LANGUAGE_VERSION: usize = 15;
 pub const TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION: usize = 13;
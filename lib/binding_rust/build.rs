@@ -24,7 +24,12 @@ fn main() {
         println!("cargo:rerun-if-changed={}", path.to_str().unwrap());
     }
 
-    cc::Build::new()
+    let mut config = cc::Build::new();
+    if env::var("CARGO_FEATURE_WASM").is_ok() {
+        config.define("TREE_SITTER_FEATURE_WASM", None);
+    }
+
+    config
         .flag_if_supported("-std=c99")
         .flag_if_supported("-Wno-unused-parameter")
         .include(src_path)
@@ -0,0 +1,264 @@
+use super::{Language, Node, Range};
+use std::fmt::Write;
+
+/// The maximum number of expected symbols that are listed in a [SyntaxError]'s message.
+const MAX_EXPECTED_IN_MESSAGE: usize = 6;
+
+/// The maximum number of characters of unexpected text that are quoted in a
+/// [SyntaxError]'s message.
+const MAX_UNEXPECTED_LENGTH: usize = 32;
+
+/// The type of a [SyntaxError].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxErrorKind {
+    /// Text that could not be incorporated into the tree, represented by an `ERROR` node.
+    Unexpected,
+    /// A token that the parser inserted in order to recover, represented by a `MISSING`
+    /// node.
+    Missing,
+}
+
+/// A syntax error within a tree, as produced by [Node::syntax_errors].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError<'tree> {
+    pub kind: SyntaxErrorKind,
+    /// The `ERROR` or `MISSING` node that represents the error.
+    pub node: Node<'tree>,
+    /// The range of the token that caused the error. For a missing node, or for an
+    /// error at the end of the input, this is an empty range where the token was
+    /// expected.
+    pub range: Range,
+    /// The text of the token that caused the error, or `None` if the error is a
+    /// missing node, or is at the end of the input.
+    pub unexpected: Option<String>,
+    /// The kinds of the nodes that would have been valid instead of the unexpected
+    /// token, or the kind of the missing node.
+    pub expected: Vec<&'static str>,
+    /// A description of the error, such as `unexpected "b", expected "," or ")"`.
+    pub message: String,
+}
+
+pub(crate) fn syntax_errors<'tree>(node: Node<'tree>, source: &[u8]) -> Vec<SyntaxError<'tree>> {
+    let mut result = Vec::new();
+    let mut cursor = node.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_error() {
+            result.push(unexpected_error(node, source));
+        } else if node.is_missing() {
+            result.push(missing_error(node));
+        } else if node.has_error() && cursor.goto_first_child() {
+            continue;
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return result;
+            }
+        }
+    }
+}
+
+fn missing_error(node: Node) -> SyntaxError {
+    let start = node.start_position();
+    let kind = node.kind();
+    SyntaxError {
+        kind: SyntaxErrorKind::Missing,
+        node,
+        range: Range {
+            start_byte: node.start_byte(),
+            end_byte: node.start_byte(),
+            start_point: start,
+            end_point: start,
+        },
+        unexpected: None,
+        expected: vec![kind],
+        message: format!("missing {}", symbol_name(kind, node.is_named())),
+    }
+}
+
+/// Describe an `ERROR` node by finding the first token within it that was not valid in
+/// the parse state where it was encountered. If every token within the node was valid,
+/// then the error was caused by the token that follows the node.
+fn unexpected_error<'tree>(error: Node<'tree>, source: &[u8]) -> SyntaxError<'tree> {
+    let language = error.language();
+    let mut state = previous_leaf(error).map_or(0, |leaf| leaf.next_parse_state());
+    let mut unexpected_leaf = None;
+    let mut cursor = error.walk();
+    'leaves: loop {
+        if !cursor.goto_first_child() {
+            let leaf = cursor.node();
+            let leaf_state = leaf.parse_state();
+            if !is_known_state(leaf_state) {
+                unexpected_leaf = Some(leaf);
+                break;
+            }
+            if !is_valid_symbol(language, leaf_state, leaf.grammar_id()) {
+                state = leaf_state;
+                unexpected_leaf = Some(leaf);
+                break;
+            }
+            if !leaf.is_extra() {
+                state = leaf.next_parse_state();
+            }
+
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    break 'leaves;
+                }
+            }
+        }
+    }
+    let unexpected_leaf = unexpected_leaf.or_else(|| next_leaf(error));
+
+    let mut expected = Vec::new();
+    let mut expected_descriptions = Vec::new();
+    if let Some(lookahead) = Some(state)
+        .filter(|state| is_known_state(*state))
+        .and_then(|state| language.lookahead_iterator(state))
+    {
+        for symbol in lookahead {
+            // Extras, such as comments, are valid in every state, and shifting them
+            // does not change the state.
+            if !language.node_kind_is_visible(symbol) || language.next_state(state, symbol) == state
+            {
+                continue;
+            }
+            let kind = language.node_kind_for_id(symbol).unwrap();
+            let is_named = language.node_kind_is_named(symbol);
+            let description = symbol_name(kind, is_named);
+            if !expected_descriptions.contains(&description) {
+                expected.push(kind);
+                expected_descriptions.push(description);
+            }
+        }
+    }
+
+    let (range, unexpected) = match unexpected_leaf {
+        Some(leaf) => (
+            leaf.range(),
+            Some(String::from_utf8_lossy(&source[leaf.byte_range()]).into_owned()),
+        ),
+        None => {
+            let end = error.end_position();
+            let range = Range {
+                start_byte: error.end_byte(),
+                end_byte: error.end_byte(),
+                start_point: end,
+                end_point: end,
+            };
+            (range, None)
+        }
+    };
+
+    let mut message = match &unexpected {
+        Some(text) => format!("unexpected {:?}", truncate(text)),
+        None => "unexpected end of input".to_string(),
+    };
+    match expected_descriptions.as_slice() {
+        [] => {}
+        [symbol] => write!(message, ", expected {}", symbol).unwrap(),
+        [first, second] => write!(message, ", expected {} or {}", first, second).unwrap(),
+        symbols => {
+            let count = symbols.len().min(MAX_EXPECTED_IN_MESSAGE);
+            write!(message, ", expected one of {}", symbols[..count].join(", ")).unwrap();
+            if symbols.len() > count {
+                write!(message, " and {} more", symbols.len() - count).unwrap();
+            }
+        }
+    }
+
+    SyntaxError {
+        kind: SyntaxErrorKind::Unexpected,
+        node: error,
+        range,
+        unexpected,
+        expected,
+        message,
+    }
+}
+
+/// The error state, and the state of nodes that were not produced by the parse table,
+/// do not describe which symbols were expected.
+fn is_known_state(state: u16) -> bool {
+    state != 0 && state != u16::MAX
+}
+
+fn is_valid_symbol(language: Language, state: u16, symbol: u16) -> bool {
+    language
+        .lookahead_iterator(state)
+        .map_or(false, |mut lookahead| {
+            lookahead.any(|valid_symbol| valid_symbol == symbol)
+        })
+}
+
+fn symbol_name(kind: &str, is_named: bool) -> String {
+    if is_named {
+        kind.to_string()
+    } else {
+        format!("{:?}", kind)
+    }
+}
+
+fn truncate(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("");
+    if line.len() < text.len() || line.chars().count() > MAX_UNEXPECTED_LENGTH {
+        let mut result = line.chars().take(MAX_UNEXPECTED_LENGTH).collect::<String>();
+        result.push_str("...");
+        result
+    } else {
+        line.to_string()
+    }
+}
+
+/// Find the last leaf of the given node that is not part of an extra node.
+fn last_leaf(node: Node) -> Option<Node> {
+    if node.is_extra() {
+        None
+    } else if node.child_count() == 0 {
+        Some(node)
+    } else {
+        (0..node.child_count())
+            .rev()
+            .find_map(|i| last_leaf(node.child(i).unwrap()))
+    }
+}
+
+/// Find the first leaf of the given node that is not part of an extra node.
+fn first_leaf(node: Node) -> Option<Node> {
+    if node.is_extra() {
+        None
+    } else if node.child_count() == 0 {
+        Some(node)
+    } else {
+        (0..node.child_count()).find_map(|i| first_leaf(node.child(i).unwrap()))
+    }
+}
+
+fn previous_leaf(node: Node) -> Option<Node> {
+    let mut node = node;
+    loop {
+        let mut sibling = node.prev_sibling();
+        while let Some(node) = sibling {
+            if let Some(leaf) = last_leaf(node) {
+                return Some(leaf);
+            }
+            sibling = node.prev_sibling();
+        }
+        node = node.parent()?;
+    }
+}
+
+fn next_leaf(node: Node) -> Option<Node> {
+    let mut node = node;
+    loop {
+        let mut sibling = node.next_sibling();
+        while let Some(node) = sibling {
+            if let Some(leaf) = first_leaf(node) {
+                return Some(leaf);
+            }
+            sibling = node.next_sibling();
+        }
+        node = node.parent()?;
+    }
+}
@@ -0,0 +1,384 @@
+use super::{Node, Tree};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// The type of change described by a [DiffEdit].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiffEditKind {
+    /// A node that only exists in the new tree.
+    Insert,
+    /// A node that only exists in the old tree.
+    Delete,
+    /// A leaf node whose text changed.
+    Update,
+    /// A node that was moved to a different parent, or reordered within its parent.
+    Move,
+}
+
+/// A single change within a structural diff of two syntax trees, as produced by
+/// [Tree::diff].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiffEdit<'old, 'new> {
+    pub kind: DiffEditKind,
+    /// The node in the old tree, or `None` if the node was inserted.
+    pub old_node: Option<Node<'old>>,
+    /// The node in the new tree, or `None` if the node was deleted.
+    pub new_node: Option<Node<'new>>,
+    /// The field name that is associated with the node in the new tree, or in the old
+    /// tree if the node was deleted.
+    pub field_name: Option<&'static str>,
+}
+
+/// A syntax tree flattened into a list of nodes in pre-order.
+struct FlatTree<'tree> {
+    nodes: Vec<Node<'tree>>,
+    parents: Vec<Option<usize>>,
+    field_names: Vec<Option<&'static str>>,
+    children: Vec<Vec<usize>>,
+    ends: Vec<usize>,
+    hashes: Vec<u64>,
+}
+
+/// A mapping between the nodes of two flattened trees.
+struct Matching {
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+}
+
+/// Compute the edits that transform one syntax tree into another.
+///
+/// Nodes are matched in three phases:
+/// 1. Subtrees that are identical and that appear exactly once in each tree.
+/// 2. Parent nodes of the same kind whose children have been matched to each other.
+/// 3. Children of matched nodes, first by identical content, and then by kind.
+pub(crate) fn diff_trees<'old, 'new>(
+    old_tree: &'old Tree,
+    old_source: &[u8],
+    new_tree: &'new Tree,
+    new_source: &[u8],
+) -> Vec<DiffEdit<'old, 'new>> {
+    let old = FlatTree::new(old_tree, old_source);
+    let new = FlatTree::new(new_tree, new_source);
+    let mut matching = Matching {
+        old_to_new: vec![None; old.len()],
+        new_to_old: vec![None; new.len()],
+    };
+    matching.match_unique_subtrees(&old, &new);
+    matching.match_parents(&old, &new);
+    matching.match_children(&old, &new);
+    matching.edit_script(&old, &new)
+}
+
+impl<'tree> FlatTree<'tree> {
+    fn new(tree: &'tree Tree, source: &[u8]) -> Self {
+        let mut result = FlatTree {
+            nodes: Vec::new(),
+            parents: Vec::new(),
+            field_names: Vec::new(),
+            children: Vec::new(),
+            ends: Vec::new(),
+            hashes: Vec::new(),
+        };
+
+        let mut ancestors = Vec::<usize>::new();
+        let mut traversal = tree.preorder();
+        while let Some(node) = traversal.next() {
+            let index = result.nodes.len();
+            ancestors.truncate(traversal.depth());
+            let parent = ancestors.last().copied();
+            if let Some(parent) = parent {
+                result.children[parent].push(index);
+            }
+            result.nodes.push(node);
+            result.parents.push(parent);
+            result.field_names.push(traversal.field_name());
+            result.children.push(Vec::new());
+            ancestors.push(index);
+        }
+
+        // Children always appear after their parents, so compute each node's hash and
+        // extent in reverse order.
+        result.ends = (1..=result.len()).collect();
+        result.hashes = vec![0; result.len()];
+        for i in (0..result.len()).rev() {
+            let node = result.nodes[i];
+            let mut hasher = DefaultHasher::new();
+            node.kind().hash(&mut hasher);
+            node.is_named().hash(&mut hasher);
+            if result.children[i].is_empty() {
+                source.get(node.byte_range()).hash(&mut hasher);
+            }
+            for &child in &result.children[i] {
+                result.field_names[child].hash(&mut hasher);
+                result.hashes[child].hash(&mut hasher);
+                result.ends[i] = result.ends[child];
+            }
+            result.hashes[i] = hasher.finish();
+        }
+        result
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn size(&self, i: usize) -> usize {
+        self.ends[i] - i
+    }
+
+    fn has_same_kind(&self, i: usize, other: &FlatTree, j: usize) -> bool {
+        self.nodes[i].kind_id() == other.nodes[j].kind_id()
+            && self.nodes[i].kind() == other.nodes[j].kind()
+    }
+
+    fn nodes_by_hash(&self) -> HashMap<u64, Vec<usize>> {
+        let mut result = HashMap::<u64, Vec<usize>>::new();
+        for (i, hash) in self.hashes.iter().enumerate() {
+            result.entry(*hash).or_default().push(i);
+        }
+        result
+    }
+}
+
+impl Matching {
+    fn link(&mut self, old_index: usize, new_index: usize) {
+        self.old_to_new[old_index] = Some(new_index);
+        self.new_to_old[new_index] = Some(old_index);
+    }
+
+    /// Match two identical subtrees, if none of their nodes have been matched yet.
+    fn link_subtrees(
+        &mut self,
+        old: &FlatTree,
+        new: &FlatTree,
+        old_index: usize,
+        new_index: usize,
+    ) -> bool {
+        let size = old.size(old_index);
+        if size != new.size(new_index)
+            || self.old_to_new[old_index..old.ends[old_index]]
+                .iter()
+                .any(Option::is_some)
+            || self.new_to_old[new_index..new.ends[new_index]]
+                .iter()
+                .any(Option::is_some)
+        {
+            return false;
+        }
+        for offset in 0..size {
+            self.link(old_index + offset, new_index + offset);
+        }
+        true
+    }
+
+    fn match_unique_subtrees(&mut self, old: &FlatTree, new: &FlatTree) {
+        let old_by_hash = old.nodes_by_hash();
+        let new_by_hash = new.nodes_by_hash();
+        let mut new_index = 0;
+        while new_index < new.len() {
+            let hash = new.hashes[new_index];
+            if let (Some([old_index]), Some([_])) = (
+                old_by_hash.get(&hash).map(Vec::as_slice),
+                new_by_hash.get(&hash).map(Vec::as_slice),
+            ) {
+                if self.link_subtrees(old, new, *old_index, new_index) {
+                    new_index = new.ends[new_index];
+                    continue;
+                }
+            }
+            new_index += 1;
+        }
+    }
+
+    fn match_parents(&mut self, old: &FlatTree, new: &FlatTree) {
+        if self.old_to_new[0].is_none()
+            && self.new_to_old[0].is_none()
+            && old.has_same_kind(0, new, 0)
+        {
+            self.link(0, 0);
+        }
+
+        // Visit children before their parents, so that matches propagate upward.
+        for new_index in (0..new.len()).rev() {
+            if self.new_to_old[new_index].is_some() {
+                continue;
+            }
+            let mut votes = Vec::<(usize, usize)>::new();
+            for &new_child in &new.children[new_index] {
+                let old_parent = match self.new_to_old[new_child].and_then(|i| old.parents[i]) {
+                    Some(old_parent) => old_parent,
+                    None => continue,
+                };
+                if self.old_to_new[old_parent].is_some()
+                    || !old.has_same_kind(old_parent, new, new_index)
+                {
+                    continue;
+                }
+                match votes.iter_mut().find(|(i, _)| *i == old_parent) {
+                    Some((_, count)) => *count += 1,
+                    None => votes.push((old_parent, 1)),
+                }
+            }
+            let mut best = None;
+            for (old_parent, count) in votes {
+                if best.map_or(true, |(_, best_count)| count > best_count) {
+                    best = Some((old_parent, count));
+                }
+            }
+            if let Some((old_parent, _)) = best {
+                self.link(old_parent, new_index);
+            }
+        }
+    }
+
+    fn match_children(&mut self, old: &FlatTree, new: &FlatTree) {
+        for new_index in 0..new.len() {
+            let old_index = match self.new_to_old[new_index] {
+                Some(old_index) => old_index,
+                None => continue,
+            };
+
+            // First match children with identical content, and then children of the
+            // same kind, preserving their order.
+            let old_children = &old.children[old_index];
+            for identical in [true, false] {
+                let mut start = 0;
+                for &new_child in &new.children[new_index] {
+                    if self.new_to_old[new_child].is_some() {
+                        continue;
+                    }
+                    for (offset, &old_child) in old_children[start..].iter().enumerate() {
+                        if self.old_to_new[old_child].is_some() {
+                            continue;
+                        }
+                        let is_match = if identical {
+                            old.hashes[old_child] == new.hashes[new_child]
+                                && self.link_subtrees(old, new, old_child, new_child)
+                        } else if old.has_same_kind(old_child, new, new_child) {
+                            self.link(old_child, new_child);
+                            true
+                        } else {
+                            false
+                        };
+                        if is_match {
+                            start += offset + 1;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn edit_script<'old, 'new>(
+        &self,
+        old: &FlatTree<'old>,
+        new: &FlatTree<'new>,
+    ) -> Vec<DiffEdit<'old, 'new>> {
+        let mut result = Vec::new();
+
+        for old_index in 0..old.len() {
+            if self.old_to_new[old_index].is_none()
+                && old.parents[old_index].map_or(true, |i| self.old_to_new[i].is_some())
+            {
+                result.push(DiffEdit {
+                    kind: DiffEditKind::Delete,
+                    old_node: Some(old.nodes[old_index]),
+                    new_node: None,
+                    field_name: old.field_names[old_index],
+                });
+            }
+        }
+
+        let mut reordered = vec![false; new.len()];
+        for new_index in 0..new.len() {
+            let old_index = match self.new_to_old[new_index] {
+                Some(old_index) => old_index,
+                None => {
+                    if new.parents[new_index].map_or(true, |i| self.new_to_old[i].is_some()) {
+                        result.push(DiffEdit {
+                            kind: DiffEditKind::Insert,
+                            old_node: None,
+                            new_node: Some(new.nodes[new_index]),
+                            field_name: new.field_names[new_index],
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            let edit = |kind| DiffEdit {
+                kind,
+                old_node: Some(old.nodes[old_index]),
+                new_node: Some(new.nodes[new_index]),
+                field_name: new.field_names[new_index],
+            };
+            let parent_is_matched = match new.parents[new_index] {
+                Some(new_parent) => self.new_to_old[new_parent] == old.parents[old_index],
+                None => old.parents[old_index].is_none(),
+            };
+            if !parent_is_matched || reordered[new_index] {
+                result.push(edit(DiffEditKind::Move));
+            }
+            if new.children[new_index].is_empty()
+                && old.children[old_index].is_empty()
+                && new.hashes[new_index] != old.hashes[old_index]
+            {
+                result.push(edit(DiffEditKind::Update));
+            }
+
+            // Children that stayed within this node, but whose order relative to the
+            // other children changed, are treated as moves.
+            let stayed = new.children[new_index]
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    self.new_to_old[i].map_or(false, |j| old.parents[j] == Some(old_index))
+                })
+                .collect::<Vec<_>>();
+            let old_positions = stayed
+                .iter()
+                .map(|&i| self.new_to_old[i].unwrap())
+                .collect::<Vec<_>>();
+            let mut in_order = vec![false; stayed.len()];
+            for i in longest_increasing_subsequence(&old_positions) {
+                in_order[i] = true;
+            }
+            for (new_child, in_order) in stayed.into_iter().zip(in_order) {
+                reordered[new_child] = !in_order;
+            }
+        }
+
+        result
+    }
+}
+
+/// Get the indices of a longest strictly increasing subsequence of the given values.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // For each length, the index of the smallest value that ends a subsequence of
+    // that length.
+    let mut tails = Vec::<usize>::new();
+    let mut predecessors = vec![None; values.len()];
+    for (i, value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&j| values[j] < *value);
+        if length > 0 {
+            predecessors[i] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(i);
+        } else {
+            tails[length] = i;
+        }
+    }
+
+    let mut result = Vec::with_capacity(tails.len());
+    let mut i = tails.last().copied();
+    while let Some(index) = i {
+        result.push(index);
+        i = predecessors[index];
+    }
+    result.reverse();
+    result
+}
@@ -0,0 +1,335 @@
+use super::{InputEdit, Node, Parser, Point, Range, TextProvider, Tree};
+use std::{borrow::Cow, iter, ops, option, str};
+
+/// A text document that keeps its source code and its syntax tree in sync.
+///
+/// A `Document` owns its text, stored in a gap buffer so that consecutive edits near
+/// the same location are cheap. Each edit is applied to both the text and the current
+/// syntax tree, using an [InputEdit] that is computed automatically. Positions can be
+/// expressed either as byte offsets, as [Point]s whose columns are measured in bytes,
+/// or as points whose columns are measured in UTF-16 code units, as used by editors
+/// that speak the Language Server Protocol.
+#[derive(Clone)]
+pub struct Document {
+    buffer: Vec<u8>,
+    gap_start: usize,
+    gap_end: usize,
+    line_starts: Vec<usize>,
+    tree: Option<Tree>,
+}
+
+impl Document {
+    /// Create a new document with the given text. The document is not parsed until
+    /// [Document::parse] is called.
+    pub fn new(text: impl Into<Vec<u8>>) -> Self {
+        let buffer = text.into();
+        let mut line_starts = vec![0];
+        line_starts.extend(line_breaks(&buffer, 0));
+        Document {
+            gap_start: buffer.len(),
+            gap_end: buffer.len(),
+            buffer,
+            line_starts,
+            tree: None,
+        }
+    }
+
+    /// Get the length of the document's text in bytes.
+    pub fn len(&self) -> usize {
+        self.buffer.len() - (self.gap_end - self.gap_start)
+    }
+
+    /// Check if the document's text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of lines in the document.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Get the syntax tree from the most recent call to [Document::parse], updated to
+    /// reflect any edits that have been made since then.
+    pub fn tree(&self) -> Option<&Tree> {
+        self.tree.as_ref()
+    }
+
+    /// Get the document's text as a single contiguous buffer.
+    pub fn text(&self) -> Cow<'_, [u8]> {
+        self.text_for_byte_range(0..self.len())
+    }
+
+    /// Get the text within the given byte range.
+    ///
+    /// The text is only copied if the range spans the position of the most recent edit.
+    pub fn text_for_byte_range(&self, range: ops::Range<usize>) -> Cow<'_, [u8]> {
+        let (before, after) = self.slices();
+        if range.end <= before.len() {
+            Cow::Borrowed(&before[range])
+        } else if range.start >= before.len() {
+            Cow::Borrowed(&after[range.start - before.len()..range.end - before.len()])
+        } else {
+            let mut result = before[range.start..].to_vec();
+            result.extend_from_slice(&after[..range.end - before.len()]);
+            Cow::Owned(result)
+        }
+    }
+
+    /// Get the row and column of the given byte offset, where the column is measured in
+    /// bytes.
+    pub fn point_for_byte(&self, byte: usize) -> Point {
+        let byte = byte.min(self.len());
+        let row = self.line_starts.partition_point(|&start| start <= byte) - 1;
+        Point::new(row, byte - self.line_starts[row])
+    }
+
+    /// Get the byte offset of the given row and column, where the column is measured in
+    /// bytes.
+    ///
+    /// Positions beyond the end of a line are clamped to the end of that line.
+    pub fn byte_for_point(&self, point: Point) -> usize {
+        let line = self.line_byte_range(point.row);
+        (line.start + point.column).min(line.end)
+    }
+
+    /// Get the row and column of the given byte offset, where the column is measured in
+    /// UTF-16 code units.
+    pub fn utf16_point_for_byte(&self, byte: usize) -> Point {
+        let point = self.point_for_byte(byte);
+        let line = self.line_byte_range(point.row);
+        let text = self.text_for_byte_range(line.start..line.start + point.column);
+        let column = utf8_chars(&text).map(|(_, utf16_len)| utf16_len).sum();
+        Point::new(point.row, column)
+    }
+
+    /// Get the byte offset of the given row and column, where the column is measured in
+    /// UTF-16 code units.
+    ///
+    /// Positions beyond the end of a line are clamped to the end of that line, and
+    /// positions within a character are rounded up to the end of that character.
+    pub fn byte_for_utf16_point(&self, point: Point) -> usize {
+        let line = self.line_byte_range(point.row);
+        let text = self.text_for_byte_range(line.clone());
+        let mut byte = line.start;
+        let mut column = 0;
+        for (utf8_len, utf16_len) in utf8_chars(&text) {
+            if column >= point.column {
+                break;
+            }
+            byte += utf8_len;
+            column += utf16_len;
+        }
+        byte
+    }
+
+    /// Replace the text within the given byte range, updating the syntax tree to
+    /// reflect the change.
+    ///
+    /// Returns the [InputEdit] that was applied to the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn edit(&mut self, range: ops::Range<usize>, text: impl AsRef<[u8]>) -> InputEdit {
+        let text = text.as_ref();
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "edit range {:?} is out of bounds for a document of length {}",
+            range,
+            self.len()
+        );
+
+        let start_position = self.point_for_byte(range.start);
+        let old_end_position = self.point_for_byte(range.end);
+        self.splice(range.clone(), text);
+        let new_end_byte = range.start + text.len();
+        let edit = InputEdit {
+            start_byte: range.start,
+            old_end_byte: range.end,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position: self.point_for_byte(new_end_byte),
+        };
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&edit);
+        }
+        edit
+    }
+
+    /// Replace the text between the given positions, whose columns are measured in
+    /// bytes.
+    ///
+    /// See [Document::edit].
+    pub fn edit_points(&mut self, start: Point, end: Point, text: impl AsRef<[u8]>) -> InputEdit {
+        let range = self.byte_for_point(start)..self.byte_for_point(end);
+        self.edit(range, text)
+    }
+
+    /// Replace the text between the given positions, whose columns are measured in
+    /// UTF-16 code units.
+    ///
+    /// See [Document::edit].
+    pub fn edit_utf16_points(
+        &mut self,
+        start: Point,
+        end: Point,
+        text: impl AsRef<[u8]>,
+    ) -> InputEdit {
+        let range = self.byte_for_utf16_point(start)..self.byte_for_utf16_point(end);
+        self.edit(range, text)
+    }
+
+    /// Parse the document's current text, reusing the previous syntax tree if there is
+    /// one.
+    ///
+    /// Returns the ranges whose syntactic structure changed since the previous parse, as
+    /// computed by [Tree::changed_ranges]. If the document had not been parsed before,
+    /// a single range spanning the entire document is returned. Returns `None` if the
+    /// parser did not produce a tree, in which case the previous tree is kept.
+    pub fn parse(&mut self, parser: &mut Parser) -> Option<Vec<Range>> {
+        let new_tree = self.parse_text(parser, self.tree.as_ref())?;
+        let changed_ranges = match &self.tree {
+            Some(old_tree) => old_tree.changed_ranges(&new_tree).collect(),
+            None => vec![new_tree.root_node().range()],
+        };
+        self.tree = Some(new_tree);
+        Some(changed_ranges)
+    }
+
+    /// Parse the document's current text without storing the resulting tree.
+    pub(crate) fn parse_text(&self, parser: &mut Parser, old_tree: Option<&Tree>) -> Option<Tree> {
+        let (before, after) = self.slices();
+        parser.parse_with(
+            &mut |i, _| {
+                if i < before.len() {
+                    &before[i..]
+                } else if i - before.len() < after.len() {
+                    &after[i - before.len()..]
+                } else {
+                    &[]
+                }
+            },
+            old_tree,
+        )
+    }
+
+    fn slices(&self) -> (&[u8], &[u8]) {
+        (&self.buffer[..self.gap_start], &self.buffer[self.gap_end..])
+    }
+
+    fn line_byte_range(&self, row: usize) -> ops::Range<usize> {
+        match self.line_starts.get(row) {
+            Some(&start) => {
+                let end = self
+                    .line_starts
+                    .get(row + 1)
+                    .map_or(self.len(), |&next_start| next_start - 1);
+                start..end
+            }
+            None => self.len()..self.len(),
+        }
+    }
+
+    fn splice(&mut self, range: ops::Range<usize>, text: &[u8]) {
+        // Move the gap to the start of the range, and then widen it to cover the
+        // removed text.
+        if range.start < self.gap_start {
+            let len = self.gap_start - range.start;
+            self.buffer
+                .copy_within(range.start..self.gap_start, self.gap_end - len);
+            self.gap_start -= len;
+            self.gap_end -= len;
+        } else if range.start > self.gap_start {
+            let len = range.start - self.gap_start;
+            self.buffer
+                .copy_within(self.gap_end..self.gap_end + len, self.gap_start);
+            self.gap_start += len;
+            self.gap_end += len;
+        }
+        self.gap_end += range.end - range.start;
+
+        // Grow the gap if the inserted text doesn't fit.
+        let gap_len = self.gap_end - self.gap_start;
+        if gap_len < text.len() {
+            let growth = (text.len() - gap_len).max(self.buffer.len() / 2);
+            let after_len = self.buffer.len() - self.gap_end;
+            self.buffer.resize(self.buffer.len() + growth, 0);
+            let new_gap_end = self.buffer.len() - after_len;
+            self.buffer
+                .copy_within(self.gap_end..self.gap_end + after_len, new_gap_end);
+            self.gap_end = new_gap_end;
+        }
+        self.buffer[self.gap_start..self.gap_start + text.len()].copy_from_slice(text);
+        self.gap_start += text.len();
+
+        // Update the offsets of the line starts that follow the edit.
+        let first_removed = self
+            .line_starts
+            .partition_point(|&start| start <= range.start);
+        let first_kept = self
+            .line_starts
+            .partition_point(|&start| start <= range.end);
+        let inserted = line_breaks(text, range.start).collect::<Vec<_>>();
+        let inserted_len = inserted.len();
+        self.line_starts.splice(first_removed..first_kept, inserted);
+        for start in &mut self.line_starts[first_removed + inserted_len..] {
+            *start = *start - (range.end - range.start) + text.len();
+        }
+    }
+}
+
+impl<'a> TextProvider<'a> for &'a Document {
+    type I = iter::Chain<option::IntoIter<&'a [u8]>, option::IntoIter<&'a [u8]>>;
+
+    fn text(&mut self, node: Node) -> Self::I {
+        let (before, after) = self.slices();
+        let range = node.byte_range();
+        let start = range.start.min(self.len());
+        let end = range.end.min(self.len());
+        let first = if start < before.len() {
+            Some(&before[start..end.min(before.len())])
+        } else {
+            None
+        };
+        let second = if end > before.len() {
+            Some(&after[start.max(before.len()) - before.len()..end - before.len()])
+        } else {
+            None
+        };
+        first.into_iter().chain(second)
+    }
+}
+
+/// Get the offsets of the lines that start within the given text, which begins at the
+/// given offset.
+fn line_breaks(text: &[u8], offset: usize) -> impl Iterator<Item = usize> + '_ {
+    text.iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+        .map(move |(i, _)| offset + i + 1)
+}
+
+/// Iterate over the characters in the given UTF-8 text, yielding each character's
+/// length in bytes and in UTF-16 code units. Each byte of an invalid sequence is
+/// treated as a separate character.
+fn utf8_chars(text: &[u8]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut i = 0;
+    iter::from_fn(move || {
+        let byte = *text.get(i)?;
+        let len = match byte {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        let result = match text.get(i..i + len).map(str::from_utf8) {
+            Some(Ok(_)) => (len, if len == 4 { 2 } else { 1 }),
+            _ => (1, 1),
+        };
+        i += result.0;
+        Some(result)
+    })
+}
@@ -0,0 +1,450 @@
+use super::{
+    Document, InputEdit, Language, Node, Parser, Point, Query, QueryCursor, QueryError, Range, Tree,
+};
+use std::{collections::HashMap, ops, sync::Arc};
+
+/// The maximum depth of nested injections, which prevents a language that injects
+/// itself from being parsed indefinitely.
+const MAX_INJECTION_DEPTH: usize = 32;
+
+/// A language that can be parsed as a layer of a [LayeredDocument], along with a query
+/// that finds the ranges of the other languages that are injected into it.
+///
+/// The injection query uses the same conventions as syntax highlighting: the
+/// `@injection.content` capture marks the nodes whose text belongs to the injected
+/// language, and the language's name is either the text of the `@injection.language`
+/// capture, or the value of an `injection.language` property. The
+/// `injection.include-children` property includes the content nodes' children in the
+/// injected ranges, and the `injection.combined` property parses all of a pattern's
+/// content nodes as a single tree.
+pub struct LayerConfiguration {
+    name: String,
+    language: Language,
+    injection_query: Query,
+    content_capture_index: Option<u32>,
+    language_capture_index: Option<u32>,
+}
+
+/// A syntax tree for one of the languages within a [LayeredDocument].
+pub struct Layer {
+    config: Arc<LayerConfiguration>,
+    tree: Tree,
+    depth: usize,
+}
+
+/// A text document that contains several languages, such as an HTML document with
+/// embedded scripts and style sheets.
+///
+/// The document has a *host* layer, which is parsed from the entire text, and a layer
+/// for each injection that is found by the [LayerConfiguration]s' injection queries,
+/// which is parsed from only the ranges of the text that belong to that injection.
+/// Edits are applied to every layer, so that each layer can be parsed incrementally.
+pub struct LayeredDocument {
+    document: Document,
+    host: Arc<LayerConfiguration>,
+    configs: HashMap<String, Arc<LayerConfiguration>>,
+    layers: Vec<Layer>,
+}
+
+/// An injection that was found within a layer.
+struct Injection {
+    config: Arc<LayerConfiguration>,
+    ranges: Vec<Range>,
+}
+
+impl LayerConfiguration {
+    /// Create a configuration for the language with the given name. The name is
+    /// matched against the names of injected languages.
+    ///
+    /// The injection query can be empty if no other languages are injected into this
+    /// language.
+    pub fn new(name: &str, language: Language, injection_query: &str) -> Result<Self, QueryError> {
+        let injection_query = Query::new(language, injection_query)?;
+        Ok(LayerConfiguration {
+            name: name.to_string(),
+            language,
+            content_capture_index: injection_query.capture_index_for_name("injection.content"),
+            language_capture_index: injection_query.capture_index_for_name("injection.language"),
+            injection_query,
+        })
+    }
+
+    /// Get the name of the language.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the language.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+}
+
+impl Layer {
+    /// Get the name of the layer's language.
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Get the layer's language.
+    pub fn language(&self) -> Language {
+        self.config.language
+    }
+
+    /// Get the layer's syntax tree, updated to reflect any edits that have been made
+    /// since the document was parsed.
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// Get the number of layers that this layer is nested within. The host layer has a
+    /// depth of zero.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the ranges of the document's text that belong to this layer.
+    pub fn included_ranges(&self) -> Vec<Range> {
+        self.tree.included_ranges()
+    }
+
+    fn contains_byte(&self, byte: usize) -> bool {
+        self.tree
+            .included_ranges()
+            .iter()
+            .any(|range| range.start_byte <= byte && byte < range.end_byte)
+    }
+}
+
+impl LayeredDocument {
+    /// Create a new document with the given text, whose host layer uses the given
+    /// language. The document is not parsed until [LayeredDocument::parse] is called.
+    pub fn new(text: impl Into<Vec<u8>>, host: LayerConfiguration) -> Self {
+        let host = Arc::new(host);
+        let mut configs = HashMap::new();
+        configs.insert(host.name.clone(), host.clone());
+        LayeredDocument {
+            document: Document::new(text),
+            host,
+            configs,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add a language that can be injected into the document, replacing any language
+    /// with the same name. The new language is used the next time the document is
+    /// parsed.
+    pub fn add_language(&mut self, config: LayerConfiguration) {
+        self.configs.insert(config.name.clone(), Arc::new(config));
+    }
+
+    /// Get the document's text.
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Get the layers from the most recent call to [LayeredDocument::parse]. The host
+    /// layer is first, and every other layer follows the layer that it is injected
+    /// into.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Get the innermost layer whose included ranges contain the given byte offset.
+    pub fn layer_for_byte(&self, byte: usize) -> Option<&Layer> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.depth > 0)
+            .filter(|layer| layer.contains_byte(byte))
+            .max_by_key(|layer| layer.depth)
+            .or_else(|| self.layers.first())
+    }
+
+    /// Get the innermost layer whose included ranges contain the given position, whose
+    /// column is measured in bytes.
+    pub fn layer_for_point(&self, point: Point) -> Option<&Layer> {
+        self.layer_for_byte(self.document.byte_for_point(point))
+    }
+
+    /// Replace the text within the given byte range, updating the syntax tree of every
+    /// layer to reflect the change.
+    ///
+    /// See [Document::edit].
+    pub fn edit(&mut self, range: ops::Range<usize>, text: impl AsRef<[u8]>) -> InputEdit {
+        let edit = self.document.edit(range, text);
+        for layer in &mut self.layers {
+            layer.tree.edit(&edit);
+        }
+        edit
+    }
+
+    /// Replace the text between the given positions, whose columns are measured in
+    /// bytes.
+    ///
+    /// See [Document::edit_points].
+    pub fn edit_points(&mut self, start: Point, end: Point, text: impl AsRef<[u8]>) -> InputEdit {
+        let range = self.document.byte_for_point(start)..self.document.byte_for_point(end);
+        self.edit(range, text)
+    }
+
+    /// Replace the text between the given positions, whose columns are measured in
+    /// UTF-16 code units.
+    ///
+    /// See [Document::edit_utf16_points].
+    pub fn edit_utf16_points(
+        &mut self,
+        start: Point,
+        end: Point,
+        text: impl AsRef<[u8]>,
+    ) -> InputEdit {
+        let range =
+            self.document.byte_for_utf16_point(start)..self.document.byte_for_utf16_point(end);
+        self.edit(range, text)
+    }
+
+    /// Parse the document's current text, along with every language that is injected
+    /// into it, reusing the layers' previous syntax trees where possible.
+    ///
+    /// This changes the parser's language and included ranges. Injections whose
+    /// language has not been added to the document are ignored. Returns `false` if the
+    /// parser did not produce a tree for one of the layers, in which case the previous
+    /// layers are kept.
+    pub fn parse(&mut self, parser: &mut Parser) -> bool {
+        let old_ranges = self
+            .layers
+            .iter()
+            .map(|layer| layer.tree.included_ranges())
+            .collect::<Vec<_>>();
+        let mut is_reused = vec![false; self.layers.len()];
+
+        let host_tree = parser
+            .set_language(self.host.language)
+            .ok()
+            .and_then(|_| parser.set_included_ranges(&[]).ok())
+            .and_then(|_| {
+                let old_tree = self.layers.first().map(|layer| &layer.tree);
+                self.document.parse_text(parser, old_tree)
+            });
+        let host_tree = match host_tree {
+            Some(tree) => tree,
+            None => return false,
+        };
+        if let Some(is_host_reused) = is_reused.first_mut() {
+            *is_host_reused = true;
+        }
+
+        let mut layers = vec![Layer {
+            config: self.host.clone(),
+            tree: host_tree,
+            depth: 0,
+        }];
+        let mut i = 0;
+        while i < layers.len() {
+            let depth = layers[i].depth + 1;
+            if depth > MAX_INJECTION_DEPTH {
+                i += 1;
+                continue;
+            }
+
+            for injection in self.injections(&layers[i]) {
+                // Reuse the tree of an old layer with the same language, whose ranges
+                // overlap the injection's ranges.
+                let old_layer_index = self.layers.iter().enumerate().position(|(j, layer)| {
+                    !is_reused[j]
+                        && layer.depth == depth
+                        && Arc::ptr_eq(&layer.config, &injection.config)
+                        && ranges_overlap(&old_ranges[j], &injection.ranges)
+                });
+                let old_tree = old_layer_index.map(|j| {
+                    is_reused[j] = true;
+                    &self.layers[j].tree
+                });
+
+                if parser.set_language(injection.config.language).is_err()
+                    || parser.set_included_ranges(&injection.ranges).is_err()
+                {
+                    continue;
+                }
+                match self.document.parse_text(parser, old_tree) {
+                    Some(tree) => layers.push(Layer {
+                        config: injection.config,
+                        tree,
+                        depth,
+                    }),
+                    None => {
+                        parser.set_included_ranges(&[]).unwrap();
+                        return false;
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        parser.set_included_ranges(&[]).unwrap();
+        self.layers = layers;
+        true
+    }
+
+    /// Run the layer's injection query, and compute the ranges of each injection.
+    fn injections(&self, layer: &Layer) -> Vec<Injection> {
+        let config = &layer.config;
+        let query = &config.injection_query;
+        let parent_ranges = layer.tree.included_ranges();
+        let mut result = Vec::new();
+        let mut combined_injections = HashMap::new();
+        let mut cursor = QueryCursor::new();
+        for query_match in cursor.matches(query, layer.tree.root_node(), &self.document) {
+            let mut language_name = None;
+            let mut content_nodes = Vec::new();
+            for capture in query_match.captures {
+                if Some(capture.index) == config.language_capture_index {
+                    language_name = Some(capture.node.utf8_text_with(&self.document));
+                } else if Some(capture.index) == config.content_capture_index {
+                    content_nodes.push(capture.node);
+                }
+            }
+
+            let mut include_children = false;
+            let mut is_combined = false;
+            for property in query.property_settings(query_match.pattern_index) {
+                match property.key.as_ref() {
+                    "injection.language" if language_name.is_none() => {
+                        language_name = property.value.as_deref().map(Into::into);
+                    }
+                    "injection.include-children" => include_children = true,
+                    "injection.combined" => is_combined = true,
+                    _ => {}
+                }
+            }
+
+            let injected_config = match language_name.and_then(|name| self.configs.get(&*name)) {
+                Some(config) => config,
+                None => continue,
+            };
+            if content_nodes.is_empty() {
+                continue;
+            }
+
+            if is_combined {
+                let (_, _, nodes) = combined_injections
+                    .entry(query_match.pattern_index)
+                    .or_insert_with(|| (injected_config.clone(), include_children, Vec::new()));
+                nodes.extend(content_nodes);
+            } else {
+                let ranges = intersect_ranges(&parent_ranges, &content_nodes, include_children);
+                if !ranges.is_empty() {
+                    result.push(Injection {
+                        config: injected_config.clone(),
+                        ranges,
+                    });
+                }
+            }
+        }
+
+        let mut combined_injections = combined_injections.into_iter().collect::<Vec<_>>();
+        combined_injections.sort_unstable_by_key(|(pattern_index, _)| *pattern_index);
+        for (_, (config, include_children, nodes)) in combined_injections {
+            let ranges = intersect_ranges(&parent_ranges, &nodes, include_children);
+            if !ranges.is_empty() {
+                result.push(Injection { config, ranges });
+            }
+        }
+        result
+    }
+}
+
+fn ranges_overlap(a: &[Range], b: &[Range]) -> bool {
+    a.iter().any(|a| {
+        b.iter()
+            .any(|b| a.start_byte < b.end_byte && b.start_byte < a.end_byte)
+    })
+}
+
+/// Compute the ranges that should be included when parsing an injection, which are
+/// the ranges of the given nodes, excluding the ranges of their children unless
+/// `include_children` is set, and clipped to the given ranges of the parent document.
+///
+/// The nodes must be in order. This returns no ranges if `nodes` or `parent_ranges`
+/// is empty.
+pub fn intersect_ranges(
+    parent_ranges: &[Range],
+    nodes: &[Node],
+    include_children: bool,
+) -> Vec<Range> {
+    let mut result = Vec::new();
+    let mut cursor = match nodes.first() {
+        Some(node) => node.walk(),
+        None => return result,
+    };
+    let mut parent_range_iter = parent_ranges.iter();
+    let mut parent_range = match parent_range_iter.next() {
+        Some(range) => range,
+        None => return result,
+    };
+    for node in nodes.iter() {
+        let mut preceding_range = Range {
+            start_byte: 0,
+            start_point: Point::new(0, 0),
+            end_byte: node.start_byte(),
+            end_point: node.start_position(),
+        };
+        let following_range = Range {
+            start_byte: node.end_byte(),
+            start_point: node.end_position(),
+            end_byte: usize::MAX,
+            end_point: Point::new(usize::MAX, usize::MAX),
+        };
+
+        for excluded_range in node
+            .children(&mut cursor)
+            .filter(|_| !include_children)
+            .map(|child| child.range())
+            .chain(Some(following_range))
+        {
+            let mut range = Range {
+                start_byte: preceding_range.end_byte,
+                start_point: preceding_range.end_point,
+                end_byte: excluded_range.start_byte,
+                end_point: excluded_range.start_point,
+            };
+            preceding_range = excluded_range;
+
+            if range.end_byte < parent_range.start_byte {
+                continue;
+            }
+
+            while parent_range.start_byte <= range.end_byte {
+                if parent_range.end_byte > range.start_byte {
+                    if range.start_byte < parent_range.start_byte {
+                        range.start_byte = parent_range.start_byte;
+                        range.start_point = parent_range.start_point;
+                    }
+
+                    if parent_range.end_byte < range.end_byte {
+                        if range.start_byte < parent_range.end_byte {
+                            result.push(Range {
+                                start_byte: range.start_byte,
+                                start_point: range.start_point,
+                                end_byte: parent_range.end_byte,
+                                end_point: parent_range.end_point,
+                            });
+                        }
+                        range.start_byte = parent_range.end_byte;
+                        range.start_point = parent_range.end_point;
+                    } else {
+                        if range.start_byte < range.end_byte {
+                            result.push(range);
+                        }
+                        break;
+                    }
+                }
+
+                match parent_range_iter.next() {
+                    Some(next_range) => parent_range = next_range,
+                    None => return result,
+                }
+            }
+        }
+    }
+    result
+}
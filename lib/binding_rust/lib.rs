@@ -1,20 +1,62 @@
+mod diagnostics;
+mod diff;
+mod document;
 mod ffi;
+mod layered_document;
+mod node_map;
+mod pool;
+mod sexp;
 mod util;
 
+#[cfg(feature = "serde")]
+mod snapshot;
+
+#[cfg(feature = "wasm")]
+mod wasm_language;
+
+pub use diagnostics::{SyntaxError, SyntaxErrorKind};
+pub use diff::{DiffEdit, DiffEditKind};
+pub use document::Document;
+pub use layered_document::{Layer, LayerConfiguration, LayeredDocument};
+pub use node_map::{NodeChange, NodeChangeKind, NodeMap};
+pub use pool::ParserPool;
+pub use sexp::{format_sexp, SexpOptions};
+
+// Shared with `tree-sitter-highlight`, which computes the ranges of its injections
+// in the same way.
+#[doc(hidden)]
+pub use layered_document::intersect_ranges;
+
+#[cfg(feature = "serde")]
+pub use snapshot::{SnapshotError, SnapshotNode, TreeSnapshot};
+
+#[cfg(feature = "wasm")]
+pub use wasm_language::{WasmError, WasmErrorKind, WasmStore};
+#[cfg(feature = "wasm")]
+pub use wasmtime;
+
 #[cfg(unix)]
 use std::os::unix::io::AsRawFd;
 
 use std::{
-    char, error,
+    borrow::Cow,
+    cell::RefCell,
+    char,
+    collections::HashMap,
+    error,
     ffi::CStr,
-    fmt, hash, iter,
+    fmt, hash, io, iter,
     marker::PhantomData,
     mem::MaybeUninit,
     ops,
     os::raw::{c_char, c_void},
     ptr::{self, NonNull},
+    rc::Rc,
     slice, str,
-    sync::atomic::AtomicUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     u16,
 };
 
@@ -33,6 +75,9 @@ pub const LANGUAGE_VERSION: usize = ffi::TREE_SITTER_LANGUAGE_VERSION;
 #[doc(alias = "TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION")]
 pub const MIN_COMPATIBLE_LANGUAGE_VERSION: usize = ffi::TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION;
 
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+const PROGRESS_CHUNK_SIZE: usize = 4 * 1024;
+
 pub const PARSER_HEADER: &'static str = include_str!("../include/tree_sitter/parser.h");
 
 /// An opaque object that defines how to parse a particular language. The code for each
@@ -42,14 +87,53 @@ pub const PARSER_HEADER: &'static str = include_str!("../include/tree_sitter/par
 #[repr(transparent)]
 pub struct Language(*const ffi::TSLanguage);
 
+/// A description of a set of children that a node of a particular kind can have, as
+/// recorded in the grammar's `node-types.json` file.
+///
+/// See [Language::node_kind_fields] and [Language::node_kind_children].
+#[doc(alias = "TSChildInfo")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChildInfo {
+    /// The id of the field that contains the children, or `None` for the named
+    /// children that are not associated with any field.
+    pub field_id: Option<u16>,
+    /// Whether there can be more than one of these children.
+    pub multiple: bool,
+    /// Whether there is always at least one of these children.
+    pub required: bool,
+    /// The node kind ids that these children can have. These may include supertypes.
+    pub types: &'static [u16],
+}
+
+/// Statistics about a node and its descendants.
+///
+/// See [Node::stats] and [Tree::stats].
+#[doc(alias = "TSNodeStats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeStats {
+    /// The number of nodes in the subtree, including the node itself. This is the
+    /// same as [Node::descendant_count].
+    pub node_count: usize,
+    /// The number of nodes in the subtree that are shared with the old tree, which
+    /// are the nodes that were reused if the tree was parsed incrementally from the
+    /// old tree.
+    pub reused_node_count: usize,
+    /// The number of nodes on the longest path from the node to a leaf, including
+    /// both of them.
+    pub depth: usize,
+    /// The number of bytes of memory that are used by the subtree.
+    pub memory_usage: usize,
+}
+
 /// A tree that represents the syntactic structure of a source code file.
 #[doc(alias = "TSTree")]
-pub struct Tree(NonNull<ffi::TSTree>);
+pub struct Tree(NonNull<ffi::TSTree>, Option<Arc<dyn Allocator>>);
 
 /// A position in a multi-line text document, in terms of rows and columns.
 ///
 /// Rows and columns are zero-based.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub row: usize,
     pub column: usize,
@@ -58,6 +142,7 @@ pub struct Point {
 /// A range of positions in a multi-line text document, both in terms of bytes and of
 /// rows and columns.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Range {
     pub start_byte: usize,
     pub end_byte: usize,
@@ -82,9 +167,20 @@ pub struct InputEdit {
 #[repr(transparent)]
 pub struct Node<'a>(ffi::TSNode, PhantomData<&'a ()>);
 
+/// A handle to a single node that shares ownership of its syntax `Tree`.
+///
+/// Unlike [Node], an `OwnedNode` does not borrow its tree, so it can be stored in
+/// long-lived structs or sent to other threads. Use [OwnedNode::node] to access the
+/// node itself.
+#[derive(Clone)]
+pub struct OwnedNode {
+    tree: Arc<Tree>,
+    node: ffi::TSNode,
+}
+
 /// A stateful object that this is used to produce a `Tree` based on some source code.
 #[doc(alias = "TSParser")]
-pub struct Parser(NonNull<ffi::TSParser>);
+pub struct Parser(NonNull<ffi::TSParser>, Option<Arc<dyn Allocator>>);
 
 /// A type of log message.
 #[derive(Debug, PartialEq, Eq)]
@@ -93,6 +189,18 @@ pub enum LogType {
     Lex,
 }
 
+/// An action that a parser should take after reporting its progress to the callback
+/// passed to [Parser::parse_with_progress].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseAction {
+    /// Keep parsing.
+    Continue,
+    /// Halt parsing, so that it can be resumed later.
+    Yield,
+    /// Halt parsing, and discard the work that has been done so far.
+    Cancel,
+}
+
 /// A callback that receives log messages during parser.
 type Logger<'a> = Box<dyn FnMut(LogType, &str) + 'a>;
 
@@ -100,6 +208,65 @@ type Logger<'a> = Box<dyn FnMut(LogType, &str) + 'a>;
 #[doc(alias = "TSTreeCursor")]
 pub struct TreeCursor<'a>(ffi::TSTreeCursor, PhantomData<&'a ()>);
 
+/// An iterator over the symbols that are valid in a particular parse state.
+///
+/// This can be used to suggest completions, or to describe what was expected at the
+/// position of a syntax error.
+#[doc(alias = "TSLookaheadIterator")]
+pub struct LookaheadIterator(NonNull<ffi::TSLookaheadIterator>);
+
+/// What a [Visitor] should do after entering a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisitAction {
+    /// Visit the node's children, and then leave the node.
+    Continue,
+    /// Leave the node without visiting its children.
+    SkipChildren,
+    /// Stop walking the tree immediately.
+    Stop,
+}
+
+/// A set of callbacks that are invoked while walking a syntax tree in depth-first order.
+///
+/// Each callback receives the node, the name of the field that the node is associated
+/// with in its parent, if any, and the node's depth relative to the node where the walk
+/// started. See [Node::visit].
+pub trait Visitor<'tree> {
+    /// Called before any of the node's children are visited.
+    fn enter_node(
+        &mut self,
+        _node: Node<'tree>,
+        _field_name: Option<&'static str>,
+        _depth: usize,
+    ) -> VisitAction {
+        VisitAction::Continue
+    }
+
+    /// Called after all of the node's children have been visited, or after the node was
+    /// entered if its children were skipped.
+    fn leave_node(&mut self, _node: Node<'tree>, _field_name: Option<&'static str>, _depth: usize) {
+    }
+}
+
+/// An iterator over a node and all of its descendants, visiting each node before its
+/// children.
+pub struct PreorderTraversal<'tree> {
+    cursor: TreeCursor<'tree>,
+    depth: usize,
+    started: bool,
+    skip_children: bool,
+    done: bool,
+}
+
+/// An iterator over a node and all of its descendants, visiting each node after its
+/// children.
+pub struct PostorderTraversal<'tree> {
+    cursor: TreeCursor<'tree>,
+    depth: usize,
+    started: bool,
+    done: bool,
+}
+
 /// A set of patterns that match nodes in a syntax tree.
 #[doc(alias = "TSQuery")]
 #[derive(Debug)]
@@ -111,6 +278,7 @@ pub struct Query {
     property_settings: Vec<Box<[QueryProperty]>>,
     property_predicates: Vec<Box<[(QueryProperty, bool)]>>,
     general_predicates: Vec<Box<[QueryPredicate]>>,
+    custom_predicates: Vec<Box<[CustomPredicateCall]>>,
 }
 
 /// A quantifier for captures
@@ -136,10 +304,46 @@ impl From<ffi::TSQuantifier> for CaptureQuantifier {
     }
 }
 
+impl From<ffi::TSQueryToken> for QueryToken {
+    fn from(token: ffi::TSQueryToken) -> Self {
+        let kind = match token.type_ {
+            ffi::TSQueryTokenType_TSQueryTokenTypeComment => QueryTokenKind::Comment,
+            ffi::TSQueryTokenType_TSQueryTokenTypeAlternationStart => {
+                QueryTokenKind::AlternationStart
+            }
+            ffi::TSQueryTokenType_TSQueryTokenTypeGroupStart => QueryTokenKind::GroupStart,
+            ffi::TSQueryTokenType_TSQueryTokenTypeNodeStart => QueryTokenKind::NodeStart,
+            ffi::TSQueryTokenType_TSQueryTokenTypePredicateStart => QueryTokenKind::PredicateStart,
+            ffi::TSQueryTokenType_TSQueryTokenTypeEnd => QueryTokenKind::End,
+            ffi::TSQueryTokenType_TSQueryTokenTypeNodeName => QueryTokenKind::NodeName,
+            ffi::TSQueryTokenType_TSQueryTokenTypeSupertypeSeparator => {
+                QueryTokenKind::SupertypeSeparator
+            }
+            ffi::TSQueryTokenType_TSQueryTokenTypeWildcard => QueryTokenKind::Wildcard,
+            ffi::TSQueryTokenType_TSQueryTokenTypeString => QueryTokenKind::String,
+            ffi::TSQueryTokenType_TSQueryTokenTypeFieldName => QueryTokenKind::FieldName,
+            ffi::TSQueryTokenType_TSQueryTokenTypeColon => QueryTokenKind::Colon,
+            ffi::TSQueryTokenType_TSQueryTokenTypeNegation => QueryTokenKind::Negation,
+            ffi::TSQueryTokenType_TSQueryTokenTypeAnchor => QueryTokenKind::Anchor,
+            ffi::TSQueryTokenType_TSQueryTokenTypeQuantifier => QueryTokenKind::Quantifier,
+            ffi::TSQueryTokenType_TSQueryTokenTypeCapture => QueryTokenKind::Capture,
+            ffi::TSQueryTokenType_TSQueryTokenTypePredicateName => QueryTokenKind::PredicateName,
+            ffi::TSQueryTokenType_TSQueryTokenTypeIdentifier => QueryTokenKind::Identifier,
+            _ => panic!("Unrecognized query token type: {}", token.type_),
+        };
+        QueryToken {
+            kind,
+            start_byte: token.start_byte as usize,
+            end_byte: token.end_byte as usize,
+        }
+    }
+}
+
 /// A stateful object for executing a `Query` on a syntax `Tree`.
 #[doc(alias = "TSQueryCursor")]
 pub struct QueryCursor {
     ptr: NonNull<ffi::TSQueryCursor>,
+    _allocator: Option<Arc<dyn Allocator>>,
 }
 
 /// A key-value pair associated with a particular pattern in a `Query`.
@@ -163,6 +367,114 @@ pub struct QueryPredicate {
     pub args: Vec<QueryPredicateArg>,
 }
 
+/// The kind of a token in the source code of a [Query]. See [Query::tokenize].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryTokenKind {
+    /// A comment, from its `;` to the end of its line.
+    Comment,
+    /// The `[` that starts an alternation, such as `[(identifier) (number)]`.
+    AlternationStart,
+    /// The `(` that starts a grouped sequence of sibling patterns, such as
+    /// `((comment) (function_declaration))`.
+    GroupStart,
+    /// The `(` that starts a named node pattern, such as `(identifier)`.
+    NodeStart,
+    /// The `(` that starts a predicate, such as `(#eq? @a @b)`.
+    PredicateStart,
+    /// The `)` or `]` that ends an alternation, a group, a named node or a predicate.
+    End,
+    /// The name of a named node, such as `identifier` or `_`, or of the supertype
+    /// before a [QueryTokenKind::SupertypeSeparator].
+    NodeName,
+    /// The `/` between a supertype and a subtype, as in `(expression/identifier)`.
+    SupertypeSeparator,
+    /// The `_` wildcard, when it is not within parentheses.
+    Wildcard,
+    /// A string literal, including its quotes, which is either an anonymous node or an
+    /// argument to a predicate.
+    String,
+    /// The name of a field, which is followed by a [QueryTokenKind::Colon], or preceded
+    /// by a [QueryTokenKind::Negation].
+    FieldName,
+    /// The `:` after a field name.
+    Colon,
+    /// The `!` before a negated field name.
+    Negation,
+    /// The `.` anchor.
+    Anchor,
+    /// One of the quantifiers `*`, `+` and `?`.
+    Quantifier,
+    /// A capture name, including its `@`.
+    Capture,
+    /// The name of a predicate, including its `#`, if any.
+    PredicateName,
+    /// A bare symbol argument to a predicate.
+    Identifier,
+}
+
+/// A token in the source code of a [Query]. See [Query::tokenize].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueryToken {
+    pub kind: QueryTokenKind,
+    pub start_byte: usize,
+    pub end_byte: usize,
+}
+
+/// The type of an argument that is accepted by a custom predicate in a [QueryPredicates]
+/// registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PredicateArgType {
+    /// A capture name, such as `@name`.
+    Capture,
+    /// A literal string, such as `"name"`.
+    String,
+    /// Either a capture name or a literal string.
+    Any,
+}
+
+/// An argument that is passed to a custom predicate when a match is checked.
+#[derive(Clone, Copy, Debug)]
+pub enum PredicateArg<'a, 'tree> {
+    /// The nodes that were captured by a capture argument, in order. This is empty if
+    /// the capture is optional, and it did not match.
+    Capture(&'a [PredicateCapture<'a, 'tree>]),
+    /// A literal string argument.
+    String(&'a str),
+}
+
+/// A node that was captured by a capture argument to a custom predicate.
+#[derive(Clone, Copy)]
+pub struct PredicateCapture<'a, 'tree> {
+    pub node: Node<'tree>,
+    read_text: &'a dyn Fn(Node<'tree>) -> Cow<'a, [u8]>,
+}
+
+type PredicateFn = dyn Fn(&[PredicateArg]) -> bool + Send + Sync;
+
+/// A registry of custom predicates that are evaluated automatically when a [Query] is
+/// executed.
+///
+/// Predicates whose operators are registered here are validated when the query is
+/// created with [Query::with_predicates], and are checked by [QueryCursor::matches]
+/// and [QueryCursor::captures], which skip any match for which a predicate returns
+/// `false`. The built-in operators, such as `eq?` and `set!`, cannot be registered.
+#[derive(Clone, Default)]
+pub struct QueryPredicates {
+    predicates: HashMap<String, CustomPredicate>,
+}
+
+#[derive(Clone)]
+struct CustomPredicate {
+    arg_types: Box<[PredicateArgType]>,
+    function: Arc<PredicateFn>,
+}
+
+/// A custom predicate within a particular pattern of a [Query].
+struct CustomPredicateCall {
+    predicate: QueryPredicate,
+    function: Arc<PredicateFn>,
+}
+
 /// A match of a `Query` to a particular set of `Node`s.
 pub struct QueryMatch<'cursor, 'tree> {
     pub pattern_index: usize,
@@ -208,6 +520,7 @@ pub struct QueryCapture<'a> {
 #[derive(Debug, PartialEq, Eq)]
 pub struct LanguageError {
     version: usize,
+    wasm: bool,
 }
 
 /// An error that occurred in `Parser::set_included_ranges`.
@@ -235,11 +548,53 @@ pub enum QueryErrorKind {
     Language,
 }
 
+impl<'a, 'tree> fmt::Debug for PredicateCapture<'a, 'tree> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PredicateCapture")
+            .field("node", &self.node)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for CustomPredicateCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.predicate.fmt(f)
+    }
+}
+
+/// The operators of the predicates that are handled by [Query] itself.
+const BUILT_IN_PREDICATES: &[&str] = &[
+    "eq?",
+    "not-eq?",
+    "any-eq?",
+    "any-not-eq?",
+    "match?",
+    "not-match?",
+    "any-match?",
+    "any-not-match?",
+    "any-of?",
+    "not-any-of?",
+    "set!",
+    "is?",
+    "is-not?",
+];
+
+/// A predicate on the text of captured nodes. The first boolean is whether the
+/// predicate is positive, and the second is whether every captured node must satisfy
+/// it, rather than any one of them.
 #[derive(Debug)]
 enum TextPredicate {
-    CaptureEqString(u32, String, bool),
-    CaptureEqCapture(u32, u32, bool),
-    CaptureMatchString(u32, regex::bytes::Regex, bool),
+    CaptureEqString(u32, String, bool, bool),
+    CaptureEqCapture(u32, u32, bool, bool),
+    CaptureMatchString(u32, regex::bytes::Regex, bool, bool),
+    CaptureAnyString(u32, Box<[String]>, bool),
+}
+
+/// A part of a chunk of text, starting at a given offset and limited to a given length.
+struct ChunkSlice<T> {
+    chunk: T,
+    start: usize,
+    max_len: usize,
 }
 
 // TODO: Remove this struct at at some point. If `core::str::lossy::Utf8Lossy`
@@ -257,6 +612,12 @@ impl Language {
         unsafe { ffi::ts_language_version(self.0) as usize }
     }
 
+    /// Check whether this language was loaded from a WebAssembly module.
+    #[doc(alias = "ts_language_is_wasm")]
+    pub fn is_wasm(&self) -> bool {
+        unsafe { ffi::ts_language_is_wasm(self.0) }
+    }
+
     /// Get the number of distinct node types in this language.
     #[doc(alias = "ts_language_symbol_count")]
     pub fn node_kind_count(&self) -> usize {
@@ -300,6 +661,35 @@ impl Language {
         }
     }
 
+    /// Get the number of valid parse states in this language.
+    #[doc(alias = "ts_language_state_count")]
+    pub fn parse_state_count(&self) -> usize {
+        unsafe { ffi::ts_language_state_count(self.0) as usize }
+    }
+
+    /// Get the parse state that follows the given state after the given symbol.
+    ///
+    /// The symbol should be the grammar id of a node (see [Node::grammar_id]) or a
+    /// symbol that was produced by a [LookaheadIterator]. This returns zero if the
+    /// state or the symbol is not valid for this language, or if the symbol is not
+    /// valid in the given state.
+    #[doc(alias = "ts_language_next_state")]
+    pub fn next_state(&self, state: u16, id: u16) -> u16 {
+        if state as usize >= self.parse_state_count() || id as usize >= self.node_kind_count() {
+            return 0;
+        }
+        unsafe { ffi::ts_language_next_state(self.0, state, id) }
+    }
+
+    /// Create a new lookahead iterator for this language and the given parse state.
+    ///
+    /// This returns `None` if the state is not valid for this language.
+    #[doc(alias = "ts_lookahead_iterator_new")]
+    pub fn lookahead_iterator(&self, state: u16) -> Option<LookaheadIterator> {
+        let ptr = unsafe { ffi::ts_lookahead_iterator_new(self.0, state) };
+        NonNull::new(ptr).map(LookaheadIterator)
+    }
+
     /// Get the number of distinct field names in this language.
     #[doc(alias = "ts_language_field_count")]
     pub fn field_count(&self) -> usize {
@@ -334,6 +724,72 @@ impl Language {
             Some(id)
         }
     }
+
+    /// Get the node kind ids of this language's supertypes.
+    ///
+    /// Supertypes are hidden rules that are listed in the grammar's `supertypes`, such
+    /// as `_expression` or `_statement`. This information is only available for
+    /// languages that were generated with ABI version 15 or later. `tree-sitter
+    /// generate` produces ABI version 14 unless it is run with `--abi 15` or
+    /// `--abi latest`. For older languages, this returns an empty slice.
+    #[doc(alias = "ts_language_supertypes")]
+    pub fn supertypes(&self) -> &'static [u16] {
+        let mut length = 0u32;
+        let ptr = unsafe { ffi::ts_language_supertypes(self.0, &mut length) };
+        unsafe { util::slice_from_raw_parts(ptr, length) }
+    }
+
+    /// Get the node kind ids of the given supertype's subtypes.
+    ///
+    /// If the given kind is not a supertype, or if the language was generated with an
+    /// ABI version earlier than 15 (see [Language::supertypes]), this returns an empty
+    /// slice.
+    #[doc(alias = "ts_language_subtypes")]
+    pub fn subtypes(&self, supertype: u16) -> &'static [u16] {
+        let mut length = 0u32;
+        let ptr = unsafe { ffi::ts_language_subtypes(self.0, supertype, &mut length) };
+        unsafe { util::slice_from_raw_parts(ptr, length) }
+    }
+
+    /// Get descriptions of the fields that a node of the given kind can contain, in
+    /// order of their field ids.
+    ///
+    /// Like [Language::supertypes], this is only available for languages that were
+    /// generated with ABI version 15 or later. For other languages, this returns an
+    /// empty vector.
+    pub fn node_kind_fields(&self, id: u16) -> Vec<ChildInfo> {
+        self.child_infos(id)
+            .filter(|info| info.field_id.is_some())
+            .collect()
+    }
+
+    /// Get a description of the named children that a node of the given kind can
+    /// contain, which are not associated with any field.
+    ///
+    /// This returns `None` if nodes of this kind can't have any such children, or if
+    /// the language was generated with an ABI version earlier than 15 (see
+    /// [Language::supertypes]).
+    pub fn node_kind_children(&self, id: u16) -> Option<ChildInfo> {
+        self.child_infos(id).find(|info| info.field_id.is_none())
+    }
+
+    #[doc(alias = "ts_language_child_info_at")]
+    fn child_infos(&self, id: u16) -> impl Iterator<Item = ChildInfo> + '_ {
+        let count = unsafe { ffi::ts_language_child_info_count(self.0, id) };
+        (0..count).map(move |i| {
+            let info = unsafe { ffi::ts_language_child_info_at(self.0, id, i) };
+            ChildInfo {
+                field_id: if info.field_id == 0 {
+                    None
+                } else {
+                    Some(info.field_id)
+                },
+                multiple: info.multiple,
+                required: info.required,
+                types: unsafe { util::slice_from_raw_parts(info.types, info.type_count) },
+            }
+        })
+    }
 }
 
 impl Parser {
@@ -341,7 +797,22 @@ impl Parser {
     pub fn new() -> Parser {
         unsafe {
             let parser = ffi::ts_parser_new();
-            Parser(NonNull::new_unchecked(parser))
+            Parser(NonNull::new_unchecked(parser), None)
+        }
+    }
+
+    /// Create a new parser that uses the given allocator for all of its memory,
+    /// instead of the global allocation functions.
+    ///
+    /// The trees that the parser produces use the same allocator, and keep it alive
+    /// until they are dropped. A tree that was produced with a different allocator
+    /// can still be passed as the old tree to [Parser::parse], but none of its nodes
+    /// will be reused.
+    #[doc(alias = "ts_parser_new_with_allocator")]
+    pub fn with_allocator<A: Allocator + 'static>(allocator: Arc<A>) -> Parser {
+        unsafe {
+            let parser = ffi::ts_parser_new_with_allocator(c_allocator(&allocator));
+            Parser(NonNull::new_unchecked(parser), Some(allocator))
         }
     }
 
@@ -353,16 +824,24 @@ impl Parser {
     /// Tree-sitter CLI. Check the language's version using [Language::version]
     /// and compare it to this library's [LANGUAGE_VERSION](LANGUAGE_VERSION) and
     /// [MIN_COMPATIBLE_LANGUAGE_VERSION](MIN_COMPATIBLE_LANGUAGE_VERSION) constants.
+    ///
+    /// Assigning a WebAssembly language also fails if the parser does not have a
+    /// WebAssembly store, or if the language cannot be instantiated in that store.
     #[doc(alias = "ts_parser_set_language")]
     pub fn set_language(&mut self, language: Language) -> Result<(), LanguageError> {
         let version = language.version();
         if version < MIN_COMPATIBLE_LANGUAGE_VERSION || version > LANGUAGE_VERSION {
-            Err(LanguageError { version })
-        } else {
-            unsafe {
-                ffi::ts_parser_set_language(self.0.as_ptr(), language.0);
-            }
+            Err(LanguageError {
+                version,
+                wasm: false,
+            })
+        } else if unsafe { ffi::ts_parser_set_language(self.0.as_ptr(), language.0) } {
             Ok(())
+        } else {
+            Err(LanguageError {
+                version,
+                wasm: true,
+            })
         }
     }
 
@@ -445,6 +924,16 @@ impl Parser {
         unsafe { ffi::ts_parser_print_dot_graphs(self.0.as_ptr(), -1) }
     }
 
+    /// Get an estimate of the number of bytes of memory that the parser is using.
+    ///
+    /// This includes the buffers that the parser keeps between parses, but not the
+    /// syntax trees that it has returned, or the memory used by a language's
+    /// external scanner.
+    #[doc(alias = "ts_parser_memory_usage")]
+    pub fn memory_usage(&self) -> usize {
+        unsafe { ffi::ts_parser_memory_usage(self.0.as_ptr()) }
+    }
+
     /// Parse a slice of UTF8 text.
     ///
     /// # Arguments:
@@ -500,6 +989,12 @@ impl Parser {
     ///   If the text of the document has changed since `old_tree` was
     ///   created, then you must edit `old_tree` to match the new text using
     ///   [Tree::edit].
+    ///
+    /// The value returned by the callback is kept alive until the next time the
+    /// callback is called, or until parsing finishes, whichever comes first. This
+    /// allows the callback to return owned values, such as vectors. The parser does
+    /// not retain any reference to the text once this method returns, and the
+    /// callback may be called more than once for the same offset.
     pub fn parse_with<'a, T: AsRef<[u8]>, F: FnMut(usize, Point) -> T>(
         &mut self,
         callback: &mut F,
@@ -535,8 +1030,168 @@ impl Parser {
         let c_old_tree = old_tree.map_or(ptr::null_mut(), |t| t.0.as_ptr());
         unsafe {
             let c_new_tree = ffi::ts_parser_parse(self.0.as_ptr(), c_old_tree, c_input);
-            NonNull::new(c_new_tree).map(Tree)
+            NonNull::new(c_new_tree).map(|ptr| Tree(ptr, self.1.clone()))
+        }
+    }
+
+    /// Parse UTF8 text that is stored as a sequence of chunks, such as a rope.
+    ///
+    /// # Arguments:
+    /// * `chunk_at_byte` A function that takes a byte offset and returns the chunk
+    ///   of text that contains that offset, along with the byte offset where that
+    ///   chunk starts. If the given offset is at the end of the text, the callback
+    ///   should return an empty chunk.
+    /// * `old_tree` A previous syntax tree parsed from the same document.
+    ///   If the text of the document has changed since `old_tree` was
+    ///   created, then you must edit `old_tree` to match the new text using
+    ///   [Tree::edit].
+    ///
+    /// Chunks are kept alive for the same duration as the values returned by the
+    /// callback passed to [Parser::parse_with].
+    pub fn parse_chunks<T: AsRef<[u8]>, F: FnMut(usize) -> (T, usize)>(
+        &mut self,
+        chunk_at_byte: &mut F,
+        old_tree: Option<&Tree>,
+    ) -> Option<Tree> {
+        self.parse_with(
+            &mut |offset, _| {
+                let (chunk, chunk_start) = chunk_at_byte(offset);
+                ChunkSlice {
+                    chunk,
+                    start: offset.saturating_sub(chunk_start),
+                    max_len: usize::MAX,
+                }
+            },
+            old_tree,
+        )
+    }
+
+    /// Parse UTF8 text from a reader that supports seeking, such as a file.
+    ///
+    /// # Arguments:
+    /// * `reader` The source of the text to parse. The text is read in chunks of
+    ///   a fixed size, so the whole document never needs to be loaded at once.
+    ///   The reader may be asked to seek backwards when the parser needs to
+    ///   re-read text that it has already seen.
+    /// * `old_tree` A previous syntax tree parsed from the same document.
+    ///   If the text of the document has changed since `old_tree` was
+    ///   created, then you must edit `old_tree` to match the new text using
+    ///   [Tree::edit].
+    ///
+    /// Returns an error if reading from or seeking within the reader failed.
+    /// Otherwise, returns the same result as [Parser::parse].
+    ///
+    /// Memory-mapped files do not need to go through a reader, because they can
+    /// be passed directly to [Parser::parse] as a byte slice.
+    pub fn parse_reader<R: io::Read + io::Seek>(
+        &mut self,
+        reader: &mut R,
+        old_tree: Option<&Tree>,
+    ) -> io::Result<Option<Tree>> {
+        let mut chunk = Rc::<[u8]>::from(Vec::new());
+        let mut chunk_start = 0;
+        let mut error = None;
+        let tree = self.parse_chunks(
+            &mut |offset| {
+                if error.is_none() && !(chunk_start..chunk_start + chunk.len()).contains(&offset) {
+                    match read_chunk(reader, offset) {
+                        Ok(text) => {
+                            chunk = Rc::from(text);
+                            chunk_start = offset;
+                        }
+                        Err(e) => {
+                            chunk = Rc::from(Vec::new());
+                            chunk_start = offset;
+                            error = Some(e);
+                        }
+                    }
+                }
+                (chunk.clone(), chunk_start)
+            },
+            old_tree,
+        );
+        match error {
+            Some(error) => Err(error),
+            None => Ok(tree),
+        }
+    }
+
+    /// Parse UTF8 text provided in chunks by a callback, periodically reporting the
+    /// parser's progress to another callback.
+    ///
+    /// # Arguments:
+    /// * `callback` A function that provides the text to parse, as described in
+    ///   [Parser::parse_with].
+    /// * `old_tree` A previous syntax tree parsed from the same document.
+    ///   If the text of the document has changed since `old_tree` was
+    ///   created, then you must edit `old_tree` to match the new text using
+    ///   [Tree::edit].
+    /// * `progress` A function that takes the byte offset that the parser has read
+    ///   up to, and returns a [ParseAction] that determines whether the parser
+    ///   should continue. It is called at least once for every few kilobytes of
+    ///   text that is read.
+    ///
+    /// If the progress callback returns [ParseAction::Yield], the parser halts
+    /// shortly afterward and this method returns `None`. Calling this method again
+    /// with the same input and old tree resumes the parse where it left off. This
+    /// allows a large document to be parsed in small increments, for example on a
+    /// thread that must periodically respond to other events.
+    ///
+    /// If the progress callback returns [ParseAction::Cancel], the parser halts,
+    /// returns `None`, and is [reset](Parser::reset), so that the next parse starts
+    /// from the beginning.
+    ///
+    /// The timeout set with [Parser::set_timeout_micros] and the cancellation flag
+    /// set with [Parser::set_cancellation_flag] still apply, and halt the parser in
+    /// the same way as [ParseAction::Yield].
+    pub fn parse_with_progress<T, F, P>(
+        &mut self,
+        callback: &mut F,
+        old_tree: Option<&Tree>,
+        progress: &mut P,
+    ) -> Option<Tree>
+    where
+        T: AsRef<[u8]>,
+        F: FnMut(usize, Point) -> T,
+        P: FnMut(usize) -> ParseAction,
+    {
+        // Temporarily replace the parser's cancellation flag with one that is set
+        // when the progress callback asks the parser to halt.
+        let halt_flag = AtomicUsize::new(0);
+        let cancellation_flag =
+            unsafe { ffi::ts_parser_cancellation_flag(self.0.as_ptr()) } as *const AtomicUsize;
+        unsafe { self.set_cancellation_flag(Some(&halt_flag)) };
+
+        let mut action = ParseAction::Continue;
+        let result = self.parse_with(
+            &mut |offset, position| {
+                if action == ParseAction::Continue {
+                    let flag = unsafe { cancellation_flag.as_ref() };
+                    if flag.map_or(false, |flag| flag.load(Ordering::SeqCst) != 0) {
+                        halt_flag.store(1, Ordering::SeqCst);
+                    } else {
+                        action = progress(offset);
+                        if action != ParseAction::Continue {
+                            halt_flag.store(1, Ordering::SeqCst);
+                        }
+                    }
+                }
+
+                // Limit the size of each chunk so that progress is reported regularly.
+                ChunkSlice {
+                    chunk: callback(offset, position),
+                    start: 0,
+                    max_len: PROGRESS_CHUNK_SIZE,
+                }
+            },
+            old_tree,
+        );
+
+        unsafe { self.set_cancellation_flag(cancellation_flag.as_ref()) };
+        if result.is_none() && action == ParseAction::Cancel {
+            self.reset();
         }
+        result
     }
 
     /// Parse UTF16 text provided in chunks by a callback.
@@ -591,7 +1246,7 @@ impl Parser {
         let c_old_tree = old_tree.map_or(ptr::null_mut(), |t| t.0.as_ptr());
         unsafe {
             let c_new_tree = ffi::ts_parser_parse(self.0.as_ptr(), c_old_tree, c_input);
-            NonNull::new(c_new_tree).map(Tree)
+            NonNull::new(c_new_tree).map(|ptr| Tree(ptr, self.1.clone()))
         }
     }
 
@@ -728,6 +1383,40 @@ impl Tree {
         Language(unsafe { ffi::ts_tree_language(self.0.as_ptr()) })
     }
 
+    /// Get the included ranges that were used to parse the syntax tree, updated to
+    /// reflect any edits that have been made to the tree since then.
+    ///
+    /// See [Parser::set_included_ranges].
+    #[doc(alias = "ts_tree_included_ranges")]
+    pub fn included_ranges(&self) -> Vec<Range> {
+        let mut count = 0u32;
+        unsafe {
+            let ptr = ffi::ts_tree_included_ranges(self.0.as_ptr(), &mut count as *mut u32);
+            util::CBufferIter::new(ptr, count as usize)
+                .map(|r| r.into())
+                .collect()
+        }
+    }
+
+    /// Get the number of bytes of memory that the syntax tree is using.
+    ///
+    /// Trees that are created by incremental parsing share the nodes of the old tree
+    /// that were reused, and clones of a tree share all of its nodes. Shared memory
+    /// is included in the usage of every tree that shares it.
+    #[doc(alias = "ts_tree_memory_usage")]
+    pub fn memory_usage(&self) -> usize {
+        unsafe { ffi::ts_tree_memory_usage(self.0.as_ptr()) }
+    }
+
+    /// Get statistics about the syntax tree.
+    ///
+    /// If `old_tree` is the tree that was passed to [Parser::parse] to create this
+    /// tree, then the statistics count the nodes that were reused from it. See
+    /// [Node::stats].
+    pub fn stats(&self, old_tree: Option<&Tree>) -> NodeStats {
+        self.root_node().stats(old_tree)
+    }
+
     /// Edit the syntax tree to keep it in sync with source code that has been
     /// edited.
     ///
@@ -744,6 +1433,66 @@ impl Tree {
         self.root_node().walk()
     }
 
+    /// Walk the whole tree, invoking the given [Visitor]'s callbacks for each node.
+    ///
+    /// See [Node::visit].
+    pub fn visit<'tree>(&'tree self, visitor: &mut impl Visitor<'tree>) {
+        self.root_node().visit(visitor)
+    }
+
+    /// Iterate over all of the nodes in the tree in pre-order.
+    ///
+    /// See [Node::preorder].
+    pub fn preorder(&self) -> PreorderTraversal<'_> {
+        self.root_node().preorder()
+    }
+
+    /// Iterate over all of the nodes in the tree in post-order.
+    ///
+    /// See [Node::postorder].
+    pub fn postorder(&self) -> PostorderTraversal<'_> {
+        self.root_node().postorder()
+    }
+
+    /// Compare this syntax tree to another syntax tree, returning a list of node-level
+    /// edits that transform this tree into the other one.
+    ///
+    /// Unlike [Tree::changed_ranges], the two trees do not need to be related by
+    /// [Tree::edit], and can be parsed from unrelated source code. Nodes are matched
+    /// based on their kinds, their structure and the text of their leaves, so the
+    /// source code of both trees must be provided.
+    ///
+    /// The edits that only involve this tree, which are deletions, are listed first
+    /// in the order of this tree. The other edits follow, in the order of the other
+    /// tree. Only the outermost node of each inserted or deleted subtree is listed;
+    /// the nodes within it are inserted or deleted too, unless they are listed as
+    /// moved.
+    pub fn diff<'a, 'b>(
+        &'a self,
+        source: &[u8],
+        other: &'b Tree,
+        other_source: &[u8],
+    ) -> Vec<DiffEdit<'a, 'b>> {
+        diff::diff_trees(self, source, other, other_source)
+    }
+
+    /// Match the nodes of this syntax tree with the nodes of a new syntax tree that
+    /// was parsed after applying the given edits, so that state which is associated
+    /// with nodes can be carried over to the new tree.
+    ///
+    /// This tree must be the tree as it was *before* the edits were applied, so if
+    /// the tree is edited in place before being passed to [Parser::parse], call this
+    /// method on a clone that was made before editing it. The edits must be listed in
+    /// the order that they were applied.
+    ///
+    /// Unlike [Node::id], which changes whenever the parser rebuilds a subtree, the
+    /// resulting [NodeMap] also matches nodes that were rebuilt, as long as they have
+    /// the same kind and their ranges correspond, or they have the same position among
+    /// the children of a matched parent.
+    pub fn map_nodes<'a, 'b>(&'a self, edits: &[InputEdit], new_tree: &'b Tree) -> NodeMap<'a, 'b> {
+        node_map::map_nodes(self, edits, new_tree)
+    }
+
     /// Compare this old edited syntax tree to a new syntax tree representing the same
     /// document, returning a sequence of ranges whose syntactic structure has changed.
     ///
@@ -779,7 +1528,69 @@ impl Drop for Tree {
 
 impl Clone for Tree {
     fn clone(&self) -> Tree {
-        unsafe { Tree(NonNull::new_unchecked(ffi::ts_tree_copy(self.0.as_ptr()))) }
+        unsafe {
+            Tree(
+                NonNull::new_unchecked(ffi::ts_tree_copy(self.0.as_ptr())),
+                self.1.clone(),
+            )
+        }
+    }
+}
+
+impl OwnedNode {
+    /// Create a handle to the given node, which must belong to the given tree.
+    ///
+    /// Returns `None` if the node was obtained from a different tree, including a
+    /// clone of the given tree.
+    pub fn new(tree: Arc<Tree>, node: Node) -> Option<Self> {
+        if ptr::eq(node.0.tree, tree.0.as_ptr()) {
+            Some(OwnedNode { tree, node: node.0 })
+        } else {
+            None
+        }
+    }
+
+    /// Create a handle to the root node of the given tree.
+    pub fn root(tree: Arc<Tree>) -> Self {
+        let node = tree.root_node().0;
+        OwnedNode { tree, node }
+    }
+
+    /// Get the syntax tree that this node belongs to.
+    pub fn tree(&self) -> &Arc<Tree> {
+        &self.tree
+    }
+
+    /// Get the node that this handle refers to.
+    pub fn node(&self) -> Node<'_> {
+        Node(self.node, PhantomData)
+    }
+
+    /// Create a handle to another node in the same tree.
+    ///
+    /// Returns `None` if the node was obtained from a different tree.
+    pub fn with_node(&self, node: Node) -> Option<Self> {
+        Self::new(self.tree.clone(), node)
+    }
+}
+
+impl PartialEq for OwnedNode {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.tree, &other.tree) && self.node() == other.node()
+    }
+}
+
+impl Eq for OwnedNode {}
+
+impl hash::Hash for OwnedNode {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.node().hash(state)
+    }
+}
+
+impl fmt::Debug for OwnedNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{{OwnedNode {:?}}}", self.node())
     }
 }
 
@@ -808,6 +1619,29 @@ impl<'tree> Node<'tree> {
         unsafe { ffi::ts_node_symbol(self.0) }
     }
 
+    /// Get this node's type as a numerical id as it appears in the grammar, ignoring
+    /// aliases. This is the id that should be passed to [Language::next_state].
+    #[doc(alias = "ts_node_grammar_symbol")]
+    pub fn grammar_id(&self) -> u16 {
+        unsafe { ffi::ts_node_grammar_symbol(self.0) }
+    }
+
+    /// Get the parse state of this node, or `u16::MAX` if the state is not known.
+    ///
+    /// Use [Language::lookahead_iterator] to list the symbols that are valid in this
+    /// state. For an `ERROR` node, the state of its first leaf describes what the
+    /// parser expected at the start of the error.
+    #[doc(alias = "ts_node_parse_state")]
+    pub fn parse_state(&self) -> u16 {
+        unsafe { ffi::ts_node_parse_state(self.0) }
+    }
+
+    /// Get the parse state after this node, or `u16::MAX` if the state is not known.
+    #[doc(alias = "ts_node_next_parse_state")]
+    pub fn next_parse_state(&self) -> u16 {
+        unsafe { ffi::ts_node_next_parse_state(self.0) }
+    }
+
     /// Get this node's type as a string.
     #[doc(alias = "ts_node_type")]
     pub fn kind(&self) -> &'static str {
@@ -948,6 +1782,31 @@ impl<'tree> Node<'tree> {
         unsafe { ffi::ts_node_named_child_count(self.0) as usize }
     }
 
+    /// Get the number of nodes within this node's subtree, including this node itself.
+    ///
+    /// Only the nodes that are visited by a [TreeCursor] are counted.
+    #[doc(alias = "ts_node_descendant_count")]
+    pub fn descendant_count(&self) -> usize {
+        unsafe { ffi::ts_node_descendant_count(self.0) as usize }
+    }
+
+    /// Get statistics about this node and its descendants.
+    ///
+    /// If `old_tree` is given, the statistics also count the nodes in this subtree
+    /// that are shared with it. When this node's tree was parsed incrementally from
+    /// `old_tree`, these are the nodes that were reused rather than created.
+    #[doc(alias = "ts_node_stats")]
+    pub fn stats(&self, old_tree: Option<&Tree>) -> NodeStats {
+        let old_tree = old_tree.map_or(ptr::null(), |tree| tree.0.as_ptr() as *const _);
+        let stats = unsafe { ffi::ts_node_stats(self.0, old_tree) };
+        NodeStats {
+            node_count: stats.node_count as usize,
+            reused_node_count: stats.reused_node_count as usize,
+            depth: stats.depth as usize,
+            memory_usage: stats.memory_usage,
+        }
+    }
+
     /// Get the first child with the given field name.
     ///
     /// If multiple children may have the same field name, access them using
@@ -1142,20 +2001,138 @@ impl<'tree> Node<'tree> {
         result
     }
 
-    pub fn utf8_text<'a>(&self, source: &'a [u8]) -> Result<&'a str, str::Utf8Error> {
-        str::from_utf8(&source[self.start_byte()..self.end_byte()])
+    /// Get an S-expression representing the node, printed according to the given
+    /// options.
+    pub fn to_sexp_with(&self, options: &SexpOptions) -> String {
+        let mut result = String::new();
+        self.write_sexp(&mut result, options).unwrap();
+        result
     }
 
-    pub fn utf16_text<'a>(&self, source: &'a [u16]) -> &'a [u16] {
-        &source.as_ref()[self.start_byte()..self.end_byte()]
+    /// Write an S-expression representing the node, printed according to the given
+    /// options.
+    pub fn write_sexp(&self, output: &mut impl fmt::Write, options: &SexpOptions) -> fmt::Result {
+        sexp::write_sexp(*self, output, options)
     }
 
-    /// Create a new [TreeCursor] starting from this node.
-    #[doc(alias = "ts_tree_cursor_new")]
+    /// Describe every syntax error within this node, in the order in which they appear
+    /// in the source code.
+    ///
+    /// Each `ERROR` node produces one error, which describes the first token within it
+    /// that the parser could not accept, along with the kinds of nodes that it expected
+    /// instead. Each `MISSING` node produces an error that describes the missing node.
+    pub fn syntax_errors(&self, source: &[u8]) -> Vec<SyntaxError<'tree>> {
+        diagnostics::syntax_errors(*self, source)
+    }
+
+    pub fn utf8_text<'a>(&self, source: &'a [u8]) -> Result<&'a str, str::Utf8Error> {
+        str::from_utf8(&source[self.start_byte()..self.end_byte()])
+    }
+
+    pub fn utf16_text<'a>(&self, source: &'a [u16]) -> &'a [u16] {
+        &source.as_ref()[self.start_byte()..self.end_byte()]
+    }
+
+    /// Get the text of this node from a [TextProvider], such as a [Document] or a
+    /// function that returns the chunks of a rope, without copying the source code
+    /// into a single slice.
+    ///
+    /// The text is returned as a single string, which is only borrowed if the node's
+    /// text is contained in one chunk. Invalid UTF-8 is replaced with
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn utf8_text_with<'a>(&self, text_provider: impl TextProvider<'a>) -> Cow<'a, str> {
+        let mut chunks = self.utf8_text_chunks(text_provider);
+        let first_chunk = chunks.next().unwrap_or_default();
+        match chunks.next() {
+            Some(next_chunk) => {
+                let mut result = first_chunk.into_owned();
+                result.push_str(&next_chunk);
+                chunks.for_each(|chunk| result.push_str(&chunk));
+                Cow::Owned(result)
+            }
+            None => first_chunk,
+        }
+    }
+
+    /// Get the text of this node from a [TextProvider] as a sequence of strings, one
+    /// for each chunk of the source code that the node spans.
+    ///
+    /// Each chunk is borrowed unless it contains part of a character that is split
+    /// between chunks. Invalid UTF-8 is replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn utf8_text_chunks<'a>(
+        &self,
+        mut text_provider: impl TextProvider<'a>,
+    ) -> impl Iterator<Item = Cow<'a, str>> + 'a {
+        util::Utf8Chunks::new(text_provider.text(*self))
+    }
+
+    /// Create a new [TreeCursor] starting from this node.
+    #[doc(alias = "ts_tree_cursor_new")]
     pub fn walk(&self) -> TreeCursor<'tree> {
         TreeCursor(unsafe { ffi::ts_tree_cursor_new(self.0) }, PhantomData)
     }
 
+    /// Walk this node and all of its descendants in depth-first order, invoking the
+    /// given [Visitor]'s callbacks for each node.
+    ///
+    /// Depths are relative to this node, which has a depth of zero. The visitor can
+    /// skip the children of any node, or stop the walk entirely, by returning the
+    /// corresponding [VisitAction] from [Visitor::enter_node]. Once the walk is stopped,
+    /// no further callbacks are invoked.
+    pub fn visit(&self, visitor: &mut impl Visitor<'tree>) {
+        let mut cursor = self.walk();
+        let mut depth = 0;
+        loop {
+            let node = cursor.node();
+            let field_name = cursor.field_name();
+            match visitor.enter_node(node, field_name, depth) {
+                VisitAction::Stop => return,
+                VisitAction::Continue if cursor.goto_first_child() => {
+                    depth += 1;
+                    continue;
+                }
+                _ => visitor.leave_node(node, field_name, depth),
+            }
+            loop {
+                if depth == 0 {
+                    return;
+                }
+                if cursor.goto_next_sibling() {
+                    break;
+                }
+                cursor.goto_parent();
+                depth -= 1;
+                visitor.leave_node(cursor.node(), cursor.field_name(), depth);
+            }
+        }
+    }
+
+    /// Iterate over this node and all of its descendants, yielding each node before its
+    /// children.
+    ///
+    /// Use [PreorderTraversal::skip_children] to avoid descending into the node that
+    /// was most recently yielded.
+    pub fn preorder(&self) -> PreorderTraversal<'tree> {
+        PreorderTraversal {
+            cursor: self.walk(),
+            depth: 0,
+            started: false,
+            skip_children: false,
+            done: false,
+        }
+    }
+
+    /// Iterate over this node and all of its descendants, yielding each node after its
+    /// children.
+    pub fn postorder(&self) -> PostorderTraversal<'tree> {
+        PostorderTraversal {
+            cursor: self.walk(),
+            depth: 0,
+            started: false,
+            done: false,
+        }
+    }
+
     /// Edit this node to keep it in-sync with source code that has been edited.
     ///
     /// This function is only rarely needed. When you edit a syntax tree with the
@@ -1265,6 +2242,53 @@ impl<'a> TreeCursor<'a> {
         return unsafe { ffi::ts_tree_cursor_goto_next_sibling(&mut self.0) };
     }
 
+    /// Move this cursor to the last child of its current node.
+    ///
+    /// This returns `true` if the cursor successfully moved, and returns `false`
+    /// if there were no children.
+    ///
+    /// Note that this may be slower than [TreeCursor::goto_first_child], because it
+    /// needs to iterate through all of the children to find the last one.
+    #[doc(alias = "ts_tree_cursor_goto_last_child")]
+    pub fn goto_last_child(&mut self) -> bool {
+        unsafe { ffi::ts_tree_cursor_goto_last_child(&mut self.0) }
+    }
+
+    /// Move this cursor to the previous sibling of its current node.
+    ///
+    /// This returns `true` if the cursor successfully moved, and returns `false`
+    /// if there was no previous sibling node.
+    ///
+    /// Note that this may be slower than [TreeCursor::goto_next_sibling], because it
+    /// needs to iterate through the earlier siblings to find the previous one.
+    #[doc(alias = "ts_tree_cursor_goto_previous_sibling")]
+    pub fn goto_previous_sibling(&mut self) -> bool {
+        unsafe { ffi::ts_tree_cursor_goto_previous_sibling(&mut self.0) }
+    }
+
+    /// Move this cursor to the node that is the nth descendant of the node that the
+    /// cursor was constructed with, in pre-order. Zero represents the original node.
+    ///
+    /// If the index is out of range, the cursor is moved to the original node.
+    #[doc(alias = "ts_tree_cursor_goto_descendant")]
+    pub fn goto_descendant(&mut self, descendant_index: usize) {
+        unsafe { ffi::ts_tree_cursor_goto_descendant(&mut self.0, descendant_index as u32) }
+    }
+
+    /// Get the index of the cursor's current node out of all of the descendants of
+    /// the node that the cursor was constructed with, in pre-order.
+    #[doc(alias = "ts_tree_cursor_current_descendant_index")]
+    pub fn descendant_index(&self) -> usize {
+        unsafe { ffi::ts_tree_cursor_current_descendant_index(&self.0) as usize }
+    }
+
+    /// Get the depth of the cursor's current node relative to the node that the
+    /// cursor was constructed with.
+    #[doc(alias = "ts_tree_cursor_current_depth")]
+    pub fn depth(&self) -> usize {
+        unsafe { ffi::ts_tree_cursor_current_depth(&self.0) as usize }
+    }
+
     /// Move this cursor to the first child of its current node that extends beyond
     /// the given byte offset.
     ///
@@ -1304,6 +2328,97 @@ impl<'a> TreeCursor<'a> {
     }
 }
 
+impl<'tree> PreorderTraversal<'tree> {
+    /// Get the depth of the most recently yielded node, relative to the node where the
+    /// traversal started.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the field name of the most recently yielded node.
+    pub fn field_name(&self) -> Option<&'static str> {
+        self.cursor.field_name()
+    }
+
+    /// Don't descend into the children of the most recently yielded node.
+    pub fn skip_children(&mut self) {
+        self.skip_children = true;
+    }
+}
+
+impl<'tree> Iterator for PreorderTraversal<'tree> {
+    type Item = Node<'tree>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            return Some(self.cursor.node());
+        }
+        if !self.skip_children && self.cursor.goto_first_child() {
+            self.depth += 1;
+            return Some(self.cursor.node());
+        }
+        self.skip_children = false;
+        while self.depth > 0 {
+            if self.cursor.goto_next_sibling() {
+                return Some(self.cursor.node());
+            }
+            self.cursor.goto_parent();
+            self.depth -= 1;
+        }
+        self.done = true;
+        None
+    }
+}
+
+impl<'tree> PostorderTraversal<'tree> {
+    /// Get the depth of the most recently yielded node, relative to the node where the
+    /// traversal started.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the field name of the most recently yielded node.
+    pub fn field_name(&self) -> Option<&'static str> {
+        self.cursor.field_name()
+    }
+
+    fn descend(&mut self) -> Node<'tree> {
+        while self.cursor.goto_first_child() {
+            self.depth += 1;
+        }
+        self.cursor.node()
+    }
+}
+
+impl<'tree> Iterator for PostorderTraversal<'tree> {
+    type Item = Node<'tree>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            return Some(self.descend());
+        }
+        if self.depth == 0 {
+            self.done = true;
+            return None;
+        }
+        if self.cursor.goto_next_sibling() {
+            Some(self.descend())
+        } else {
+            self.cursor.goto_parent();
+            self.depth -= 1;
+            Some(self.cursor.node())
+        }
+    }
+}
+
 impl<'a> Clone for TreeCursor<'a> {
     fn clone(&self) -> Self {
         TreeCursor(unsafe { ffi::ts_tree_cursor_copy(&self.0) }, PhantomData)
@@ -1316,6 +2431,74 @@ impl<'a> Drop for TreeCursor<'a> {
     }
 }
 
+impl LookaheadIterator {
+    /// Get the current language of this lookahead iterator.
+    #[doc(alias = "ts_lookahead_iterator_language")]
+    pub fn language(&self) -> Language {
+        Language(unsafe { ffi::ts_lookahead_iterator_language(self.0.as_ptr()) })
+    }
+
+    /// Get the current symbol of this lookahead iterator.
+    #[doc(alias = "ts_lookahead_iterator_current_symbol")]
+    pub fn current_symbol(&self) -> u16 {
+        unsafe { ffi::ts_lookahead_iterator_current_symbol(self.0.as_ptr()) }
+    }
+
+    /// Get the name of the current symbol of this lookahead iterator.
+    #[doc(alias = "ts_lookahead_iterator_current_symbol_name")]
+    pub fn current_symbol_name(&self) -> &'static str {
+        unsafe {
+            CStr::from_ptr(ffi::ts_lookahead_iterator_current_symbol_name(
+                self.0.as_ptr(),
+            ))
+        }
+        .to_str()
+        .unwrap()
+    }
+
+    /// Reset this lookahead iterator to another parse state of the same language.
+    ///
+    /// This returns `true` if the iterator was reset to the given state, and `false`
+    /// if the state is not valid.
+    #[doc(alias = "ts_lookahead_iterator_reset_state")]
+    pub fn reset_state(&mut self, state: u16) -> bool {
+        unsafe { ffi::ts_lookahead_iterator_reset_state(self.0.as_ptr(), state) }
+    }
+
+    /// Reset this lookahead iterator to a parse state of another language.
+    ///
+    /// This returns `true` if the iterator was reset, and `false` if the state is not
+    /// valid for the given language.
+    #[doc(alias = "ts_lookahead_iterator_reset")]
+    pub fn reset(&mut self, language: Language, state: u16) -> bool {
+        unsafe { ffi::ts_lookahead_iterator_reset(self.0.as_ptr(), language.0, state) }
+    }
+
+    /// Iterate over the names of the valid symbols, rather than their ids.
+    pub fn iter_names(&mut self) -> impl Iterator<Item = &'static str> + '_ {
+        iter::from_fn(move || self.next().map(|_| self.current_symbol_name()))
+    }
+}
+
+impl Iterator for LookaheadIterator {
+    type Item = u16;
+
+    #[doc(alias = "ts_lookahead_iterator_next")]
+    fn next(&mut self) -> Option<Self::Item> {
+        if unsafe { ffi::ts_lookahead_iterator_next(self.0.as_ptr()) } {
+            Some(self.current_symbol())
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for LookaheadIterator {
+    fn drop(&mut self) {
+        unsafe { ffi::ts_lookahead_iterator_delete(self.0.as_ptr()) }
+    }
+}
+
 impl Query {
     /// Create a new query from a string containing one or more S-expression
     /// patterns.
@@ -1324,6 +2507,19 @@ impl Query {
     /// on syntax nodes parsed with that language. References to Queries can be
     /// shared between multiple threads.
     pub fn new(language: Language, source: &str) -> Result<Self, QueryError> {
+        Self::with_predicates(language, source, &QueryPredicates::new())
+    }
+
+    /// Create a new query whose patterns can use the custom predicates in the given
+    /// registry.
+    ///
+    /// Returns an error if one of the patterns uses a registered predicate with the
+    /// wrong number or types of arguments.
+    pub fn with_predicates(
+        language: Language,
+        source: &str,
+        predicates: &QueryPredicates,
+    ) -> Result<Self, QueryError> {
         let mut error_offset = 0u32;
         let mut error_type: ffi::TSQueryError = 0;
         let bytes = source.as_bytes();
@@ -1341,75 +2537,7 @@ impl Query {
 
         // On failure, build an error based on the error code and offset.
         if ptr.is_null() {
-            if error_type == ffi::TSQueryError_TSQueryErrorLanguage {
-                return Err(QueryError {
-                    row: 0,
-                    column: 0,
-                    offset: 0,
-                    message: LanguageError {
-                        version: language.version(),
-                    }
-                    .to_string(),
-                    kind: QueryErrorKind::Language,
-                });
-            }
-
-            let offset = error_offset as usize;
-            let mut line_start = 0;
-            let mut row = 0;
-            let mut line_containing_error = None;
-            for line in source.split("\n") {
-                let line_end = line_start + line.len() + 1;
-                if line_end > offset {
-                    line_containing_error = Some(line);
-                    break;
-                }
-                line_start = line_end;
-                row += 1;
-            }
-            let column = offset - line_start;
-
-            let kind;
-            let message;
-            match error_type {
-                // Error types that report names
-                ffi::TSQueryError_TSQueryErrorNodeType
-                | ffi::TSQueryError_TSQueryErrorField
-                | ffi::TSQueryError_TSQueryErrorCapture => {
-                    let suffix = source.split_at(offset).1;
-                    let end_offset = suffix
-                        .find(|c| !char::is_alphanumeric(c) && c != '_' && c != '-')
-                        .unwrap_or(source.len());
-                    message = suffix.split_at(end_offset).0.to_string();
-                    kind = match error_type {
-                        ffi::TSQueryError_TSQueryErrorNodeType => QueryErrorKind::NodeType,
-                        ffi::TSQueryError_TSQueryErrorField => QueryErrorKind::Field,
-                        ffi::TSQueryError_TSQueryErrorCapture => QueryErrorKind::Capture,
-                        _ => unreachable!(),
-                    };
-                }
-
-                // Error types that report positions
-                _ => {
-                    message = if let Some(line) = line_containing_error {
-                        line.to_string() + "\n" + &" ".repeat(offset - line_start) + "^"
-                    } else {
-                        "Unexpected EOF".to_string()
-                    };
-                    kind = match error_type {
-                        ffi::TSQueryError_TSQueryErrorStructure => QueryErrorKind::Structure,
-                        _ => QueryErrorKind::Syntax,
-                    };
-                }
-            };
-
-            return Err(QueryError {
-                row,
-                column,
-                offset,
-                kind,
-                message,
-            });
+            return Err(query_error(language, source, error_offset, error_type));
         }
 
         let string_count = unsafe { ffi::ts_query_string_count(ptr) };
@@ -1423,6 +2551,7 @@ impl Query {
             property_predicates: Vec::with_capacity(pattern_count),
             property_settings: Vec::with_capacity(pattern_count),
             general_predicates: Vec::with_capacity(pattern_count),
+            custom_predicates: Vec::with_capacity(pattern_count),
         };
 
         // Build a vector of strings to store the capture names.
@@ -1490,6 +2619,7 @@ impl Query {
             let mut property_predicates = Vec::new();
             let mut property_settings = Vec::new();
             let mut general_predicates = Vec::new();
+            let mut custom_predicates = Vec::new();
             for p in predicate_steps.split(|s| s.type_ == type_done) {
                 if p.is_empty() {
                     continue;
@@ -1508,60 +2638,73 @@ impl Query {
                 // Build a predicate for each of the known predicate function names.
                 let operator_name = &string_values[p[0].value_id as usize];
                 match operator_name.as_str() {
-                    "eq?" | "not-eq?" => {
+                    "eq?" | "not-eq?" | "any-eq?" | "any-not-eq?" => {
                         if p.len() != 3 {
                             return Err(predicate_error(
                                 row,
                                 format!(
-                                "Wrong number of arguments to #eq? predicate. Expected 2, got {}.",
-                                p.len() - 1
-                            ),
+                                    "Wrong number of arguments to #{} predicate. Expected 2, got {}.",
+                                    operator_name,
+                                    p.len() - 1
+                                ),
                             ));
                         }
                         if p[1].type_ != type_capture {
                             return Err(predicate_error(row, format!(
-                                "First argument to #eq? predicate must be a capture name. Got literal \"{}\".",
+                                "First argument to #{} predicate must be a capture name. Got literal \"{}\".",
+                                operator_name,
                                 string_values[p[1].value_id as usize],
                             )));
                         }
 
-                        let is_positive = operator_name == "eq?";
+                        let is_positive = operator_name == "eq?" || operator_name == "any-eq?";
+                        let match_all = !operator_name.starts_with("any-");
                         text_predicates.push(if p[2].type_ == type_capture {
                             TextPredicate::CaptureEqCapture(
                                 p[1].value_id,
                                 p[2].value_id,
                                 is_positive,
+                                match_all,
                             )
                         } else {
                             TextPredicate::CaptureEqString(
                                 p[1].value_id,
                                 string_values[p[2].value_id as usize].clone(),
                                 is_positive,
+                                match_all,
                             )
                         });
                     }
 
-                    "match?" | "not-match?" => {
+                    "match?" | "not-match?" | "any-match?" | "any-not-match?" => {
                         if p.len() != 3 {
-                            return Err(predicate_error(row, format!(
-                                "Wrong number of arguments to #match? predicate. Expected 2, got {}.",
+                            return Err(predicate_error(
+                                row,
+                                format!(
+                                "Wrong number of arguments to #{} predicate. Expected 2, got {}.",
+                                operator_name,
                                 p.len() - 1
-                            )));
+                            ),
+                            ));
                         }
                         if p[1].type_ != type_capture {
                             return Err(predicate_error(row, format!(
-                                "First argument to #match? predicate must be a capture name. Got literal \"{}\".",
+                                "First argument to #{} predicate must be a capture name. Got literal \"{}\".",
+                                operator_name,
                                 string_values[p[1].value_id as usize],
                             )));
                         }
                         if p[2].type_ == type_capture {
                             return Err(predicate_error(row, format!(
-                                "Second argument to #match? predicate must be a literal. Got capture @{}.",
+                                "Second argument to #{} predicate must be a literal. Got capture @{}.",
+                                operator_name,
                                 result.capture_names[p[2].value_id as usize],
                             )));
                         }
 
-                        let is_positive = operator_name == "match?";
+                        let is_positive =
+                            operator_name == "match?" || operator_name == "any-match?";
+                        let match_all = !operator_name.starts_with("any-");
                         let regex = &string_values[p[2].value_id as usize];
                         text_predicates.push(TextPredicate::CaptureMatchString(
                             p[1].value_id,
@@ -1569,6 +2712,44 @@ impl Query {
                                 predicate_error(row, format!("Invalid regex '{}'", regex))
                             })?,
                             is_positive,
+                            match_all,
+                        ));
+                    }
+
+                    "any-of?" | "not-any-of?" => {
+                        if p.len() < 3 {
+                            return Err(predicate_error(row, format!(
+                                "Wrong number of arguments to #{} predicate. Expected at least 2, got {}.",
+                                operator_name,
+                                p.len() - 1
+                            )));
+                        }
+                        if p[1].type_ != type_capture {
+                            return Err(predicate_error(row, format!(
+                                "First argument to #{} predicate must be a capture name. Got literal \"{}\".",
+                                operator_name,
+                                string_values[p[1].value_id as usize],
+                            )));
+                        }
+
+                        let mut values = Vec::with_capacity(p.len() - 2);
+                        for arg in &p[2..] {
+                            if arg.type_ == type_capture {
+                                return Err(predicate_error(
+                                    row,
+                                    format!(
+                                    "Arguments to #{} predicate must be literals. Got capture @{}.",
+                                    operator_name,
+                                    result.capture_names[arg.value_id as usize],
+                                ),
+                                ));
+                            }
+                            values.push(string_values[arg.value_id as usize].clone());
+                        }
+                        text_predicates.push(TextPredicate::CaptureAnyString(
+                            p[1].value_id,
+                            values.into_boxed_slice(),
+                            operator_name == "any-of?",
                         ));
                     }
 
@@ -1591,21 +2772,39 @@ impl Query {
                         operator_name == "is?",
                     )),
 
-                    _ => general_predicates.push(QueryPredicate {
-                        operator: operator_name.clone().into_boxed_str(),
-                        args: p[1..]
-                            .iter()
-                            .map(|a| {
-                                if a.type_ == type_capture {
-                                    QueryPredicateArg::Capture(a.value_id)
-                                } else {
-                                    QueryPredicateArg::String(
-                                        string_values[a.value_id as usize].clone().into_boxed_str(),
-                                    )
-                                }
-                            })
-                            .collect(),
-                    }),
+                    _ => {
+                        let predicate = QueryPredicate {
+                            operator: operator_name.clone().into_boxed_str(),
+                            args: p[1..]
+                                .iter()
+                                .map(|a| {
+                                    if a.type_ == type_capture {
+                                        QueryPredicateArg::Capture(a.value_id)
+                                    } else {
+                                        QueryPredicateArg::String(
+                                            string_values[a.value_id as usize]
+                                                .clone()
+                                                .into_boxed_str(),
+                                        )
+                                    }
+                                })
+                                .collect(),
+                        };
+                        match predicates.predicates.get(operator_name) {
+                            Some(custom_predicate) => {
+                                custom_predicate.validate(
+                                    row,
+                                    &predicate,
+                                    &result.capture_names,
+                                )?;
+                                custom_predicates.push(CustomPredicateCall {
+                                    predicate,
+                                    function: custom_predicate.function.clone(),
+                                });
+                            }
+                            None => general_predicates.push(predicate),
+                        }
+                    }
                 }
             }
 
@@ -1621,10 +2820,43 @@ impl Query {
             result
                 .general_predicates
                 .push(general_predicates.into_boxed_slice());
+            result
+                .custom_predicates
+                .push(custom_predicates.into_boxed_slice());
         }
         Ok(result)
     }
 
+    /// Get the tokens of a query's source code, in order, including its comments.
+    ///
+    /// The source code is parsed in the same way as by [Query::new], so this is useful
+    /// for tools that need the positions of a query's elements, such as formatters.
+    /// Every character of the source code, except for whitespace, is part of exactly
+    /// one token. Returns an error if the query is invalid.
+    #[doc(alias = "ts_query_tokenize")]
+    pub fn tokenize(language: Language, source: &str) -> Result<Vec<QueryToken>, QueryError> {
+        let mut token_count = 0u32;
+        let mut error_offset = 0u32;
+        let mut error_type: ffi::TSQueryError = 0;
+        let bytes = source.as_bytes();
+        let ptr = unsafe {
+            ffi::ts_query_tokenize(
+                language.0,
+                bytes.as_ptr() as *const c_char,
+                bytes.len() as u32,
+                &mut token_count as *mut u32,
+                &mut error_offset as *mut u32,
+                &mut error_type as *mut ffi::TSQueryError,
+            )
+        };
+        if error_type != ffi::TSQueryError_TSQueryErrorNone {
+            return Err(query_error(language, source, error_offset, error_type));
+        }
+        Ok(unsafe { util::CBufferIter::new(ptr, token_count as usize) }
+            .map(QueryToken::from)
+            .collect())
+    }
+
     /// Get the byte offset where the given pattern starts in the query's source.
     #[doc(alias = "ts_query_start_byte_for_pattern")]
     pub fn start_byte_for_pattern(&self, pattern_index: usize) -> usize {
@@ -1681,10 +2913,14 @@ impl Query {
     /// Get the other user-defined predicates associated with the given index.
     ///
     /// This includes predicate with operators other than:
-    /// * `match?`
-    /// * `eq?` and `not-eq?`
+    /// * `match?`, `not-match?`, `any-match?` and `any-not-match?`
+    /// * `eq?`, `not-eq?`, `any-eq?` and `any-not-eq?`
+    /// * `any-of?` and `not-any-of?`
     /// * `is?` and `is-not?`
     /// * `set!`
+    ///
+    /// Predicates that were registered in the [QueryPredicates] that were used to
+    /// create the query are not included, because they are evaluated automatically.
     pub fn general_predicates(&self, index: usize) -> &[QueryPredicate] {
         &self.general_predicates[index]
     }
@@ -1801,6 +3037,18 @@ impl QueryCursor {
     pub fn new() -> Self {
         QueryCursor {
             ptr: unsafe { NonNull::new_unchecked(ffi::ts_query_cursor_new()) },
+            _allocator: None,
+        }
+    }
+
+    /// Create a new cursor that uses the given allocator for all of its memory,
+    /// instead of the global allocation functions.
+    #[doc(alias = "ts_query_cursor_new_with_allocator")]
+    pub fn with_allocator<A: Allocator + 'static>(allocator: Arc<A>) -> Self {
+        let cursor = unsafe { ffi::ts_query_cursor_new_with_allocator(c_allocator(&allocator)) };
+        QueryCursor {
+            ptr: unsafe { NonNull::new_unchecked(cursor) },
+            _allocator: Some(allocator),
         }
     }
 
@@ -1966,45 +3214,214 @@ impl<'a, 'tree> QueryMatch<'a, 'tree> {
             }
         }
 
+        // A predicate on a quantified capture is checked against each of the captured
+        // nodes. If the capture did not match any nodes, the predicate is satisfied.
+        fn check<T>(
+            items: impl Iterator<Item = T>,
+            match_all: bool,
+            f: impl FnMut(T) -> bool,
+        ) -> bool {
+            let mut items = items.peekable();
+            if items.peek().is_none() {
+                true
+            } else if match_all {
+                items.all(f)
+            } else {
+                items.any(f)
+            }
+        }
+
         query.text_predicates[self.pattern_index]
             .iter()
             .all(|predicate| match predicate {
-                TextPredicate::CaptureEqCapture(i, j, is_positive) => {
-                    let node1 = self.nodes_for_capture_index(*i).next();
-                    let node2 = self.nodes_for_capture_index(*j).next();
-                    match (node1, node2) {
-                        (Some(node1), Some(node2)) => {
-                            let text1 = get_text(buffer1, text_provider.text(node1));
-                            let text2 = get_text(buffer2, text_provider.text(node2));
-                            (text1 == text2) == *is_positive
+                TextPredicate::CaptureEqCapture(i, j, is_positive, match_all) => {
+                    // The captured nodes are compared in pairs, by position. When every
+                    // pair must satisfy the predicate, non-empty captures with different
+                    // numbers of nodes are unequal. Otherwise, the extra nodes are ignored.
+                    if *match_all {
+                        let count1 = self.nodes_for_capture_index(*i).count();
+                        let count2 = self.nodes_for_capture_index(*j).count();
+                        if count1 > 0 && count2 > 0 && count1 != count2 {
+                            return !*is_positive;
                         }
-                        _ => true,
                     }
+                    let nodes1 = self.nodes_for_capture_index(*i);
+                    let nodes2 = self.nodes_for_capture_index(*j);
+                    check(nodes1.zip(nodes2), *match_all, |(node1, node2)| {
+                        let text1 = get_text(buffer1, text_provider.text(node1));
+                        let text2 = get_text(buffer2, text_provider.text(node2));
+                        (text1 == text2) == *is_positive
+                    })
                 }
-                TextPredicate::CaptureEqString(i, s, is_positive) => {
-                    let node = self.nodes_for_capture_index(*i).next();
-                    match node {
-                        Some(node) => {
-                            let text = get_text(buffer1, text_provider.text(node));
-                            (text == s.as_bytes()) == *is_positive
-                        }
-                        None => true,
-                    }
+                TextPredicate::CaptureEqString(i, s, is_positive, match_all) => {
+                    check(self.nodes_for_capture_index(*i), *match_all, |node| {
+                        let text = get_text(buffer1, text_provider.text(node));
+                        (text == s.as_bytes()) == *is_positive
+                    })
+                }
+                TextPredicate::CaptureMatchString(i, r, is_positive, match_all) => {
+                    check(self.nodes_for_capture_index(*i), *match_all, |node| {
+                        let text = get_text(buffer1, text_provider.text(node));
+                        r.is_match(text) == *is_positive
+                    })
+                }
+                TextPredicate::CaptureAnyString(i, values, is_positive) => {
+                    check(self.nodes_for_capture_index(*i), true, |node| {
+                        let text = get_text(buffer1, text_provider.text(node));
+                        values.iter().any(|value| text == value.as_bytes()) == *is_positive
+                    })
                 }
-                TextPredicate::CaptureMatchString(i, r, is_positive) => {
-                    let node = self.nodes_for_capture_index(*i).next();
-                    match node {
-                        Some(node) => {
-                            let text = get_text(buffer1, text_provider.text(node));
-                            r.is_match(text) == *is_positive
+            })
+            && self.satisfies_custom_predicates(query, text_provider)
+    }
+
+    fn satisfies_custom_predicates(
+        &self,
+        query: &Query,
+        text_provider: &mut impl TextProvider<'a>,
+    ) -> bool {
+        let text_provider = RefCell::new(text_provider);
+        query.custom_predicates[self.pattern_index]
+            .iter()
+            .all(|custom_predicate| {
+                // The captured nodes' text is only read if the predicate asks for it.
+                let read_text = |node: Node| {
+                    let mut chunks = text_provider.borrow_mut().text(node);
+                    let first_chunk = chunks.next().unwrap_or(&[]);
+                    match chunks.next() {
+                        Some(next_chunk) => {
+                            let mut result = first_chunk.to_vec();
+                            result.extend_from_slice(next_chunk);
+                            chunks.for_each(|chunk| result.extend_from_slice(chunk));
+                            Cow::Owned(result)
                         }
-                        None => true,
+                        None => Cow::Borrowed(first_chunk),
                     }
-                }
+                };
+                let captures = custom_predicate
+                    .predicate
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        QueryPredicateArg::Capture(i) => self
+                            .nodes_for_capture_index(*i)
+                            .map(|node| PredicateCapture {
+                                node,
+                                read_text: &read_text,
+                            })
+                            .collect(),
+                        QueryPredicateArg::String(_) => Vec::new(),
+                    })
+                    .collect::<Vec<_>>();
+                let args = custom_predicate
+                    .predicate
+                    .args
+                    .iter()
+                    .zip(&captures)
+                    .map(|(arg, captures)| match arg {
+                        QueryPredicateArg::Capture(_) => PredicateArg::Capture(captures),
+                        QueryPredicateArg::String(s) => PredicateArg::String(s),
+                    })
+                    .collect::<Vec<_>>();
+                (custom_predicate.function)(&args)
             })
     }
 }
 
+impl<'a, 'tree> PredicateCapture<'a, 'tree> {
+    /// Get the text of the captured node from the query cursor's [TextProvider].
+    ///
+    /// The text is read each time this is called. It is only borrowed if it is
+    /// contained in one chunk.
+    pub fn text(&self) -> Cow<'a, [u8]> {
+        (self.read_text)(self.node)
+    }
+}
+
+impl QueryPredicates {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a predicate with the given operator name, such as `contains?`, which
+    /// accepts arguments of the given types.
+    ///
+    /// When a match is checked, the function is called with the predicate's arguments,
+    /// and the match is skipped if the function returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is the name of a built-in predicate, such as `eq?` or `set!`.
+    pub fn register(
+        &mut self,
+        name: &str,
+        arg_types: &[PredicateArgType],
+        function: impl Fn(&[PredicateArg]) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        if BUILT_IN_PREDICATES.contains(&name) {
+            panic!("Cannot register the built-in predicate #{}", name);
+        }
+        self.predicates.insert(
+            name.to_string(),
+            CustomPredicate {
+                arg_types: arg_types.into(),
+                function: Arc::new(function),
+            },
+        );
+        self
+    }
+}
+
+impl CustomPredicate {
+    fn validate(
+        &self,
+        row: usize,
+        predicate: &QueryPredicate,
+        capture_names: &[String],
+    ) -> Result<(), QueryError> {
+        if predicate.args.len() != self.arg_types.len() {
+            return Err(predicate_error(
+                row,
+                format!(
+                    "Wrong number of arguments to #{} predicate. Expected {}, got {}.",
+                    predicate.operator,
+                    self.arg_types.len(),
+                    predicate.args.len(),
+                ),
+            ));
+        }
+        for (i, (arg, arg_type)) in predicate.args.iter().zip(self.arg_types.iter()).enumerate() {
+            match (arg, arg_type) {
+                (QueryPredicateArg::String(value), PredicateArgType::Capture) => {
+                    return Err(predicate_error(
+                        row,
+                        format!(
+                            "Argument {} to #{} predicate must be a capture name. Got literal \"{}\".",
+                            i + 1,
+                            predicate.operator,
+                            value,
+                        ),
+                    ))
+                }
+                (QueryPredicateArg::Capture(id), PredicateArgType::String) => {
+                    return Err(predicate_error(
+                        row,
+                        format!(
+                            "Argument {} to #{} predicate must be a literal. Got capture @{}.",
+                            i + 1,
+                            predicate.operator,
+                            capture_names[*id as usize],
+                        ),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
 impl QueryProperty {
     pub fn new(key: &str, value: Option<&str>, capture_id: Option<usize>) -> Self {
         QueryProperty {
@@ -2263,6 +3680,108 @@ impl<'a> Iterator for LossyUtf8<'a> {
     }
 }
 
+impl<T: AsRef<[u8]>> AsRef<[u8]> for ChunkSlice<T> {
+    fn as_ref(&self) -> &[u8] {
+        let chunk = self.chunk.as_ref();
+        let start = self.start.min(chunk.len());
+        let end = start.saturating_add(self.max_len).min(chunk.len());
+        &chunk[start..end]
+    }
+}
+
+/// Read the chunk of text that starts at the given offset, returning an empty chunk
+/// at the end of the reader.
+fn read_chunk<R: io::Read + io::Seek>(reader: &mut R, offset: usize) -> io::Result<Vec<u8>> {
+    use io::Read;
+
+    let mut result = Vec::with_capacity(READ_CHUNK_SIZE);
+    reader.seek(io::SeekFrom::Start(offset as u64))?;
+    reader
+        .take(READ_CHUNK_SIZE as u64)
+        .read_to_end(&mut result)?;
+    Ok(result)
+}
+
+/// Build an error for a query that could not be parsed, based on the error code and
+/// offset that were reported by the library.
+fn query_error(
+    language: Language,
+    source: &str,
+    error_offset: u32,
+    error_type: ffi::TSQueryError,
+) -> QueryError {
+    if error_type == ffi::TSQueryError_TSQueryErrorLanguage {
+        return QueryError {
+            row: 0,
+            column: 0,
+            offset: 0,
+            message: LanguageError {
+                version: language.version(),
+                wasm: false,
+            }
+            .to_string(),
+            kind: QueryErrorKind::Language,
+        };
+    }
+
+    let offset = error_offset as usize;
+    let mut line_start = 0;
+    let mut row = 0;
+    let mut line_containing_error = None;
+    for line in source.split("\n") {
+        let line_end = line_start + line.len() + 1;
+        if line_end > offset {
+            line_containing_error = Some(line);
+            break;
+        }
+        line_start = line_end;
+        row += 1;
+    }
+    let column = offset - line_start;
+
+    let kind;
+    let message;
+    match error_type {
+        // Error types that report names
+        ffi::TSQueryError_TSQueryErrorNodeType
+        | ffi::TSQueryError_TSQueryErrorField
+        | ffi::TSQueryError_TSQueryErrorCapture => {
+            let suffix = source.split_at(offset).1;
+            let end_offset = suffix
+                .find(|c| !char::is_alphanumeric(c) && c != '_' && c != '-')
+                .unwrap_or(source.len());
+            message = suffix.split_at(end_offset).0.to_string();
+            kind = match error_type {
+                ffi::TSQueryError_TSQueryErrorNodeType => QueryErrorKind::NodeType,
+                ffi::TSQueryError_TSQueryErrorField => QueryErrorKind::Field,
+                ffi::TSQueryError_TSQueryErrorCapture => QueryErrorKind::Capture,
+                _ => unreachable!(),
+            };
+        }
+
+        // Error types that report positions
+        _ => {
+            message = if let Some(line) = line_containing_error {
+                line.to_string() + "\n" + &" ".repeat(offset - line_start) + "^"
+            } else {
+                "Unexpected EOF".to_string()
+            };
+            kind = match error_type {
+                ffi::TSQueryError_TSQueryErrorStructure => QueryErrorKind::Structure,
+                _ => QueryErrorKind::Syntax,
+            };
+        }
+    };
+
+    QueryError {
+        row,
+        column,
+        offset,
+        kind,
+        message,
+    }
+}
+
 fn predicate_error(row: usize, message: String) -> QueryError {
     QueryError {
         kind: QueryErrorKind::Predicate,
@@ -2281,11 +3800,18 @@ impl fmt::Display for IncludedRangesError {
 
 impl fmt::Display for LanguageError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "Incompatible language version {}. Expected minimum {}, maximum {}",
-            self.version, MIN_COMPATIBLE_LANGUAGE_VERSION, LANGUAGE_VERSION,
-        )
+        if self.wasm {
+            write!(
+                f,
+                "WebAssembly language could not be instantiated in the parser's WebAssembly store",
+            )
+        } else {
+            write!(
+                f,
+                "Incompatible language version {}. Expected minimum {}, maximum {}",
+                self.version, MIN_COMPATIBLE_LANGUAGE_VERSION, LANGUAGE_VERSION,
+            )
+        }
     }
 }
 
@@ -2332,17 +3858,100 @@ pub unsafe fn set_allocator(
     ffi::ts_set_allocator(new_malloc, new_calloc, new_realloc, new_free);
 }
 
+/// A memory allocator that can be used by an individual [Parser], along with the
+/// [Tree]s that it produces, or by a [QueryCursor], instead of the global allocation
+/// functions.
+///
+/// This makes it possible to account for, and to limit, the memory that is used for
+/// each document or each request. To stop a parse once a limit is reached, set the
+/// parser's [cancellation flag](Parser::set_cancellation_flag) from within the
+/// allocator, and keep returning memory until the parse is halted.
+///
+/// # Safety
+///
+/// The methods must behave like `malloc`, `calloc`, `realloc`, and `free`. They must
+/// never return a null pointer for a non-zero size, and the memory that they return
+/// must be aligned for any type. They may be called from any thread that uses the
+/// objects that own the allocator.
+pub unsafe trait Allocator: Send + Sync {
+    /// Allocate a block of memory with the given size.
+    fn allocate(&self, size: usize) -> *mut c_void;
+
+    /// Allocate a zeroed block of memory for an array of `count` items of the given
+    /// size.
+    fn allocate_zeroed(&self, count: usize, size: usize) -> *mut c_void;
+
+    /// Resize a block of memory that was returned by this allocator, or allocate a
+    /// new block if `buffer` is null.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be null, or a block of memory returned by this allocator that
+    /// has not been deallocated.
+    unsafe fn reallocate(&self, buffer: *mut c_void, size: usize) -> *mut c_void;
+
+    /// Free a block of memory that was returned by this allocator.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be a non-null block of memory returned by this allocator that
+    /// has not already been deallocated.
+    unsafe fn deallocate(&self, buffer: *mut c_void);
+}
+
+fn c_allocator<A: Allocator>(allocator: &Arc<A>) -> ffi::TSAllocator {
+    unsafe extern "C" fn allocate<A: Allocator>(payload: *mut c_void, size: usize) -> *mut c_void {
+        (*(payload as *const A)).allocate(size)
+    }
+
+    unsafe extern "C" fn allocate_zeroed<A: Allocator>(
+        payload: *mut c_void,
+        count: usize,
+        size: usize,
+    ) -> *mut c_void {
+        (*(payload as *const A)).allocate_zeroed(count, size)
+    }
+
+    unsafe extern "C" fn reallocate<A: Allocator>(
+        payload: *mut c_void,
+        buffer: *mut c_void,
+        size: usize,
+    ) -> *mut c_void {
+        (*(payload as *const A)).reallocate(buffer, size)
+    }
+
+    unsafe extern "C" fn deallocate<A: Allocator>(payload: *mut c_void, buffer: *mut c_void) {
+        (*(payload as *const A)).deallocate(buffer)
+    }
+
+    ffi::TSAllocator {
+        payload: Arc::as_ptr(allocator) as *mut c_void,
+        allocate: Some(allocate::<A>),
+        allocate_zeroed: Some(allocate_zeroed::<A>),
+        reallocate: Some(reallocate::<A>),
+        deallocate: Some(deallocate::<A>),
+    }
+}
+
 impl error::Error for IncludedRangesError {}
 impl error::Error for LanguageError {}
 impl error::Error for QueryError {}
 
 unsafe impl Send for Language {}
+unsafe impl Send for LookaheadIterator {}
+unsafe impl Send for OwnedNode {}
 unsafe impl Send for Parser {}
 unsafe impl Send for Query {}
 unsafe impl Send for QueryCursor {}
 unsafe impl Send for Tree {}
+#[cfg(feature = "wasm")]
+unsafe impl Send for WasmStore {}
 unsafe impl Sync for Language {}
+unsafe impl Sync for LookaheadIterator {}
+unsafe impl Sync for OwnedNode {}
 unsafe impl Sync for Parser {}
 unsafe impl Sync for Query {}
 unsafe impl Sync for QueryCursor {}
 unsafe impl Sync for Tree {}
+#[cfg(feature = "wasm")]
+unsafe impl Sync for WasmStore {}
@@ -0,0 +1,258 @@
+use super::{InputEdit, Node, Tree};
+use std::collections::HashMap;
+
+/// What happened to a node from an old syntax tree after the tree was edited and
+/// reparsed, as described by a [NodeChange].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeChangeKind {
+    /// The node corresponds to a node in the new tree that starts at the same position.
+    /// Its contents may have changed, if an edit was made within it.
+    Reused,
+    /// The node corresponds to a node in the new tree that starts at a different
+    /// position, because text was inserted or removed before it.
+    Moved,
+    /// The node has no corresponding node in the new tree.
+    Removed,
+}
+
+/// The node in a new syntax tree that corresponds to a node in an old syntax tree, as
+/// produced by [Tree::map_nodes].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeChange<'old, 'new> {
+    pub kind: NodeChangeKind,
+    pub old_node: Node<'old>,
+    /// The corresponding node in the new tree, or `None` if the node was removed.
+    pub new_node: Option<Node<'new>>,
+}
+
+/// A mapping from the nodes of an old syntax tree to the nodes of a new syntax tree
+/// that was parsed after editing the old tree, as produced by [Tree::map_nodes].
+pub struct NodeMap<'old, 'new> {
+    changes: Vec<NodeChange<'old, 'new>>,
+    old_indices: HashMap<usize, usize>,
+    new_indices: HashMap<usize, usize>,
+}
+
+impl<'old, 'new> NodeMap<'old, 'new> {
+    /// Get the change for every node in the old tree, in pre-order.
+    pub fn changes(&self) -> &[NodeChange<'old, 'new>] {
+        &self.changes
+    }
+
+    /// Get the change for the given node from the old tree.
+    pub fn get(&self, old_node: Node) -> Option<&NodeChange<'old, 'new>> {
+        self.old_indices
+            .get(&old_node.id())
+            .map(|index| &self.changes[*index])
+    }
+
+    /// Get the node in the new tree that corresponds to the given node from the old
+    /// tree.
+    pub fn new_node(&self, old_node: Node) -> Option<Node<'new>> {
+        self.get(old_node).and_then(|change| change.new_node)
+    }
+
+    /// Get the node in the old tree that corresponds to the given node from the new
+    /// tree.
+    pub fn old_node(&self, new_node: Node) -> Option<Node<'old>> {
+        self.new_indices
+            .get(&new_node.id())
+            .map(|index| self.changes[*index].old_node)
+    }
+}
+
+/// Match the nodes of an old tree with the nodes of a new tree.
+///
+/// Nodes are matched in three phases:
+/// 1. Nodes whose subtrees were reused by the parser, which keep their ids.
+/// 2. Nodes of the same kind, whose ranges in the old tree correspond to their ranges
+///    in the new tree after applying the edits.
+/// 3. The remaining children of matched nodes, in order, when they have the same
+///    sequence of kinds in both trees, and their ranges overlap after applying the
+///    edits.
+pub(crate) fn map_nodes<'old, 'new>(
+    old_tree: &'old Tree,
+    edits: &[InputEdit],
+    new_tree: &'new Tree,
+) -> NodeMap<'old, 'new> {
+    let old_nodes = old_tree.preorder().collect::<Vec<_>>();
+    let new_nodes = new_tree.preorder().collect::<Vec<_>>();
+    let old_indices_by_id = indices_by_id(&old_nodes);
+    let new_indices_by_id = indices_by_id(&new_nodes);
+    let mut old_to_new = vec![None; old_nodes.len()];
+    let mut new_to_old = vec![None; new_nodes.len()];
+
+    for (i, old_node) in old_nodes.iter().enumerate() {
+        if let Some(&j) = new_indices_by_id.get(&old_node.id()) {
+            if has_same_kind(*old_node, new_nodes[j]) && new_to_old[j].is_none() {
+                old_to_new[i] = Some(j);
+                new_to_old[j] = Some(i);
+            }
+        }
+    }
+
+    let new_root = new_tree.root_node();
+    for (i, old_node) in old_nodes.iter().enumerate() {
+        if old_to_new[i].is_some() {
+            continue;
+        }
+        // Text that was inserted at the boundaries of the node may or may not have
+        // become part of it, so try each possibility, preferring the node's own text.
+        for (include_start, include_end) in
+            [(false, false), (true, false), (false, true), (true, true)]
+        {
+            let start = map_start(old_node.start_byte(), edits, include_start);
+            let end = map_end(old_node.end_byte(), edits, include_end).max(start);
+            if let Some(j) = find_node(
+                new_root,
+                start,
+                end,
+                *old_node,
+                &new_indices_by_id,
+                &new_to_old,
+            ) {
+                old_to_new[i] = Some(j);
+                new_to_old[j] = Some(i);
+                break;
+            }
+        }
+    }
+
+    let mut cursor = old_tree.walk();
+    let mut new_cursor = new_tree.walk();
+    for (i, old_node) in old_nodes.iter().enumerate() {
+        let j = match old_to_new[i] {
+            Some(j) => j,
+            None => continue,
+        };
+        let old_children = old_node
+            .children(&mut cursor)
+            .filter(|child| old_to_new[old_indices_by_id[&child.id()]].is_none())
+            .collect::<Vec<_>>();
+        let new_children = new_nodes[j]
+            .children(&mut new_cursor)
+            .filter(|child| new_to_old[new_indices_by_id[&child.id()]].is_none())
+            .collect::<Vec<_>>();
+        if old_children.len() != new_children.len()
+            || old_children
+                .iter()
+                .zip(&new_children)
+                .any(|(old_child, new_child)| !has_same_kind(*old_child, *new_child))
+        {
+            continue;
+        }
+        for (old_child, new_child) in old_children.into_iter().zip(new_children) {
+            let start = map_start(old_child.start_byte(), edits, true);
+            let end = map_end(old_child.end_byte(), edits, true);
+            if start >= new_child.end_byte() || new_child.start_byte() >= end {
+                continue;
+            }
+            let old_index = old_indices_by_id[&old_child.id()];
+            let new_index = new_indices_by_id[&new_child.id()];
+            old_to_new[old_index] = Some(new_index);
+            new_to_old[new_index] = Some(old_index);
+        }
+    }
+
+    let changes = old_nodes
+        .iter()
+        .zip(old_to_new)
+        .map(|(old_node, j)| match j {
+            Some(j) => NodeChange {
+                kind: if new_nodes[j].start_byte() == old_node.start_byte() {
+                    NodeChangeKind::Reused
+                } else {
+                    NodeChangeKind::Moved
+                },
+                old_node: *old_node,
+                new_node: Some(new_nodes[j]),
+            },
+            None => NodeChange {
+                kind: NodeChangeKind::Removed,
+                old_node: *old_node,
+                new_node: None,
+            },
+        })
+        .collect::<Vec<_>>();
+    NodeMap {
+        old_indices: old_indices_by_id,
+        new_indices: changes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, change)| Some((change.new_node?.id(), i)))
+            .collect(),
+        changes,
+    }
+}
+
+/// Find an unmatched node in the new tree with the given range and the same kind as
+/// the given node from the old tree.
+fn find_node(
+    new_root: Node,
+    start: usize,
+    end: usize,
+    old_node: Node,
+    new_indices_by_id: &HashMap<usize, usize>,
+    new_to_old: &[Option<usize>],
+) -> Option<usize> {
+    let mut candidate = new_root.descendant_for_byte_range(start, end);
+    while let Some(new_node) = candidate {
+        if new_node.start_byte() != start || new_node.end_byte() != end {
+            break;
+        }
+        let j = new_indices_by_id[&new_node.id()];
+        if has_same_kind(old_node, new_node) && new_to_old[j].is_none() {
+            return Some(j);
+        }
+        candidate = new_node.parent();
+    }
+    None
+}
+
+fn indices_by_id(nodes: &[Node]) -> HashMap<usize, usize> {
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id(), i))
+        .collect()
+}
+
+fn has_same_kind(a: Node, b: Node) -> bool {
+    a.kind_id() == b.kind_id() && a.is_named() == b.is_named()
+}
+
+/// Map the start of a node through a sequence of edits. If an edit removed the text at
+/// the start of the node, the node starts where the edit started. Text that was
+/// inserted at the start of the node is treated as part of the node if
+/// `include_insertions` is set.
+fn map_start(mut byte: usize, edits: &[InputEdit], include_insertions: bool) -> usize {
+    for edit in edits {
+        if include_insertions && byte == edit.start_byte && byte == edit.old_end_byte {
+            continue;
+        }
+        if byte >= edit.old_end_byte {
+            byte = byte - edit.old_end_byte + edit.new_end_byte;
+        } else if byte > edit.start_byte {
+            byte = edit.start_byte;
+        }
+    }
+    byte
+}
+
+/// Map the end of a node through a sequence of edits. If an edit replaced the text at
+/// the end of the node, the node ends where the edit's new text ends. Text that was
+/// inserted at the end of the node is treated as part of the node if
+/// `include_insertions` is set.
+fn map_end(mut byte: usize, edits: &[InputEdit], include_insertions: bool) -> usize {
+    for edit in edits {
+        if !include_insertions && byte == edit.start_byte && byte == edit.old_end_byte {
+            continue;
+        }
+        if byte >= edit.old_end_byte {
+            byte = byte - edit.old_end_byte + edit.new_end_byte;
+        } else if byte > edit.start_byte {
+            byte = edit.new_end_byte;
+        }
+    }
+    byte
+}
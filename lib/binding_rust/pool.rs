@@ -0,0 +1,217 @@
+use super::{Language, LanguageError, Parser, Tree};
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+};
+
+/// A set of parsers that can be used to process many documents in parallel.
+///
+/// A `Parser` can only be used by one thread at a time. A `ParserPool` creates up to
+/// a fixed number of parsers, and distributes a batch of work across that many threads,
+/// reusing its parsers from one batch to the next.
+pub struct ParserPool {
+    parsers: Mutex<Vec<Parser>>,
+    jobs: usize,
+    timeout_micros: u64,
+}
+
+impl ParserPool {
+    /// Create a new pool that runs the given number of jobs at once. If `jobs` is zero,
+    /// the number of jobs is based on the available parallelism of the system.
+    pub fn new(jobs: usize) -> Self {
+        let jobs = if jobs == 0 {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            jobs
+        };
+        ParserPool {
+            parsers: Mutex::new(Vec::new()),
+            jobs,
+            timeout_micros: 0,
+        }
+    }
+
+    /// Get the number of jobs that this pool runs at once.
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Set the maximum duration in microseconds that parsing each document should be
+    /// allowed to take when using [ParserPool::parse_all].
+    ///
+    /// See [Parser::set_timeout_micros].
+    pub fn set_timeout_micros(&mut self, timeout_micros: u64) {
+        self.timeout_micros = timeout_micros;
+    }
+
+    /// Get the duration in microseconds that parsing each document is allowed to take.
+    pub fn timeout_micros(&self) -> u64 {
+        self.timeout_micros
+    }
+
+    /// Parse a batch of documents in parallel, using the given language.
+    ///
+    /// If `cancellation_flags` is provided, it must contain one flag for each source.
+    /// Setting a document's flag cancels the parsing of that document only.
+    ///
+    /// The resulting trees are returned in the same order as the sources. A document's
+    /// tree is `None` if parsing it timed out, or if its cancellation flag was set while
+    /// it was being parsed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of cancellation flags differs from the number of sources.
+    pub fn parse_all<T: AsRef<[u8]> + Sync>(
+        &self,
+        language: Language,
+        sources: &[T],
+        cancellation_flags: Option<&[AtomicUsize]>,
+    ) -> Result<Vec<Option<Tree>>, LanguageError> {
+        if let Some(cancellation_flags) = cancellation_flags {
+            assert_eq!(
+                cancellation_flags.len(),
+                sources.len(),
+                "There must be one cancellation flag for each source"
+            );
+        }
+
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        self.release(parser);
+
+        let items = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| (source, cancellation_flags.map(|flags| &flags[i])))
+            .collect::<Vec<_>>();
+        Ok(self.map(&items, |parser, (source, cancellation_flag)| {
+            parser.set_language(language).unwrap();
+            parser.set_timeout_micros(self.timeout_micros);
+            unsafe { parser.set_cancellation_flag(*cancellation_flag) };
+            parser.parse(source, None)
+        }))
+    }
+
+    /// Apply the given function to each of the given items in parallel, providing it
+    /// with a parser from the pool.
+    ///
+    /// The results are returned in the same order as the items. After each call, the
+    /// parser is reset and its logger, timeout, included ranges and cancellation flag
+    /// are cleared before it is reused. The parser's language is left unchanged.
+    pub fn map<T, R, F>(&self, items: &[T], f: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&mut Parser, &T) -> R + Sync,
+    {
+        let mut results = Vec::with_capacity(items.len());
+        self.map_in_order(items, f, |result| {
+            results.push(result);
+            Ok::<_, Infallible>(())
+        })
+        .unwrap();
+        results
+    }
+
+    /// Apply the given function to each of the given items in parallel, like
+    /// [ParserPool::map], but pass each result to `handle_result` as soon as it and the
+    /// results of all of the preceding items are available.
+    ///
+    /// `handle_result` is called on the current thread, in the same order as the items.
+    /// If it returns an error, then no more items are processed, and the error is
+    /// returned once the items that are already being processed are finished.
+    pub fn map_in_order<T, R, E, F>(
+        &self,
+        items: &[T],
+        f: F,
+        mut handle_result: impl FnMut(R) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&mut Parser, &T) -> R + Sync,
+    {
+        let thread_count = self.jobs.min(items.len());
+        if thread_count <= 1 {
+            let mut parser = self.acquire();
+            let result = items
+                .iter()
+                .try_for_each(|item| handle_result(self.apply(&mut parser, item, &f)));
+            self.release(parser);
+            return result;
+        }
+
+        // Each thread takes the next unprocessed item until there are none left, and
+        // sends its result back to this thread, which reorders the results.
+        let next_index = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..thread_count {
+                let sender = sender.clone();
+                let (next_index, f) = (&next_index, &f);
+                scope.spawn(move || {
+                    let mut parser = self.acquire();
+                    loop {
+                        let i = next_index.fetch_add(1, Ordering::SeqCst);
+                        if i >= items.len() {
+                            break;
+                        }
+                        let result = self.apply(&mut parser, &items[i], f);
+                        if sender.send((i, result)).is_err() {
+                            break;
+                        }
+                    }
+                    self.release(parser);
+                });
+            }
+            drop(sender);
+
+            // If a result can't be handled, dropping the receiver stops the threads.
+            let receiver = receiver;
+            let mut pending_results = (0..items.len()).map(|_| None).collect::<Vec<_>>();
+            let mut next_result_index = 0;
+            for (i, result) in receiver.iter() {
+                pending_results[i] = Some(result);
+                while let Some(result) = pending_results
+                    .get_mut(next_result_index)
+                    .and_then(Option::take)
+                {
+                    handle_result(result)?;
+                    next_result_index += 1;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Apply the given function to an item, and then clear the parser's settings so
+    /// that it can be reused.
+    fn apply<T, R>(&self, parser: &mut Parser, item: &T, f: impl Fn(&mut Parser, &T) -> R) -> R {
+        let result = f(parser, item);
+        parser.reset();
+        parser.set_logger(None);
+        parser.set_timeout_micros(0);
+        parser.set_included_ranges(&[]).unwrap();
+        unsafe { parser.set_cancellation_flag(None) };
+        result
+    }
+
+    fn acquire(&self) -> Parser {
+        self.parsers
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(Parser::new)
+    }
+
+    fn release(&self, parser: Parser) {
+        let mut parsers = self.parsers.lock().unwrap();
+        if parsers.len() < self.jobs {
+            parsers.push(parser);
+        }
+    }
+}
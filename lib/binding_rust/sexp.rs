@@ -0,0 +1,240 @@
+use super::Node;
+use std::fmt;
+
+/// Options that control how a node is printed by [Node::to_sexp_with].
+///
+/// The default options produce the same output as [Node::to_sexp], except that missing
+/// nodes whose kinds are hidden are never printed, because they cannot be reached with
+/// a [TreeCursor](super::TreeCursor).
+#[derive(Clone, Copy, Debug)]
+pub struct SexpOptions<'a> {
+    /// The string used to indent each level of nesting. If this is `None`, the whole
+    /// S-expression is printed on one line. Otherwise, each node is printed on its own
+    /// line.
+    pub indent: Option<&'a str>,
+    /// Whether to print anonymous nodes, such as punctuation and keywords, as quoted
+    /// strings.
+    pub anonymous_nodes: bool,
+    /// Whether to print the field name that is associated with each node.
+    pub fields: bool,
+    /// Whether to print the start and end position of each node.
+    pub ranges: bool,
+    /// Whether to print the text of each leaf node. This requires the `source`.
+    pub text: bool,
+    /// Whether to mark missing nodes with `MISSING`, and error nodes with no children
+    /// with `UNEXPECTED` and the character that caused the error. When this is false, missing nodes
+    /// are printed as ordinary nodes, and anonymous missing nodes are omitted unless
+    /// `anonymous_nodes` is set.
+    pub markers: bool,
+    /// The source code that the node was parsed from, which is needed to print the
+    /// text of leaf nodes.
+    pub source: Option<&'a [u8]>,
+}
+
+impl<'a> Default for SexpOptions<'a> {
+    fn default() -> Self {
+        SexpOptions {
+            indent: None,
+            anonymous_nodes: false,
+            fields: true,
+            ranges: false,
+            text: false,
+            markers: true,
+            source: None,
+        }
+    }
+}
+
+pub(crate) fn write_sexp(
+    node: Node,
+    output: &mut impl fmt::Write,
+    options: &SexpOptions,
+) -> fmt::Result {
+    let mut cursor = node.walk();
+
+    // For each ancestor of the current node, whether it was printed within parentheses.
+    let mut stack = Vec::new();
+    let mut depth = 0;
+    loop {
+        let node = cursor.node();
+        let is_missing_marker = options.markers && node.is_missing();
+        let is_visible = node.is_named() || is_missing_marker || options.anonymous_nodes;
+        let is_parenthesized = is_visible && (node.is_named() || is_missing_marker);
+        if is_visible {
+            if !stack.is_empty() {
+                match options.indent {
+                    Some(indent) => {
+                        writeln!(output)?;
+                        for _ in 0..depth {
+                            output.write_str(indent)?;
+                        }
+                    }
+                    None => output.write_char(' ')?,
+                }
+            }
+            if options.fields {
+                if let Some(field_name) = cursor.field_name() {
+                    write!(output, "{}: ", field_name)?;
+                }
+            }
+            write_node(node, output, options)?;
+        }
+        if is_parenthesized {
+            depth += 1;
+        }
+        stack.push(is_parenthesized);
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if stack.pop() == Some(true) {
+                output.write_char(')')?;
+                depth -= 1;
+            }
+            if stack.is_empty() {
+                return Ok(());
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            cursor.goto_parent();
+        }
+    }
+}
+
+fn write_node(node: Node, output: &mut impl fmt::Write, options: &SexpOptions) -> fmt::Result {
+    // The character that caused an `UNEXPECTED` error is only available to the C
+    // library, so reuse its representation of the node.
+    let unexpected_marker = if options.markers && node.is_error() && node.child_count() == 0 {
+        Some(node.to_sexp()).filter(|marker| marker.starts_with("(UNEXPECTED "))
+    } else {
+        None
+    };
+
+    if let Some(marker) = unexpected_marker {
+        output.write_str(marker.trim_end_matches(')'))?;
+    } else if options.markers && node.is_missing() {
+        if node.is_named() {
+            write!(output, "(MISSING {}", node.kind())?;
+        } else {
+            write!(output, "(MISSING \"{}\"", node.kind())?;
+        }
+    } else if node.is_named() {
+        write!(output, "({}", node.kind())?;
+    } else {
+        write!(output, "{:?}", node.kind())?;
+    }
+
+    if options.ranges {
+        let start = node.start_position();
+        let end = node.end_position();
+        write!(
+            output,
+            " [{}, {}] - [{}, {}]",
+            start.row, start.column, end.row, end.column
+        )?;
+    }
+
+    if options.text && node.is_named() && node.child_count() == 0 {
+        if let Some(text) = options
+            .source
+            .and_then(|source| source.get(node.byte_range()))
+        {
+            write!(output, " {:?}", String::from_utf8_lossy(text))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reformat an S-expression that is printed on one line, such as the output of
+/// [Node::to_sexp], so that each node is printed on its own line, as it would be by
+/// [Node::to_sexp_with] with the [indent](SexpOptions::indent) option. Each line is
+/// indented with `indent` once per level of nesting, starting at `initial_depth`.
+pub fn format_sexp(sexp: &str, indent: &str, initial_depth: usize) -> String {
+    let mut result = String::new();
+    let mut depth = initial_depth;
+    let mut has_field = false;
+    let newline = |result: &mut String, depth: usize| {
+        result.push('\n');
+        for _ in 0..depth {
+            result.push_str(indent);
+        }
+    };
+
+    let mut tokens = sexp_tokens(sexp).peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            "(" => {
+                // A node that follows a field name is printed on the field's line.
+                if has_field {
+                    has_field = false;
+                } else {
+                    if depth > 0 {
+                        newline(&mut result, depth);
+                    }
+                    depth += 1;
+                }
+                result.push('(');
+                if let Some(kind) = tokens.next_if(|token| *token != "(" && *token != ")") {
+                    result.push_str(kind);
+                    if kind == "MISSING" || kind == "UNEXPECTED" {
+                        if let Some(name) = tokens.next_if(|token| *token != "(" && *token != ")") {
+                            result.push(' ');
+                            result.push_str(name);
+                        }
+                    }
+                }
+            }
+            ")" => {
+                result.push(')');
+                depth = depth.saturating_sub(1);
+            }
+            _ if token.ends_with(':') => {
+                newline(&mut result, depth);
+                result.push_str(token);
+                result.push(' ');
+                has_field = true;
+                depth += 1;
+            }
+            _ => {
+                result.push(' ');
+                result.push_str(token);
+            }
+        }
+    }
+    result
+}
+
+/// Split an S-expression into parentheses and atoms.
+///
+/// Quoted atoms, such as the names of anonymous nodes, may contain whitespace,
+/// parentheses and quotes, because the names are not escaped. A quoted atom ends at the
+/// first matching quote that is followed by whitespace or a closing parenthesis.
+fn sexp_tokens(sexp: &str) -> impl Iterator<Item = &str> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let rest = sexp[offset..].trim_start();
+        offset = sexp.len() - rest.len();
+        let bytes = rest.as_bytes();
+        let length = match bytes.first()? {
+            b'(' | b')' => 1,
+            quote @ (b'"' | b'\'') => (2..bytes.len())
+                .find(|&i| {
+                    bytes[i] == *quote
+                        && match bytes.get(i + 1) {
+                            Some(next) => next.is_ascii_whitespace() || *next == b')',
+                            None => true,
+                        }
+                })
+                .map_or(bytes.len(), |i| i + 1),
+            _ => rest
+                .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                .unwrap_or(rest.len()),
+        };
+        let token = &rest[..length];
+        offset += length;
+        Some(token)
+    })
+}
@@ -0,0 +1,408 @@
+use super::{Point, Range, Tree};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom, error, fmt, ptr};
+
+const EXTRA_FLAG: u8 = 1 << 0;
+const MISSING_FLAG: u8 = 1 << 1;
+const HAS_ERROR_FLAG: u8 = 1 << 2;
+
+/// A serializable, read-only copy of a syntax [Tree].
+///
+/// A snapshot records the kind, field name, flags and range of every node in a tree,
+/// so that the tree's structure can be persisted using any [serde] data format and
+/// inspected later without re-parsing the source code. Snapshots do not hold on to the
+/// [Language](super::Language) that produced them, and cannot be edited or used as the
+/// old tree when reparsing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "SnapshotData")]
+pub struct TreeSnapshot {
+    kinds: Vec<SnapshotKind>,
+    fields: Vec<String>,
+    nodes: Vec<SnapshotEntry>,
+}
+
+/// A single node within a [TreeSnapshot].
+#[derive(Clone, Copy)]
+pub struct SnapshotNode<'a> {
+    snapshot: &'a TreeSnapshot,
+    index: usize,
+}
+
+/// An error that occurred when deserializing a [TreeSnapshot] whose nodes do not form a
+/// valid tree.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SnapshotError {
+    pub node_index: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct SnapshotKind {
+    id: u16,
+    name: String,
+    named: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct SnapshotEntry {
+    kind: u16,
+    field: Option<u16>,
+    flags: u8,
+    parent: Option<u32>,
+    child_count: u32,
+    descendant_count: u32,
+    range: Range,
+}
+
+#[derive(Deserialize)]
+struct SnapshotData {
+    kinds: Vec<SnapshotKind>,
+    fields: Vec<String>,
+    nodes: Vec<SnapshotEntry>,
+}
+
+impl TreeSnapshot {
+    /// Record the structure of the given syntax tree.
+    pub fn new(tree: &Tree) -> Self {
+        let mut kinds = Vec::new();
+        let mut fields = Vec::new();
+        let mut nodes = Vec::<SnapshotEntry>::new();
+        let mut kind_indices = HashMap::new();
+        let mut field_indices = HashMap::new();
+        let mut stack = Vec::<usize>::new();
+        let mut cursor = tree.walk();
+        loop {
+            let node = cursor.node();
+            let kind = *kind_indices.entry(node.kind_id()).or_insert_with(|| {
+                kinds.push(SnapshotKind {
+                    id: node.kind_id(),
+                    name: node.kind().to_string(),
+                    named: node.is_named(),
+                });
+                kinds.len() as u16 - 1
+            });
+            let field = cursor.field_name().map(|name| {
+                *field_indices.entry(name).or_insert_with(|| {
+                    fields.push(name.to_string());
+                    fields.len() as u16 - 1
+                })
+            });
+
+            let mut flags = 0;
+            if node.is_extra() {
+                flags |= EXTRA_FLAG;
+            }
+            if node.is_missing() {
+                flags |= MISSING_FLAG;
+            }
+            if node.has_error() {
+                flags |= HAS_ERROR_FLAG;
+            }
+
+            let index = nodes.len();
+            let parent = stack.last().copied();
+            if let Some(parent) = parent {
+                nodes[parent].child_count += 1;
+            }
+            nodes.push(SnapshotEntry {
+                kind,
+                field,
+                flags,
+                parent: parent.map(|i| i as u32),
+                child_count: 0,
+                descendant_count: 0,
+                range: node.range(),
+            });
+
+            if cursor.goto_first_child() {
+                stack.push(index);
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return TreeSnapshot {
+                        kinds,
+                        fields,
+                        nodes,
+                    };
+                }
+                let parent = stack.pop().unwrap();
+                nodes[parent].descendant_count = (nodes.len() - parent - 1) as u32;
+            }
+        }
+    }
+
+    /// Get the root node of the snapshot.
+    pub fn root_node(&self) -> SnapshotNode<'_> {
+        SnapshotNode {
+            snapshot: self,
+            index: 0,
+        }
+    }
+
+    /// Get the total number of nodes in the snapshot.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn validate(&self) -> Result<(), SnapshotError> {
+        if self.nodes.is_empty() {
+            return Err(SnapshotError { node_index: 0 });
+        }
+        for (i, entry) in self.nodes.iter().enumerate() {
+            let error = SnapshotError { node_index: i };
+            let end = i + 1 + entry.descendant_count as usize;
+            if let Some(field) = entry.field {
+                if field as usize >= self.fields.len() {
+                    return Err(error);
+                }
+            }
+            if entry.kind as usize >= self.kinds.len()
+                || end > self.nodes.len()
+                || (i == 0) != entry.parent.is_none()
+                || (i == 0 && end != self.nodes.len())
+            {
+                return Err(error);
+            }
+
+            let mut child = i + 1;
+            for _ in 0..entry.child_count {
+                if child >= end || self.nodes[child].parent != Some(i as u32) {
+                    return Err(error);
+                }
+                child += 1 + self.nodes[child].descendant_count as usize;
+            }
+            if child != end {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a Tree> for TreeSnapshot {
+    fn from(tree: &'a Tree) -> Self {
+        Self::new(tree)
+    }
+}
+
+impl TryFrom<SnapshotData> for TreeSnapshot {
+    type Error = SnapshotError;
+
+    fn try_from(data: SnapshotData) -> Result<Self, Self::Error> {
+        let result = TreeSnapshot {
+            kinds: data.kinds,
+            fields: data.fields,
+            nodes: data.nodes,
+        };
+        result.validate()?;
+        Ok(result)
+    }
+}
+
+impl<'a> SnapshotNode<'a> {
+    fn entry(&self) -> &'a SnapshotEntry {
+        &self.snapshot.nodes[self.index]
+    }
+
+    fn node_kind(&self) -> &'a SnapshotKind {
+        &self.snapshot.kinds[self.entry().kind as usize]
+    }
+
+    fn at(&self, index: usize) -> Self {
+        SnapshotNode {
+            snapshot: self.snapshot,
+            index,
+        }
+    }
+
+    /// Get this node's index within the snapshot, in pre-order.
+    pub fn id(&self) -> usize {
+        self.index
+    }
+
+    /// Get this node's type as the numerical id used by the language that produced it.
+    pub fn kind_id(&self) -> u16 {
+        self.node_kind().id
+    }
+
+    /// Get this node's type as a string.
+    pub fn kind(&self) -> &'a str {
+        &self.node_kind().name
+    }
+
+    /// Check if this node is *named*.
+    pub fn is_named(&self) -> bool {
+        self.node_kind().named
+    }
+
+    /// Check if this node is *extra*.
+    pub fn is_extra(&self) -> bool {
+        self.entry().flags & EXTRA_FLAG != 0
+    }
+
+    /// Check if this node represents a syntax error or contains any syntax errors anywhere
+    /// within it.
+    pub fn has_error(&self) -> bool {
+        self.entry().flags & HAS_ERROR_FLAG != 0
+    }
+
+    /// Check if this node represents a syntax error.
+    pub fn is_error(&self) -> bool {
+        self.kind_id() == u16::MAX
+    }
+
+    /// Check if this node is *missing*.
+    pub fn is_missing(&self) -> bool {
+        self.entry().flags & MISSING_FLAG != 0
+    }
+
+    /// Get the name of the field that this node is associated with in its parent, if any.
+    pub fn field_name(&self) -> Option<&'a str> {
+        let field = self.entry().field?;
+        Some(&self.snapshot.fields[field as usize])
+    }
+
+    /// Get the byte offsets where this node starts.
+    pub fn start_byte(&self) -> usize {
+        self.entry().range.start_byte
+    }
+
+    /// Get the byte offsets where this node end.
+    pub fn end_byte(&self) -> usize {
+        self.entry().range.end_byte
+    }
+
+    /// Get the byte range of source code that this node represents.
+    pub fn byte_range(&self) -> std::ops::Range<usize> {
+        self.start_byte()..self.end_byte()
+    }
+
+    /// Get the range of source code that this node represents, both in terms of raw bytes
+    /// and of row/column coordinates.
+    pub fn range(&self) -> Range {
+        self.entry().range
+    }
+
+    /// Get this node's start position in terms of rows and columns.
+    pub fn start_position(&self) -> Point {
+        self.entry().range.start_point
+    }
+
+    /// Get this node's end position in terms of rows and columns.
+    pub fn end_position(&self) -> Point {
+        self.entry().range.end_point
+    }
+
+    /// Get this node's number of children.
+    pub fn child_count(&self) -> usize {
+        self.entry().child_count as usize
+    }
+
+    /// Get the node's child at the given index, where zero represents the first
+    /// child.
+    ///
+    /// The cost of this method is linear in `i`, so if you are iterating over
+    /// a long list of children, you should use [SnapshotNode::children] instead.
+    pub fn child(&self, i: usize) -> Option<Self> {
+        self.children().nth(i)
+    }
+
+    /// Iterate over this node's children.
+    pub fn children(&self) -> impl ExactSizeIterator<Item = SnapshotNode<'a>> + 'a {
+        let snapshot = self.snapshot;
+        let mut index = self.index + 1;
+        (0..self.child_count()).map(move |_| {
+            let result = SnapshotNode { snapshot, index };
+            index += 1 + snapshot.nodes[index].descendant_count as usize;
+            result
+        })
+    }
+
+    /// Iterate over this node's named children.
+    pub fn named_children(&self) -> impl Iterator<Item = SnapshotNode<'a>> + 'a {
+        self.children().filter(|child| child.is_named())
+    }
+
+    /// Get the first child with the given field name.
+    pub fn child_by_field_name(&self, field_name: &str) -> Option<Self> {
+        self.children()
+            .find(|child| child.field_name() == Some(field_name))
+    }
+
+    /// Iterate over this node's children with a given field name.
+    pub fn children_by_field_name<'b>(
+        &self,
+        field_name: &'b str,
+    ) -> impl Iterator<Item = SnapshotNode<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        self.children()
+            .filter(move |child| child.field_name() == Some(field_name))
+    }
+
+    /// Get this node's immediate parent.
+    pub fn parent(&self) -> Option<Self> {
+        let parent = self.entry().parent?;
+        Some(self.at(parent as usize))
+    }
+
+    /// Get this node's next sibling.
+    pub fn next_sibling(&self) -> Option<Self> {
+        let parent = self.parent()?;
+        let index = self.index + 1 + self.entry().descendant_count as usize;
+        if index < parent.index + 1 + parent.entry().descendant_count as usize {
+            Some(self.at(index))
+        } else {
+            None
+        }
+    }
+
+    /// Get this node's previous sibling.
+    pub fn prev_sibling(&self) -> Option<Self> {
+        let mut previous = None;
+        for sibling in self.parent()?.children() {
+            if sibling.index == self.index {
+                return previous;
+            }
+            previous = Some(sibling);
+        }
+        None
+    }
+
+    /// Iterate over this node and all of its descendants, in pre-order.
+    pub fn descendants(&self) -> impl ExactSizeIterator<Item = SnapshotNode<'a>> + 'a {
+        let snapshot = self.snapshot;
+        let start = self.index;
+        let end = start + 1 + self.entry().descendant_count as usize;
+        (start..end).map(move |index| SnapshotNode { snapshot, index })
+    }
+}
+
+impl<'a> PartialEq for SnapshotNode<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.snapshot, other.snapshot) && self.index == other.index
+    }
+}
+
+impl<'a> Eq for SnapshotNode<'a> {}
+
+impl<'a> fmt::Debug for SnapshotNode<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{{SnapshotNode {} {} - {}}}",
+            self.kind(),
+            self.start_position(),
+            self.end_position()
+        )
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid tree snapshot at node {}", self.node_index)
+    }
+}
+
+impl error::Error for SnapshotError {}
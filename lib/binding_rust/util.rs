@@ -1,5 +1,14 @@
 use super::FREE_FN;
-use std::os::raw::c_void;
+use std::{borrow::Cow, mem, os::raw::c_void};
+
+/// Create a slice from a pointer and a length, which may be null if the length is zero.
+pub unsafe fn slice_from_raw_parts<'a, T>(ptr: *const T, length: u32) -> &'a [T] {
+    if length == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, length as usize)
+    }
+}
 
 /// A raw pointer and a length, exposed as an iterator.
 pub struct CBufferIter<T> {
@@ -40,3 +49,79 @@ impl<T> Drop for CBufferIter<T> {
         unsafe { (FREE_FN)(self.ptr as *mut c_void) };
     }
 }
+
+/// An iterator that decodes chunks of UTF-8 text into strings, borrowing each chunk
+/// when it does not contain a partial character. A character that is split between
+/// chunks is decoded along with the start of the next chunk. Invalid UTF-8 is replaced
+/// with `U+FFFD REPLACEMENT CHARACTER`.
+pub struct Utf8Chunks<I> {
+    chunks: I,
+    partial_char: Vec<u8>,
+}
+
+impl<I> Utf8Chunks<I> {
+    pub fn new(chunks: I) -> Self {
+        Self {
+            chunks,
+            partial_char: Vec::new(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]>> Iterator for Utf8Chunks<I> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let chunk = match self.chunks.next() {
+                Some(chunk) => chunk,
+                None if self.partial_char.is_empty() => return None,
+                None => {
+                    let bytes = mem::take(&mut self.partial_char);
+                    return Some(Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()));
+                }
+            };
+
+            let bytes = if self.partial_char.is_empty() {
+                Cow::Borrowed(chunk)
+            } else {
+                let mut bytes = mem::take(&mut self.partial_char);
+                bytes.extend_from_slice(chunk);
+                Cow::Owned(bytes)
+            };
+            let complete_len = bytes.len() - partial_char_len(&bytes);
+            self.partial_char.extend_from_slice(&bytes[complete_len..]);
+            if complete_len == 0 {
+                continue;
+            }
+
+            return Some(match bytes {
+                Cow::Borrowed(bytes) => String::from_utf8_lossy(&bytes[..complete_len]),
+                Cow::Owned(mut bytes) => {
+                    bytes.truncate(complete_len);
+                    Cow::Owned(String::from_utf8(bytes).unwrap_or_else(|error| {
+                        String::from_utf8_lossy(error.as_bytes()).into_owned()
+                    }))
+                }
+            });
+        }
+    }
+}
+
+/// Get the length of the incomplete UTF-8 sequence at the end of the given bytes.
+fn partial_char_len(bytes: &[u8]) -> usize {
+    for len in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - len];
+        if byte & 0b1100_0000 == 0b1000_0000 {
+            continue;
+        }
+        let char_len = match byte {
+            0xf0..=0xff => 4,
+            0xe0..=0xef => 3,
+            0xc0..=0xdf => 2,
+            _ => 1,
+        };
+        return if char_len > len { len } else { 0 };
+    }
+    0
+}
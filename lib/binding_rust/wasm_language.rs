@@ -0,0 +1,1129 @@
+use super::{ffi, Language, Parser};
+use std::{
+    collections::HashMap,
+    error, fmt,
+    os::raw::{c_char, c_void},
+    ptr, slice,
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Arc,
+    },
+};
+use wasmtime::{
+    AsContextMut, Caller, Engine, Error, Extern, ExternType, Func, FuncType, Global, GlobalType,
+    Instance, Memory, MemoryType, Module, Mutability, Store, Table, TableType, TypedFunc, Val,
+    ValType, WasmParams, WasmResults,
+};
+
+const PAGE_SIZE: u32 = 64 * 1024;
+const STACK_SIZE: u32 = 1024 * 1024;
+const SERIALIZATION_BUFFER_SIZE: u32 = 1024;
+const MIN_ALLOCATION_SIZE_CLASS: usize = 4;
+
+// The size of a `TSLexer` struct in 32-bit WebAssembly memory, and the offsets
+// of its fields.
+const LEXER_SIZE: u32 = 28;
+const LEXER_LOOKAHEAD_OFFSET: u32 = 0;
+const LEXER_RESULT_SYMBOL_OFFSET: u32 = 4;
+const LEXER_FUNCTIONS_OFFSET: u32 = 8;
+
+// The offsets of some fields of a `TSLanguage` struct in 32-bit WebAssembly memory.
+const LANGUAGE_TOKEN_COUNT_OFFSET: u32 = 12;
+const LANGUAGE_EXTERNAL_TOKEN_COUNT_OFFSET: u32 = 16;
+const LANGUAGE_LEX_FN_OFFSET: u32 = 92;
+const LANGUAGE_KEYWORD_LEX_FN_OFFSET: u32 = 96;
+const LANGUAGE_EXTERNAL_SCANNER_OFFSET: u32 = 104;
+
+/// A sandboxed WebAssembly runtime in which languages can be loaded from
+/// WebAssembly modules.
+///
+/// Each [Parser] that parses a WebAssembly language needs its own store, which is
+/// assigned using [Parser::set_wasm_store]. A language that was loaded using one
+/// store can be used with any other store that was created with the same
+/// [Engine](wasmtime::Engine).
+///
+/// The WebAssembly modules are expected to be built the way that the Tree-sitter
+/// CLI builds them with `tree-sitter build-wasm`: as Emscripten side modules that
+/// export a `tree_sitter_<name>` function.
+pub struct WasmStore {
+    store: Store<StoreData>,
+    memory: Memory,
+    table: Table,
+    stack_pointer: Global,
+    lexer_address: u32,
+    buffer_address: u32,
+    instances: Vec<LanguageInstance>,
+    current_instance: Option<usize>,
+    has_error: bool,
+}
+
+/// An error that occurred when loading a language from a WebAssembly module.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WasmError {
+    pub kind: WasmErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WasmErrorKind {
+    /// The module is not a valid Emscripten side module, or it does not contain a
+    /// valid language.
+    Parse,
+    /// The module could not be compiled.
+    Compile,
+    /// The module could not be instantiated, because it imports something that the
+    /// store does not provide, or because its initialization failed.
+    Instantiate,
+    /// The store's memory could not be grown to fit the module.
+    Allocate,
+}
+
+/// The native `TSLexer` struct, defined in `tree_sitter/parser.h`.
+#[repr(C)]
+pub(crate) struct TSLexer {
+    lookahead: i32,
+    result_symbol: u16,
+    advance: unsafe extern "C" fn(*mut TSLexer, bool),
+    mark_end: unsafe extern "C" fn(*mut TSLexer),
+    get_column: unsafe extern "C" fn(*mut TSLexer) -> u32,
+    is_at_included_range_start: unsafe extern "C" fn(*const TSLexer) -> bool,
+    eof: unsafe extern "C" fn(*const TSLexer) -> bool,
+}
+
+/// The state that is shared with the functions that the store provides to
+/// WebAssembly modules.
+struct StoreData {
+    lexer: *mut TSLexer,
+    lexer_address: u32,
+    memory: Option<Memory>,
+    heap: Heap,
+}
+
+/// A simple allocator for the store's memory, which hands out blocks whose sizes are
+/// powers of two, and reuses blocks of the same size after they are freed.
+#[derive(Default)]
+struct Heap {
+    end: u32,
+    blocks: HashMap<u32, usize>,
+    free_blocks: Vec<Vec<u32>>,
+}
+
+/// A compiled WebAssembly module, which is stored in each language that is loaded
+/// from it, so that the language can be instantiated in other stores.
+///
+/// The module is shared by every store in which the language is instantiated, and
+/// it owns the language, which is freed along with the module.
+struct WasmLanguageModule {
+    module: Module,
+    function_name: String,
+    memory_size: u32,
+    memory_align: u32,
+    table_size: u32,
+    language: AtomicPtr<ffi::TSLanguage>,
+}
+
+/// A function that takes a lexer and a lex state, and returns whether it found a token.
+type LexFunction = TypedFunc<(i32, i32), i32>;
+
+/// A language's WebAssembly module, instantiated within a particular store.
+struct LanguageInstance {
+    module: Arc<WasmLanguageModule>,
+    token_count: u32,
+    external_token_count: u32,
+    lex_main: LexFunction,
+    lex_keyword: Option<LexFunction>,
+    scanner: Option<ScannerInstance>,
+}
+
+struct ScannerInstance {
+    states_address: u32,
+    create: TypedFunc<(), i32>,
+    destroy: TypedFunc<i32, ()>,
+    scan: TypedFunc<(i32, i32, i32), i32>,
+    serialize: TypedFunc<(i32, i32), i32>,
+    deserialize: TypedFunc<(i32, i32, i32), ()>,
+}
+
+impl WasmStore {
+    /// Create a new store that uses the given engine.
+    pub fn new(engine: &Engine) -> Result<Self, WasmError> {
+        let mut store = Store::new(
+            engine,
+            StoreData {
+                lexer: ptr::null_mut(),
+                lexer_address: 0,
+                memory: None,
+                heap: Heap::default(),
+            },
+        );
+
+        let memory = Memory::new(&mut store, MemoryType::new(0, None))
+            .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?;
+        let table = Table::new(
+            &mut store,
+            TableType::new(ValType::FuncRef, 1, None),
+            Val::FuncRef(None),
+        )
+        .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?;
+        store.data_mut().memory = Some(memory);
+
+        // The stack is at the start of the memory, and grows downward. The first
+        // 16 bytes are left unused, so that no object has the address zero.
+        let stack_top = 16 + STACK_SIZE;
+        store.data_mut().heap.end = stack_top;
+        grow_memory(&mut store, stack_top)
+            .ok_or_else(|| WasmError::new(WasmErrorKind::Allocate, "Failed to grow memory"))?;
+        let stack_pointer = Global::new(
+            &mut store,
+            GlobalType::new(ValType::I32, Mutability::Var),
+            Val::I32(stack_top as i32),
+        )
+        .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?;
+
+        let lexer_address = allocate(&mut store, LEXER_SIZE);
+        let buffer_address = allocate(&mut store, SERIALIZATION_BUFFER_SIZE);
+        if lexer_address == 0 || buffer_address == 0 {
+            return Err(WasmError::new(
+                WasmErrorKind::Allocate,
+                "Failed to allocate memory",
+            ));
+        }
+        store.data_mut().lexer_address = lexer_address;
+
+        let mut result = WasmStore {
+            store,
+            memory,
+            table,
+            stack_pointer,
+            lexer_address,
+            buffer_address,
+            instances: Vec::new(),
+            current_instance: None,
+            has_error: false,
+        };
+
+        // The WebAssembly code calls the lexer's functions through the function
+        // table, so the lexer's function pointers are indices in the table.
+        let lexer_functions = [
+            Func::wrap(&mut result.store, lexer_advance),
+            Func::wrap(&mut result.store, lexer_mark_end),
+            Func::wrap(&mut result.store, lexer_get_column),
+            Func::wrap(&mut result.store, lexer_is_at_included_range_start),
+            Func::wrap(&mut result.store, lexer_eof),
+        ];
+        let mut lexer_function_indices = Vec::new();
+        for function in lexer_functions {
+            lexer_function_indices.extend_from_slice(
+                &result
+                    .add_table_function(function)
+                    .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?
+                    .to_le_bytes(),
+            );
+        }
+        result
+            .memory
+            .write(
+                &mut result.store,
+                (lexer_address + LEXER_FUNCTIONS_OFFSET) as usize,
+                &lexer_function_indices,
+            )
+            .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?;
+
+        Ok(result)
+    }
+
+    /// Load a language from the given WebAssembly module. The `name` is the name of
+    /// the language, which is used to find the module's `tree_sitter_<name>`
+    /// function.
+    ///
+    /// The language's parse tables are copied out of the module. They are freed
+    /// when this store, and every other store in which the language has been used,
+    /// have been dropped, so any trees that were parsed with the language must be
+    /// dropped before that.
+    pub fn load_language(&mut self, name: &str, wasm: &[u8]) -> Result<Language, WasmError> {
+        let (memory_size, memory_align, table_size) = parse_dylink_section(wasm)?;
+        let module = Module::new(self.store.engine(), wasm)
+            .map_err(|e| WasmError::new(WasmErrorKind::Compile, e))?;
+        let module = Arc::new(WasmLanguageModule {
+            module,
+            function_name: format!("tree_sitter_{}", name),
+            memory_size,
+            memory_align,
+            table_size,
+            language: AtomicPtr::new(ptr::null_mut()),
+        });
+
+        let language_address = self.instantiate(&module)?;
+        let memory = self.memory.data(&self.store);
+        let language = unsafe {
+            ffi::ts_wasm_language_new(
+                memory.as_ptr(),
+                memory.len() as u32,
+                language_address,
+                Arc::as_ptr(&module) as *mut c_void,
+            )
+        };
+        if language.is_null() {
+            return Err(WasmError::new(
+                WasmErrorKind::Parse,
+                "The module does not contain a valid language",
+            ));
+        }
+        module
+            .language
+            .store(language as *mut ffi::TSLanguage, Ordering::SeqCst);
+
+        let instance = self
+            .language_instance(module, language_address)
+            .map_err(|e| WasmError::new(WasmErrorKind::Instantiate, e))?;
+        self.instances.push(instance);
+        Ok(Language(language))
+    }
+
+    /// Instantiate the given module in this store, and return the address of its
+    /// language struct.
+    fn instantiate(&mut self, module: &WasmLanguageModule) -> Result<u32, WasmError> {
+        let instantiate_error = |e| WasmError::new(WasmErrorKind::Instantiate, e);
+
+        let memory_base =
+            allocate_aligned(&mut self.store, module.memory_size, module.memory_align);
+        if memory_base == 0 {
+            return Err(WasmError::new(
+                WasmErrorKind::Allocate,
+                "Failed to allocate the module's memory",
+            ));
+        }
+        let table_base = self
+            .table
+            .grow(&mut self.store, module.table_size, Val::FuncRef(None))
+            .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?;
+
+        let mut imports = Vec::new();
+        let mut got_entries = Vec::new();
+        for import in module.module.imports() {
+            let name = import.name();
+            let import = match (import.module(), name, import.ty()) {
+                ("env", "memory", ExternType::Memory(_)) => Extern::Memory(self.memory),
+                ("env", "__indirect_function_table", ExternType::Table(_)) => {
+                    Extern::Table(self.table)
+                }
+                ("env", "__stack_pointer", ExternType::Global(_)) => {
+                    Extern::Global(self.stack_pointer)
+                }
+                ("env", "__memory_base", ExternType::Global(_)) => {
+                    Extern::Global(self.constant(memory_base)?)
+                }
+                ("env", "__table_base", ExternType::Global(_)) => {
+                    Extern::Global(self.constant(table_base)?)
+                }
+                ("env", _, ExternType::Func(ty)) => {
+                    Extern::Func(stdlib_function(&mut self.store, name, ty))
+                }
+                ("GOT.mem" | "GOT.func", _, ExternType::Global(_)) => {
+                    let global = Global::new(
+                        &mut self.store,
+                        GlobalType::new(ValType::I32, Mutability::Var),
+                        Val::I32(0),
+                    )
+                    .map_err(instantiate_error)?;
+                    got_entries.push((import.module() == "GOT.func", name.to_string(), global));
+                    Extern::Global(global)
+                }
+                (module_name, _, _) => {
+                    return Err(WasmError::new(
+                        WasmErrorKind::Instantiate,
+                        format!("Unsupported import {}.{}", module_name, name),
+                    ))
+                }
+            };
+            imports.push(import);
+        }
+
+        let instance =
+            Instance::new(&mut self.store, &module.module, &imports).map_err(instantiate_error)?;
+
+        // Resolve the module's references to its own functions and data, and to the
+        // functions that are provided by the store.
+        for (is_function, name, global) in got_entries {
+            let value = if is_function {
+                let function = match instance.get_func(&mut self.store, &name) {
+                    Some(function) => function,
+                    None => {
+                        let ty = FuncType::new([], []);
+                        stdlib_function(&mut self.store, &name, ty)
+                    }
+                };
+                self.add_table_function(function)
+                    .map_err(|e| WasmError::new(WasmErrorKind::Allocate, e))?
+            } else if name == "__dso_handle" {
+                memory_base
+            } else {
+                match instance.get_global(&mut self.store, &name) {
+                    Some(export) => match export.get(&mut self.store) {
+                        Val::I32(offset) => memory_base.wrapping_add(offset as u32),
+                        _ => 0,
+                    },
+                    None => {
+                        return Err(WasmError::new(
+                            WasmErrorKind::Instantiate,
+                            format!("Unsupported import GOT.mem.{}", name),
+                        ))
+                    }
+                }
+            };
+            global
+                .set(&mut self.store, Val::I32(value as i32))
+                .map_err(instantiate_error)?;
+        }
+
+        let initializers = if instance
+            .get_func(&mut self.store, "__post_instantiate")
+            .is_some()
+        {
+            &["__post_instantiate"][..]
+        } else {
+            &["__wasm_apply_data_relocs", "__wasm_call_ctors"][..]
+        };
+        for name in initializers {
+            if let Some(function) = instance.get_func(&mut self.store, name) {
+                function
+                    .typed::<(), ()>(&self.store)
+                    .and_then(|function| function.call(&mut self.store, ()))
+                    .map_err(instantiate_error)?;
+            }
+        }
+
+        let language_function = instance
+            .get_typed_func::<(), i32>(&mut self.store, &module.function_name)
+            .map_err(|_| {
+                WasmError::new(
+                    WasmErrorKind::Instantiate,
+                    format!("The module does not export {}", module.function_name),
+                )
+            })?;
+        let language_address = language_function
+            .call(&mut self.store, ())
+            .map_err(instantiate_error)?;
+        Ok(language_address as u32)
+    }
+
+    /// Find the lexing functions of the language struct at the given address.
+    fn language_instance(
+        &mut self,
+        module: Arc<WasmLanguageModule>,
+        address: u32,
+    ) -> Result<LanguageInstance, Error> {
+        let token_count = self.read_u32(address + LANGUAGE_TOKEN_COUNT_OFFSET);
+        let external_token_count = self.read_u32(address + LANGUAGE_EXTERNAL_TOKEN_COUNT_OFFSET);
+        let lex_main = self.table_function(self.read_u32(address + LANGUAGE_LEX_FN_OFFSET))?;
+        let lex_keyword = match self.read_u32(address + LANGUAGE_KEYWORD_LEX_FN_OFFSET) {
+            0 => None,
+            index => Some(self.table_function(index)?),
+        };
+
+        let mut scanner = None;
+        if external_token_count > 0 {
+            let address = address + LANGUAGE_EXTERNAL_SCANNER_OFFSET;
+            scanner = Some(ScannerInstance {
+                states_address: self.read_u32(address),
+                create: self.table_function(self.read_u32(address + 8))?,
+                destroy: self.table_function(self.read_u32(address + 12))?,
+                scan: self.table_function(self.read_u32(address + 16))?,
+                serialize: self.table_function(self.read_u32(address + 20))?,
+                deserialize: self.table_function(self.read_u32(address + 24))?,
+            });
+        }
+
+        Ok(LanguageInstance {
+            module,
+            token_count,
+            external_token_count,
+            lex_main,
+            lex_keyword,
+            scanner,
+        })
+    }
+
+    fn constant(&mut self, value: u32) -> Result<Global, WasmError> {
+        Global::new(
+            &mut self.store,
+            GlobalType::new(ValType::I32, Mutability::Const),
+            Val::I32(value as i32),
+        )
+        .map_err(|e| WasmError::new(WasmErrorKind::Instantiate, e))
+    }
+
+    fn add_table_function(&mut self, function: Func) -> Result<u32, Error> {
+        self.table
+            .grow(&mut self.store, 1, Val::FuncRef(Some(function)))
+    }
+
+    fn table_function<P: WasmParams, R: WasmResults>(
+        &mut self,
+        index: u32,
+    ) -> Result<TypedFunc<P, R>, Error> {
+        match self.table.get(&mut self.store, index) {
+            Some(Val::FuncRef(Some(function))) => function.typed(&self.store),
+            _ => Err(Error::msg(format!("Invalid function pointer {}", index))),
+        }
+    }
+
+    fn read_u32(&self, address: u32) -> u32 {
+        let mut bytes = [0; 4];
+        match self.memory.read(&self.store, address as usize, &mut bytes) {
+            Ok(()) => u32::from_le_bytes(bytes),
+            Err(_) => 0,
+        }
+    }
+
+    fn start(&mut self, lexer: *mut TSLexer, language: Language) -> bool {
+        self.has_error = false;
+        self.store.data_mut().lexer = lexer;
+        let module =
+            unsafe { ffi::ts_wasm_language_module(language.0) } as *const WasmLanguageModule;
+        if module.is_null() {
+            return false;
+        }
+        if let Some(index) = self
+            .instances
+            .iter()
+            .position(|i| Arc::as_ptr(&i.module) == module)
+        {
+            self.current_instance = Some(index);
+            return true;
+        }
+
+        // The language's module is kept alive by the stores in which the language
+        // is instantiated, so this store shares ownership of it.
+        let module = unsafe {
+            Arc::increment_strong_count(module);
+            Arc::from_raw(module)
+        };
+        let instance = match self.instantiate(&module) {
+            Ok(address) => self.language_instance(module, address),
+            Err(_) => return false,
+        };
+        match instance {
+            Ok(instance) => {
+                self.current_instance = Some(self.instances.len());
+                self.instances.push(instance);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Call a function of the current language, recording an error if the function
+    /// traps or returns an invalid result.
+    fn call<T: Default>(
+        &mut self,
+        f: impl FnOnce(&mut Store<StoreData>, &LanguageInstance) -> Result<T, Error>,
+    ) -> T {
+        let instance = match self.current_instance {
+            Some(index) if !self.has_error => &self.instances[index],
+            _ => {
+                self.has_error = true;
+                return T::default();
+            }
+        };
+        match f(&mut self.store, instance) {
+            Ok(result) => result,
+            Err(_) => {
+                self.has_error = true;
+                T::default()
+            }
+        }
+    }
+
+    fn call_lex_function(
+        &mut self,
+        get_function: fn(&LanguageInstance) -> Option<LexFunction>,
+        state: u16,
+    ) -> bool {
+        let lexer_address = self.lexer_address;
+        self.call(|store, instance| {
+            let function = get_function(instance)
+                .ok_or_else(|| Error::msg("The language has no keyword lex function"))?;
+            write_lexer(store)?;
+            let found_token = function.call(&mut *store, (lexer_address as i32, state as i32))?;
+            let symbol = read_result_symbol(store)?;
+            if found_token != 0 && u32::from(symbol) >= instance.token_count {
+                return Err(Error::msg(format!("Invalid symbol {}", symbol)));
+            }
+            Ok(found_token != 0)
+        })
+    }
+
+    fn scanner_call<T: Default>(
+        &mut self,
+        f: impl FnOnce(&mut Store<StoreData>, &ScannerInstance, u32) -> Result<T, Error>,
+    ) -> T {
+        self.call(|store, instance| {
+            let scanner = instance
+                .scanner
+                .as_ref()
+                .ok_or_else(|| Error::msg("The language has no external scanner"))?;
+            f(store, scanner, instance.external_token_count)
+        })
+    }
+}
+
+impl Drop for WasmLanguageModule {
+    fn drop(&mut self) {
+        unsafe { ffi::ts_language_delete(*self.language.get_mut()) }
+    }
+}
+
+impl WasmError {
+    fn new(kind: WasmErrorKind, message: impl ToString) -> Self {
+        WasmError {
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for WasmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            WasmErrorKind::Parse => "Failed to parse WebAssembly module",
+            WasmErrorKind::Compile => "Failed to compile WebAssembly module",
+            WasmErrorKind::Instantiate => "Failed to instantiate WebAssembly module",
+            WasmErrorKind::Allocate => "Failed to allocate WebAssembly memory",
+        };
+        write!(f, "{}: {}", kind, self.message)
+    }
+}
+
+impl error::Error for WasmError {}
+
+impl Parser {
+    /// Assign a WebAssembly store to the parser. A parser needs a WebAssembly store
+    /// in order to use languages that were loaded from WebAssembly modules.
+    ///
+    /// If the parser's current language is a WebAssembly language, then the parser's
+    /// language is unset.
+    #[doc(alias = "ts_parser_set_wasm_store")]
+    pub fn set_wasm_store(&mut self, store: WasmStore) {
+        let store = Box::into_raw(Box::new(store)) as *mut ffi::TSWasmStore;
+        unsafe { ffi::ts_parser_set_wasm_store(self.0.as_ptr(), store) };
+    }
+
+    /// Remove the parser's WebAssembly store, if it has one, and return it.
+    ///
+    /// If the parser's current language is a WebAssembly language, then the parser's
+    /// language is unset.
+    #[doc(alias = "ts_parser_take_wasm_store")]
+    pub fn take_wasm_store(&mut self) -> Option<WasmStore> {
+        let store = unsafe { ffi::ts_parser_take_wasm_store(self.0.as_ptr()) };
+        if store.is_null() {
+            None
+        } else {
+            Some(*unsafe { Box::from_raw(store as *mut WasmStore) })
+        }
+    }
+}
+
+/// Read the sizes of the memory and the table that an Emscripten side module needs
+/// from the module's `dylink` or `dylink.0` custom section.
+fn parse_dylink_section(wasm: &[u8]) -> Result<(u32, u32, u32), WasmError> {
+    let parse_error = || WasmError::new(WasmErrorKind::Parse, "Invalid WebAssembly module");
+    if wasm.get(0..8) != Some(b"\0asm\x01\0\0\0") {
+        return Err(parse_error());
+    }
+
+    let mut reader = Reader(&wasm[8..]);
+    while !reader.0.is_empty() {
+        let section_id = reader.byte().ok_or_else(parse_error)?;
+        let section_size = reader.u32().ok_or_else(parse_error)?;
+        let mut section = Reader(reader.bytes(section_size).ok_or_else(parse_error)?);
+        if section_id != 0 {
+            continue;
+        }
+
+        let name_length = section.u32().ok_or_else(parse_error)?;
+        let mem_info = match section.bytes(name_length).ok_or_else(parse_error)? {
+            b"dylink" => section,
+            b"dylink.0" => loop {
+                let subsection_type = section.byte().ok_or_else(parse_error)?;
+                let subsection_size = section.u32().ok_or_else(parse_error)?;
+                let subsection = section.bytes(subsection_size).ok_or_else(parse_error)?;
+                if subsection_type == 1 {
+                    break Reader(subsection);
+                }
+            },
+            _ => continue,
+        };
+
+        let mut mem_info = mem_info;
+        let memory_size = mem_info.u32().ok_or_else(parse_error)?;
+        let memory_align = mem_info.u32().ok_or_else(parse_error)?;
+        let table_size = mem_info.u32().ok_or_else(parse_error)?;
+        if memory_align > 16 {
+            return Err(parse_error());
+        }
+        return Ok((memory_size, 1 << memory_align, table_size));
+    }
+
+    Err(WasmError::new(
+        WasmErrorKind::Parse,
+        "The module is not an Emscripten side module",
+    ))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (byte, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(*byte)
+    }
+
+    fn bytes(&mut self, length: u32) -> Option<&'a [u8]> {
+        let length = length as usize;
+        if length > self.0.len() {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    /// Read an unsigned LEB128 integer.
+    fn u32(&mut self) -> Option<u32> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            result |= u32::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+        None
+    }
+}
+
+fn grow_memory(mut store: impl AsContextMut<Data = StoreData>, end: u32) -> Option<()> {
+    let mut store = store.as_context_mut();
+    let memory = store.data().memory?;
+    let size = memory.data_size(&store) as u64;
+    if u64::from(end) > size {
+        let pages = (u64::from(end) - size + u64::from(PAGE_SIZE) - 1) / u64::from(PAGE_SIZE);
+        memory.grow(&mut store, pages).ok()?;
+    }
+    Some(())
+}
+
+/// Allocate a region of memory that is never freed. This returns zero if the memory
+/// cannot be grown.
+fn allocate_aligned(mut store: impl AsContextMut<Data = StoreData>, size: u32, align: u32) -> u32 {
+    let mut store = store.as_context_mut();
+    let start = match store.data().heap.end.checked_add(align - 1) {
+        Some(end) => end / align * align,
+        None => return 0,
+    };
+    match start.checked_add(size) {
+        Some(end) if grow_memory(&mut store, end).is_some() => {
+            store.data_mut().heap.end = end;
+            start
+        }
+        _ => 0,
+    }
+}
+
+/// Allocate a block of memory for `malloc`. This returns zero if the memory cannot
+/// be grown.
+fn allocate(mut store: impl AsContextMut<Data = StoreData>, size: u32) -> u32 {
+    let mut store = store.as_context_mut();
+    let size_class =
+        (32 - size.saturating_sub(1).leading_zeros() as usize).max(MIN_ALLOCATION_SIZE_CLASS);
+    if size_class >= 32 {
+        return 0;
+    }
+
+    let heap = &mut store.data_mut().heap;
+    if heap.free_blocks.len() <= size_class {
+        heap.free_blocks.resize(size_class + 1, Vec::new());
+    }
+    let address = match heap.free_blocks[size_class].pop() {
+        Some(address) => address,
+        None => allocate_aligned(&mut store, 1 << size_class, 16),
+    };
+    if address != 0 {
+        store.data_mut().heap.blocks.insert(address, size_class);
+    }
+    address
+}
+
+fn free(mut store: impl AsContextMut<Data = StoreData>, address: u32) {
+    let mut store = store.as_context_mut();
+    let heap = &mut store.data_mut().heap;
+    if let Some(size_class) = heap.blocks.remove(&address) {
+        heap.free_blocks[size_class].push(address);
+    }
+}
+
+fn block_size(store: &Caller<StoreData>, address: u32) -> Option<u32> {
+    let size_class = *store.data().heap.blocks.get(&address)?;
+    Some(1 << size_class)
+}
+
+/// Copy the native lexer's current state into the WebAssembly lexer.
+fn write_lexer(store: &mut Store<StoreData>) -> Result<(), Error> {
+    let data = store.data();
+    let lexer = unsafe { &*data.lexer };
+    let (memory, address) = (data.memory.unwrap(), data.lexer_address as usize);
+    let memory = memory.data_mut(store);
+    let lexer_memory = memory
+        .get_mut(address..address + LEXER_FUNCTIONS_OFFSET as usize)
+        .ok_or_else(|| Error::msg("Invalid lexer address"))?;
+    lexer_memory[0..4].copy_from_slice(&lexer.lookahead.to_le_bytes());
+    lexer_memory[4..6].copy_from_slice(&lexer.result_symbol.to_le_bytes());
+    Ok(())
+}
+
+/// Copy the WebAssembly lexer's result symbol into the native lexer.
+fn read_result_symbol(store: &mut Store<StoreData>) -> Result<u16, Error> {
+    let data = store.data();
+    let memory = data.memory.unwrap();
+    let address = (data.lexer_address + LEXER_RESULT_SYMBOL_OFFSET) as usize;
+    let mut bytes = [0; 2];
+    memory.read(&*store, address, &mut bytes)?;
+    let symbol = u16::from_le_bytes(bytes);
+    unsafe { (*store.data().lexer).result_symbol = symbol };
+    Ok(symbol)
+}
+
+fn lexer_advance(mut caller: Caller<StoreData>, _lexer: i32, skip: i32) -> Result<(), Error> {
+    let lexer = caller.data().lexer;
+    let lookahead = unsafe {
+        ((*lexer).advance)(lexer, skip != 0);
+        (*lexer).lookahead
+    };
+    let memory = caller.data().memory.unwrap();
+    let address = caller.data().lexer_address + LEXER_LOOKAHEAD_OFFSET;
+    memory.write(&mut caller, address as usize, &lookahead.to_le_bytes())?;
+    Ok(())
+}
+
+fn lexer_mark_end(caller: Caller<StoreData>, _lexer: i32) {
+    let lexer = caller.data().lexer;
+    unsafe { ((*lexer).mark_end)(lexer) }
+}
+
+fn lexer_get_column(caller: Caller<StoreData>, _lexer: i32) -> i32 {
+    let lexer = caller.data().lexer;
+    unsafe { ((*lexer).get_column)(lexer) as i32 }
+}
+
+fn lexer_is_at_included_range_start(caller: Caller<StoreData>, _lexer: i32) -> i32 {
+    let lexer = caller.data().lexer;
+    unsafe { ((*lexer).is_at_included_range_start)(lexer) as i32 }
+}
+
+fn lexer_eof(caller: Caller<StoreData>, _lexer: i32) -> i32 {
+    let lexer = caller.data().lexer;
+    unsafe { ((*lexer).eof)(lexer) as i32 }
+}
+
+fn memory_range(
+    caller: &mut Caller<StoreData>,
+    address: i32,
+    length: i32,
+) -> Result<std::ops::Range<usize>, Error> {
+    let memory = caller.data().memory.unwrap();
+    let start = address as u32 as usize;
+    let end = start + length as u32 as usize;
+    if end > memory.data_size(&*caller) {
+        return Err(Error::msg("Out of bounds memory access"));
+    }
+    Ok(start..end)
+}
+
+fn memory_string(caller: &mut Caller<StoreData>, address: i32, max_length: usize) -> Vec<u8> {
+    let memory = caller.data().memory.unwrap();
+    let data = memory.data(&*caller);
+    data.get(address as u32 as usize..)
+        .unwrap_or(&[])
+        .iter()
+        .take(max_length)
+        .take_while(|b| **b != 0)
+        .copied()
+        .collect()
+}
+
+fn char_predicate(store: &mut Store<StoreData>, f: fn(char) -> bool) -> Func {
+    Func::wrap(store, move |c: i32| {
+        char::from_u32(c as u32).map_or(false, f) as i32
+    })
+}
+
+fn char_conversion(store: &mut Store<StoreData>, f: fn(char) -> Option<char>) -> Func {
+    Func::wrap(store, move |c: i32| {
+        char::from_u32(c as u32).and_then(f).map_or(c, |c| c as i32)
+    })
+}
+
+fn to_single_char(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let result = chars.next();
+    if chars.next().is_some() {
+        None
+    } else {
+        result
+    }
+}
+
+/// Get the function that the store provides to WebAssembly modules under the given
+/// name. The store provides a subset of the C standard library that external
+/// scanners commonly use. Calling any other function causes a trap.
+fn stdlib_function(store: &mut Store<StoreData>, name: &str, ty: FuncType) -> Func {
+    match name {
+        "malloc" | "_Znwj" | "_Znaj" => {
+            Func::wrap(store, |mut caller: Caller<StoreData>, size: i32| {
+                allocate(&mut caller, size as u32) as i32
+            })
+        }
+        "calloc" => Func::wrap(
+            store,
+            |mut caller: Caller<StoreData>, count: i32, size: i32| -> Result<i32, Error> {
+                let size = match (count as u32).checked_mul(size as u32) {
+                    Some(size) => size,
+                    None => return Ok(0),
+                };
+                let address = allocate(&mut caller, size);
+                if address != 0 {
+                    let range = memory_range(&mut caller, address as i32, size as i32)?;
+                    let memory = caller.data().memory.unwrap();
+                    memory.data_mut(&mut caller)[range].fill(0);
+                }
+                Ok(address as i32)
+            },
+        ),
+        "realloc" => Func::wrap(
+            store,
+            |mut caller: Caller<StoreData>, address: i32, size: i32| -> Result<i32, Error> {
+                let old_size = match block_size(&caller, address as u32) {
+                    Some(old_size) => old_size,
+                    None => return Ok(allocate(&mut caller, size as u32) as i32),
+                };
+                if size as u32 <= old_size {
+                    return Ok(address);
+                }
+                let new_address = allocate(&mut caller, size as u32);
+                if new_address != 0 {
+                    let range = memory_range(&mut caller, address, old_size as i32)?;
+                    let memory = caller.data().memory.unwrap();
+                    memory
+                        .data_mut(&mut caller)
+                        .copy_within(range, new_address as usize);
+                    free(&mut caller, address as u32);
+                }
+                Ok(new_address as i32)
+            },
+        ),
+        "free" | "_ZdlPv" | "_ZdaPv" => {
+            Func::wrap(store, |mut caller: Caller<StoreData>, address: i32| {
+                free(&mut caller, address as u32)
+            })
+        }
+        "memcpy" | "memmove" => Func::wrap(
+            store,
+            |mut caller: Caller<StoreData>, dest: i32, src: i32, n: i32| -> Result<i32, Error> {
+                let src_range = memory_range(&mut caller, src, n)?;
+                memory_range(&mut caller, dest, n)?;
+                let memory = caller.data().memory.unwrap();
+                memory
+                    .data_mut(&mut caller)
+                    .copy_within(src_range, dest as u32 as usize);
+                Ok(dest)
+            },
+        ),
+        "memset" => Func::wrap(
+            store,
+            |mut caller: Caller<StoreData>, dest: i32, c: i32, n: i32| -> Result<i32, Error> {
+                let range = memory_range(&mut caller, dest, n)?;
+                let memory = caller.data().memory.unwrap();
+                memory.data_mut(&mut caller)[range].fill(c as u8);
+                Ok(dest)
+            },
+        ),
+        "memcmp" => Func::wrap(
+            store,
+            |mut caller: Caller<StoreData>, a: i32, b: i32, n: i32| -> Result<i32, Error> {
+                let a = memory_range(&mut caller, a, n)?;
+                let b = memory_range(&mut caller, b, n)?;
+                let data = caller.data().memory.unwrap().data(&caller);
+                Ok(data[a].cmp(&data[b]) as i32)
+            },
+        ),
+        "strlen" => Func::wrap(store, |mut caller: Caller<StoreData>, s: i32| {
+            memory_string(&mut caller, s, usize::MAX).len() as i32
+        }),
+        "strcmp" => Func::wrap(store, |mut caller: Caller<StoreData>, a: i32, b: i32| {
+            let a = memory_string(&mut caller, a, usize::MAX);
+            let b = memory_string(&mut caller, b, usize::MAX);
+            a.cmp(&b) as i32
+        }),
+        "strncmp" => Func::wrap(
+            store,
+            |mut caller: Caller<StoreData>, a: i32, b: i32, n: i32| {
+                let a = memory_string(&mut caller, a, n as u32 as usize);
+                let b = memory_string(&mut caller, b, n as u32 as usize);
+                a.cmp(&b) as i32
+            },
+        ),
+        "iswspace" => char_predicate(store, char::is_whitespace),
+        "iswalpha" => char_predicate(store, char::is_alphabetic),
+        "iswalnum" => char_predicate(store, char::is_alphanumeric),
+        "iswdigit" => char_predicate(store, |c| c.is_ascii_digit()),
+        "iswxdigit" => char_predicate(store, |c| c.is_ascii_hexdigit()),
+        "iswupper" => char_predicate(store, char::is_uppercase),
+        "iswlower" => char_predicate(store, char::is_lowercase),
+        "iswpunct" => char_predicate(store, |c| c.is_ascii_punctuation()),
+        "isspace" => char_predicate(store, |c| matches!(c, ' ' | '\t'..='\r')),
+        "isalpha" => char_predicate(store, |c| c.is_ascii_alphabetic()),
+        "isalnum" => char_predicate(store, |c| c.is_ascii_alphanumeric()),
+        "isdigit" => char_predicate(store, |c| c.is_ascii_digit()),
+        "isxdigit" => char_predicate(store, |c| c.is_ascii_hexdigit()),
+        "isupper" => char_predicate(store, |c| c.is_ascii_uppercase()),
+        "islower" => char_predicate(store, |c| c.is_ascii_lowercase()),
+        "ispunct" => char_predicate(store, |c| c.is_ascii_punctuation()),
+        "towupper" => char_conversion(store, |c| to_single_char(c.to_uppercase())),
+        "towlower" => char_conversion(store, |c| to_single_char(c.to_lowercase())),
+        "toupper" => char_conversion(store, |c| Some(c.to_ascii_uppercase())),
+        "tolower" => char_conversion(store, |c| Some(c.to_ascii_lowercase())),
+        "__cxa_atexit" => Func::wrap(store, |_: i32, _: i32, _: i32| 0),
+        _ => {
+            let message = format!("Unsupported function {}", name);
+            Func::new(store, ty, move |_, _, _| Err(Error::msg(message.clone())))
+        }
+    }
+}
+
+fn wasm_store<'a>(store: *mut ffi::TSWasmStore) -> &'a mut WasmStore {
+    unsafe { &mut *(store as *mut WasmStore) }
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_start(
+    store: *mut ffi::TSWasmStore,
+    lexer: *mut TSLexer,
+    language: *const ffi::TSLanguage,
+) -> bool {
+    wasm_store(store).start(lexer, Language(language))
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_has_error(store: *const ffi::TSWasmStore) -> bool {
+    wasm_store(store as *mut ffi::TSWasmStore).has_error
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_delete(store: *mut ffi::TSWasmStore) {
+    drop(unsafe { Box::from_raw(store as *mut WasmStore) });
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_lex_main(store: *mut ffi::TSWasmStore, state: u16) -> bool {
+    wasm_store(store).call_lex_function(|instance| Some(instance.lex_main), state)
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_lex_keyword(store: *mut ffi::TSWasmStore, state: u16) -> bool {
+    wasm_store(store).call_lex_function(|instance| instance.lex_keyword, state)
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_scanner_create(store: *mut ffi::TSWasmStore) -> u32 {
+    wasm_store(store).scanner_call(|store, scanner, _| Ok(scanner.create.call(store, ())? as u32))
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_scanner_destroy(
+    store: *mut ffi::TSWasmStore,
+    scanner_address: u32,
+) {
+    wasm_store(store)
+        .scanner_call(|store, scanner, _| scanner.destroy.call(store, scanner_address as i32))
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_scanner_scan(
+    store: *mut ffi::TSWasmStore,
+    scanner_address: u32,
+    valid_tokens_index: u32,
+) -> bool {
+    let lexer_address = wasm_store(store).lexer_address;
+    wasm_store(store).scanner_call(|store, scanner, external_token_count| {
+        write_lexer(store)?;
+        let valid_tokens_address = scanner.states_address + valid_tokens_index;
+        let found_token = scanner.scan.call(
+            &mut *store,
+            (
+                scanner_address as i32,
+                lexer_address as i32,
+                valid_tokens_address as i32,
+            ),
+        )?;
+        let symbol = read_result_symbol(store)?;
+        if found_token != 0 && u32::from(symbol) >= external_token_count {
+            return Err(Error::msg(format!("Invalid external symbol {}", symbol)));
+        }
+        Ok(found_token != 0)
+    })
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_scanner_serialize(
+    store: *mut ffi::TSWasmStore,
+    scanner_address: u32,
+    buffer: *mut c_char,
+) -> u32 {
+    let buffer_address = wasm_store(store).buffer_address;
+    wasm_store(store).scanner_call(|store, scanner, _| {
+        let length = scanner
+            .serialize
+            .call(&mut *store, (scanner_address as i32, buffer_address as i32))?
+            as u32;
+        if length > SERIALIZATION_BUFFER_SIZE {
+            return Err(Error::msg(format!("Invalid serialized length {}", length)));
+        }
+        let buffer = unsafe { slice::from_raw_parts_mut(buffer as *mut u8, length as usize) };
+        store
+            .data()
+            .memory
+            .unwrap()
+            .read(&*store, buffer_address as usize, buffer)?;
+        Ok(length)
+    })
+}
+
+#[no_mangle]
+extern "C" fn ts_wasm_store_call_scanner_deserialize(
+    store: *mut ffi::TSWasmStore,
+    scanner_address: u32,
+    buffer: *const c_char,
+    length: u32,
+) {
+    let buffer_address = wasm_store(store).buffer_address;
+    wasm_store(store).scanner_call(|store, scanner, _| {
+        let mut address = 0;
+        if length > 0 {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const u8, length as usize) };
+            if length > SERIALIZATION_BUFFER_SIZE {
+                return Err(Error::msg(format!("Invalid serialized length {}", length)));
+            }
+            store
+                .data()
+                .memory
+                .unwrap()
+                .write(&mut *store, buffer_address as usize, buffer)?;
+            address = buffer_address;
+        }
+        scanner.deserialize.call(
+            store,
+            (scanner_address as i32, address as i32, length as i32),
+        )
+    })
+}
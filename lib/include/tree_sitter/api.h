@@ -21,7 +21,7 @@ extern "C" {
  * The Tree-sitter library is generally backwards-compatible with languages
  * generated using older CLI versions, but is not forwards-compatible.
  */
-#define TREE_SITTER_LANGUAGE_VERSION 14
+#define TREE_SITTER_LANGUAGE_VERSION 15
 
 /**
  * The earliest ABI version that is supported by the current version of the
@@ -33,6 +33,7 @@ extern "C" {
 /* Section - Types */
 /*******************/
 
+typedef uint16_t TSStateId;
 typedef uint16_t TSSymbol;
 typedef uint16_t TSFieldId;
 typedef struct TSLanguage TSLanguage;
@@ -40,6 +41,8 @@ typedef struct TSParser TSParser;
 typedef struct TSTree TSTree;
 typedef struct TSQuery TSQuery;
 typedef struct TSQueryCursor TSQueryCursor;
+typedef struct TSLookaheadIterator TSLookaheadIterator;
+typedef struct TSWasmStore TSWasmStore;
 
 typedef enum {
   TSInputEncodingUTF8,
@@ -80,6 +83,14 @@ typedef struct {
   void (*log)(void *payload, TSLogType, const char *);
 } TSLogger;
 
+typedef struct {
+  void *payload;
+  void *(*allocate)(void *payload, size_t size);
+  void *(*allocate_zeroed)(void *payload, size_t count, size_t size);
+  void *(*reallocate)(void *payload, void *buffer, size_t size);
+  void (*deallocate)(void *payload, void *buffer);
+} TSAllocator;
+
 typedef struct {
   uint32_t start_byte;
   uint32_t old_end_byte;
@@ -121,6 +132,21 @@ typedef struct {
   const TSQueryCapture *captures;
 } TSQueryMatch;
 
+typedef struct {
+  TSFieldId field_id;
+  bool multiple;
+  bool required;
+  const TSSymbol *types;
+  uint32_t type_count;
+} TSChildInfo;
+
+typedef struct {
+  uint32_t node_count;
+  uint32_t reused_node_count;
+  uint32_t depth;
+  size_t memory_usage;
+} TSNodeStats;
+
 typedef enum {
   TSQueryPredicateStepTypeDone,
   TSQueryPredicateStepTypeCapture,
@@ -142,6 +168,33 @@ typedef enum {
   TSQueryErrorLanguage,
 } TSQueryError;
 
+typedef enum {
+  TSQueryTokenTypeComment,
+  TSQueryTokenTypeAlternationStart,
+  TSQueryTokenTypeGroupStart,
+  TSQueryTokenTypeNodeStart,
+  TSQueryTokenTypePredicateStart,
+  TSQueryTokenTypeEnd,
+  TSQueryTokenTypeNodeName,
+  TSQueryTokenTypeSupertypeSeparator,
+  TSQueryTokenTypeWildcard,
+  TSQueryTokenTypeString,
+  TSQueryTokenTypeFieldName,
+  TSQueryTokenTypeColon,
+  TSQueryTokenTypeNegation,
+  TSQueryTokenTypeAnchor,
+  TSQueryTokenTypeQuantifier,
+  TSQueryTokenTypeCapture,
+  TSQueryTokenTypePredicateName,
+  TSQueryTokenTypeIdentifier,
+} TSQueryTokenType;
+
+typedef struct {
+  TSQueryTokenType type;
+  uint32_t start_byte;
+  uint32_t end_byte;
+} TSQueryToken;
+
 /********************/
 /* Section - Parser */
 /********************/
@@ -151,6 +204,23 @@ typedef enum {
  */
 TSParser *ts_parser_new(void);
 
+/**
+ * Create a new parser that uses the given allocator, instead of the global
+ * allocation functions, for all of its memory. The syntax trees that the
+ * parser produces, and their copies, use the same allocator.
+ *
+ * The allocator's `payload` is passed to each of its functions, which behave
+ * like `malloc`, `calloc`, `realloc`, and `free`, and which must remain valid
+ * until the parser and all of its trees have been deleted. The functions must
+ * never return `NULL` for a non-zero size. To limit the memory that a parse
+ * can use, set the parser's cancellation flag from within the allocator once
+ * the limit is reached.
+ *
+ * An old tree that was passed to `ts_parser_parse` is only reused if it was
+ * created with the same allocator as the parser.
+ */
+TSParser *ts_parser_new_with_allocator(TSAllocator allocator);
+
 /**
  * Delete the parser, freeing all of the memory that it used.
  */
@@ -165,6 +235,9 @@ void ts_parser_delete(TSParser *parser);
  * Tree-sitter CLI. Check the language's version using `ts_language_version`
  * and compare it to this library's `TREE_SITTER_LANGUAGE_VERSION` and
  * `TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION` constants.
+ *
+ * Assigning a WebAssembly language also fails if the parser does not have a
+ * WebAssembly store, or if the language cannot be instantiated in that store.
  */
 bool ts_parser_set_language(TSParser *self, const TSLanguage *language);
 
@@ -237,7 +310,7 @@ const TSRange *ts_parser_included_ranges(
  *    `TSInputEncodingUTF8` or `TSInputEncodingUTF16`.
  *
  * This function returns a syntax tree on success, and `NULL` on failure. There
- * are three possible reasons for failure:
+ * are four possible reasons for failure:
  * 1. The parser does not have a language assigned. Check for this using the
       `ts_parser_language` function.
  * 2. Parsing was cancelled due to a timeout that was set by an earlier call to
@@ -249,6 +322,9 @@ const TSRange *ts_parser_included_ranges(
  *    earlier call to `ts_parser_set_cancellation_flag`. You can resume parsing
  *    from where the parser left out by calling `ts_parser_parse` again with
  *    the same arguments.
+ * 4. The parser's language is a WebAssembly language, and its lexer or
+ *    external scanner failed, or the parser does not have a WebAssembly store.
+ *    In this case, the parser is reset.
  */
 TSTree *ts_parser_parse(
   TSParser *self,
@@ -344,6 +420,15 @@ TSLogger ts_parser_logger(const TSParser *self);
  */
 void ts_parser_print_dot_graphs(TSParser *self, int file);
 
+/**
+ * Get an estimate of the number of bytes of memory that the parser is using.
+ *
+ * This includes the buffers that the parser keeps between parses, but not the
+ * syntax trees that it has returned, or the memory used by a language's
+ * external scanner.
+ */
+size_t ts_parser_memory_usage(const TSParser *self);
+
 /******************/
 /* Section - Tree */
 /******************/
@@ -381,6 +466,24 @@ TSNode ts_tree_root_node_with_offset(
  */
 const TSLanguage *ts_tree_language(const TSTree *);
 
+/**
+ * Get the array of included ranges that was used to parse the syntax tree,
+ * updated to reflect any edits that have been made to the tree since then.
+ *
+ * The returned pointer must be freed by the caller.
+ */
+TSRange *ts_tree_included_ranges(const TSTree *, uint32_t *length);
+
+/**
+ * Get the number of bytes of memory that the syntax tree is using.
+ *
+ * Syntax trees that are created by incremental parsing share the parts of the
+ * old tree that were reused, and copies made with `ts_tree_copy` share all of
+ * their nodes. Shared memory is included in the usage of every tree that
+ * shares it.
+ */
+size_t ts_tree_memory_usage(const TSTree *self);
+
 /**
  * Edit the syntax tree to keep it in sync with source code that has been
  * edited.
@@ -429,6 +532,25 @@ const char *ts_node_type(TSNode);
  */
 TSSymbol ts_node_symbol(TSNode);
 
+/**
+ * Get the node's type as a numerical id as it appears in the grammar, ignoring
+ * aliases. This should be used in `ts_language_next_state` instead of
+ * `ts_node_symbol`.
+ */
+TSSymbol ts_node_grammar_symbol(TSNode);
+
+/**
+ * Get the parse state of this node. If the parse state is not known, this
+ * returns `UINT16_MAX`.
+ */
+TSStateId ts_node_parse_state(TSNode);
+
+/**
+ * Get the parse state after this node. If the parse state is not known, this
+ * returns `UINT16_MAX`.
+ */
+TSStateId ts_node_next_parse_state(TSNode);
+
 /**
  * Get the node's start byte.
  */
@@ -529,6 +651,27 @@ TSNode ts_node_named_child(TSNode, uint32_t);
  */
 uint32_t ts_node_named_child_count(TSNode);
 
+/**
+ * Get the number of nodes within this node's subtree, including the node
+ * itself. Only nodes that would be visited by a tree cursor are counted.
+ */
+uint32_t ts_node_descendant_count(TSNode);
+
+/**
+ * Get statistics about the node's subtree:
+ * - `node_count` is the number of nodes within the subtree, including the node
+ *   itself, as returned by `ts_node_descendant_count`.
+ * - `reused_node_count` is the number of those nodes that are shared with the
+ *   given `old_tree`. If the node's tree was parsed incrementally from
+ *   `old_tree`, these are the nodes that were reused. It is zero if `old_tree`
+ *   is `NULL`.
+ * - `depth` is the number of nodes on the longest path from the node to a
+ *   leaf, including both of them.
+ * - `memory_usage` is the number of bytes of memory that are used by the
+ *   subtree.
+ */
+TSNodeStats ts_node_stats(TSNode self, const TSTree *old_tree);
+
 /**
  * Get the node's child with the given field name.
  */
@@ -666,6 +809,50 @@ bool ts_tree_cursor_goto_next_sibling(TSTreeCursor *);
  */
 bool ts_tree_cursor_goto_first_child(TSTreeCursor *);
 
+/**
+ * Move the cursor to the last child of its current node.
+ *
+ * This returns `true` if the cursor successfully moved, and returns `false`
+ * if there were no children.
+ *
+ * Note that this function may be slower than `ts_tree_cursor_goto_first_child`
+ * because it needs to iterate through all the children to find the last one.
+ */
+bool ts_tree_cursor_goto_last_child(TSTreeCursor *);
+
+/**
+ * Move the cursor to the previous sibling of its current node.
+ *
+ * This returns `true` if the cursor successfully moved, and returns `false`
+ * if there was no previous sibling node.
+ *
+ * Note that this function may be slower than `ts_tree_cursor_goto_next_sibling`
+ * because it needs to iterate through the earlier siblings to find the
+ * previous one.
+ */
+bool ts_tree_cursor_goto_previous_sibling(TSTreeCursor *);
+
+/**
+ * Move the cursor to the node that is the nth descendant of the node that
+ * the cursor was constructed with, where zero represents the original node
+ * itself. Descendants are numbered in pre-order.
+ *
+ * If the index is out of range, the cursor is moved to the original node.
+ */
+void ts_tree_cursor_goto_descendant(TSTreeCursor *, uint32_t);
+
+/**
+ * Get the index of the cursor's current node out of all of the descendants
+ * of the node that the cursor was constructed with, in pre-order.
+ */
+uint32_t ts_tree_cursor_current_descendant_index(const TSTreeCursor *);
+
+/**
+ * Get the depth of the cursor's current node relative to the node that the
+ * cursor was constructed with.
+ */
+uint32_t ts_tree_cursor_current_depth(const TSTreeCursor *);
+
 /**
  * Move the cursor to the first child of its current node that extends beyond
  * the given byte offset or point.
@@ -701,6 +888,41 @@ TSQuery *ts_query_new(
   TSQueryError *error_type
 );
 
+/**
+ * Parse a string containing one or more S-expression patterns in the same way
+ * as `ts_query_new`, and get the tokens of the query's syntax, in order,
+ * including its comments. Every character of the source code, except for
+ * whitespace, is part of exactly one token:
+ * - `TSQueryTokenTypeAlternationStart`, `TSQueryTokenTypeGroupStart`,
+ *   `TSQueryTokenTypeNodeStart` and `TSQueryTokenTypePredicateStart` - The
+ *   opening bracket or parenthesis of an alternation, a grouped sequence, a
+ *   named node or a predicate. Each one is closed by a `TSQueryTokenTypeEnd`.
+ * - `TSQueryTokenTypeNodeName` - The name of a named node, or of the
+ *   supertype before a `TSQueryTokenTypeSupertypeSeparator`.
+ * - `TSQueryTokenTypeString` - An anonymous node, or a string argument to a
+ *   predicate, including its quotes.
+ * - `TSQueryTokenTypeFieldName` - The name of a field, which is followed by a
+ *   `TSQueryTokenTypeColon`, or preceded by a `TSQueryTokenTypeNegation`.
+ * - `TSQueryTokenTypeCapture` - A capture name, including its `@`.
+ * - `TSQueryTokenTypePredicateName` - The name of a predicate, including its
+ *   `#`, if any.
+ * - `TSQueryTokenTypeIdentifier` - A bare symbol argument to a predicate.
+ *
+ * If the query is valid, this returns an array of tokens, writes its length
+ * to the `token_count` parameter, and writes `TSQueryErrorNone` to the
+ * `error_type` parameter. The array is allocated using `malloc` and the caller
+ * is responsible for freeing it using `free`. If the query is invalid, this
+ * returns `NULL`, and reports the error in the same way as `ts_query_new`.
+ */
+TSQueryToken *ts_query_tokenize(
+  const TSLanguage *language,
+  const char *source,
+  uint32_t source_len,
+  uint32_t *token_count,
+  uint32_t *error_offset,
+  TSQueryError *error_type
+);
+
 /**
  * Delete a query, freeing all of the memory that it used.
  */
@@ -821,6 +1043,13 @@ void ts_query_disable_pattern(TSQuery *, uint32_t);
  */
 TSQueryCursor *ts_query_cursor_new(void);
 
+/**
+ * Create a new cursor for executing a given query, which uses the given
+ * allocator for all of its memory. See `ts_parser_new_with_allocator` for
+ * the requirements on the allocator.
+ */
+TSQueryCursor *ts_query_cursor_new_with_allocator(TSAllocator allocator);
+
 /**
  * Delete a query cursor, freeing all of the memory that it used.
  */
@@ -888,6 +1117,11 @@ uint32_t ts_language_symbol_count(const TSLanguage *);
  */
 const char *ts_language_symbol_name(const TSLanguage *, TSSymbol);
 
+/**
+ * Get the number of valid states in this language.
+ */
+uint32_t ts_language_state_count(const TSLanguage *);
+
 /**
  * Get the numerical id for the given node type string.
  */
@@ -921,6 +1155,50 @@ TSFieldId ts_language_field_id_for_name(const TSLanguage *, const char *, uint32
  */
 TSSymbolType ts_language_symbol_type(const TSLanguage *, TSSymbol);
 
+/**
+ * Get the supertypes of this language, as an array of node type ids.
+ *
+ * The length of the array is written to `*length`. Languages generated with
+ * an ABI version earlier than 15 do not contain this information, so the
+ * array is empty for them. `tree-sitter generate` produces ABI version 14
+ * unless it is run with `--abi 15` or `--abi latest`.
+ */
+const TSSymbol *ts_language_supertypes(const TSLanguage *, uint32_t *length);
+
+/**
+ * Get the subtypes of the given supertype, as an array of node type ids.
+ *
+ * The length of the array is written to `*length`. If the given node type is
+ * not a supertype, or if the language was generated with an ABI version
+ * earlier than 15, the array is empty.
+ */
+const TSSymbol *ts_language_subtypes(
+  const TSLanguage *,
+  TSSymbol supertype,
+  uint32_t *length
+);
+
+/**
+ * Get the number of descriptions of the children that a node of the given
+ * type can have. There is one description for each field that the node type
+ * can contain, and one for the named children that are not associated with
+ * any field, if there can be any.
+ *
+ * Languages generated with an ABI version earlier than 15 do not contain this
+ * information, so this is always zero for them.
+ */
+uint32_t ts_language_child_info_count(const TSLanguage *, TSSymbol);
+
+/**
+ * Get one of the descriptions of the children that a node of the given type
+ * can have. The description's field id is zero for the named children that
+ * are not associated with any field.
+ *
+ * The index must be less than the count returned by
+ * `ts_language_child_info_count`.
+ */
+TSChildInfo ts_language_child_info_at(const TSLanguage *, TSSymbol, uint32_t index);
+
 /**
  * Get the ABI version number for this language. This version number is used
  * to ensure that languages were generated by a compatible version of
@@ -930,6 +1208,141 @@ TSSymbolType ts_language_symbol_type(const TSLanguage *, TSSymbol);
  */
 uint32_t ts_language_version(const TSLanguage *);
 
+/**
+ * Get the next parse state. Combine this with lookahead iterators to generate
+ * completion suggestions or valid symbols in error nodes. Use
+ * `ts_node_grammar_symbol` for valid symbols.
+ */
+TSStateId ts_language_next_state(const TSLanguage *, TSStateId, TSSymbol);
+
+/********************************/
+/* Section - Lookahead Iterator */
+/********************************/
+
+/**
+ * Create a new lookahead iterator for the given language and parse state.
+ *
+ * This returns `NULL` if state is invalid for the language.
+ *
+ * Repeatedly using `ts_lookahead_iterator_next` and
+ * `ts_lookahead_iterator_current_symbol` will generate valid symbols in the
+ * given parse state. Newly created lookahead iterators will contain the `ERROR`
+ * symbol.
+ *
+ * Lookahead iterators can be useful to generate suggestions and improve syntax
+ * error diagnostics. To get symbols valid in an ERROR node, use the lookahead
+ * iterator on its first leaf node state. For `MISSING` nodes, a lookahead
+ * iterator created on the previous non-extra leaf node may be appropriate.
+ */
+TSLookaheadIterator *ts_lookahead_iterator_new(const TSLanguage *, TSStateId);
+
+/**
+ * Delete a lookahead iterator freeing all the memory used.
+ */
+void ts_lookahead_iterator_delete(TSLookaheadIterator *);
+
+/**
+ * Reset the lookahead iterator to another state.
+ *
+ * This returns `true` if the iterator was reset to the given state and `false`
+ * otherwise.
+ */
+bool ts_lookahead_iterator_reset_state(TSLookaheadIterator *, TSStateId);
+
+/**
+ * Reset the lookahead iterator.
+ *
+ * This returns `true` if the language was set successfully and `false`
+ * otherwise.
+ */
+bool ts_lookahead_iterator_reset(TSLookaheadIterator *, const TSLanguage *, TSStateId);
+
+/**
+ * Get the current language of the lookahead iterator.
+ */
+const TSLanguage *ts_lookahead_iterator_language(const TSLookaheadIterator *);
+
+/**
+ * Advance the lookahead iterator to the next symbol.
+ *
+ * This returns `true` if there is a new symbol and `false` otherwise.
+ */
+bool ts_lookahead_iterator_next(TSLookaheadIterator *);
+
+/**
+ * Get the current symbol of the lookahead iterator.
+ */
+TSSymbol ts_lookahead_iterator_current_symbol(const TSLookaheadIterator *);
+
+/**
+ * Get the current symbol type of the lookahead iterator as a null terminated
+ * string.
+ */
+const char *ts_lookahead_iterator_current_symbol_name(const TSLookaheadIterator *);
+
+/*************************************/
+/* Section - WebAssembly Integration */
+/*************************************/
+
+/**
+ * Assign a WebAssembly store to the parser. A parser needs a WebAssembly store
+ * in order to use languages that were loaded from WebAssembly modules.
+ *
+ * The parser takes ownership of the store, and deletes the store that it
+ * previously owned, if any. If the parser's current language is a WebAssembly
+ * language, then the parser's language is unset.
+ *
+ * WebAssembly stores are implemented by the Rust bindings, when they are
+ * compiled with the `wasm` feature.
+ */
+void ts_parser_set_wasm_store(TSParser *self, TSWasmStore *store);
+
+/**
+ * Remove the parser's WebAssembly store and return it, transferring ownership
+ * of the store to the caller. If the parser's current language is a
+ * WebAssembly language, then the parser's language is unset.
+ */
+TSWasmStore *ts_parser_take_wasm_store(TSParser *self);
+
+/**
+ * Check whether the given language was loaded from a WebAssembly module.
+ */
+bool ts_language_is_wasm(const TSLanguage *self);
+
+/**
+ * Create a language by copying the parse tables of the `TSLanguage` struct at
+ * the given address in the linear memory of a 32-bit WebAssembly module.
+ *
+ * The `module` pointer is stored in the language, so that the language can be
+ * instantiated in other WebAssembly stores. It can be retrieved using
+ * `ts_wasm_language_module`.
+ *
+ * This returns `NULL` if the struct or any of its tables do not fit within
+ * the given memory, or if their contents are invalid. The resulting language
+ * must be freed using `ts_language_delete`. Freeing it does not free the
+ * `module` pointer.
+ */
+const TSLanguage *ts_wasm_language_new(
+  const uint8_t *memory,
+  uint32_t memory_length,
+  uint32_t address,
+  void *module
+);
+
+/**
+ * Get the module pointer that was passed to `ts_wasm_language_new` when the
+ * given WebAssembly language was created, or `NULL` for a native language.
+ */
+void *ts_wasm_language_module(const TSLanguage *self);
+
+/**
+ * Free a language that was created using `ts_wasm_language_new`. Native
+ * languages are statically allocated, so this does nothing for them.
+ *
+ * The language must not be used afterward, by parsers, trees, or queries.
+ */
+void ts_language_delete(const TSLanguage *self);
+
 /**********************************/
 /* Section - Global Configuration */
 /**********************************/
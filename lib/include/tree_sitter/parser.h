@@ -13,9 +13,8 @@ extern "C" {
 #define ts_builtin_sym_end 0
 #define TREE_SITTER_SERIALIZATION_BUFFER_SIZE 1024
 
-typedef uint16_t TSStateId;
-
 #ifndef TREE_SITTER_API_H_
+typedef uint16_t TSStateId;
 typedef uint16_t TSSymbol;
 typedef uint16_t TSFieldId;
 typedef struct TSLanguage TSLanguage;
@@ -32,6 +31,18 @@ typedef struct {
   uint16_t length;
 } TSFieldMapSlice;
 
+typedef struct {
+  uint16_t index;
+  uint16_t length;
+} TSMapSlice;
+
+typedef struct {
+  TSFieldId field_id;
+  bool multiple;
+  bool required;
+  TSMapSlice types;
+} TSChildInfoEntry;
+
 typedef struct {
   bool visible;
   bool named;
@@ -124,6 +135,13 @@ struct TSLanguage {
     void (*deserialize)(void *, const char *, unsigned);
   } external_scanner;
   const TSStateId *primary_state_ids;
+  uint32_t supertype_count;
+  const TSSymbol *supertype_symbols;
+  const TSMapSlice *supertype_map_slices;
+  const TSSymbol *supertype_map_entries;
+  const TSMapSlice *child_info_map_slices;
+  const TSChildInfoEntry *child_info_entries;
+  const TSSymbol *child_type_entries;
 };
 
 /*
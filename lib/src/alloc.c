@@ -34,6 +34,8 @@ void *(*ts_current_calloc)(size_t, size_t) = ts_calloc_default;
 void *(*ts_current_realloc)(void *, size_t) = ts_realloc_default;
 void (*ts_current_free)(void *) = free;
 
+TS_THREAD_LOCAL const TSAllocator *ts_current_allocator = NULL;
+
 void ts_set_allocator(
   void *(*new_malloc)(size_t),
   void *(*new_calloc)(size_t, size_t),
@@ -16,18 +16,105 @@ extern void *(*ts_current_calloc)(size_t, size_t);
 extern void *(*ts_current_realloc)(void *, size_t);
 extern void (*ts_current_free)(void *);
 
+#if defined(_MSC_VER)
+#define TS_THREAD_LOCAL __declspec(thread)
+#elif defined(__GNUC__) || defined(__clang__)
+#define TS_THREAD_LOCAL __thread
+#else
+#define TS_THREAD_LOCAL _Thread_local
+#endif
+
+// The allocator of the parser, tree, or query cursor that the current thread
+// is operating on, or `NULL` if that object uses the global allocation
+// functions. It is set for the duration of each public function that can
+// allocate or free memory belonging to such an object, and cleared while
+// client callbacks run.
+extern TS_THREAD_LOCAL const TSAllocator *ts_current_allocator;
+
+static inline void *ts_scoped_malloc(size_t size) {
+  const TSAllocator *allocator = ts_current_allocator;
+  if (allocator) return allocator->allocate(allocator->payload, size);
+  return ts_current_malloc(size);
+}
+
+static inline void *ts_scoped_calloc(size_t count, size_t size) {
+  const TSAllocator *allocator = ts_current_allocator;
+  if (allocator) return allocator->allocate_zeroed(allocator->payload, count, size);
+  return ts_current_calloc(count, size);
+}
+
+static inline void *ts_scoped_realloc(void *buffer, size_t size) {
+  const TSAllocator *allocator = ts_current_allocator;
+  if (allocator) return allocator->reallocate(allocator->payload, buffer, size);
+  return ts_current_realloc(buffer, size);
+}
+
+static inline void ts_scoped_free(void *buffer) {
+  const TSAllocator *allocator = ts_current_allocator;
+  if (allocator) {
+    if (buffer) allocator->deallocate(allocator->payload, buffer);
+    return;
+  }
+  ts_current_free(buffer);
+}
+
+// An allocator with no functions represents the global allocation functions.
+static inline bool ts_allocator_is_global(const TSAllocator *self) {
+  return !self->allocate;
+}
+
+static inline bool ts_allocator_eq(const TSAllocator *self, const TSAllocator *other) {
+  if (ts_allocator_is_global(self) || ts_allocator_is_global(other)) {
+    return ts_allocator_is_global(self) && ts_allocator_is_global(other);
+  }
+  return
+    self->payload == other->payload &&
+    self->allocate == other->allocate &&
+    self->allocate_zeroed == other->allocate_zeroed &&
+    self->reallocate == other->reallocate &&
+    self->deallocate == other->deallocate;
+}
+
+// Returns a copy of the current thread's allocator.
+static inline TSAllocator ts_allocator_current(void) {
+  if (ts_current_allocator) return *ts_current_allocator;
+  return (TSAllocator) {0};
+}
+
+// Make the given allocator the current thread's allocator, returning the
+// previous one, which must be restored with `ts_allocator_exit`.
+static inline const TSAllocator *ts_allocator_enter(const TSAllocator *self) {
+  const TSAllocator *previous = ts_current_allocator;
+  ts_current_allocator = ts_allocator_is_global(self) ? NULL : self;
+  return previous;
+}
+
+// Make the global allocation functions current while calling back into client
+// code, such as an input callback or a logger, which may use the library with
+// other objects. Returns the previous allocator, which must be restored with
+// `ts_allocator_exit`.
+static inline const TSAllocator *ts_allocator_suspend(void) {
+  const TSAllocator *previous = ts_current_allocator;
+  ts_current_allocator = NULL;
+  return previous;
+}
+
+static inline void ts_allocator_exit(const TSAllocator *previous) {
+  ts_current_allocator = previous;
+}
+
 // Allow clients to override allocation functions
 #ifndef ts_malloc
-#define ts_malloc  ts_current_malloc
+#define ts_malloc  ts_scoped_malloc
 #endif
 #ifndef ts_calloc
-#define ts_calloc  ts_current_calloc
+#define ts_calloc  ts_scoped_calloc
 #endif
 #ifndef ts_realloc
-#define ts_realloc ts_current_realloc
+#define ts_realloc ts_scoped_realloc
 #endif
 #ifndef ts_free
-#define ts_free    ts_current_free
+#define ts_free    ts_scoped_free
 #endif
 
 #ifdef __cplusplus
@@ -1,3 +1,4 @@
+#include "./alloc.h"
 #include "./language.h"
 #include "./subtree.h"
 #include "./error_costs.h"
@@ -11,6 +12,10 @@ uint32_t ts_language_version(const TSLanguage *self) {
   return self->version;
 }
 
+uint32_t ts_language_state_count(const TSLanguage *self) {
+  return self->state_count;
+}
+
 uint32_t ts_language_field_count(const TSLanguage *self) {
   return self->field_count;
 }
@@ -104,6 +109,61 @@ TSSymbolType ts_language_symbol_type(
   }
 }
 
+const TSSymbol *ts_language_supertypes(const TSLanguage *self, uint32_t *length) {
+  if (self->version >= 15) {
+    *length = self->supertype_count;
+    return self->supertype_symbols;
+  } else {
+    *length = 0;
+    return NULL;
+  }
+}
+
+const TSSymbol *ts_language_subtypes(
+  const TSLanguage *self,
+  TSSymbol supertype,
+  uint32_t *length
+) {
+  if (
+    self->version < 15 ||
+    !self->supertype_map_slices ||
+    supertype >= self->symbol_count
+  ) {
+    *length = 0;
+    return NULL;
+  }
+
+  TSMapSlice slice = self->supertype_map_slices[supertype];
+  *length = slice.length;
+  return slice.length ? &self->supertype_map_entries[slice.index] : NULL;
+}
+
+uint32_t ts_language_child_info_count(const TSLanguage *self, TSSymbol symbol) {
+  if (
+    self->version < 15 ||
+    !self->child_info_map_slices ||
+    symbol >= ts_language_symbol_count(self)
+  ) return 0;
+  return self->child_info_map_slices[symbol].length;
+}
+
+TSChildInfo ts_language_child_info_at(
+  const TSLanguage *self,
+  TSSymbol symbol,
+  uint32_t index
+) {
+  assert(index < ts_language_child_info_count(self, symbol));
+  TSMapSlice slice = self->child_info_map_slices[symbol];
+  const TSChildInfoEntry *entry = &self->child_info_entries[slice.index + index];
+  return (TSChildInfo) {
+    .field_id = entry->field_id,
+    .multiple = entry->multiple,
+    .required = entry->required,
+    .types = entry->types.length ? &self->child_type_entries[entry->types.index] : NULL,
+    .type_count = entry->types.length,
+  };
+}
+
 const char *ts_language_field_name_for_id(
   const TSLanguage *self,
   TSFieldId id
@@ -135,3 +195,79 @@ TSFieldId ts_language_field_id_for_name(
   }
   return 0;
 }
+
+TSStateId ts_language_next_state(
+  const TSLanguage *self,
+  TSStateId state,
+  TSSymbol symbol
+) {
+  if (
+    symbol == ts_builtin_sym_error ||
+    symbol == ts_builtin_sym_error_repeat ||
+    symbol >= self->symbol_count
+  ) {
+    return 0;
+  } else if (symbol < self->token_count) {
+    uint32_t count;
+    const TSParseAction *actions = ts_language_actions(self, state, symbol, &count);
+    if (count > 0) {
+      TSParseAction action = actions[count - 1];
+      if (action.type == TSParseActionTypeShift) {
+        return action.shift.extra ? state : action.shift.state;
+      }
+    }
+    return 0;
+  } else {
+    return ts_language_lookup(self, state, symbol);
+  }
+}
+
+
+TSLookaheadIterator *ts_lookahead_iterator_new(const TSLanguage *self, TSStateId state) {
+  if (state >= self->state_count) return NULL;
+  LookaheadIterator *iterator = ts_malloc(sizeof(LookaheadIterator));
+  *iterator = ts_language_lookaheads(self, state);
+  return (TSLookaheadIterator *)iterator;
+}
+
+void ts_lookahead_iterator_delete(TSLookaheadIterator *self) {
+  ts_free(self);
+}
+
+bool ts_lookahead_iterator_reset_state(TSLookaheadIterator *self, TSStateId state) {
+  LookaheadIterator *iterator = (LookaheadIterator *)self;
+  if (state >= iterator->language->state_count) return false;
+  *iterator = ts_language_lookaheads(iterator->language, state);
+  return true;
+}
+
+bool ts_lookahead_iterator_reset(
+  TSLookaheadIterator *self,
+  const TSLanguage *language,
+  TSStateId state
+) {
+  if (state >= language->state_count) return false;
+  LookaheadIterator *iterator = (LookaheadIterator *)self;
+  *iterator = ts_language_lookaheads(language, state);
+  return true;
+}
+
+const TSLanguage *ts_lookahead_iterator_language(const TSLookaheadIterator *self) {
+  const LookaheadIterator *iterator = (const LookaheadIterator *)self;
+  return iterator->language;
+}
+
+bool ts_lookahead_iterator_next(TSLookaheadIterator *self) {
+  LookaheadIterator *iterator = (LookaheadIterator *)self;
+  return ts_lookahead_iterator__next(iterator);
+}
+
+TSSymbol ts_lookahead_iterator_current_symbol(const TSLookaheadIterator *self) {
+  const LookaheadIterator *iterator = (const LookaheadIterator *)self;
+  return iterator->symbol;
+}
+
+const char *ts_lookahead_iterator_current_symbol_name(const TSLookaheadIterator *self) {
+  const LookaheadIterator *iterator = (const LookaheadIterator *)self;
+  return ts_language_symbol_name(iterator->language, iterator->symbol);
+}
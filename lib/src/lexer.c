@@ -14,11 +14,14 @@
         message " character:%d",             \
       character                              \
     );                                       \
+    const TSAllocator *previous_allocator =  \
+      ts_allocator_suspend();                \
     self->logger.log(                        \
       self->logger.payload,                  \
       TSLogTypeLex,                          \
       self->debug_buffer                     \
     );                                       \
+    ts_allocator_exit(previous_allocator);   \
   }
 
 static const int32_t BYTE_ORDER_MARK = 0xFEFF;
@@ -56,12 +59,14 @@ static void ts_lexer__clear_chunk(Lexer *self) {
 // for the current position.
 static void ts_lexer__get_chunk(Lexer *self) {
   self->chunk_start = self->current_position.bytes;
+  const TSAllocator *previous_allocator = ts_allocator_suspend();
   self->chunk = self->input.read(
     self->input.payload,
     self->current_position.bytes,
     self->current_position.extent,
     &self->chunk_size
   );
+  ts_allocator_exit(previous_allocator);
   if (!self->chunk_size) {
     self->current_included_range_index = self->included_range_count;
     self->chunk = NULL;
@@ -16,3 +16,4 @@
 #include "./subtree.c"
 #include "./tree_cursor.c"
 #include "./tree.c"
+#include "./wasm_store.c"
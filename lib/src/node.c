@@ -417,12 +417,26 @@ TSSymbol ts_node_symbol(TSNode self) {
   return ts_language_public_symbol(self.tree->language, symbol);
 }
 
+TSSymbol ts_node_grammar_symbol(TSNode self) {
+  return ts_subtree_symbol(ts_node__subtree(self));
+}
+
 const char *ts_node_type(TSNode self) {
   TSSymbol symbol = ts_node__alias(&self);
   if (!symbol) symbol = ts_subtree_symbol(ts_node__subtree(self));
   return ts_language_symbol_name(self.tree->language, symbol);
 }
 
+TSStateId ts_node_parse_state(TSNode self) {
+  return ts_subtree_parse_state(ts_node__subtree(self));
+}
+
+TSStateId ts_node_next_parse_state(TSNode self) {
+  TSStateId state = ts_node_parse_state(self);
+  if (state == TS_TREE_STATE_NONE) return TS_TREE_STATE_NONE;
+  return ts_language_next_state(self.tree->language, state, ts_node_grammar_symbol(self));
+}
+
 char *ts_node_string(TSNode self) {
   return ts_subtree_string(ts_node__subtree(self), self.tree->language, false);
 }
@@ -621,6 +635,106 @@ uint32_t ts_node_named_child_count(TSNode self) {
   }
 }
 
+uint32_t ts_node_descendant_count(TSNode self) {
+  return ts_subtree_visible_descendant_count(ts_node__subtree(self)) + 1;
+}
+
+typedef struct {
+  Subtree subtree;
+  uint32_t depth;
+  bool is_visible;
+  bool is_reused;
+} NodeStatsEntry;
+
+static int ts_node__compare_subtree_pointers(const void *a, const void *b) {
+  const SubtreeHeapData *left = *(const SubtreeHeapData *const *)a;
+  const SubtreeHeapData *right = *(const SubtreeHeapData *const *)b;
+  if (left < right) return -1;
+  if (left > right) return 1;
+  return 0;
+}
+
+TSNodeStats ts_node_stats(TSNode self, const TSTree *old_tree) {
+  TSNodeStats result = {0, 0, 0, 0};
+
+  // Collect the old tree's heap-allocated subtrees, sorted by address, so
+  // that the subtrees that are shared with it can be found quickly.
+  Array(const SubtreeHeapData *) old_subtrees = array_new();
+  if (old_tree) {
+    SubtreeArray stack = array_new();
+    array_push(&stack, old_tree->root);
+    while (stack.size > 0) {
+      Subtree tree = array_pop(&stack);
+      if (tree.data.is_inline) continue;
+      array_push(&old_subtrees, tree.ptr);
+      for (uint32_t i = 0; i < tree.ptr->child_count; i++) {
+        array_push(&stack, ts_subtree_children(tree)[i]);
+      }
+    }
+    array_delete(&stack);
+    qsort(
+      old_subtrees.contents,
+      old_subtrees.size,
+      sizeof(const SubtreeHeapData *),
+      ts_node__compare_subtree_pointers
+    );
+  }
+
+  // Visit every subtree, including hidden ones, but only count the visible
+  // ones as nodes. Inline subtrees are stored within their parent, so they
+  // are only shared if their parent is shared.
+  Array(NodeStatsEntry) stack = array_new();
+  array_push(&stack, ((NodeStatsEntry) {ts_node__subtree(self), 1, true, false}));
+  while (stack.size > 0) {
+    NodeStatsEntry entry = array_pop(&stack);
+    Subtree tree = entry.subtree;
+    bool is_reused = entry.is_reused || (
+      !tree.data.is_inline &&
+      old_subtrees.size > 0 &&
+      bsearch(
+        &tree.ptr,
+        old_subtrees.contents,
+        old_subtrees.size,
+        sizeof(const SubtreeHeapData *),
+        ts_node__compare_subtree_pointers
+      )
+    );
+
+    if (entry.is_visible) {
+      result.node_count++;
+      if (is_reused) result.reused_node_count++;
+      if (entry.depth > result.depth) result.depth = entry.depth;
+    }
+    result.memory_usage += ts_subtree_allocation_size(tree);
+
+    uint32_t child_count = ts_subtree_child_count(tree);
+    if (child_count == 0) continue;
+    const TSSymbol *alias_sequence = ts_language_alias_sequence(
+      self.tree->language,
+      tree.ptr->production_id
+    );
+    uint32_t structural_child_index = 0;
+    for (uint32_t i = 0; i < child_count; i++) {
+      Subtree child = ts_subtree_children(tree)[i];
+      bool is_visible = ts_subtree_visible(child);
+      if (!ts_subtree_extra(child)) {
+        if (alias_sequence && alias_sequence[structural_child_index]) is_visible = true;
+        structural_child_index++;
+      }
+      array_push(&stack, ((NodeStatsEntry) {
+        .subtree = child,
+        .depth = entry.depth + (is_visible ? 1 : 0),
+        .is_visible = is_visible,
+        .is_reused = is_reused,
+      }));
+    }
+  }
+
+  array_delete(&stack);
+  array_delete(&old_subtrees);
+  return result;
+}
+
 TSNode ts_node_next_sibling(TSNode self) {
   return ts_node__next_sibling(self, true);
 }
@@ -18,6 +18,7 @@
 #include "./stack.h"
 #include "./subtree.h"
 #include "./tree.h"
+#include "./wasm_store.h"
 
 #define LOG(...)                                                                            \
   if (self->lexer.logger.log || self->dot_graph_file) {                                     \
@@ -105,6 +106,9 @@ struct TSParser {
   Subtree old_tree;
   TSRangeArray included_range_differences;
   unsigned included_range_difference_index;
+  TSWasmStore *wasm_store;
+  bool has_scanner_error;
+  TSAllocator allocator;
 };
 
 typedef struct {
@@ -150,11 +154,13 @@ static const char *ts_string_input_read(
 
 static void ts_parser__log(TSParser *self) {
   if (self->lexer.logger.log) {
+    const TSAllocator *previous_allocator = ts_allocator_suspend();
     self->lexer.logger.log(
       self->lexer.logger.payload,
       TSLogTypeParse,
       self->lexer.debug_buffer
     );
+    ts_allocator_exit(previous_allocator);
   }
 
   if (self->dot_graph_file) {
@@ -332,18 +338,133 @@ static bool ts_parser__better_version_exists(
   return false;
 }
 
-static void ts_parser__restore_external_scanner(
+// The lexing functions of a WebAssembly language must be called through the
+// parser's WebAssembly store. If one of them fails, the current parse is
+// aborted.
+
+static void ts_parser__check_wasm_store(TSParser *self) {
+  if (ts_wasm_store_has_error(self->wasm_store)) {
+    self->has_scanner_error = true;
+  }
+}
+
+static void ts_parser__external_scanner_create(TSParser *self) {
+  self->external_scanner_payload = NULL;
+  if (ts_language_is_wasm(self->language)) {
+    if (self->language->external_scanner.states) {
+      self->external_scanner_payload = (void *)(uintptr_t)ts_wasm_store_call_scanner_create(
+        self->wasm_store
+      );
+      ts_parser__check_wasm_store(self);
+    }
+  } else if (self->language->external_scanner.create) {
+    self->external_scanner_payload = self->language->external_scanner.create();
+  }
+}
+
+static void ts_parser__external_scanner_destroy(TSParser *self) {
+  if (self->language && self->external_scanner_payload) {
+    if (ts_language_is_wasm(self->language)) {
+      if (ts_wasm_store_start(self->wasm_store, &self->lexer.data, self->language)) {
+        ts_wasm_store_call_scanner_destroy(
+          self->wasm_store,
+          (uintptr_t)self->external_scanner_payload
+        );
+      }
+    } else if (self->language->external_scanner.destroy) {
+      self->language->external_scanner.destroy(self->external_scanner_payload);
+    }
+  }
+  self->external_scanner_payload = NULL;
+}
+
+static unsigned ts_parser__external_scanner_serialize(TSParser *self) {
+  if (ts_language_is_wasm(self->language)) {
+    unsigned length = ts_wasm_store_call_scanner_serialize(
+      self->wasm_store,
+      (uintptr_t)self->external_scanner_payload,
+      self->lexer.debug_buffer
+    );
+    ts_parser__check_wasm_store(self);
+    return length;
+  } else {
+    return self->language->external_scanner.serialize(
+      self->external_scanner_payload,
+      self->lexer.debug_buffer
+    );
+  }
+}
+
+static void ts_parser__external_scanner_deserialize(
   TSParser *self,
   Subtree external_token
 ) {
+  const char *data = NULL;
+  uint32_t length = 0;
   if (external_token.ptr) {
+    data = ts_external_scanner_state_data(&external_token.ptr->external_scanner_state);
+    length = external_token.ptr->external_scanner_state.length;
+  }
+
+  if (ts_language_is_wasm(self->language)) {
+    ts_wasm_store_call_scanner_deserialize(
+      self->wasm_store,
+      (uintptr_t)self->external_scanner_payload,
+      data,
+      length
+    );
+    ts_parser__check_wasm_store(self);
+  } else {
     self->language->external_scanner.deserialize(
       self->external_scanner_payload,
-      ts_external_scanner_state_data(&external_token.ptr->external_scanner_state),
-      external_token.ptr->external_scanner_state.length
+      data,
+      length
     );
+  }
+}
+
+static bool ts_parser__external_scanner_scan(
+  TSParser *self,
+  TSStateId external_lex_state
+) {
+  if (ts_language_is_wasm(self->language)) {
+    bool result = ts_wasm_store_call_scanner_scan(
+      self->wasm_store,
+      (uintptr_t)self->external_scanner_payload,
+      external_lex_state * self->language->external_token_count
+    );
+    ts_parser__check_wasm_store(self);
+    return result;
+  } else {
+    const bool *valid_external_tokens = ts_language_enabled_external_tokens(
+      self->language,
+      external_lex_state
+    );
+    return self->language->external_scanner.scan(
+      self->external_scanner_payload,
+      &self->lexer.data,
+      valid_external_tokens
+    );
+  }
+}
+
+static bool ts_parser__call_main_lex_fn(TSParser *self, TSLexMode lex_mode) {
+  if (ts_language_is_wasm(self->language)) {
+    bool result = ts_wasm_store_call_lex_main(self->wasm_store, lex_mode.lex_state);
+    ts_parser__check_wasm_store(self);
+    return result;
   } else {
-    self->language->external_scanner.deserialize(self->external_scanner_payload, NULL, 0);
+    return self->language->lex_fn(&self->lexer.data, lex_mode.lex_state);
+  }
+}
+
+static bool ts_parser__call_keyword_lex_fn(TSParser *self) {
+  if (ts_language_is_wasm(self->language)) {
+    bool result = ts_wasm_store_call_lex_keyword(self->wasm_store, 0);
+    ts_parser__check_wasm_store(self);
+    return result;
+  } else {
+    return self->language->keyword_lex_fn(&self->lexer.data, 0);
   }
 }
 
@@ -423,19 +544,14 @@ static Subtree ts_parser__lex(
         current_position.extent.column
       );
       ts_lexer_start(&self->lexer);
-      ts_parser__restore_external_scanner(self, external_token);
-      bool found_token = self->language->external_scanner.scan(
-        self->external_scanner_payload,
-        &self->lexer.data,
-        valid_external_tokens
-      );
+      ts_parser__external_scanner_deserialize(self, external_token);
+      bool found_token = ts_parser__external_scanner_scan(self, lex_mode.external_lex_state);
       ts_lexer_finish(&self->lexer, &lookahead_end_byte);
+      if (self->has_scanner_error) return NULL_SUBTREE;
 
       if (found_token) {
-        external_scanner_state_len = self->language->external_scanner.serialize(
-          self->external_scanner_payload,
-          self->lexer.debug_buffer
-        );
+        external_scanner_state_len = ts_parser__external_scanner_serialize(self);
+        if (self->has_scanner_error) return NULL_SUBTREE;
         external_scanner_state_changed = !ts_external_scanner_state_eq(
           ts_subtree_external_scanner_state(external_token),
           self->lexer.debug_buffer,
@@ -476,8 +592,9 @@ static Subtree ts_parser__lex(
       current_position.extent.column
     );
     ts_lexer_start(&self->lexer);
-    bool found_token = self->language->lex_fn(&self->lexer.data, lex_mode.lex_state);
+    bool found_token = ts_parser__call_main_lex_fn(self, lex_mode);
     ts_lexer_finish(&self->lexer, &lookahead_end_byte);
+    if (self->has_scanner_error) return NULL_SUBTREE;
     if (found_token) break;
 
     if (!error_mode) {
@@ -541,8 +658,10 @@ static Subtree ts_parser__lex(
       uint32_t end_byte = self->lexer.token_end_position.bytes;
       ts_lexer_reset(&self->lexer, self->lexer.token_start_position);
       ts_lexer_start(&self->lexer);
+      bool found_keyword = ts_parser__call_keyword_lex_fn(self);
+      if (self->has_scanner_error) return NULL_SUBTREE;
       if (
-        self->language->keyword_lex_fn(&self->lexer.data, 0) &&
+        found_keyword &&
         self->lexer.token_end_position.bytes == end_byte &&
         ts_language_has_actions(self->language, parse_state, self->lexer.data.result_symbol)
       ) {
@@ -1437,6 +1556,7 @@ static bool ts_parser__advance(
     if (needs_lex) {
       needs_lex = false;
       lookahead = ts_parser__lex(self, version, state);
+      if (self->has_scanner_error) return false;
 
       if (lookahead.ptr) {
         ts_parser__set_cached_token(self, position, last_external_token, lookahead);
@@ -1727,7 +1847,13 @@ static bool ts_parser_has_outstanding_parse(TSParser *self) {
 // Parser - Public
 
 TSParser *ts_parser_new(void) {
+  return ts_parser_new_with_allocator((TSAllocator) {0});
+}
+
+TSParser *ts_parser_new_with_allocator(TSAllocator allocator) {
+  const TSAllocator *previous_allocator = ts_allocator_enter(&allocator);
   TSParser *self = ts_calloc(1, sizeof(TSParser));
+  self->allocator = allocator;
   ts_lexer_init(&self->lexer);
   array_init(&self->reduce_actions);
   array_reserve(&self->reduce_actions, 4);
@@ -1743,13 +1869,19 @@ TSParser *ts_parser_new(void) {
   self->old_tree = NULL_SUBTREE;
   self->included_range_differences = (TSRangeArray) array_new();
   self->included_range_difference_index = 0;
+  self->wasm_store = NULL;
+  self->has_scanner_error = false;
   ts_parser__set_cached_token(self, 0, NULL_SUBTREE, NULL_SUBTREE);
+  ts_allocator_exit(previous_allocator);
   return self;
 }
 
 void ts_parser_delete(TSParser *self) {
   if (!self) return;
 
+  // The parser itself is freed with its allocator, so use a copy of it.
+  TSAllocator allocator = self->allocator;
+  const TSAllocator *previous_allocator = ts_allocator_enter(&allocator);
   ts_parser_set_language(self, NULL);
   ts_stack_delete(self->stack);
   if (self->reduce_actions.contents) {
@@ -1769,7 +1901,9 @@ void ts_parser_delete(TSParser *self) {
   array_delete(&self->trailing_extras);
   array_delete(&self->trailing_extras2);
   array_delete(&self->scratch_trees);
+  if (self->wasm_store) ts_wasm_store_delete(self->wasm_store);
   ts_free(self);
+  ts_allocator_exit(previous_allocator);
 }
 
 const TSLanguage *ts_parser_language(const TSParser *self) {
@@ -1780,20 +1914,31 @@ bool ts_parser_set_language(TSParser *self, const TSLanguage *language) {
   if (language) {
     if (language->version > TREE_SITTER_LANGUAGE_VERSION) return false;
     if (language->version < TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION) return false;
+    if (ts_language_is_wasm(language) && (
+      !self->wasm_store ||
+      !ts_wasm_store_start(self->wasm_store, &self->lexer.data, language)
+    )) return false;
   }
 
-  if (self->external_scanner_payload && self->language->external_scanner.destroy) {
-    self->language->external_scanner.destroy(self->external_scanner_payload);
-  }
-
-  if (language && language->external_scanner.create) {
-    self->external_scanner_payload = language->external_scanner.create();
-  } else {
-    self->external_scanner_payload = NULL;
-  }
-
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
+  ts_parser__external_scanner_destroy(self);
   self->language = language;
+  if (language) {
+    if (ts_language_is_wasm(language)) {
+      ts_wasm_store_start(self->wasm_store, &self->lexer.data, language);
+    }
+    self->has_scanner_error = false;
+    ts_parser__external_scanner_create(self);
+    if (self->has_scanner_error) {
+      self->external_scanner_payload = NULL;
+      self->language = NULL;
+      ts_parser_reset(self);
+      ts_allocator_exit(previous_allocator);
+      return false;
+    }
+  }
   ts_parser_reset(self);
+  ts_allocator_exit(previous_allocator);
   return true;
 }
 
@@ -1817,6 +1962,22 @@ void ts_parser_print_dot_graphs(TSParser *self, int fd) {
   }
 }
 
+size_t ts_parser_memory_usage(const TSParser *self) {
+  return
+    sizeof(TSParser) +
+    self->lexer.included_range_count * sizeof(TSRange) +
+    ts_stack_memory_usage(self->stack) +
+    ts_subtree_pool_memory_usage(&self->tree_pool) +
+    self->reduce_actions.capacity * sizeof(ReduceAction) +
+    self->trailing_extras.capacity * sizeof(Subtree) +
+    self->trailing_extras2.capacity * sizeof(Subtree) +
+    self->scratch_trees.capacity * sizeof(Subtree) +
+    ts_subtree_allocation_size(self->token_cache.token) +
+    ts_subtree_allocation_size(self->token_cache.last_external_token) +
+    self->reusable_node.stack.capacity * sizeof(StackEntry) +
+    self->included_range_differences.capacity * sizeof(TSRange);
+}
+
 const size_t *ts_parser_cancellation_flag(const TSParser *self) {
   return (const size_t *)self->cancellation_flag;
 }
@@ -1838,16 +1999,40 @@ bool ts_parser_set_included_ranges(
   const TSRange *ranges,
   uint32_t count
 ) {
-  return ts_lexer_set_included_ranges(&self->lexer, ranges, count);
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
+  bool result = ts_lexer_set_included_ranges(&self->lexer, ranges, count);
+  ts_allocator_exit(previous_allocator);
+  return result;
 }
 
 const TSRange *ts_parser_included_ranges(const TSParser *self, uint32_t *count) {
   return ts_lexer_included_ranges(&self->lexer, count);
 }
 
+void ts_parser_set_wasm_store(TSParser *self, TSWasmStore *store) {
+  TSWasmStore *previous_store = ts_parser_take_wasm_store(self);
+  if (previous_store) ts_wasm_store_delete(previous_store);
+  self->wasm_store = store;
+}
+
+TSWasmStore *ts_parser_take_wasm_store(TSParser *self) {
+  if (self->language && ts_language_is_wasm(self->language)) {
+    ts_parser_set_language(self, NULL);
+  }
+  TSWasmStore *result = self->wasm_store;
+  self->wasm_store = NULL;
+  return result;
+}
+
 void ts_parser_reset(TSParser *self) {
-  if (self->language && self->language->external_scanner.deserialize) {
-    self->language->external_scanner.deserialize(self->external_scanner_payload, NULL, 0);
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
+  if (
+    self->language &&
+    (ts_language_is_wasm(self->language)
+      ? self->language->external_scanner.states != NULL
+      : self->language->external_scanner.deserialize != NULL)
+  ) {
+    ts_parser__external_scanner_deserialize(self, NULL_SUBTREE);
   }
 
   if (self->old_tree.ptr) {
@@ -1864,14 +2049,19 @@ void ts_parser_reset(TSParser *self) {
     self->finished_tree = NULL_SUBTREE;
   }
   self->accept_count = 0;
+  ts_allocator_exit(previous_allocator);
 }
 
-TSTree *ts_parser_parse(
+static TSTree *ts_parser__parse(
   TSParser *self,
   const TSTree *old_tree,
   TSInput input
 ) {
   if (!self->language || !input.read) return NULL;
+  if (ts_language_is_wasm(self->language)) {
+    if (!ts_wasm_store_start(self->wasm_store, &self->lexer.data, self->language)) return NULL;
+  }
+  self->has_scanner_error = false;
 
   ts_lexer_set_input(&self->lexer, input);
 
@@ -1926,7 +2116,10 @@ TSTree *ts_parser_parse(
           ts_stack_position(self->stack, version).extent.column
         );
 
-        if (!ts_parser__advance(self, version, allow_node_reuse)) return NULL;
+        if (!ts_parser__advance(self, version, allow_node_reuse)) {
+          if (self->has_scanner_error) ts_parser_reset(self);
+          return NULL;
+        }
         LOG_STACK();
 
         position = ts_stack_position(self->stack, version).bytes;
@@ -1968,6 +2161,23 @@ TSTree *ts_parser_parse(
   return result;
 }
 
+TSTree *ts_parser_parse(
+  TSParser *self,
+  const TSTree *old_tree,
+  TSInput input
+) {
+  // An old tree's nodes can only be reused if they were allocated by the
+  // same allocator as the new tree's nodes.
+  if (old_tree && !ts_allocator_eq(&old_tree->allocator, &self->allocator)) {
+    old_tree = NULL;
+  }
+
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
+  TSTree *result = ts_parser__parse(self, old_tree, input);
+  ts_allocator_exit(previous_allocator);
+  return result;
+}
+
 TSTree *ts_parser_parse_string(
   TSParser *self,
   const TSTree *old_tree,
@@ -16,9 +16,13 @@
 #define MAX_ANALYSIS_STATE_DEPTH 8
 #define MAX_ANALYSIS_ITERATION_COUNT 256
 
+typedef Array(TSQueryToken) QueryTokenList;
+
 /*
  * Stream - A sequence of unicode characters derived from a UTF8 string.
- * This struct is used in parsing queries from S-expressions.
+ * This struct is used in parsing queries from S-expressions. If `tokens`
+ * is set, the tokens of the query's syntax are added to it as they are
+ * parsed.
  */
 typedef struct {
   const char *input;
@@ -26,6 +30,7 @@ typedef struct {
   const char *end;
   int32_t next;
   uint8_t next_size;
+  QueryTokenList *tokens;
 } Stream;
 
 /*
@@ -300,6 +305,7 @@ struct TSQueryCursor {
   bool ascending;
   bool halted;
   bool did_exceed_match_limit;
+  TSAllocator allocator;
 };
 
 static const TSQueryError PARENT_DONE = -1;
@@ -350,16 +356,47 @@ static Stream stream_new(const char *string, uint32_t length) {
   return self;
 }
 
+// Add a token that starts at the given position and ends at the current
+// position, if the stream is collecting tokens. Returns the token's index.
+static uint32_t stream_add_token(
+  Stream *self,
+  TSQueryTokenType type,
+  const char *start
+) {
+  if (!self->tokens) return 0;
+  array_push(self->tokens, ((TSQueryToken) {
+    .type = type,
+    .start_byte = start - self->start,
+    .end_byte = self->input - self->start,
+  }));
+  return self->tokens->size - 1;
+}
+
+// Advance past a single-character token, adding it to the stream's tokens.
+static uint32_t stream_advance_token(Stream *self, TSQueryTokenType type) {
+  const char *start = self->input;
+  stream_advance(self);
+  return stream_add_token(self, type, start);
+}
+
+// Change the type of a token that was added before the construct that it
+// belongs to was known.
+static void stream_set_token_type(Stream *self, uint32_t index, TSQueryTokenType type) {
+  if (self->tokens) self->tokens->contents[index].type = type;
+}
+
 static void stream_skip_whitespace(Stream *self) {
   for (;;) {
     if (iswspace(self->next)) {
       stream_advance(self);
     } else if (self->next == ';') {
       // skip over comments
+      const char *comment_start = self->input;
       stream_advance(self);
       while (self->next && self->next != '\n') {
         if (!stream_advance(self)) break;
       }
+      stream_add_token(self, TSQueryTokenTypeComment, comment_start);
     } else {
       break;
     }
@@ -1182,7 +1219,7 @@ static bool ts_query__analyze_patterns(TSQuery *self, unsigned *error_offset) {
   for (TSStateId state = 1; state < self->language->state_count; state++) {
     unsigned subgraph_index, exists;
     LookaheadIterator lookahead_iterator = ts_language_lookaheads(self->language, state);
-    while (ts_lookahead_iterator_next(&lookahead_iterator)) {
+    while (ts_lookahead_iterator__next(&lookahead_iterator)) {
       if (lookahead_iterator.action_count) {
         for (unsigned i = 0; i < lookahead_iterator.action_count; i++) {
           const TSParseAction *action = &lookahead_iterator.actions[i];
@@ -1478,7 +1515,7 @@ static bool ts_query__analyze_patterns(TSQuery *self, unsigned *error_offset) {
         // Follow every possible path in the parse table, but only visit states that
         // are part of the subgraph for the current symbol.
         LookaheadIterator lookahead_iterator = ts_language_lookaheads(self->language, parse_state);
-        while (ts_lookahead_iterator_next(&lookahead_iterator)) {
+        while (ts_lookahead_iterator__next(&lookahead_iterator)) {
           TSSymbol sym = lookahead_iterator.symbol;
 
           AnalysisSubgraphNode successor = {
@@ -1945,11 +1982,13 @@ static TSQueryError ts_query__parse_string_literal(
 // symbols, which also represent strings.
 static TSQueryError ts_query__parse_predicate(
   TSQuery *self,
-  Stream *stream
+  Stream *stream,
+  const char *predicate_start
 ) {
   if (!stream_is_ident_start(stream)) return TSQueryErrorSyntax;
   const char *predicate_name = stream->input;
   stream_scan_identifier(stream);
+  stream_add_token(stream, TSQueryTokenTypePredicateName, predicate_start);
   uint32_t length = stream->input - predicate_name;
   uint16_t id = symbol_table_insert_name(
     &self->predicate_values,
@@ -1964,7 +2003,7 @@ static TSQueryError ts_query__parse_predicate(
 
   for (;;) {
     if (stream->next == ')') {
-      stream_advance(stream);
+      stream_advance_token(stream, TSQueryTokenTypeEnd);
       stream_skip_whitespace(stream);
       array_push(&self->predicate_steps, ((TSQueryPredicateStep) {
         .type = TSQueryPredicateStepTypeDone,
@@ -1975,12 +2014,14 @@ static TSQueryError ts_query__parse_predicate(
 
     // Parse an '@'-prefixed capture name
     else if (stream->next == '@') {
+      const char *capture_start = stream->input;
       stream_advance(stream);
 
       // Parse the capture name
       if (!stream_is_ident_start(stream)) return TSQueryErrorSyntax;
       const char *capture_name = stream->input;
       stream_scan_identifier(stream);
+      stream_add_token(stream, TSQueryTokenTypeCapture, capture_start);
       uint32_t length = stream->input - capture_name;
 
       // Add the capture id to the first step of the pattern
@@ -2002,8 +2043,10 @@ static TSQueryError ts_query__parse_predicate(
 
     // Parse a string literal
     else if (stream->next == '"') {
+      const char *string_start = stream->input;
       TSQueryError e = ts_query__parse_string_literal(self, stream);
       if (e) return e;
+      stream_add_token(stream, TSQueryTokenTypeString, string_start);
       uint16_t id = symbol_table_insert_name(
         &self->predicate_values,
         self->string_buffer.contents,
@@ -2019,6 +2062,7 @@ static TSQueryError ts_query__parse_predicate(
     else if (stream_is_ident_start(stream)) {
       const char *symbol_start = stream->input;
       stream_scan_identifier(stream);
+      stream_add_token(stream, TSQueryTokenTypeIdentifier, symbol_start);
       uint32_t length = stream->input - symbol_start;
       uint16_t id = symbol_table_insert_name(
         &self->predicate_values,
@@ -2072,7 +2116,7 @@ static TSQueryError ts_query__parse_pattern(
 
   // An open bracket is the start of an alternation.
   if (stream->next == '[') {
-    stream_advance(stream);
+    stream_advance_token(stream, TSQueryTokenTypeAlternationStart);
     stream_skip_whitespace(stream);
 
     // Parse each branch, and add a placeholder step in between the branches.
@@ -2090,7 +2134,7 @@ static TSQueryError ts_query__parse_pattern(
 
       if (e == PARENT_DONE) {
         if (stream->next == ']' && branch_step_indices.size > 0) {
-          stream_advance(stream);
+          stream_advance_token(stream, TSQueryTokenTypeEnd);
           break;
         }
         e = TSQueryErrorSyntax;
@@ -2134,17 +2178,18 @@ static TSQueryError ts_query__parse_pattern(
   // * A predicate
   // * A named node
   else if (stream->next == '(') {
-    stream_advance(stream);
+    uint32_t start_token = stream_advance_token(stream, TSQueryTokenTypeNodeStart);
     stream_skip_whitespace(stream);
 
     // If this parenthesis is followed by a node, then it represents a grouped sequence.
     if (stream->next == '(' || stream->next == '"' || stream->next == '[') {
+      stream_set_token_type(stream, start_token, TSQueryTokenTypeGroupStart);
       bool child_is_immediate = false;
       CaptureQuantifiers child_capture_quantifiers = capture_quantifiers_new();
       for (;;) {
         if (stream->next == '.') {
           child_is_immediate = true;
-          stream_advance(stream);
+          stream_advance_token(stream, TSQueryTokenTypeAnchor);
           stream_skip_whitespace(stream);
         }
         TSQueryError e = ts_query__parse_pattern(
@@ -2156,7 +2201,7 @@ static TSQueryError ts_query__parse_pattern(
         );
         if (e == PARENT_DONE) {
           if (stream->next == ')') {
-            stream_advance(stream);
+            stream_advance_token(stream, TSQueryTokenTypeEnd);
             break;
           }
           e = TSQueryErrorSyntax;
@@ -2176,8 +2221,10 @@ static TSQueryError ts_query__parse_pattern(
 
     // A dot/pound character indicates the start of a predicate.
     else if (stream->next == '.' || stream->next == '#') {
+      stream_set_token_type(stream, start_token, TSQueryTokenTypePredicateStart);
+      const char *predicate_start = stream->input;
       stream_advance(stream);
-      return ts_query__parse_predicate(self, stream);
+      return ts_query__parse_predicate(self, stream, predicate_start);
     }
 
     // Otherwise, this parenthesis is the start of a named node.
@@ -2193,8 +2240,9 @@ static TSQueryError ts_query__parse_pattern(
         // TODO - remove.
         // For temporary backward compatibility, handle predicates without the leading '#' sign.
         if (length > 0 && (node_name[length - 1] == '!' || node_name[length - 1] == '?')) {
+          stream_set_token_type(stream, start_token, TSQueryTokenTypePredicateStart);
           stream_reset(stream, node_name);
-          return ts_query__parse_predicate(self, stream);
+          return ts_query__parse_predicate(self, stream, node_name);
         }
 
         // Parse the wildcard symbol
@@ -2214,6 +2262,7 @@ static TSQueryError ts_query__parse_pattern(
             return TSQueryErrorNodeType;
           }
         }
+        stream_add_token(stream, TSQueryTokenTypeNodeName, node_name);
       } else {
         return TSQueryErrorSyntax;
       }
@@ -2232,13 +2281,14 @@ static TSQueryError ts_query__parse_pattern(
       stream_skip_whitespace(stream);
 
       if (stream->next == '/') {
-        stream_advance(stream);
+        stream_advance_token(stream, TSQueryTokenTypeSupertypeSeparator);
         if (!stream_is_ident_start(stream)) {
           return TSQueryErrorSyntax;
         }
 
         const char *node_name = stream->input;
         stream_scan_identifier(stream);
+        stream_add_token(stream, TSQueryTokenTypeNodeName, node_name);
         uint32_t length = stream->input - node_name;
 
         step->symbol = ts_language_symbol_for_name(
@@ -2264,7 +2314,7 @@ static TSQueryError ts_query__parse_pattern(
       for (;;) {
         // Parse a negated field assertion
         if (stream->next == '!') {
-          stream_advance(stream);
+          stream_advance_token(stream, TSQueryTokenTypeNegation);
           stream_skip_whitespace(stream);
           if (!stream_is_ident_start(stream)) {
             capture_quantifiers_delete(&child_capture_quantifiers);
@@ -2272,6 +2322,7 @@ static TSQueryError ts_query__parse_pattern(
           }
           const char *field_name = stream->input;
           stream_scan_identifier(stream);
+          stream_add_token(stream, TSQueryTokenTypeFieldName, field_name);
           uint32_t length = stream->input - field_name;
           stream_skip_whitespace(stream);
 
@@ -2298,7 +2349,7 @@ static TSQueryError ts_query__parse_pattern(
         // Parse a sibling anchor
         if (stream->next == '.') {
           child_is_immediate = true;
-          stream_advance(stream);
+          stream_advance_token(stream, TSQueryTokenTypeAnchor);
           stream_skip_whitespace(stream);
         }
 
@@ -2329,7 +2380,7 @@ static TSQueryError ts_query__parse_pattern(
               );
             }
 
-            stream_advance(stream);
+            stream_advance_token(stream, TSQueryTokenTypeEnd);
             break;
           }
           e = TSQueryErrorSyntax;
@@ -2351,7 +2402,7 @@ static TSQueryError ts_query__parse_pattern(
 
   // Parse a wildcard pattern
   else if (stream->next == '_') {
-    stream_advance(stream);
+    stream_advance_token(stream, TSQueryTokenTypeWildcard);
     stream_skip_whitespace(stream);
 
     // Add a step that matches any kind of node
@@ -2363,6 +2414,7 @@ static TSQueryError ts_query__parse_pattern(
     const char *string_start = stream->input;
     TSQueryError e = ts_query__parse_string_literal(self, stream);
     if (e) return e;
+    stream_add_token(stream, TSQueryTokenTypeString, string_start);
 
     // Add a step for the node
     TSSymbol symbol = ts_language_symbol_for_name(
@@ -2383,6 +2435,7 @@ static TSQueryError ts_query__parse_pattern(
     // Parse the field name
     const char *field_name = stream->input;
     stream_scan_identifier(stream);
+    stream_add_token(stream, TSQueryTokenTypeFieldName, field_name);
     uint32_t length = stream->input - field_name;
     stream_skip_whitespace(stream);
 
@@ -2390,7 +2443,7 @@ static TSQueryError ts_query__parse_pattern(
       stream_reset(stream, field_name);
       return TSQueryErrorSyntax;
     }
-    stream_advance(stream);
+    stream_advance_token(stream, TSQueryTokenTypeColon);
     stream_skip_whitespace(stream);
 
     // Parse the pattern
@@ -2452,7 +2505,7 @@ static TSQueryError ts_query__parse_pattern(
     if (stream->next == '+') {
       quantifier = quantifier_join(TSQuantifierOneOrMore, quantifier);
 
-      stream_advance(stream);
+      stream_advance_token(stream, TSQueryTokenTypeQuantifier);
       stream_skip_whitespace(stream);
 
       QueryStep repeat_step = query_step__new(WILDCARD_SYMBOL, depth, false);
@@ -2466,7 +2519,7 @@ static TSQueryError ts_query__parse_pattern(
     else if (stream->next == '*') {
       quantifier = quantifier_join(TSQuantifierZeroOrMore, quantifier);
 
-      stream_advance(stream);
+      stream_advance_token(stream, TSQueryTokenTypeQuantifier);
       stream_skip_whitespace(stream);
 
       QueryStep repeat_step = query_step__new(WILDCARD_SYMBOL, depth, false);
@@ -2486,7 +2539,7 @@ static TSQueryError ts_query__parse_pattern(
     else if (stream->next == '?') {
       quantifier = quantifier_join(TSQuantifierZeroOrOne, quantifier);
 
-      stream_advance(stream);
+      stream_advance_token(stream, TSQueryTokenTypeQuantifier);
       stream_skip_whitespace(stream);
 
       QueryStep *step = &self->steps.contents[starting_step_index];
@@ -2498,10 +2551,12 @@ static TSQueryError ts_query__parse_pattern(
 
     // Parse an '@'-prefixed capture pattern
     else if (stream->next == '@') {
+      const char *capture_start = stream->input;
       stream_advance(stream);
       if (!stream_is_ident_start(stream)) return TSQueryErrorSyntax;
       const char *capture_name = stream->input;
       stream_scan_identifier(stream);
+      stream_add_token(stream, TSQueryTokenTypeCapture, capture_start);
       uint32_t length = stream->input - capture_name;
       stream_skip_whitespace(stream);
 
@@ -2543,12 +2598,15 @@ static TSQueryError ts_query__parse_pattern(
   return 0;
 }
 
-TSQuery *ts_query_new(
+// Create a query, adding the tokens of its syntax to the given list if it
+// is not `NULL`.
+static TSQuery *ts_query__new(
   const TSLanguage *language,
   const char *source,
   uint32_t source_len,
   uint32_t *error_offset,
-  TSQueryError *error_type
+  TSQueryError *error_type,
+  QueryTokenList *tokens
 ) {
   if (
     !language ||
@@ -2579,6 +2637,7 @@ TSQuery *ts_query_new(
 
   // Parse all of the S-expressions in the given string.
   Stream stream = stream_new(source, source_len);
+  stream.tokens = tokens;
   stream_skip_whitespace(&stream);
   while (stream.input < stream.end) {
     uint32_t pattern_index = self->patterns.size;
@@ -2676,6 +2735,44 @@ TSQuery *ts_query_new(
   return self;
 }
 
+TSQuery *ts_query_new(
+  const TSLanguage *language,
+  const char *source,
+  uint32_t source_len,
+  uint32_t *error_offset,
+  TSQueryError *error_type
+) {
+  return ts_query__new(language, source, source_len, error_offset, error_type, NULL);
+}
+
+TSQueryToken *ts_query_tokenize(
+  const TSLanguage *language,
+  const char *source,
+  uint32_t source_len,
+  uint32_t *token_count,
+  uint32_t *error_offset,
+  TSQueryError *error_type
+) {
+  QueryTokenList tokens = array_new();
+  *token_count = 0;
+  *error_type = TSQueryErrorNone;
+  TSQuery *query = ts_query__new(
+    language,
+    source,
+    source_len,
+    error_offset,
+    error_type,
+    &tokens
+  );
+  if (!query) {
+    array_delete(&tokens);
+    return NULL;
+  }
+  ts_query_delete(query);
+  *token_count = tokens.size;
+  return tokens.contents;
+}
+
 void ts_query_delete(TSQuery *self) {
   if (self) {
     array_delete(&self->steps);
@@ -2833,8 +2930,14 @@ void ts_query_disable_pattern(
  ***************/
 
 TSQueryCursor *ts_query_cursor_new(void) {
+  return ts_query_cursor_new_with_allocator((TSAllocator) {0});
+}
+
+TSQueryCursor *ts_query_cursor_new_with_allocator(TSAllocator allocator) {
+  const TSAllocator *previous_allocator = ts_allocator_enter(&allocator);
   TSQueryCursor *self = ts_malloc(sizeof(TSQueryCursor));
   *self = (TSQueryCursor) {
+    .allocator = allocator,
     .did_exceed_match_limit = false,
     .ascending = false,
     .halted = false,
@@ -2848,15 +2951,20 @@ TSQueryCursor *ts_query_cursor_new(void) {
   };
   array_reserve(&self->states, 8);
   array_reserve(&self->finished_states, 8);
+  ts_allocator_exit(previous_allocator);
   return self;
 }
 
 void ts_query_cursor_delete(TSQueryCursor *self) {
+  // The cursor itself is freed with its allocator, so use a copy of it.
+  TSAllocator allocator = self->allocator;
+  const TSAllocator *previous_allocator = ts_allocator_enter(&allocator);
   array_delete(&self->states);
   array_delete(&self->finished_states);
   ts_tree_cursor_delete(&self->cursor);
   capture_list_pool_delete(&self->capture_list_pool);
   ts_free(self);
+  ts_allocator_exit(previous_allocator);
 }
 
 bool ts_query_cursor_did_exceed_match_limit(const TSQueryCursor *self) {
@@ -2876,6 +2984,7 @@ void ts_query_cursor_exec(
   const TSQuery *query,
   TSNode node
 ) {
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
   array_clear(&self->states);
   array_clear(&self->finished_states);
   ts_tree_cursor_reset(&self->cursor, node);
@@ -2886,6 +2995,7 @@ void ts_query_cursor_exec(
   self->halted = false;
   self->query = query;
   self->did_exceed_match_limit = false;
+  ts_allocator_exit(previous_allocator);
 }
 
 void ts_query_cursor_set_byte_range(
@@ -3716,9 +3826,10 @@ bool ts_query_cursor_next_match(
   TSQueryMatch *match
 ) {
   if (self->finished_states.size == 0) {
-    if (!ts_query_cursor__advance(self, false)) {
-      return false;
-    }
+    const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
+    bool did_advance = ts_query_cursor__advance(self, false);
+    ts_allocator_exit(previous_allocator);
+    if (!did_advance) return false;
   }
 
   QueryState *state = &self->finished_states.contents[0];
@@ -3876,10 +3987,10 @@ bool ts_query_cursor_next_capture(
 
     // If there are no finished matches that are ready to be returned, then
     // continue finding more matches.
-    if (
-      !ts_query_cursor__advance(self, true) &&
-      self->finished_states.size == 0
-    ) return false;
+    const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
+    bool did_advance = ts_query_cursor__advance(self, true);
+    ts_allocator_exit(previous_allocator);
+    if (!did_advance && self->finished_states.size == 0) return false;
   }
 }
 
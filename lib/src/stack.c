@@ -440,6 +440,21 @@ void ts_stack_delete(Stack *self) {
   ts_free(self);
 }
 
+size_t ts_stack_memory_usage(const Stack *self) {
+  size_t result =
+    sizeof(Stack) +
+    self->heads.capacity * sizeof(StackHead) +
+    self->slices.capacity * sizeof(StackSlice) +
+    self->iterators.capacity * sizeof(StackIterator) +
+    self->node_pool.capacity * sizeof(StackNode *) +
+    self->node_pool.size * sizeof(StackNode);
+  for (uint32_t i = 0; i < self->heads.size; i++) {
+    StackSummary *summary = self->heads.contents[i].summary;
+    if (summary) result += sizeof(StackSummary) + summary->capacity * sizeof(StackSummaryEntry);
+  }
+  return result;
+}
+
 uint32_t ts_stack_version_count(const Stack *self) {
   return self->heads.size;
 }
@@ -34,6 +34,10 @@ Stack *ts_stack_new(SubtreePool *);
 // Release the memory reserved for a given stack.
 void ts_stack_delete(Stack *);
 
+// Get an estimate of the number of bytes of memory that the stack is using,
+// not including the stack nodes that are in use, or their subtrees.
+size_t ts_stack_memory_usage(const Stack *);
+
 // Get the stack's current number of versions.
 uint32_t ts_stack_version_count(const Stack *);
 
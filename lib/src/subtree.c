@@ -133,6 +133,12 @@ void ts_subtree_pool_delete(SubtreePool *self) {
   if (self->tree_stack.contents) array_delete(&self->tree_stack);
 }
 
+size_t ts_subtree_pool_memory_usage(const SubtreePool *self) {
+  return
+    self->free_trees.size * sizeof(SubtreeHeapData) +
+    (self->free_trees.capacity + self->tree_stack.capacity) * sizeof(MutableSubtree);
+}
+
 static SubtreeHeapData *ts_subtree_pool_allocate(SubtreePool *self) {
   if (self->free_trees.size > 0) {
     return array_pop(&self->free_trees).ptr;
@@ -374,6 +380,7 @@ void ts_subtree_summarize_children(
 
   self.ptr->named_child_count = 0;
   self.ptr->visible_child_count = 0;
+  self.ptr->visible_descendant_count = 0;
   self.ptr->error_cost = 0;
   self.ptr->repeat_depth = 0;
   self.ptr->node_count = 1;
@@ -436,13 +443,16 @@ void ts_subtree_summarize_children(
 
     self.ptr->dynamic_precedence += ts_subtree_dynamic_precedence(child);
     self.ptr->node_count += ts_subtree_node_count(child);
+    self.ptr->visible_descendant_count += ts_subtree_visible_descendant_count(child);
 
     if (alias_sequence && alias_sequence[structural_index] != 0 && !ts_subtree_extra(child)) {
+      self.ptr->visible_descendant_count++;
       self.ptr->visible_child_count++;
       if (ts_language_symbol_metadata(language, alias_sequence[structural_index]).named) {
         self.ptr->named_child_count++;
       }
     } else if (ts_subtree_visible(child)) {
+      self.ptr->visible_descendant_count++;
       self.ptr->visible_child_count++;
       if (ts_subtree_named(child)) self.ptr->named_child_count++;
     } else if (grandchild_count > 0) {
@@ -616,6 +626,21 @@ void ts_subtree_release(SubtreePool *pool, Subtree self) {
   }
 }
 
+size_t ts_subtree_memory_usage(Subtree self) {
+  size_t result = 0;
+  SubtreeArray stack = array_new();
+  array_push(&stack, self);
+  while (stack.size > 0) {
+    Subtree tree = array_pop(&stack);
+    result += ts_subtree_allocation_size(tree);
+    for (uint32_t i = 0, n = ts_subtree_child_count(tree); i < n; i++) {
+      array_push(&stack, ts_subtree_children(tree)[i]);
+    }
+  }
+  array_delete(&stack);
+  return result;
+}
+
 int ts_subtree_compare(Subtree left, Subtree right) {
   if (ts_subtree_symbol(left) < ts_subtree_symbol(right)) return -1;
   if (ts_subtree_symbol(right) < ts_subtree_symbol(left)) return 1;
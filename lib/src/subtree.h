@@ -135,6 +135,7 @@ typedef struct {
     struct {
       uint32_t visible_child_count;
       uint32_t named_child_count;
+      uint32_t visible_descendant_count;
       uint32_t node_count;
       int32_t dynamic_precedence;
       uint16_t repeat_depth;
@@ -186,6 +187,7 @@ void ts_subtree_array_reverse(SubtreeArray *);
 
 SubtreePool ts_subtree_pool_new(uint32_t capacity);
 void ts_subtree_pool_delete(SubtreePool *);
+size_t ts_subtree_pool_memory_usage(const SubtreePool *);
 
 Subtree ts_subtree_new_leaf(
   SubtreePool *, TSSymbol, Length, Length, uint32_t,
@@ -211,6 +213,7 @@ void ts_subtree_print_dot_graph(Subtree, const TSLanguage *, FILE *);
 Subtree ts_subtree_last_external_token(Subtree);
 const ExternalScannerState *ts_subtree_external_scanner_state(Subtree self);
 bool ts_subtree_external_scanner_state_eq(Subtree, Subtree);
+size_t ts_subtree_memory_usage(Subtree);
 
 #define SUBTREE_GET(self, name) (self.data.is_inline ? self.data.name : self.ptr->name)
 
@@ -237,6 +240,19 @@ static inline size_t ts_subtree_alloc_size(uint32_t child_count) {
 #define ts_subtree_children(self) \
   ((self).data.is_inline ? NULL : (Subtree *)((self).ptr) - (self).ptr->child_count)
 
+// Get the number of bytes that are allocated for a subtree, not including
+// its children's own allocations.
+static inline size_t ts_subtree_allocation_size(Subtree self) {
+  if (!self.ptr || self.data.is_inline) return 0;
+  if (self.ptr->child_count > 0) return ts_subtree_alloc_size(self.ptr->child_count);
+  size_t result = sizeof(SubtreeHeapData);
+  const ExternalScannerState *state = &self.ptr->external_scanner_state;
+  if (self.ptr->has_external_tokens && state->length > sizeof(state->short_data)) {
+    result += state->length;
+  }
+  return result;
+}
+
 static inline void ts_subtree_set_extra(MutableSubtree *self, bool is_extra) {
   if (self->data.is_inline) {
     self->data.extra = is_extra;
@@ -303,6 +319,14 @@ static inline uint32_t ts_subtree_visible_child_count(Subtree self) {
   }
 }
 
+static inline uint32_t ts_subtree_visible_descendant_count(Subtree self) {
+  if (ts_subtree_child_count(self) > 0) {
+    return self.ptr->visible_descendant_count;
+  } else {
+    return 0;
+  }
+}
+
 static inline uint32_t ts_subtree_error_cost(Subtree self) {
   if (ts_subtree_missing(self)) {
     return ERROR_COST_PER_MISSING_TREE + ERROR_COST_PER_RECOVERY;
@@ -16,22 +16,30 @@ TSTree *ts_tree_new(
   result->included_ranges = ts_calloc(included_range_count, sizeof(TSRange));
   memcpy(result->included_ranges, included_ranges, included_range_count * sizeof(TSRange));
   result->included_range_count = included_range_count;
+  result->allocator = ts_allocator_current();
   return result;
 }
 
 TSTree *ts_tree_copy(const TSTree *self) {
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
   ts_subtree_retain(self->root);
-  return ts_tree_new(self->root, self->language, self->included_ranges, self->included_range_count);
+  TSTree *result = ts_tree_new(self->root, self->language, self->included_ranges, self->included_range_count);
+  ts_allocator_exit(previous_allocator);
+  return result;
 }
 
 void ts_tree_delete(TSTree *self) {
   if (!self) return;
 
+  // The tree itself is freed with its allocator, so use a copy of it.
+  TSAllocator allocator = self->allocator;
+  const TSAllocator *previous_allocator = ts_allocator_enter(&allocator);
   SubtreePool pool = ts_subtree_pool_new(0);
   ts_subtree_release(&pool, self->root);
   ts_subtree_pool_delete(&pool);
   ts_free(self->included_ranges);
   ts_free(self);
+  ts_allocator_exit(previous_allocator);
 }
 
 TSNode ts_tree_root_node(const TSTree *self) {
@@ -51,6 +59,20 @@ const TSLanguage *ts_tree_language(const TSTree *self) {
   return self->language;
 }
 
+TSRange *ts_tree_included_ranges(const TSTree *self, uint32_t *length) {
+  *length = self->included_range_count;
+  TSRange *ranges = ts_calloc(self->included_range_count, sizeof(TSRange));
+  memcpy(ranges, self->included_ranges, self->included_range_count * sizeof(TSRange));
+  return ranges;
+}
+
+size_t ts_tree_memory_usage(const TSTree *self) {
+  return
+    sizeof(TSTree) +
+    self->included_range_count * sizeof(TSRange) +
+    ts_subtree_memory_usage(self->root);
+}
+
 void ts_tree_edit(TSTree *self, const TSInputEdit *edit) {
   for (unsigned i = 0; i < self->included_range_count; i++) {
     TSRange *range = &self->included_ranges[i];
@@ -80,9 +102,11 @@ void ts_tree_edit(TSTree *self, const TSInputEdit *edit) {
     }
   }
 
+  const TSAllocator *previous_allocator = ts_allocator_enter(&self->allocator);
   SubtreePool pool = ts_subtree_pool_new(0);
   self->root = ts_subtree_edit(self->root, edit, &pool);
   ts_subtree_pool_delete(&pool);
+  ts_allocator_exit(previous_allocator);
 }
 
 TSRange *ts_tree_get_changed_ranges(const TSTree *self, const TSTree *other, uint32_t *count) {
@@ -65,6 +65,18 @@ static inline bool ts_tree_cursor_child_iterator_next(
   return true;
 }
 
+static inline bool ts_tree_cursor_is_entry_visible(const TreeCursor *self, uint32_t index) {
+  TreeCursorEntry *entry = &self->stack.contents[index];
+  if (index == 0 || ts_subtree_visible(*entry->subtree)) return true;
+  if (ts_subtree_extra(*entry->subtree)) return false;
+  TreeCursorEntry *parent_entry = &self->stack.contents[index - 1];
+  return ts_language_alias_at(
+    self->tree->language,
+    parent_entry->subtree->ptr->production_id,
+    entry->structural_child_index
+  );
+}
+
 // TSTreeCursor - lifecycle
 
 TSTreeCursor ts_tree_cursor_new(TSNode node) {
@@ -125,6 +137,34 @@ bool ts_tree_cursor_goto_first_child(TSTreeCursor *_self) {
   return false;
 }
 
+bool ts_tree_cursor_goto_last_child(TSTreeCursor *_self) {
+  TreeCursor *self = (TreeCursor *)_self;
+
+  bool did_descend;
+  do {
+    did_descend = false;
+
+    bool visible, last_visible = false, found = false;
+    TreeCursorEntry entry, last_entry;
+    CursorChildIterator iterator = ts_tree_cursor_iterate_children(self);
+    while (ts_tree_cursor_child_iterator_next(&iterator, &entry, &visible)) {
+      if (visible || ts_subtree_visible_child_count(*entry.subtree) > 0) {
+        last_entry = entry;
+        last_visible = visible;
+        found = true;
+      }
+    }
+
+    if (found) {
+      array_push(&self->stack, last_entry);
+      if (last_visible) return true;
+      did_descend = true;
+    }
+  } while (did_descend);
+
+  return false;
+}
+
 int64_t ts_tree_cursor_goto_first_child_for_byte(TSTreeCursor *_self, uint32_t goal_byte) {
   TreeCursor *self = (TreeCursor *)_self;
   uint32_t initial_size = self->stack.size;
@@ -236,6 +276,115 @@ bool ts_tree_cursor_goto_next_sibling(TSTreeCursor *_self) {
   return false;
 }
 
+bool ts_tree_cursor_goto_previous_sibling(TSTreeCursor *_self) {
+  TreeCursor *self = (TreeCursor *)_self;
+  uint32_t initial_size = self->stack.size;
+
+  while (self->stack.size > 1) {
+    TreeCursorEntry entry = array_pop(&self->stack);
+
+    // Find the last sibling before this entry that is visible or that has
+    // visible children.
+    bool visible, entry_visible = false, previous_visible = false, found = false;
+    TreeCursorEntry child, previous_entry;
+    CursorChildIterator iterator = ts_tree_cursor_iterate_children(self);
+    while (ts_tree_cursor_child_iterator_next(&iterator, &child, &visible)) {
+      if (child.child_index == entry.child_index) {
+        entry_visible = visible;
+        break;
+      }
+      if (visible || ts_subtree_visible_child_count(*child.subtree) > 0) {
+        previous_entry = child;
+        previous_visible = visible;
+        found = true;
+      }
+    }
+    if (entry_visible && self->stack.size + 1 < initial_size) break;
+
+    if (found) {
+      array_push(&self->stack, previous_entry);
+      if (!previous_visible) ts_tree_cursor_goto_last_child(_self);
+      return true;
+    }
+  }
+
+  self->stack.size = initial_size;
+  return false;
+}
+
+void ts_tree_cursor_goto_descendant(TSTreeCursor *_self, uint32_t goal_descendant_index) {
+  TreeCursor *self = (TreeCursor *)_self;
+  self->stack.size = 1;
+
+  // The index that will be assigned to the next visible node.
+  uint32_t descendant_index = 1;
+  if (goal_descendant_index == 0) return;
+
+  bool did_descend;
+  do {
+    did_descend = false;
+
+    bool visible;
+    TreeCursorEntry entry;
+    CursorChildIterator iterator = ts_tree_cursor_iterate_children(self);
+    while (ts_tree_cursor_child_iterator_next(&iterator, &entry, &visible)) {
+      uint32_t count = ts_subtree_visible_descendant_count(*entry.subtree);
+      if (visible) count++;
+
+      if (descendant_index + count > goal_descendant_index) {
+        array_push(&self->stack, entry);
+        if (visible) {
+          if (descendant_index == goal_descendant_index) return;
+          descendant_index++;
+        }
+        did_descend = true;
+        break;
+      }
+
+      descendant_index += count;
+    }
+  } while (did_descend);
+
+  self->stack.size = 1;
+}
+
+uint32_t ts_tree_cursor_current_descendant_index(const TSTreeCursor *_self) {
+  const TreeCursor *self = (const TreeCursor *)_self;
+  uint32_t result = 0;
+  for (uint32_t i = 1; i < self->stack.size; i++) {
+    if (ts_tree_cursor_is_entry_visible(self, i - 1)) result++;
+
+    // Count the visible nodes within the earlier siblings of this entry.
+    TreeCursorEntry *entry = &self->stack.contents[i];
+    Subtree parent = *self->stack.contents[i - 1].subtree;
+    const TSSymbol *alias_sequence = ts_language_alias_sequence(
+      self->tree->language,
+      parent.ptr->production_id
+    );
+    uint32_t structural_child_index = 0;
+    for (uint32_t j = 0; j < entry->child_index; j++) {
+      Subtree sibling = ts_subtree_children(parent)[j];
+      bool visible = ts_subtree_visible(sibling);
+      if (!ts_subtree_extra(sibling)) {
+        if (alias_sequence && alias_sequence[structural_child_index]) visible = true;
+        structural_child_index++;
+      }
+      result += ts_subtree_visible_descendant_count(sibling);
+      if (visible) result++;
+    }
+  }
+  return result;
+}
+
+uint32_t ts_tree_cursor_current_depth(const TSTreeCursor *_self) {
+  const TreeCursor *self = (const TreeCursor *)_self;
+  uint32_t depth = 0;
+  for (uint32_t i = 1; i < self->stack.size; i++) {
+    if (ts_tree_cursor_is_entry_visible(self, i)) depth++;
+  }
+  return depth;
+}
+
 bool ts_tree_cursor_goto_parent(TSTreeCursor *_self) {
   TreeCursor *self = (TreeCursor *)_self;
   for (unsigned i = self->stack.size - 2; i + 1 > 0; i--) {
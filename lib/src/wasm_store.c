@@ -0,0 +1,753 @@
+#include <stddef.h>
+#include <string.h>
+#include "tree_sitter/api.h"
+#include "./alloc.h"
+#include "./language.h"
+#include "./wasm_store.h"
+
+// A language whose lexing functions live in a WebAssembly module. Its parse
+// tables are copied into native memory, and its function pointers are NULL.
+typedef struct {
+  TSLanguage language;
+  void *module;
+} WasmLanguage;
+
+// The layout of a `TSLanguage` struct in the memory of a 32-bit WebAssembly
+// module, where pointers are 32-bit addresses.
+typedef struct {
+  uint32_t version;
+  uint32_t symbol_count;
+  uint32_t alias_count;
+  uint32_t token_count;
+  uint32_t external_token_count;
+  uint32_t state_count;
+  uint32_t large_state_count;
+  uint32_t production_id_count;
+  uint32_t field_count;
+  uint16_t max_alias_sequence_length;
+  uint32_t parse_table;
+  uint32_t small_parse_table;
+  uint32_t small_parse_table_map;
+  uint32_t parse_actions;
+  uint32_t symbol_names;
+  uint32_t field_names;
+  uint32_t field_map_slices;
+  uint32_t field_map_entries;
+  uint32_t symbol_metadata;
+  uint32_t public_symbol_map;
+  uint32_t alias_map;
+  uint32_t alias_sequences;
+  uint32_t lex_modes;
+  uint32_t lex_fn;
+  uint32_t keyword_lex_fn;
+  TSSymbol keyword_capture_token;
+  struct {
+    uint32_t states;
+    uint32_t symbol_map;
+    uint32_t create;
+    uint32_t destroy;
+    uint32_t scan;
+    uint32_t serialize;
+    uint32_t deserialize;
+  } external_scanner;
+  uint32_t primary_state_ids;
+  uint32_t supertype_count;
+  uint32_t supertype_symbols;
+  uint32_t supertype_map_slices;
+  uint32_t supertype_map_entries;
+  uint32_t child_info_map_slices;
+  uint32_t child_info_entries;
+  uint32_t child_type_entries;
+} WasmLanguageStruct;
+
+typedef struct {
+  const uint8_t *data;
+  uint32_t length;
+  bool failed;
+} WasmMemory;
+
+// Get a pointer to an array within the WebAssembly memory, or `NULL` if the
+// array is empty or does not fit within the memory.
+static const void *wasm_memory__get(
+  WasmMemory *self,
+  uint64_t address,
+  uint32_t count,
+  size_t element_size
+) {
+  if (count == 0) return NULL;
+  uint64_t size = (uint64_t)count * element_size;
+  if (address == 0 || address + size > self->length) {
+    self->failed = true;
+    return NULL;
+  }
+  return &self->data[address];
+}
+
+static void *wasm_memory__copy(
+  WasmMemory *self,
+  uint32_t address,
+  uint32_t count,
+  size_t element_size
+) {
+  const void *data = wasm_memory__get(self, address, count, element_size);
+  if (!data) return NULL;
+  void *result = ts_malloc(count * element_size);
+  memcpy(result, data, count * element_size);
+  return result;
+}
+
+static uint16_t wasm_memory__u16(WasmMemory *self, uint32_t address, uint32_t index) {
+  uint16_t result = 0;
+  const void *data = wasm_memory__get(self, address + 2 * (uint64_t)index, 1, sizeof(uint16_t));
+  if (data) memcpy(&result, data, sizeof(uint16_t));
+  return result;
+}
+
+static uint32_t wasm_memory__u32(WasmMemory *self, uint32_t address, uint32_t index) {
+  uint32_t result = 0;
+  const void *data = wasm_memory__get(self, address + 4 * (uint64_t)index, 1, sizeof(uint32_t));
+  if (data) memcpy(&result, data, sizeof(uint32_t));
+  return result;
+}
+
+static const char *wasm_memory__copy_string(WasmMemory *self, uint32_t address) {
+  if (address == 0 || address >= self->length) {
+    self->failed = true;
+    return NULL;
+  }
+  const char *string = (const char *)&self->data[address];
+  const char *end = memchr(string, '\0', self->length - address);
+  if (!end) {
+    self->failed = true;
+    return NULL;
+  }
+  size_t length = end - string;
+  char *result = ts_malloc(length + 1);
+  memcpy(result, string, length + 1);
+  return result;
+}
+
+static const char **wasm_memory__copy_strings(
+  WasmMemory *self,
+  uint32_t address,
+  uint32_t count,
+  uint32_t first_index
+) {
+  if (count == 0) return NULL;
+  const char **result = ts_calloc(count, sizeof(char *));
+  for (uint32_t i = first_index; i < count && !self->failed; i++) {
+    result[i] = wasm_memory__copy_string(self, wasm_memory__u32(self, address, i));
+  }
+  return result;
+}
+
+// Get the number of entries that are referenced by an array of slices.
+static uint32_t wasm_memory__slice_entry_count(
+  WasmMemory *self,
+  uint32_t address,
+  uint32_t count
+) {
+  uint32_t result = 0;
+  for (uint32_t i = 0; i < count && !self->failed; i++) {
+    uint32_t end = wasm_memory__u16(self, address, 2 * i) + wasm_memory__u16(self, address, 2 * i + 1);
+    if (end > result) result = end;
+  }
+  return result;
+}
+
+static void wasm_language__delete(WasmLanguage *self) {
+  TSLanguage *language = &self->language;
+  uint32_t symbol_count = language->symbol_count + language->alias_count;
+  if (language->symbol_names) {
+    for (uint32_t i = 0; i < symbol_count; i++) {
+      ts_free((void *)language->symbol_names[i]);
+    }
+  }
+  if (language->field_names) {
+    for (uint32_t i = 0; i <= language->field_count; i++) {
+      ts_free((void *)language->field_names[i]);
+    }
+  }
+  ts_free((void *)language->parse_table);
+  ts_free((void *)language->small_parse_table);
+  ts_free((void *)language->small_parse_table_map);
+  ts_free((void *)language->parse_actions);
+  ts_free((void *)language->symbol_names);
+  ts_free((void *)language->field_names);
+  ts_free((void *)language->field_map_slices);
+  ts_free((void *)language->field_map_entries);
+  ts_free((void *)language->symbol_metadata);
+  ts_free((void *)language->public_symbol_map);
+  ts_free((void *)language->alias_map);
+  ts_free((void *)language->alias_sequences);
+  ts_free((void *)language->lex_modes);
+  ts_free((void *)language->external_scanner.states);
+  ts_free((void *)language->external_scanner.symbol_map);
+  ts_free((void *)language->primary_state_ids);
+  ts_free((void *)language->supertype_symbols);
+  ts_free((void *)language->supertype_map_slices);
+  ts_free((void *)language->supertype_map_entries);
+  ts_free((void *)language->child_info_map_slices);
+  ts_free((void *)language->child_info_entries);
+  ts_free((void *)language->child_type_entries);
+  ts_free(self);
+}
+
+// The lengths of the tables whose lengths are not stored in the language.
+typedef struct {
+  uint32_t parse_action_count;
+  uint32_t alias_map_length;
+  uint32_t field_map_entry_count;
+  uint32_t supertype_map_entry_count;
+  uint32_t child_info_count;
+  uint32_t child_type_count;
+} WasmTableLengths;
+
+static bool wasm_language__symbols_are_valid(
+  const TSSymbol *symbols,
+  uint32_t count,
+  uint32_t symbol_count
+) {
+  for (uint32_t i = 0; i < count; i++) {
+    if (symbols[i] >= symbol_count) return false;
+  }
+  return true;
+}
+
+// Check that every value in the language's tables that the parser uses as an
+// index is within the bounds of the table that it indexes, so that a malicious
+// module cannot cause the parser to read outside of those tables.
+static bool wasm_language__is_valid(
+  const TSLanguage *self,
+  const WasmTableLengths *lengths
+) {
+  uint32_t total_symbol_count = self->symbol_count + self->alias_count;
+
+  // Parsing starts in state 1, and state 0 is the error state.
+  if (
+    self->state_count < 2 ||
+    self->keyword_capture_token >= self->symbol_count ||
+    (self->field_count > 0 && self->production_id_count == 0)
+  ) return false;
+
+  // Action indices must refer to the start of an entry in the parse actions,
+  // because each entry begins with its number of actions.
+  bool *is_action_entry = ts_calloc(lengths->parse_action_count, sizeof(bool));
+  bool is_valid = true;
+  for (uint32_t i = 0; i < lengths->parse_action_count && is_valid; i++) {
+    is_action_entry[i] = true;
+    uint32_t action_count = self->parse_actions[i].entry.count;
+    for (uint32_t j = i + 1; j <= i + action_count && is_valid; j++) {
+      TSParseAction action = self->parse_actions[j].action;
+      switch (action.type) {
+        case TSParseActionTypeShift:
+          is_valid = action.shift.state < self->state_count;
+          break;
+        case TSParseActionTypeReduce:
+          is_valid =
+            action.reduce.symbol < self->symbol_count &&
+            (action.reduce.production_id == 0 || (
+              action.reduce.production_id < self->production_id_count &&
+              action.reduce.child_count <= self->max_alias_sequence_length
+            ));
+          break;
+        case TSParseActionTypeAccept:
+        case TSParseActionTypeRecover:
+          break;
+        default:
+          is_valid = false;
+      }
+    }
+    i += action_count;
+  }
+
+  // In the parse table, the values for tokens are action indices, and the
+  // values for other symbols are states.
+  for (uint32_t i = 0; i < self->large_state_count * self->symbol_count && is_valid; i++) {
+    uint16_t value = self->parse_table[i];
+    if (i % self->symbol_count < self->token_count) {
+      is_valid = is_action_entry[value];
+    } else {
+      is_valid = value < self->state_count;
+    }
+  }
+  uint32_t small_state_count = self->state_count - self->large_state_count;
+  for (uint32_t i = 0; i < small_state_count && is_valid; i++) {
+    uint32_t index = self->small_parse_table_map[i];
+    uint16_t group_count = self->small_parse_table[index++];
+    for (unsigned j = 0; j < group_count && is_valid; j++) {
+      uint16_t value = self->small_parse_table[index++];
+      uint16_t group_symbol_count = self->small_parse_table[index++];
+      for (unsigned k = 0; k < group_symbol_count && is_valid; k++) {
+        TSSymbol symbol = self->small_parse_table[index++];
+        if (symbol >= self->symbol_count) {
+          is_valid = false;
+        } else if (symbol < self->token_count) {
+          is_valid = is_action_entry[value];
+        } else {
+          is_valid = value < self->state_count;
+        }
+      }
+    }
+  }
+  ts_free(is_action_entry);
+  if (!is_valid) return false;
+
+  for (uint32_t i = 0; i < self->state_count; i++) {
+    if (self->external_token_count == 0 && self->lex_modes[i].external_lex_state != 0) {
+      return false;
+    }
+    if (self->primary_state_ids && self->primary_state_ids[i] >= self->state_count) {
+      return false;
+    }
+  }
+
+  for (uint32_t i = 0; i < lengths->field_map_entry_count; i++) {
+    TSFieldMapEntry entry = self->field_map_entries[i];
+    if (
+      entry.field_id > self->field_count ||
+      entry.child_index >= self->max_alias_sequence_length
+    ) return false;
+  }
+
+  for (uint32_t i = 0; i < lengths->child_info_count; i++) {
+    if (self->child_info_entries[i].field_id > self->field_count) return false;
+  }
+
+  return
+    wasm_language__symbols_are_valid(
+      self->public_symbol_map,
+      total_symbol_count,
+      total_symbol_count
+    ) &&
+    wasm_language__symbols_are_valid(
+      self->alias_map,
+      lengths->alias_map_length,
+      total_symbol_count
+    ) &&
+    wasm_language__symbols_are_valid(
+      self->alias_sequences,
+      self->production_id_count * self->max_alias_sequence_length,
+      total_symbol_count
+    ) &&
+    wasm_language__symbols_are_valid(
+      self->external_scanner.symbol_map,
+      self->external_token_count,
+      self->symbol_count
+    ) &&
+    wasm_language__symbols_are_valid(
+      self->supertype_symbols,
+      self->supertype_count,
+      total_symbol_count
+    ) &&
+    wasm_language__symbols_are_valid(
+      self->supertype_map_entries,
+      lengths->supertype_map_entry_count,
+      total_symbol_count
+    ) &&
+    wasm_language__symbols_are_valid(
+      self->child_type_entries,
+      lengths->child_type_count,
+      total_symbol_count
+    );
+}
+
+const TSLanguage *ts_wasm_language_new(
+  const uint8_t *data,
+  uint32_t length,
+  uint32_t address,
+  void *module
+) {
+  WasmMemory memory = {.data = data, .length = length, .failed = false};
+
+  // Older ABI versions have fewer fields at the end of the struct.
+  WasmLanguageStruct wasm_language;
+  memset(&wasm_language, 0, sizeof(wasm_language));
+  uint32_t version = wasm_memory__u32(&memory, address, 0);
+  size_t struct_size = sizeof(wasm_language);
+  if (version < 14) {
+    struct_size = offsetof(WasmLanguageStruct, primary_state_ids);
+  } else if (version < 15) {
+    struct_size = offsetof(WasmLanguageStruct, supertype_count);
+  }
+  const void *struct_data = wasm_memory__get(&memory, address, 1, struct_size);
+  if (
+    !struct_data ||
+    version < TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION ||
+    version > TREE_SITTER_LANGUAGE_VERSION
+  ) return NULL;
+  memcpy(&wasm_language, struct_data, struct_size);
+
+  // Symbols, states, fields and production ids are all stored as 16-bit
+  // integers.
+  if (
+    wasm_language.lex_fn == 0 ||
+    wasm_language.symbol_count + (uint64_t)wasm_language.alias_count > UINT16_MAX ||
+    wasm_language.token_count > wasm_language.symbol_count ||
+    wasm_language.external_token_count > wasm_language.token_count ||
+    wasm_language.state_count > UINT16_MAX ||
+    wasm_language.large_state_count > wasm_language.state_count ||
+    wasm_language.production_id_count > UINT16_MAX ||
+    wasm_language.field_count > UINT16_MAX
+  ) return NULL;
+
+  WasmLanguage *self = ts_calloc(1, sizeof(WasmLanguage));
+  self->module = module;
+  TSLanguage *language = &self->language;
+  language->version = wasm_language.version;
+  language->symbol_count = wasm_language.symbol_count;
+  language->alias_count = wasm_language.alias_count;
+  language->token_count = wasm_language.token_count;
+  language->external_token_count = wasm_language.external_token_count;
+  language->state_count = wasm_language.state_count;
+  language->large_state_count = wasm_language.large_state_count;
+  language->production_id_count = wasm_language.production_id_count;
+  language->field_count = wasm_language.field_count;
+  language->max_alias_sequence_length = wasm_language.max_alias_sequence_length;
+  language->keyword_capture_token = wasm_language.keyword_capture_token;
+  language->supertype_count = wasm_language.supertype_count;
+
+  uint32_t symbol_count = wasm_language.symbol_count + wasm_language.alias_count;
+  uint32_t small_state_count = wasm_language.state_count - wasm_language.large_state_count;
+
+  // The length of the small parse table, and of the parse actions, are not
+  // stored in the struct, so they must be computed by walking the parse table.
+  uint32_t parse_action_count = 1;
+  for (uint32_t i = 0; i < wasm_language.large_state_count * wasm_language.symbol_count; i++) {
+    if (i % wasm_language.symbol_count >= wasm_language.token_count) continue;
+    uint16_t action_index = wasm_memory__u16(&memory, wasm_language.parse_table, i);
+    if (action_index >= parse_action_count) parse_action_count = action_index + 1;
+    if (memory.failed) break;
+  }
+  uint32_t small_parse_table_length = 0;
+  for (uint32_t i = 0; i < small_state_count && !memory.failed; i++) {
+    uint32_t index = wasm_memory__u32(&memory, wasm_language.small_parse_table_map, i);
+    uint16_t group_count = wasm_memory__u16(&memory, wasm_language.small_parse_table, index++);
+    for (unsigned j = 0; j < group_count && !memory.failed; j++) {
+      uint16_t value = wasm_memory__u16(&memory, wasm_language.small_parse_table, index++);
+      uint16_t group_symbol_count = wasm_memory__u16(&memory, wasm_language.small_parse_table, index++);
+      for (unsigned k = 0; k < group_symbol_count; k++) {
+        TSSymbol symbol = wasm_memory__u16(&memory, wasm_language.small_parse_table, index++);
+        if (symbol < wasm_language.token_count && value >= parse_action_count) {
+          parse_action_count = value + 1;
+        }
+      }
+    }
+    if (index > small_parse_table_length) small_parse_table_length = index;
+  }
+
+  // Each entry in the parse actions is followed by its actions. The entry's
+  // action count is its first byte.
+  for (uint32_t i = 0; i < parse_action_count && !memory.failed; i++) {
+    const uint8_t *entry = wasm_memory__get(
+      &memory,
+      wasm_language.parse_actions + (uint64_t)i * sizeof(TSParseActionEntry),
+      1,
+      sizeof(TSParseActionEntry)
+    );
+    if (entry) {
+      uint32_t action_count = entry[0];
+      if (i + 1 + action_count > parse_action_count) {
+        parse_action_count = i + 1 + action_count;
+      }
+      i += action_count;
+    }
+  }
+
+  language->parse_table = wasm_memory__copy(
+    &memory,
+    wasm_language.parse_table,
+    wasm_language.large_state_count * wasm_language.symbol_count,
+    sizeof(uint16_t)
+  );
+  language->small_parse_table = wasm_memory__copy(
+    &memory,
+    wasm_language.small_parse_table,
+    small_parse_table_length,
+    sizeof(uint16_t)
+  );
+  language->small_parse_table_map = wasm_memory__copy(
+    &memory,
+    wasm_language.small_parse_table_map,
+    small_state_count,
+    sizeof(uint32_t)
+  );
+  language->parse_actions = wasm_memory__copy(
+    &memory,
+    wasm_language.parse_actions,
+    parse_action_count,
+    sizeof(TSParseActionEntry)
+  );
+  language->symbol_names = wasm_memory__copy_strings(
+    &memory,
+    wasm_language.symbol_names,
+    symbol_count,
+    0
+  );
+  language->symbol_metadata = wasm_memory__copy(
+    &memory,
+    wasm_language.symbol_metadata,
+    symbol_count,
+    sizeof(TSSymbolMetadata)
+  );
+  language->public_symbol_map = wasm_memory__copy(
+    &memory,
+    wasm_language.public_symbol_map,
+    symbol_count,
+    sizeof(TSSymbol)
+  );
+  language->lex_modes = wasm_memory__copy(
+    &memory,
+    wasm_language.lex_modes,
+    wasm_language.state_count,
+    sizeof(TSLexMode)
+  );
+
+  // The alias map is a sequence of symbols, each followed by its aliases, and
+  // terminated by a zero.
+  uint32_t alias_map_length = 0;
+  while (!memory.failed) {
+    TSSymbol symbol = wasm_memory__u16(&memory, wasm_language.alias_map, alias_map_length++);
+    if (symbol == 0) break;
+    alias_map_length += 1 + wasm_memory__u16(&memory, wasm_language.alias_map, alias_map_length);
+  }
+  language->alias_map = wasm_memory__copy(
+    &memory,
+    wasm_language.alias_map,
+    alias_map_length,
+    sizeof(uint16_t)
+  );
+  language->alias_sequences = wasm_memory__copy(
+    &memory,
+    wasm_language.alias_sequences,
+    wasm_language.production_id_count * wasm_language.max_alias_sequence_length,
+    sizeof(TSSymbol)
+  );
+
+  uint32_t field_map_entry_count = 0;
+  if (wasm_language.field_count > 0) {
+    field_map_entry_count = wasm_memory__slice_entry_count(
+      &memory,
+      wasm_language.field_map_slices,
+      wasm_language.production_id_count
+    );
+    language->field_names = wasm_memory__copy_strings(
+      &memory,
+      wasm_language.field_names,
+      wasm_language.field_count + 1,
+      1
+    );
+    language->field_map_slices = wasm_memory__copy(
+      &memory,
+      wasm_language.field_map_slices,
+      wasm_language.production_id_count,
+      sizeof(TSFieldMapSlice)
+    );
+    language->field_map_entries = wasm_memory__copy(
+      &memory,
+      wasm_language.field_map_entries,
+      field_map_entry_count,
+      sizeof(TSFieldMapEntry)
+    );
+  }
+
+  if (wasm_language.external_token_count > 0) {
+    uint32_t external_lex_state_count = 1;
+    for (uint32_t i = 0; i < wasm_language.state_count; i++) {
+      uint16_t external_lex_state = language->lex_modes
+        ? language->lex_modes[i].external_lex_state
+        : 0;
+      if (external_lex_state >= external_lex_state_count) {
+        external_lex_state_count = external_lex_state + 1;
+      }
+    }
+    language->external_scanner.states = wasm_memory__copy(
+      &memory,
+      wasm_language.external_scanner.states,
+      external_lex_state_count * wasm_language.external_token_count,
+      sizeof(bool)
+    );
+    language->external_scanner.symbol_map = wasm_memory__copy(
+      &memory,
+      wasm_language.external_scanner.symbol_map,
+      wasm_language.external_token_count,
+      sizeof(TSSymbol)
+    );
+  }
+
+  if (wasm_language.version >= 14) {
+    language->primary_state_ids = wasm_memory__copy(
+      &memory,
+      wasm_language.primary_state_ids,
+      wasm_language.state_count,
+      sizeof(TSStateId)
+    );
+  }
+
+  uint32_t supertype_map_entry_count = 0;
+  uint32_t child_info_count = 0;
+  uint32_t child_type_count = 0;
+  if (wasm_language.version >= 15) {
+    language->supertype_symbols = wasm_memory__copy(
+      &memory,
+      wasm_language.supertype_symbols,
+      wasm_language.supertype_count,
+      sizeof(TSSymbol)
+    );
+    if (wasm_language.supertype_map_slices) {
+      supertype_map_entry_count = wasm_memory__slice_entry_count(
+        &memory,
+        wasm_language.supertype_map_slices,
+        wasm_language.symbol_count
+      );
+      language->supertype_map_slices = wasm_memory__copy(
+        &memory,
+        wasm_language.supertype_map_slices,
+        wasm_language.symbol_count,
+        sizeof(TSMapSlice)
+      );
+      language->supertype_map_entries = wasm_memory__copy(
+        &memory,
+        wasm_language.supertype_map_entries,
+        supertype_map_entry_count,
+        sizeof(TSSymbol)
+      );
+    }
+    if (wasm_language.child_info_map_slices) {
+      child_info_count = wasm_memory__slice_entry_count(
+        &memory,
+        wasm_language.child_info_map_slices,
+        symbol_count
+      );
+      language->child_info_map_slices = wasm_memory__copy(
+        &memory,
+        wasm_language.child_info_map_slices,
+        symbol_count,
+        sizeof(TSMapSlice)
+      );
+      language->child_info_entries = wasm_memory__copy(
+        &memory,
+        wasm_language.child_info_entries,
+        child_info_count,
+        sizeof(TSChildInfoEntry)
+      );
+      for (uint32_t i = 0; i < child_info_count && language->child_info_entries; i++) {
+        TSMapSlice types = language->child_info_entries[i].types;
+        if (types.index + types.length > child_type_count) {
+          child_type_count = types.index + types.length;
+        }
+      }
+      language->child_type_entries = wasm_memory__copy(
+        &memory,
+        wasm_language.child_type_entries,
+        child_type_count,
+        sizeof(TSSymbol)
+      );
+    }
+  }
+
+  WasmTableLengths lengths = {
+    .parse_action_count = parse_action_count,
+    .alias_map_length = alias_map_length,
+    .field_map_entry_count = field_map_entry_count,
+    .supertype_map_entry_count = supertype_map_entry_count,
+    .child_info_count = child_info_count,
+    .child_type_count = child_type_count,
+  };
+  if (memory.failed || !wasm_language__is_valid(language, &lengths)) {
+    wasm_language__delete(self);
+    return NULL;
+  }
+  return language;
+}
+
+bool ts_language_is_wasm(const TSLanguage *self) {
+  return self->lex_fn == NULL;
+}
+
+void *ts_wasm_language_module(const TSLanguage *self) {
+  if (!ts_language_is_wasm(self)) return NULL;
+  return ((const WasmLanguage *)self)->module;
+}
+
+void ts_language_delete(const TSLanguage *self) {
+  if (self && ts_language_is_wasm(self)) {
+    wasm_language__delete((WasmLanguage *)self);
+  }
+}
+
+#ifndef TREE_SITTER_FEATURE_WASM
+
+bool ts_wasm_store_start(TSWasmStore *self, TSLexer *lexer, const TSLanguage *language) {
+  (void)self;
+  (void)lexer;
+  (void)language;
+  return false;
+}
+
+bool ts_wasm_store_has_error(const TSWasmStore *self) {
+  (void)self;
+  return true;
+}
+
+void ts_wasm_store_delete(TSWasmStore *self) {
+  (void)self;
+}
+
+bool ts_wasm_store_call_lex_main(TSWasmStore *self, TSStateId state) {
+  (void)self;
+  (void)state;
+  return false;
+}
+
+bool ts_wasm_store_call_lex_keyword(TSWasmStore *self, TSStateId state) {
+  (void)self;
+  (void)state;
+  return false;
+}
+
+uint32_t ts_wasm_store_call_scanner_create(TSWasmStore *self) {
+  (void)self;
+  return 0;
+}
+
+void ts_wasm_store_call_scanner_destroy(TSWasmStore *self, uint32_t scanner_address) {
+  (void)self;
+  (void)scanner_address;
+}
+
+bool ts_wasm_store_call_scanner_scan(
+  TSWasmStore *self,
+  uint32_t scanner_address,
+  uint32_t valid_tokens_index
+) {
+  (void)self;
+  (void)scanner_address;
+  (void)valid_tokens_index;
+  return false;
+}
+
+unsigned ts_wasm_store_call_scanner_serialize(
+  TSWasmStore *self,
+  uint32_t scanner_address,
+  char *buffer
+) {
+  (void)self;
+  (void)scanner_address;
+  (void)buffer;
+  return 0;
+}
+
+void ts_wasm_store_call_scanner_deserialize(
+  TSWasmStore *self,
+  uint32_t scanner_address,
+  const char *buffer,
+  unsigned length
+) {
+  (void)self;
+  (void)scanner_address;
+  (void)buffer;
+  (void)length;
+}
+
+#endif
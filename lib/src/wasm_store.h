@@ -0,0 +1,38 @@
+#ifndef TREE_SITTER_WASM_STORE_H_
+#define TREE_SITTER_WASM_STORE_H_
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#include "tree_sitter/api.h"
+#include "tree_sitter/parser.h"
+
+// These functions run the lexing functions of a WebAssembly language within a
+// WebAssembly store. When the library is built with `TREE_SITTER_FEATURE_WASM`,
+// they are implemented by the Rust bindings. Otherwise, WebAssembly stores
+// cannot be created, and these functions are never called.
+//
+// Each function operates on the language that was most recently passed to
+// `ts_wasm_store_start`. If a WebAssembly function traps, or returns an
+// invalid result, the store records an error, which is cleared by the next
+// call to `ts_wasm_store_start`.
+
+bool ts_wasm_store_start(TSWasmStore *, TSLexer *, const TSLanguage *);
+bool ts_wasm_store_has_error(const TSWasmStore *);
+void ts_wasm_store_delete(TSWasmStore *);
+
+bool ts_wasm_store_call_lex_main(TSWasmStore *, TSStateId);
+bool ts_wasm_store_call_lex_keyword(TSWasmStore *, TSStateId);
+
+uint32_t ts_wasm_store_call_scanner_create(TSWasmStore *);
+void ts_wasm_store_call_scanner_destroy(TSWasmStore *, uint32_t);
+bool ts_wasm_store_call_scanner_scan(TSWasmStore *, uint32_t, uint32_t);
+unsigned ts_wasm_store_call_scanner_serialize(TSWasmStore *, uint32_t, char *);
+void ts_wasm_store_call_scanner_deserialize(TSWasmStore *, uint32_t, const char *, unsigned);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif  // TREE_SITTER_WASM_STORE_H_
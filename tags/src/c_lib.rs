@@ -49,7 +49,14 @@ pub struct TSTag {
 }
 
 pub struct TSTagger {
-    languages: HashMap<String, TagsConfiguration>,
+    languages: HashMap<String, TaggerLanguage>,
+}
+
+/// A language's tags configuration, along with pointers to the names of its syntax
+/// types, which are returned by `ts_tagger_syntax_kinds_for_scope_name`.
+struct TaggerLanguage {
+    config: TagsConfiguration,
+    c_syntax_type_names: Vec<*const c_char>,
 }
 
 pub struct TSTagsBuffer {
@@ -95,8 +102,19 @@ pub extern "C" fn ts_tagger_add_language(
     };
 
     match TagsConfiguration::new(language, tags_query, locals_query) {
-        Ok(c) => {
-            tagger.languages.insert(scope_name.to_string(), c);
+        Ok(config) => {
+            let c_syntax_type_names = config
+                .syntax_type_names
+                .iter()
+                .map(|name| name.as_ptr() as *const c_char)
+                .collect();
+            tagger.languages.insert(
+                scope_name.to_string(),
+                TaggerLanguage {
+                    config,
+                    c_syntax_type_names,
+                },
+            );
             TSTagsError::Ok
         }
         Err(Error::Query(_)) => TSTagsError::InvalidQuery,
@@ -120,7 +138,7 @@ pub extern "C" fn ts_tagger_tag(
     let buffer = unwrap_mut_ptr(output);
     let scope_name = unsafe { unwrap(CStr::from_ptr(scope_name).to_str()) };
 
-    if let Some(config) = tagger.languages.get(scope_name) {
+    if let Some(TaggerLanguage { config, .. }) = tagger.languages.get(scope_name) {
         shrink_and_clear(&mut buffer.tags, BUFFER_TAGS_RESERVE_CAPACITY);
         shrink_and_clear(&mut buffer.docs, BUFFER_DOCS_RESERVE_CAPACITY);
 
@@ -243,9 +261,9 @@ pub extern "C" fn ts_tagger_syntax_kinds_for_scope_name(
     let len = unwrap_mut_ptr(len);
 
     *len = 0;
-    if let Some(config) = tagger.languages.get(scope_name) {
-        *len = config.c_syntax_type_names.len() as u32;
-        return config.c_syntax_type_names.as_ptr() as *const *const c_char;
+    if let Some(language) = tagger.languages.get(scope_name) {
+        *len = language.c_syntax_type_names.len() as u32;
+        return language.c_syntax_type_names.as_ptr();
     }
     std::ptr::null()
 }
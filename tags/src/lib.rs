@@ -23,7 +23,6 @@ pub struct TagsConfiguration {
     pub language: Language,
     pub query: Query,
     syntax_type_names: Vec<Box<[u8]>>,
-    c_syntax_type_names: Vec<*const u8>,
     capture_map: HashMap<u32, NamedCapture>,
     doc_capture_index: Option<u32>,
     name_capture_index: Option<u32>,
@@ -175,8 +174,6 @@ impl TagsConfiguration {
             }
         }
 
-        let c_syntax_type_names = syntax_type_names.iter().map(|s| s.as_ptr()).collect();
-
         let pattern_info = (0..query.pattern_count())
             .map(|pattern_index| {
                 let mut info = PatternInfo::default();
@@ -222,7 +219,6 @@ impl TagsConfiguration {
             language,
             query,
             syntax_type_names,
-            c_syntax_type_names,
             capture_map,
             doc_capture_index,
             name_capture_index,